@@ -0,0 +1,53 @@
+//! What price alerts need from a chart: conditions made from the selected
+//! drawing and the latest indicator values crossovers are checked against.
+
+use std::collections::BTreeMap;
+
+use aim_data::alert::{AlertCondition, Cross};
+
+use crate::{Chart, PriceScale, draw::DrawType};
+
+impl Chart {
+    /// Alert condition for the selected drawing: a level for a horizontal
+    /// line and a trendline for a line, None for anything else.
+    pub fn selected_alert_condition(&self) -> Option<AlertCondition> {
+        let drawing = self.drawings.get(self.selected?)?;
+        match drawing
+            .place(&self.candle_data, PriceScale::Linear)
+            .draw_type
+        {
+            DrawType::HorizontalLine(price) => Some(AlertCondition::Level {
+                price: price as f64,
+                cross: Cross::Either,
+            }),
+            DrawType::Line(((_, from), (_, to))) => {
+                let &[from_time, to_time] = drawing.times() else {
+                    return None;
+                };
+                Some(AlertCondition::Trendline {
+                    from: (from_time as i64, from as f64),
+                    to: (to_time as i64, to as f64),
+                    cross: Cross::Either,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Latest value of the main line of each indicator, by legend name such
+    /// as `EMA 20`.
+    pub fn indicator_values(&mut self) -> BTreeMap<String, f64> {
+        let mut values = BTreeMap::new();
+        for study in &mut self.indicators {
+            study.update(&self.candle_data);
+            let last = study
+                .series()
+                .first()
+                .and_then(|series| series.values.last().copied().flatten());
+            if let Some(value) = last {
+                values.insert(study.config.label(), value as f64);
+            }
+        }
+        values
+    }
+}
@@ -1,80 +1,233 @@
+use aim_data::{ActionKind, CorporateAction, Timeframe};
+use chrono::NaiveDate;
+use plotters::style::RGBColor;
+use std::ops::RangeInclusive;
+
 use crate::{
-    CandleData, Chart, CompanyInfo, UiData,
+    CandleData, Chart, ChartType, CompanyInfo, PriceScale, UiData, Viewport,
     chart::{BackupChartOffset, ChartData},
-    draw::DrawObject,
+    draw::{AnchoredDrawing, DrawObject, DrawStyle},
+    history::DrawHistory,
+    indicator::{IndicatorConfig, Source, Study, default_indicators},
+    pane::{MAX_PANES_HEIGHT, MIN_PANE_HEIGHT},
 };
 
+/// Version of the chart cache file written by [`Chart::write_to_bytes`].
+///
+/// * 1 - initial layout
+/// * 2 - time frame label appended to each chart
+/// * 3 - price adjustment flag and corporate actions appended
+/// * 4 - indicator list appended
+/// * 5 - sub-pane height appended to each indicator
+/// * 6 - anchor times of the drawings appended
+/// * 7 - style of each drawing appended
+/// * 8 - [`crate::ChartLayout`] of the chart page appended after the charts
+/// * 9 - comparison symbols and the ratio flag appended to each chart
+/// * 10 - chart type and box size appended to each chart, whose candles are
+///   now the bars before the chart type is applied
+/// * 11 - written as an [`aim_data::cache_file::CacheFile`] tagged with
+///   [`CHART_CACHE_MAGIC`], the payload is unchanged
+/// * 12 - only the workspace: each chart keeps its settings, view and
+///   drawings but no bars or UI state, and layout cells keep no chart
+/// * 13 - price scale, auto-fit and lock flags appended to each chart
+///
+/// There is no migration step per version. [`Chart::read_from_bytes`] has
+/// two readers: one for versions 1 to 11, which held the bars and the UI
+/// state, and one for the workspace of version 12 and later. Within each, a
+/// field appended by version `N` is read behind `if version >= N` and gets
+/// the default it had before `N` otherwise; [`crate::ChartLayout`] does the
+/// same. A migrated cache is written back in the current version.
+pub const CHART_CACHE_VERSION: u32 = 13;
+
+/// Magic of the chart cache file.
+pub const CHART_CACHE_MAGIC: [u8; 4] = *b"AIMV";
+
+/// Versions of the chart cache written before it had a header, see 11.
+pub const CHART_CACHE_LEGACY_VERSIONS: RangeInclusive<u32> = 1..=10;
+
 impl Chart {
-    // Manual serialization: write Chart to bytes
+    // Manual serialization: write what the user set up on the chart, the
+    // bars come from the candle store when the workspace loads again
     pub fn write_to_bytes(&self, buf: &mut Vec<u8>) {
-        // stock_name: String
-        let name_bytes = self.stock_name.as_bytes();
-        let name_len = name_bytes.len() as u32;
-        buf.extend_from_slice(&name_len.to_le_bytes());
-        buf.extend_from_slice(name_bytes);
+        write_label(buf, &self.stock_name);
+        write_label(buf, &self.time_frame.to_string());
+        buf.push(self.adjusted as u8);
 
-        // company_info: CompanyInfo
-        buf.extend_from_slice(&self.company_info.roe.to_le_bytes());
-        buf.extend_from_slice(&self.company_info.roa.to_le_bytes());
-        buf.extend_from_slice(&self.company_info.pe.to_le_bytes());
-        buf.extend_from_slice(&self.company_info.pb.to_le_bytes());
-        buf.extend_from_slice(&self.company_info.eps.to_le_bytes());
-
-        // candle_data: CandleDataVec (assume Vec<CandleData>)
-        let candle_count = self.candle_data.len() as u32;
-        buf.extend_from_slice(&candle_count.to_le_bytes());
-        for _candle in &self.candle_data {
-            _candle.write_to_bytes(buf);
+        // chart_type: label and box size
+        write_label(buf, self.chart_type.label());
+        write_label(
+            buf,
+            &self
+                .chart_type
+                .box_size()
+                .map(|box_size| box_size.label())
+                .unwrap_or_default(),
+        );
+
+        // indicators: Vec<Study>
+        buf.extend_from_slice(&(self.indicators.len() as u32).to_le_bytes());
+        for study in &self.indicators {
+            write_study(buf, study);
         }
 
-        // chart_data: ChartData
-        self.chart_data.write_to_bytes(buf);
+        // comparisons: symbols only, their bars are fetched again
+        buf.extend_from_slice(&(self.comparisons.len() as u32).to_le_bytes());
+        for cmp in &self.comparisons {
+            write_label(buf, &cmp.symbol);
+        }
+        buf.push(self.compare_ratio as u8);
 
-        // current_draw_data: DrawObject
-        self.current_draw_data.write_to_bytes(buf);
+        // viewport: times of the edges, so it survives a longer history
+        let viewport = self.viewport();
+        buf.extend_from_slice(&viewport.from.to_le_bytes());
+        buf.extend_from_slice(&viewport.to.to_le_bytes());
+        buf.extend_from_slice(&viewport.y_offset_min.to_le_bytes());
+        buf.extend_from_slice(&viewport.y_offset_max.to_le_bytes());
 
-        // moving_object: DrawObject
-        self.moving_object.write_to_bytes(buf);
+        // drawings: shape and colour, anchor times and style of each
+        buf.extend_from_slice(&(self.drawings.len() as u32).to_le_bytes());
+        for drawing in &self.drawings {
+            drawing
+                .place(&self.candle_data, PriceScale::Linear)
+                .write_to_bytes(buf);
+            let times = drawing.times();
+            buf.extend_from_slice(&(times.len() as u32).to_le_bytes());
+            for time in times {
+                buf.extend_from_slice(&time.to_le_bytes());
+            }
+            drawing.style().write_to_bytes(buf);
+        }
 
-        // all_draw_data: Vec<DrawObject>
-        let draw_count = self.all_draw_data.len() as u32;
-        buf.extend_from_slice(&draw_count.to_le_bytes());
-        for _draw in &self.all_draw_data {
-            _draw.write_to_bytes(buf);
+        // price scale: label, then the auto-fit and lock flags
+        write_label(buf, self.price_scale.label());
+        buf.push(self.auto_fit as u8 | (self.lock_scale as u8) << 1);
+    }
+
+    // Manual deserialization: read a chart written by `version`, 12 or
+    // later, which waits for its bars with the view to show once they load
+    fn read_workspace(data: &[u8], version: u32) -> Option<(Self, usize)> {
+        let mut pos = 0;
+        let (stock_name, used) = read_label(data)?;
+        pos += used;
+        let (time_frame, used) = read_label(&data[pos..])?;
+        pos += used;
+        let adjusted = *data.get(pos)? != 0;
+        pos += 1;
+
+        // chart_type: label and box size
+        let (chart_type, used) = read_label(&data[pos..])?;
+        pos += used;
+        let (box_size, used) = read_label(&data[pos..])?;
+        pos += used;
+
+        // indicators: Vec<Study>
+        let count = u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().unwrap()) as usize;
+        pos += 4;
+        let mut indicators = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (study, used) = read_study(&data[pos..], version)?;
+            indicators.push(study);
+            pos += used;
         }
 
-        // backup_points: Vec<Point>
-        let point_count = self.backup_points.len() as u32;
-        buf.extend_from_slice(&point_count.to_le_bytes());
-        for point in &self.backup_points {
-            buf.extend_from_slice(&point.0.to_le_bytes());
-            buf.extend_from_slice(&point.1.to_le_bytes());
+        // comparisons: symbols only
+        let count = u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().unwrap()) as usize;
+        pos += 4;
+        let mut compare_symbols = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (symbol, used) = read_label(&data[pos..])?;
+            compare_symbols.push(symbol);
+            pos += used;
         }
+        let compare_ratio = *data.get(pos)? != 0;
+        pos += 1;
 
-        // backup_chart_offset: Option<BackupChartOffset>
-        match &self.backup_chart_offset {
-            Some(offset) => {
-                buf.push(1);
-                buf.extend_from_slice(&offset.y_offset_min.to_le_bytes());
-                buf.extend_from_slice(&offset.y_offset_max.to_le_bytes());
-                buf.extend_from_slice(&offset.x_offset_min.to_le_bytes());
-                buf.extend_from_slice(&offset.x_offset_max.to_le_bytes());
-            }
-            None => buf.push(0),
+        // viewport: times of the edges and the price range
+        let bytes = data.get(pos..pos + 8 * 2 + 4 * 2)?;
+        let viewport = Viewport {
+            from: f64::from_le_bytes(bytes[0..8].try_into().unwrap()),
+            to: f64::from_le_bytes(bytes[8..16].try_into().unwrap()),
+            y_offset_min: f32::from_le_bytes(bytes[16..20].try_into().unwrap()),
+            y_offset_max: f32::from_le_bytes(bytes[20..24].try_into().unwrap()),
+        };
+        pos += bytes.len();
+
+        // drawings: shape and colour, anchor times and style of each
+        let count = u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().unwrap()) as usize;
+        pos += 4;
+        let mut drawings = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (object, used) = DrawObject::read_from_bytes(&data[pos..])?;
+            pos += used;
+            let times_len =
+                u32::from_le_bytes(data.get(pos..pos + 4)?.try_into().unwrap()) as usize;
+            pos += 4;
+            let times = data
+                .get(pos..pos + 8 * times_len)?
+                .chunks_exact(8)
+                .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()))
+                .collect();
+            pos += 8 * times_len;
+            let mut drawing = AnchoredDrawing::from_parts(object, times)?;
+            let (style, used) = DrawStyle::read_from_bytes(&data[pos..])?;
+            drawing.set_style(style);
+            pos += used;
+            drawings.push(drawing);
         }
 
-        // is_in_object: (usize, bool, bool)
-        buf.extend_from_slice(&(self.is_in_object.0 as u64).to_le_bytes());
-        buf.push(self.is_in_object.1 as u8);
-        buf.push(self.is_in_object.2 as u8);
+        // price scale: linear before version 13
+        let (price_scale, flags) = if version >= 13 {
+            let (label, used) = read_label(&data[pos..])?;
+            pos += used;
+            let flags = *data.get(pos)?;
+            pos += 1;
+            (PriceScale::parse(&label).unwrap_or_default(), flags)
+        } else {
+            (PriceScale::Linear, 0)
+        };
 
-        // delta: (f32, f32)
-        buf.extend_from_slice(&self.delta.0.to_le_bytes());
-        buf.extend_from_slice(&self.delta.1.to_le_bytes());
+        let mut chart = Self {
+            stock_name,
+            company_info: CompanyInfo::default(),
+            candle_data: Vec::new(),
+            source_data: Vec::new(),
+            chart_type: ChartType::parse(&chart_type, &box_size).unwrap_or_default(),
+            series_box: None,
+            chart_data: ChartData::default(),
+            current_draw_data: DrawObject::default(),
+            moving_object: DrawObject::default(),
+            all_draw_data: Vec::new(),
+            drawings,
+            backup_points: Vec::new(),
+            backup_chart_offset: None,
+            is_in_object: (0, false, false),
+            delta: (0.0, 0.0),
+            time_frame: Timeframe::parse(&time_frame).unwrap_or_default(),
+            adjusted,
+            corporate_actions: Vec::new(),
+            indicators,
+            resizing_pane: None,
+            selected: None,
+            dragging_handle: None,
+            history: DrawHistory::default(),
+            comparisons: Vec::new(),
+            compare_ratio: false,
+            price_scale,
+            auto_fit: flags & 1 != 0,
+            lock_scale: flags & 2 != 0,
+            pending_view: Some(viewport),
+            dirty: false,
+        };
+        chart.set_comparisons(&compare_symbols, compare_ratio);
+        Some((chart, pos))
     }
 
-    // Manual deserialization: read Chart from bytes
-    pub fn read_from_bytes(data: &[u8]) -> Option<(Self, usize)> {
+    // Manual deserialization: read Chart written by cache `version` from bytes.
+    // Versions before 12 held the bars and the UI state as well
+    pub fn read_from_bytes(data: &[u8], version: u32) -> Option<(Self, usize)> {
+        if version >= 12 {
+            return Self::read_workspace(data, version);
+        }
         let mut pos = 0;
         // stock_name: String
         if data.len() < pos + 4 {
@@ -111,7 +264,7 @@ impl Chart {
             eps,
         };
 
-        // candle_data: CandleDataVec
+        // candle_data: CandleDataVec, the bars before the chart type is applied
         if data.len() < pos + 4 {
             return None;
         }
@@ -238,30 +391,273 @@ impl Chart {
         pos += 4;
         let delta = (dx, dy);
 
-        Some((
-            Self {
-                stock_name,
-                company_info,
-                candle_data,
-                chart_data,
-                current_draw_data,
-                moving_object,
-                all_draw_data,
-                backup_points,
-                backup_chart_offset,
-                is_in_object,
-                delta,
-            },
-            pos,
-        ))
+        // time_frame: charts cached before version 2 were all daily
+        let time_frame = if version >= 2 {
+            if data.len() < pos + 4 {
+                return None;
+            }
+            let label_len = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            pos += 4;
+            if data.len() < pos + label_len {
+                return None;
+            }
+            let label = String::from_utf8_lossy(&data[pos..pos + label_len]);
+            pos += label_len;
+            Timeframe::parse(&label).unwrap_or_default()
+        } else {
+            Timeframe::DAILY
+        };
+
+        // adjusted, corporate_actions: charts cached before version 3 show raw prices
+        let (adjusted, corporate_actions) = if version >= 3 {
+            if data.len() < pos + 1 + 4 {
+                return None;
+            }
+            let adjusted = data[pos] != 0;
+            pos += 1;
+            let action_count = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            pos += 4;
+            let mut actions = Vec::with_capacity(action_count);
+            for _ in 0..action_count {
+                let (action, used) = read_action(&data[pos..], &stock_name)?;
+                actions.push(action);
+                pos += used;
+            }
+            (adjusted, actions)
+        } else {
+            (false, Vec::new())
+        };
+
+        // indicators: charts cached before version 4 drew the default moving averages
+        let indicators = if version >= 4 {
+            if data.len() < pos + 4 {
+                return None;
+            }
+            let count = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            pos += 4;
+            let mut indicators = Vec::with_capacity(count);
+            for _ in 0..count {
+                let (study, used) = read_study(&data[pos..], version)?;
+                indicators.push(study);
+                pos += used;
+            }
+            indicators
+        } else {
+            default_indicators().into_iter().map(Study::new).collect()
+        };
+
+        // drawings: charts cached before version 6 stored candle indices of
+        // candle_data only, so pin them to the times of those candles
+        let mut drawings = if version >= 6 {
+            let mut drawings = Vec::with_capacity(all_draw_data.len());
+            for object in &all_draw_data {
+                if data.len() < pos + 4 {
+                    return None;
+                }
+                let count = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+                pos += 4;
+                if data.len() < pos + 8 * count {
+                    return None;
+                }
+                let times = data[pos..pos + 8 * count]
+                    .chunks_exact(8)
+                    .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()))
+                    .collect();
+                pos += 8 * count;
+                drawings.push(AnchoredDrawing::from_parts(object.clone(), times)?);
+            }
+            drawings
+        } else {
+            all_draw_data
+                .iter()
+                .map(|object| {
+                    AnchoredDrawing::new(object.clone(), &candle_data, PriceScale::Linear)
+                })
+                .collect()
+        };
+
+        // drawings: style of each drawing, default before version 7
+        if version >= 7 {
+            for drawing in &mut drawings {
+                let (style, used) = DrawStyle::read_from_bytes(&data[pos..])?;
+                drawing.set_style(style);
+                pos += used;
+            }
+        }
+
+        // comparisons: none before version 9
+        let mut compare_symbols = Vec::new();
+        let mut compare_ratio = false;
+        if version >= 9 {
+            if data.len() < pos + 4 {
+                return None;
+            }
+            let count = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            pos += 4;
+            for _ in 0..count {
+                if data.len() < pos + 4 {
+                    return None;
+                }
+                let len = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+                pos += 4;
+                let symbol = data.get(pos..pos + len)?;
+                compare_symbols.push(String::from_utf8_lossy(symbol).to_string());
+                pos += len;
+            }
+            compare_ratio = *data.get(pos)? != 0;
+            pos += 1;
+        }
+
+        // chart_type: candles before version 10
+        let chart_type = if version >= 10 {
+            let mut labels = Vec::with_capacity(2);
+            for _ in 0..2 {
+                if data.len() < pos + 4 {
+                    return None;
+                }
+                let len = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+                pos += 4;
+                let label = data.get(pos..pos + len)?;
+                labels.push(String::from_utf8_lossy(label).to_string());
+                pos += len;
+            }
+            ChartType::parse(&labels[0], &labels[1]).unwrap_or_default()
+        } else {
+            ChartType::Candles
+        };
+
+        let mut chart = Self {
+            stock_name,
+            company_info,
+            candle_data: Vec::new(),
+            source_data: candle_data,
+            chart_type,
+            series_box: None,
+            chart_data,
+            current_draw_data,
+            moving_object,
+            all_draw_data,
+            drawings,
+            backup_points,
+            backup_chart_offset,
+            is_in_object,
+            delta,
+            time_frame,
+            adjusted,
+            corporate_actions,
+            indicators,
+            resizing_pane: None,
+            selected: None,
+            dragging_handle: None,
+            history: DrawHistory::default(),
+            comparisons: Vec::new(),
+            compare_ratio: false,
+            price_scale: PriceScale::default(),
+            auto_fit: false,
+            lock_scale: false,
+            pending_view: None,
+            dirty: false,
+        };
+        chart.transform_source();
+        chart.set_comparisons(&compare_symbols, compare_ratio);
+        Some((chart, pos))
     }
+}
 
-    // Helper for hashing: serialize to bytes
-    pub fn to_bytes(&self) -> Vec<u8> {
-        let mut buf = Vec::new();
-        self.write_to_bytes(&mut buf);
-        buf
+// Manual serialization for labels: length, then UTF-8 bytes
+pub(crate) fn write_label(buf: &mut Vec<u8>, label: &str) {
+    buf.extend_from_slice(&(label.len() as u32).to_le_bytes());
+    buf.extend_from_slice(label.as_bytes());
+}
+
+pub(crate) fn read_label(data: &[u8]) -> Option<(String, usize)> {
+    if data.len() < 4 {
+        return None;
     }
+    let len = u32::from_le_bytes(data[0..4].try_into().unwrap()) as usize;
+    let label = data.get(4..4 + len)?;
+    Some((String::from_utf8_lossy(label).to_string(), 4 + len))
+}
+
+const ACTION_LEN: usize = 4 + 1 + 8 * 2;
+
+// Manual deserialization for CorporateAction: ex-date, kind tag, two values
+fn read_action(data: &[u8], symbol: &str) -> Option<(CorporateAction, usize)> {
+    if data.len() < ACTION_LEN {
+        return None;
+    }
+    let days = i32::from_le_bytes(data[0..4].try_into().unwrap());
+    let ex_date = NaiveDate::from_num_days_from_ce_opt(days)?;
+    let a = f64::from_le_bytes(data[5..13].try_into().unwrap());
+    let b = f64::from_le_bytes(data[13..21].try_into().unwrap());
+    let kind = match data[4] {
+        0 => ActionKind::CashDividend { amount: a },
+        1 => ActionKind::StockDividend { ratio: a },
+        2 => ActionKind::Split { ratio: a },
+        3 => ActionKind::Rights { ratio: a, price: b },
+        _ => return None,
+    };
+    Some((
+        CorporateAction {
+            symbol: symbol.to_string(),
+            ex_date,
+            kind,
+        },
+        ACTION_LEN,
+    ))
+}
+
+// Manual serialization for Study: kind, period, source tag, multiplier,
+// colour and sub-pane height
+fn write_study(buf: &mut Vec<u8>, study: &Study) {
+    let config = &study.config;
+    buf.extend_from_slice(&(config.kind.len() as u32).to_le_bytes());
+    buf.extend_from_slice(config.kind.as_bytes());
+    buf.extend_from_slice(&(config.period as u32).to_le_bytes());
+    let source = Source::ALL.iter().position(|s| *s == config.source);
+    buf.push(source.unwrap_or_default() as u8);
+    buf.extend_from_slice(&config.multiplier.to_le_bytes());
+    buf.push(config.color.0);
+    buf.push(config.color.1);
+    buf.push(config.color.2);
+    buf.extend_from_slice(&study.pane_height.to_le_bytes());
+}
+
+fn read_study(data: &[u8], version: u32) -> Option<(Study, usize)> {
+    if data.len() < 4 {
+        return None;
+    }
+    let kind_len = u32::from_le_bytes(data[0..4].try_into().unwrap()) as usize;
+    let mut pos = 4;
+    if data.len() < pos + kind_len + 4 + 1 + 4 + 3 {
+        return None;
+    }
+    let kind = String::from_utf8_lossy(&data[pos..pos + kind_len]).to_string();
+    pos += kind_len;
+    let period = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+    pos += 4;
+    let source = *Source::ALL.get(data[pos] as usize)?;
+    pos += 1;
+    let multiplier = f32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+    pos += 4;
+    let color = RGBColor(data[pos], data[pos + 1], data[pos + 2]);
+    pos += 3;
+    let mut study = Study::new(IndicatorConfig::new(
+        &kind, period, source, multiplier, color,
+    ));
+    // sub-pane height: since version 5
+    if version >= 5 {
+        if data.len() < pos + 4 {
+            return None;
+        }
+        let pane_height = f32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+        if !(MIN_PANE_HEIGHT..=MAX_PANES_HEIGHT).contains(&pane_height) {
+            return None;
+        }
+        study.pane_height = pane_height;
+        pos += 4;
+    }
+    Some((study, pos))
 }
 
 // Manual serialization for ChartData
@@ -322,6 +718,12 @@ impl ChartData {
         pos += 4;
         let width_ui = i32::from_le_bytes(data[pos..pos + 4].try_into().ok()?);
         pos += 4;
+        // Not cached: the window height, or the price pane while there is none
+        let image_height = if height_ui > 0 {
+            height_ui as u32
+        } else {
+            height
+        };
         // Color and type can be added if needed
         let ui_data = UiData {
             position_x,
@@ -348,6 +750,7 @@ impl ChartData {
                 range_y,
                 width,
                 height,
+                image_height,
                 candle_distance,
                 zoom_x,
                 zoom_y,
@@ -356,31 +759,96 @@ impl ChartData {
             pos,
         ))
     }
-    pub fn write_to_bytes(&self, buf: &mut Vec<u8>) {
-        buf.extend_from_slice(&self.y_offset_min.to_le_bytes());
-        buf.extend_from_slice(&self.y_offset_max.to_le_bytes());
-        buf.extend_from_slice(&self.x_offset_min.to_le_bytes());
-        buf.extend_from_slice(&self.x_offset_max.to_le_bytes());
-        buf.extend_from_slice(&self.range_x.to_le_bytes());
-        buf.extend_from_slice(&self.range_y.to_le_bytes());
-        buf.extend_from_slice(&self.width.to_le_bytes());
-        buf.extend_from_slice(&self.height.to_le_bytes());
-        buf.extend_from_slice(&self.candle_distance.to_le_bytes());
-        buf.extend_from_slice(&self.zoom_x.to_le_bytes());
-        buf.extend_from_slice(&self.zoom_y.to_le_bytes());
-        // Serialize ui_data (basic fields only, expand as needed)
-        buf.extend_from_slice(&self.ui_data.position_x.to_le_bytes());
-        buf.extend_from_slice(&self.ui_data.position_y.to_le_bytes());
-        buf.extend_from_slice(&self.ui_data.press_x.to_le_bytes());
-        buf.extend_from_slice(&self.ui_data.press_y.to_le_bytes());
-        buf.extend_from_slice(&self.ui_data.move_x.to_le_bytes());
-        buf.extend_from_slice(&self.ui_data.move_y.to_le_bytes());
-        buf.extend_from_slice(&self.ui_data.zoom.to_le_bytes());
-        buf.push(self.ui_data.is_clean as u8);
-        buf.push(self.ui_data.is_undo as u8);
-        buf.push(self.ui_data.is_release as u8);
-        buf.extend_from_slice(&self.ui_data.height.to_le_bytes());
-        buf.extend_from_slice(&self.ui_data.width.to_le_bytes());
-        // Color and type can be added if needed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{draw::DrawType, indicator::DEFAULT_PANE_HEIGHT, tests::bars};
+
+    fn study_bytes(pane_height: f32) -> Vec<u8> {
+        let mut study = Study::new(IndicatorConfig::parse("RSI 14").unwrap());
+        study.pane_height = pane_height;
+        let mut buf = Vec::new();
+        write_study(&mut buf, &study);
+        buf
+    }
+
+    #[test]
+    fn test_study_keeps_its_pane_height() {
+        let buf = study_bytes(0.3);
+        let (study, used) = read_study(&buf, CHART_CACHE_VERSION).unwrap();
+        assert_eq!(used, buf.len());
+        assert_eq!(study.pane_height, 0.3);
+
+        // Before version 5 the height was not written
+        let (study, used) = read_study(&buf[..buf.len() - 4], 4).unwrap();
+        assert_eq!(used, buf.len() - 4);
+        assert_eq!(study.pane_height, DEFAULT_PANE_HEIGHT);
+    }
+
+    #[test]
+    fn test_study_with_a_bad_pane_height_is_damaged() {
+        for pane_height in [f32::NAN, f32::INFINITY, -0.2, 0.0, MAX_PANES_HEIGHT + 0.1] {
+            assert!(read_study(&study_bytes(pane_height), CHART_CACHE_VERSION).is_none());
+        }
+    }
+
+    /// A chart as version 5 wrote it: the bars, the view and UI state, and
+    /// drawings at candle indices.
+    fn version_5_chart(candles: &[CandleData], drawings: &[DrawObject]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        write_label(&mut buf, "TEST");
+        buf.extend_from_slice(&[0; 8 * 5]); // company info
+        buf.extend_from_slice(&(candles.len() as u32).to_le_bytes());
+        for candle in candles {
+            candle.write_to_bytes(&mut buf);
+        }
+        buf.extend_from_slice(&[0; 4 * 11 + 4 * 7 + 3 + 4 * 2]); // chart and UI data
+        DrawObject::default().write_to_bytes(&mut buf); // current drawing
+        DrawObject::default().write_to_bytes(&mut buf); // moving object
+        buf.extend_from_slice(&(drawings.len() as u32).to_le_bytes());
+        for object in drawings {
+            object.write_to_bytes(&mut buf);
+        }
+        buf.extend_from_slice(&0u32.to_le_bytes()); // backup points
+        buf.push(0); // no backup offset
+        buf.extend_from_slice(&[0; 8 + 1 + 1 + 4 * 2]); // is_in_object and delta
+        write_label(&mut buf, &Timeframe::DAILY.to_string());
+        buf.push(0); // not adjusted
+        buf.extend_from_slice(&0u32.to_le_bytes()); // corporate actions
+        buf.extend_from_slice(&1u32.to_le_bytes());
+        write_study(
+            &mut buf,
+            &Study::new(IndicatorConfig::parse("RSI 14").unwrap()),
+        );
+        buf
+    }
+
+    #[test]
+    fn test_version_5_drawings_move_onto_candle_times() {
+        let candles = bars(&[10.0, 11.0, 12.0, 13.0]);
+        let line = DrawObject::new(
+            DrawType::Line(((1.0, 10.5), (2.5, 12.5))),
+            RGBColor(1, 2, 3),
+        );
+        let buf = version_5_chart(&candles, &[line]);
+
+        let (chart, used) = Chart::read_from_bytes(&buf, 5).unwrap();
+        assert_eq!(used, buf.len());
+        assert_eq!(chart.indicators.len(), 1);
+        assert_eq!(chart.source_data, candles);
+        // Fields added since version 5 get the defaults they had before
+        assert_eq!(chart.drawings[0].style(), DrawStyle::default());
+        assert!(chart.comparisons.is_empty() && !chart.compare_ratio);
+        assert_eq!(chart.chart_type, ChartType::Candles);
+        assert_eq!(chart.price_scale, PriceScale::Linear);
+        let times = chart.drawings[0].times();
+        let day = |i: usize| candles[i].time.timestamp() as f64;
+        assert_eq!(times, [day(1), (day(2) + day(3)) / 2.0]);
+        // And back on the same candles
+        let placed = chart.drawings[0].place(&chart.candle_data, chart.price_scale);
+        assert_eq!(placed.handles(), vec![(1.0, 10.5), (2.5, 12.5)]);
+        assert_eq!(placed.color, RGBColor(1, 2, 3));
     }
 }
@@ -1,5 +1,5 @@
-use aim_data::OHLCData;
-use chrono::{DateTime, Duration, Utc};
+use aim_data::{CorporateAction, OHLCData, Timeframe, corporate_actions, resample};
+use chrono::{DateTime, Utc};
 use log::debug;
 
 use plotters::{
@@ -9,24 +9,34 @@ use plotters::{
     drawing::IntoDrawingArea,
     element::{CandleStick, PathElement, Polygon},
     prelude::{Cartesian2d, ChartContext, Rectangle, Text},
-    series::LineSeries,
+    series::{DashedLineSeries, LineSeries},
     style::{BLUE, Color, IntoFont, RED, RGBColor, ShapeStyle, WHITE, full_palette::GREY},
 };
 use slint::SharedPixelBuffer;
 
-const UP_COLOR: RGBColor = RGBColor(0x2E, 0xBD, 0x85);
-const DOWN_COLOR: RGBColor = RGBColor(0xF6, 0x46, 0x5D);
+pub(crate) const UP_COLOR: RGBColor = RGBColor(0x2E, 0xBD, 0x85);
+pub(crate) const DOWN_COLOR: RGBColor = RGBColor(0xF6, 0x46, 0x5D);
 const NO_CHANGE_COLOR: RGBColor = RGBColor(0xFF, 0xEB, 0x3B);
-const THREAD_HOLD: f32 = 2.0; // in pixel
+pub(crate) const THREAD_HOLD: f32 = 2.0; // in pixel
 const DEFAULT_CANDLE_NUMER: usize = 300; // default number of candles to show
 
-use crate::{MouseType, UiData};
+use crate::{
+    MouseType, PriceScale, UiData, Viewport,
+    chart_type::ChartType,
+    compare::Comparison,
+    history::{DrawCommand, DrawHistory},
+    indicator::{IndicatorConfig, Study, default_indicators, format_indicators, parse_indicators},
+};
 
 use super::{
     CandleData, CandleDataVec,
     company_info::CompanyInfo,
     convert_candlesticks,
-    draw::{DrawObject, DrawType, Point},
+    draw::{
+        AnchoredDrawing, DEFAULT_TEXT_SIZE, DrawObject, DrawStyle, DrawType, FIB_EXTENSION_LEVELS,
+        FIB_RETRACEMENT_LEVELS, Point, parse_levels,
+    },
+    merge_candles,
 };
 
 #[derive(Debug, Clone)]
@@ -46,7 +56,8 @@ pub struct ChartData {
     pub range_x: i32,         // range value of x axis
     pub range_y: i32,         // range value of y axis
     pub width: u32,           // the width of the chart in pixels
-    pub height: u32,          // the height of the chart in pixels
+    pub height: u32,          // the height of the price pane in pixels
+    pub image_height: u32,    // the height of the whole image, price pane and sub-panes
     pub candle_distance: f32, // distance between 2 candle sticks
     pub zoom_x: f32,          // zoom factor in x direction
     pub zoom_y: f32,          // zoom factor in y direction
@@ -64,6 +75,7 @@ impl Default for ChartData {
             range_y: 60,
             width: 1200,
             height: 800,
+            image_height: 800,
             candle_distance: 0.0,
             zoom_x: 0.0,
             zoom_y: 0.0,
@@ -76,56 +88,257 @@ impl Default for ChartData {
 pub struct Chart {
     pub stock_name: String,
     pub company_info: CompanyInfo,
-    pub candle_data: CandleDataVec, // Candle sticks data
+    pub candle_data: CandleDataVec, // Candle sticks data, as drawn by `chart_type`
+    pub source_data: CandleDataVec, // bars at `time_frame` before `chart_type` is applied
+    pub chart_type: ChartType,
+    pub(crate) series_box: Option<f32>, // box size of a Renko or P&F `candle_data`
     pub chart_data: ChartData,
     pub current_draw_data: DrawObject,
     pub moving_object: DrawObject,
-    pub all_draw_data: Vec<DrawObject>,
+    pub all_draw_data: Vec<DrawObject>, // `drawings` placed on the current candles
+    pub drawings: Vec<AnchoredDrawing>, // user drawings pinned to candle times
     pub backup_points: Vec<Point>,
     pub backup_chart_offset: Option<BackupChartOffset>,
     pub is_in_object: (usize, bool, bool),
     pub delta: (f32, f32),
+    pub time_frame: Timeframe, // bars are resampled to this from the base resolution
+    pub adjusted: bool,        // back-adjust prices for corporate actions
+    pub corporate_actions: Vec<CorporateAction>,
+    pub indicators: Vec<Study>, // drawn over the prices or in sub-panes, in this order
+    pub resizing_pane: Option<(usize, f32)>, // sub-pane being resized and its height at the press
+    pub selected: Option<usize>, // index of the selected drawing
+    pub dragging_handle: Option<usize>, // handle of the selected drawing being dragged
+    pub history: DrawHistory,   // undo and redo of the edits to `drawings`
+    pub comparisons: Vec<Comparison>, // other symbols drawn over the prices
+    pub compare_ratio: bool,    // draw the ratios to the comparisons instead
+    pub price_scale: PriceScale, // how prices map to the y axis
+    pub auto_fit: bool,         // fit the y axis to the visible bars on every render
+    pub lock_scale: bool,       // keep the y axis as it is
+    pub pending_view: Option<Viewport>, // view restored from the workspace, shown once bars load
+    pub(crate) dirty: bool,     // the saved workspace changed, see `take_dirty`
+}
+
+/// Turn provider bars into chart candles: back-adjust when `actions` is
+/// given, then resample to `time_frame`.
+pub(crate) fn prepare_candles(
+    stock: &OHLCData,
+    time_frame: Timeframe,
+    actions: Option<&[CorporateAction]>,
+) -> CandleDataVec {
+    let mut candles = stock.to_candlesticks();
+    if let Some(actions) = actions {
+        candles = corporate_actions::adjust(&candles, actions);
+    }
+    convert_candlesticks(stock.symbol.len() == 3, resample(&candles, time_frame))
 }
 
 #[allow(clippy::type_complexity, clippy::too_many_arguments)]
 impl Chart {
     // new_dafault to set value default for inititalization
-    pub fn new_default(stock_name: String, stock: OHLCData, company_info: CompanyInfo) -> Self {
-        let data = convert_candlesticks(stock.symbol.len() == 3, stock.to_candlesticks());
-        let (x_offset_min, data_slice) = if data.len() < DEFAULT_CANDLE_NUMER {
-            (0 as f32, &data[0..(data.len() - 1)])
-        } else {
-            (
-                (data.len() - DEFAULT_CANDLE_NUMER) as f32,
-                &data[(data.len() - DEFAULT_CANDLE_NUMER)..(data.len() - 1)],
-            )
-        };
-        let chart_data = ChartData {
-            x_offset_min,
-            x_offset_max: (data.len() - 1) as f32,
-            y_offset_min: Self::get_y_min(data_slice.to_vec()),
-            y_offset_max: Self::get_y_max(data_slice.to_vec()),
-            ..Default::default()
-        };
+    pub fn new_default(
+        stock_name: String,
+        stock: OHLCData,
+        company_info: CompanyInfo,
+        time_frame: Timeframe,
+    ) -> Self {
+        let data = prepare_candles(&stock, time_frame, None);
+        let chart_data = Self::latest_view(&data, PriceScale::default());
 
         Self {
             stock_name,
             company_info,
             candle_data: data.clone(),
+            source_data: data,
+            chart_type: ChartType::Candles,
+            series_box: None,
             chart_data,
             moving_object: DrawObject::default(),
             current_draw_data: DrawObject::default(),
             all_draw_data: Vec::default(),
+            drawings: Vec::default(),
             backup_points: Vec::default(),
             backup_chart_offset: None,
             is_in_object: (0, false, false),
             delta: (0.0, 0.0),
+            time_frame,
+            adjusted: false,
+            corporate_actions: Vec::new(),
+            indicators: default_indicators().into_iter().map(Study::new).collect(),
+            resizing_pane: None,
+            selected: None,
+            dragging_handle: None,
+            history: DrawHistory::default(),
+            comparisons: Vec::new(),
+            compare_ratio: false,
+            price_scale: PriceScale::default(),
+            auto_fit: false,
+            lock_scale: false,
+            pending_view: None,
+            dirty: false,
         }
     }
 
-    pub fn update_candle_data(&mut self, stock: OHLCData) {
-        self.candle_data =
-            convert_candlesticks(self.stock_name.len() == 3, stock.to_candlesticks());
+    /// Whether anything saved with the workspace changed since the last
+    /// call: the time frame, prices, chart type, indicators, comparisons,
+    /// view or drawings. New bars do not count.
+    pub fn take_dirty(&mut self) -> bool {
+        let drawings = self.history.take_changed();
+        std::mem::take(&mut self.dirty) || drawings
+    }
+
+    /// Note a change of what the workspace saves.
+    pub(crate) fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Actions used by the next [`update_candle_data`](Self::update_candle_data)
+    /// when `adjusted` is set.
+    fn price_actions(&self) -> Option<&[CorporateAction]> {
+        self.adjusted.then_some(self.corporate_actions.as_slice())
+    }
+
+    /// Switch between raw and back-adjusted prices. Takes effect on the next
+    /// [`update_candle_data`](Self::update_candle_data).
+    pub fn set_price_adjustment(&mut self, adjusted: bool, actions: Vec<CorporateAction>) {
+        if adjusted != self.adjusted {
+            self.mark_dirty();
+        }
+        self.adjusted = adjusted;
+        self.corporate_actions = actions;
+    }
+
+    /// Settings of the chart's indicators, in drawing order.
+    pub fn indicator_configs(&self) -> Vec<IndicatorConfig> {
+        self.indicators
+            .iter()
+            .map(|study| study.config.clone())
+            .collect()
+    }
+
+    /// Replace the chart's indicators. Values are computed on the next render.
+    ///
+    /// A pane keeps its height when its kind stays in the list.
+    pub fn set_indicators(&mut self, configs: Vec<IndicatorConfig>) {
+        let previous = std::mem::take(&mut self.indicators);
+        self.indicators = configs
+            .into_iter()
+            .map(|config| {
+                let mut study = Study::new(config);
+                if let Some(old) = previous
+                    .iter()
+                    .find(|old| old.config.kind == study.config.kind)
+                {
+                    study.pane_height = old.pane_height;
+                }
+                study
+            })
+            .collect();
+        self.resizing_pane = None;
+        self.mark_dirty();
+    }
+
+    /// Indicator list in the text form the user edits, see
+    /// [`parse_indicators`](crate::indicator::parse_indicators).
+    pub fn indicator_spec(&self) -> String {
+        format_indicators(&self.indicator_configs())
+    }
+
+    /// Show the last `DEFAULT_CANDLE_NUMER` candles of `data` on `scale`.
+    pub(crate) fn latest_view(data: &[CandleData], scale: PriceScale) -> ChartData {
+        let (x_offset_min, data_slice) = if data.len() < DEFAULT_CANDLE_NUMER {
+            (0 as f32, &data[0..(data.len() - 1)])
+        } else {
+            (
+                (data.len() - DEFAULT_CANDLE_NUMER) as f32,
+                &data[(data.len() - DEFAULT_CANDLE_NUMER)..(data.len() - 1)],
+            )
+        };
+        ChartData {
+            x_offset_min,
+            x_offset_max: (data.len() - 1) as f32,
+            y_offset_min: scale.to_axis(Self::get_y_min(data_slice.to_vec())),
+            y_offset_max: scale.to_axis(Self::get_y_max(data_slice.to_vec())),
+            ..Default::default()
+        }
+    }
+
+    /// Replace the whole history, e.g. after the time frame changed.
+    ///
+    /// `stock` holds bars of [`Timeframe::base_resolution`] of `time_frame`.
+    /// On a new time frame the view jumps back to the latest candles, since
+    /// the old offsets index bars of a different length. Drawings are pinned
+    /// to times and find their place on the new candles when rendered.
+    pub fn update_candle_data(&mut self, stock: OHLCData, time_frame: Timeframe) {
+        let candle_data = prepare_candles(&stock, time_frame, self.price_actions());
+        if candle_data.is_empty() {
+            return;
+        }
+        // A chart restored from the workspace gets its bars here for the first time
+        let first_load = self.source_data.is_empty();
+        let pending_view = self
+            .pending_view
+            .take()
+            .filter(|_| first_load && time_frame == self.time_frame);
+        if time_frame != self.time_frame || first_load {
+            self.chart_data = ChartData {
+                ui_data: self.chart_data.ui_data.clone(),
+                ..Self::latest_view(&candle_data, self.price_scale)
+            };
+            self.backup_chart_offset = None;
+        }
+        if time_frame != self.time_frame {
+            self.time_frame = time_frame;
+            self.mark_dirty();
+        }
+        self.source_data = candle_data;
+        self.transform_source();
+        if let Some(viewport) = pending_view {
+            self.show_viewport(viewport);
+        }
+        for study in &mut self.indicators {
+            study.reset();
+        }
+    }
+
+    /// Time of the oldest cached candle, where the bars of comparisons start.
+    pub fn first_candle_time(&self) -> Option<DateTime<Utc>> {
+        self.source_data.first().map(|candle| candle.time)
+    }
+
+    /// Time of the newest cached candle, where an incremental fetch starts.
+    pub fn last_candle_time(&self) -> Option<DateTime<Utc>> {
+        self.source_data.last().map(|candle| candle.time)
+    }
+
+    /// Merge bars fetched since [`last_candle_time`](Self::last_candle_time).
+    ///
+    /// The still-forming last bar is updated in place and newer bars are
+    /// appended, so candle indices, drawings and the zoom/offset state in
+    /// `chart_data` stay valid. Adjusted history is only re-adjusted on the
+    /// next full update, so a new ex-date shows up once the chart reloads.
+    /// Transformed chart types derive the tail of their series again from
+    /// the first merged bar on, with the box size they were built with.
+    /// A chart still waiting for its history is left alone.
+    ///
+    /// # Returns
+    /// * `usize` - Number of bars appended
+    pub fn merge_candle_data(&mut self, stock: OHLCData) -> usize {
+        if self.source_data.is_empty() {
+            return 0;
+        }
+        let candles = prepare_candles(&stock, self.time_frame, self.price_actions());
+        let from = candles.first().map_or(self.source_data.len(), |first| {
+            self.source_data
+                .partition_point(|bar| bar.time < first.time)
+        });
+        let appended = merge_candles(&mut self.source_data, candles);
+        self.chart_type.extend(
+            &mut self.candle_data,
+            &self.source_data,
+            from,
+            self.series_box,
+        );
+        appended
     }
 
     pub fn update_company_info(&mut self, info: CompanyInfo) {
@@ -134,6 +347,24 @@ impl Chart {
 
     /// main function for rendering the chart with plotter
     pub fn render_plot(&mut self, ui_data: UiData) -> (slint::Image, bool) {
+        // Before the check below, since a Renko or P&F box too large for the
+        // history leaves no candles to draw
+        if ui_data.is_new_chart_type {
+            match ChartType::parse(&ui_data.chart_type, &ui_data.box_size) {
+                Some(chart_type) => self.set_chart_type(chart_type),
+                None => log::warn!(
+                    "Invalid chart type {} {}",
+                    ui_data.chart_type,
+                    ui_data.box_size
+                ),
+            }
+        }
+
+        if ui_data.is_new_scale {
+            let scale = PriceScale::parse(&ui_data.price_scale).unwrap_or_default();
+            self.set_price_scale(scale, ui_data.is_auto_fit, ui_data.is_lock_scale);
+        }
+
         // Check if candle_data is empty and return empty image
         if self.candle_data.is_empty() {
             log::warn!(
@@ -144,16 +375,22 @@ impl Chart {
         }
 
         self.chart_data.ui_data = ui_data;
-        // clean all object on the chart
-        if self.chart_data.ui_data.is_clean {
-            self.all_draw_data.clear();
-            self.current_draw_data.clear();
-        }
+        // clean, undo, redo, delete and restyle drawings
+        self.apply_drawing_actions();
+
+        // Map the drawings to the x axis of the candles shown now
+        self.all_draw_data = self
+            .drawings
+            .iter()
+            .map(|drawing| drawing.place(&self.candle_data, self.price_scale))
+            .collect();
 
-        if self.chart_data.ui_data.is_undo {
-            self.all_draw_data.pop();
+        if self.chart_data.ui_data.is_new_indicators {
+            self.set_indicators(parse_indicators(&self.chart_data.ui_data.indicators));
         }
 
+        self.resize_sub_panes();
+
         if self.chart_data.candle_distance < 1.5
             && self.chart_data.ui_data.zoom > 0
             && self.chart_data.ui_data.position_x < self.chart_data.width as i32 - 60
@@ -193,48 +430,13 @@ impl Chart {
             };
         self.chart_data.zoom_x = zoom_x;
         self.chart_data.zoom_y = zoom_y as f32;
-        let (mouse_x, mouse_y) = self.get_mouse_position();
-        if self.is_in_object.2 && !self.chart_data.ui_data.is_release {
-            let dx = self.chart_data.ui_data.move_x as f32 / self.chart_data.candle_distance;
-            let dy = -(self.chart_data.ui_data.move_y as f32)
-                * (3.0 * (self.chart_data.y_offset_max - self.chart_data.y_offset_min))
-                / (self.chart_data.height - self.chart_data.range_x as u32) as f32;
-
-            // Move the object
-            debug!("moving - dx: {dx}, dy: {dy}");
-            let mut updated_object = self.moving_object.clone();
-            updated_object.translate(dx, dy);
-            self.all_draw_data[self.is_in_object.0] = updated_object;
-            self.chart_data.ui_data.move_x = 0;
+        // A fitted or locked y axis ignores vertical drags and y zoom
+        if self.is_y_fixed() {
+            self.chart_data.zoom_y = 0.0;
             self.chart_data.ui_data.move_y = 0;
-        } else {
-            for (idx, draw_obj) in self.all_draw_data.iter().enumerate() {
-                let x_thresh_hold = (self.chart_data.x_offset_max - self.chart_data.x_offset_min)
-                    * THREAD_HOLD
-                    / (self.chart_data.width as f32 - 60.0);
-                let y_thresh_hold = (3.0
-                    * (self.chart_data.y_offset_max - self.chart_data.y_offset_min))
-                    * THREAD_HOLD
-                    / (self.chart_data.height as f32 - 60.0);
-
-                if draw_obj.hit_cursor((mouse_x, mouse_y), x_thresh_hold, y_thresh_hold) {
-                    if self.chart_data.ui_data.is_release {
-                        // If the object is not moving, set it to be moving
-                        self.is_in_object = (idx, true, false);
-                    } else {
-                        self.is_in_object = (idx, true, true);
-                        // Calculate the delta in chart coordinates
-                        self.moving_object = draw_obj.clone();
-                    }
-                    self.chart_data.ui_data.move_x = 0;
-                    self.chart_data.ui_data.move_y = 0;
-                    break;
-                }
-                if !self.is_in_object.2 {
-                    self.is_in_object = (0, false, false);
-                }
-            }
         }
+        self.fit_visible_range();
+        self.edit_drawings();
 
         if self.is_in_object.2 {
             self.chart_data.ui_data.move_x = 0;
@@ -245,12 +447,15 @@ impl Chart {
         // Update width and height of chart when users resize the window
         if self.chart_data.ui_data.height != 0 && self.chart_data.ui_data.width != 0 {
             self.chart_data.width = self.chart_data.ui_data.width as u32;
-            self.chart_data.height = self.chart_data.ui_data.height as u32;
+            self.chart_data.image_height = self.chart_data.ui_data.height as u32;
         }
+        let image_height = self.chart_data.image_height;
+        // The price pane gets what the sub-panes leave
+        let panes = self.sub_pane_heights(image_height);
+        self.chart_data.height = image_height - panes.iter().map(|(_, h)| h).sum::<u32>();
 
         // Init data for plotters
-        let mut pixel_buffer =
-            SharedPixelBuffer::new(self.chart_data.width, self.chart_data.height);
+        let mut pixel_buffer = SharedPixelBuffer::new(self.chart_data.width, image_height);
         let size = (pixel_buffer.width(), pixel_buffer.height());
         let backend = BitMapBackend::with_buffer(pixel_buffer.make_mut_bytes(), size);
         let root = backend.into_drawing_area();
@@ -262,8 +467,10 @@ impl Chart {
         let (space, max_y, min_y) = self.update_y_axis_after_moving();
         let (min_x, max_x) = self.update_x_axis_after_moving();
 
+        let (price_area, pane_area) = root.split_vertically(self.chart_data.height);
+
         // Init the first candle chart with x,y range
-        let mut chart = ChartBuilder::on(&root)
+        let mut chart = ChartBuilder::on(&price_area)
             .x_label_area_size(self.chart_data.range_x)
             .right_y_label_area_size(self.chart_data.range_y)
             .build_cartesian_2d(min_x..max_x, min_y..max_y)
@@ -276,6 +483,9 @@ impl Chart {
         // Draw stock name watermark in the background
         self.chart_draw_stock_name_watermark(&mut chart);
 
+        // Shade the lunch break and the closing auction under the bars
+        self.chart_draw_sessions(&mut chart);
+
         // Handle candle sticks on the chart
         self.chart_update_candle_sticks(&mut chart);
 
@@ -294,13 +504,25 @@ impl Chart {
 
         // Draw all user's objects on the chart
         self.chart_draw_objects(&mut chart);
+        self.chart_draw_handles(&mut chart);
+
+        self.chart_draw_indicators(&mut chart);
 
-        self.chart_draw_moving_averages(&mut chart);
+        self.chart_draw_comparisons(&mut chart);
 
         self.chart_draw_labels(&mut chart, max_x);
 
+        self.chart_draw_sub_panes(&pane_area, &panes, min_x, max_x);
+
         // update data after a mouse release
         if self.chart_data.ui_data.is_release {
+            if self.chart_data.ui_data.move_x != 0
+                || self.chart_data.ui_data.move_y != 0
+                || self.chart_data.zoom_x != 0.0
+                || self.chart_data.zoom_y != 0.0
+            {
+                self.mark_dirty();
+            }
             self.chart_data.y_offset_max = max_y - space;
             self.chart_data.y_offset_min = max_y - 2.0 * space;
             self.chart_data.x_offset_min = min_x;
@@ -310,6 +532,14 @@ impl Chart {
                 if let DrawType::Ruler(_) = self.current_draw_data.draw_type {
                     // nothing to do
                 } else {
+                    let drawing = AnchoredDrawing::new(
+                        self.current_draw_data.clone(),
+                        &self.candle_data,
+                        self.price_scale,
+                    );
+                    let index = self.drawings.len();
+                    self.history
+                        .apply(&mut self.drawings, DrawCommand::Add(index, drawing));
                     self.all_draw_data.push(self.current_draw_data.clone());
                     self.current_draw_data.clear();
                     self.backup_points.clear();
@@ -329,6 +559,8 @@ impl Chart {
 
         root.present().expect("error presenting");
         drop(chart);
+        drop(price_area);
+        drop(pane_area);
         drop(root);
 
         (slint::Image::from_rgb8(pixel_buffer), self.is_in_object.1)
@@ -342,44 +574,59 @@ impl Chart {
         let (min_candle_x, max_candle_x) = self.get_min_max_of_candle_after_moving();
         // draw candle stick on the chart
         let chart_candles = &self.candle_data[min_candle_x..max_candle_x];
+        let axis_candles: Vec<CandleData> = chart_candles
+            .iter()
+            .map(|candle| self.price_scale.candle_to_axis(candle))
+            .collect();
 
         // Calculate candles size based on the candle_distance to ensure consistency
         // This ensures the candle size remains the same regardless of how many candles are displayed
         let candles_size = self.candle_distance(0) * 0.8;
 
-        // Draw candlesticks
-        chart
-            .draw_series(chart_candles.iter().map(|x| {
-                if x.open == x.close {
-                    let (_, max_y, min_y) = self.update_y_axis_after_moving();
-                    let candle_height = self.pixels_to_y_distance(1.0, max_y - min_y);
-                    CandleStick::new(
-                        x.num,
-                        x.open - candle_height,
-                        x.high,
-                        x.low,
-                        x.close,
-                        UP_COLOR.filled(),
-                        DOWN_COLOR.filled(),
-                        candles_size as u32,
-                    )
-                } else {
-                    CandleStick::new(
-                        x.num,
-                        x.open,
-                        x.high,
-                        x.low,
-                        x.close,
-                        UP_COLOR.filled(),
-                        DOWN_COLOR.filled(),
-                        candles_size as u32,
-                    )
-                }
-            }))
-            .unwrap();
+        match self.chart_type {
+            ChartType::Bars => self.chart_draw_bars(chart, &axis_candles),
+            ChartType::Line | ChartType::Area => self.chart_draw_line(chart, &axis_candles),
+            ChartType::PointFigure(_) => {
+                self.chart_draw_point_figure(chart, chart_candles, candles_size)
+            }
+            // Candlesticks, also for Heikin-Ashi candles and Renko bricks
+            ChartType::Candles | ChartType::HeikinAshi | ChartType::Renko(_) => {
+                chart
+                    .draw_series(axis_candles.iter().map(|x| {
+                        if x.open == x.close {
+                            let (_, max_y, min_y) = self.update_y_axis_after_moving();
+                            let candle_height = self.pixels_to_y_distance(1.0, max_y - min_y);
+                            CandleStick::new(
+                                x.num,
+                                x.open - candle_height,
+                                x.high,
+                                x.low,
+                                x.close,
+                                UP_COLOR.filled(),
+                                DOWN_COLOR.filled(),
+                                candles_size as u32,
+                            )
+                        } else {
+                            CandleStick::new(
+                                x.num,
+                                x.open,
+                                x.high,
+                                x.low,
+                                x.close,
+                                UP_COLOR.filled(),
+                                DOWN_COLOR.filled(),
+                                candles_size as u32,
+                            )
+                        }
+                    }))
+                    .unwrap();
+            }
+        }
 
-        // Draw volume data
-        self.draw_volume_data(chart, chart_candles, candles_size);
+        // Draw volume data, unless it has a pane of its own
+        if !self.has_volume_pane() {
+            self.draw_volume_data(chart, chart_candles, candles_size);
+        }
     }
 
     /// Draw volume data into the chart
@@ -424,13 +671,19 @@ impl Chart {
         let (x_position, y_position) = self.get_mouse_position();
 
         // Draw cursor lines following the mouse position
-        self.draw_cursor_lines(chart, x_position, y_position);
+        if !self.chart_data.ui_data.hide_cursor {
+            self.draw_cursor_lines(chart, x_position, y_position);
+        }
         let (_, max_y, min_y) = self.update_y_axis_after_moving();
         let (min_x, max_x) = self.update_x_axis_after_moving();
 
         // Draw all saved lines but don't add new ones
         draw_all_data.push(self.current_draw_data.clone());
-        for object in draw_all_data {
+        for (index, object) in draw_all_data.into_iter().enumerate() {
+            // Hidden drawings show only while selected
+            if object.style.hidden && self.selected != Some(index) {
+                continue;
+            }
             let mut new_object = object.clone();
             new_object.clipped(min_x, max_x, min_y, max_y);
             let draw_points = new_object.to_vec((min_x, max_y), (max_x, min_y));
@@ -444,7 +697,7 @@ impl Chart {
                     let max_y = y1.max(y2);
 
                     // Draw filled rectangle with alpha (simulate blur)
-                    let fill_color = object.color.mix(0.2); // 0.2 = 20% opacity
+                    let fill_color = object.color.mix(object.style.fill_opacity as f64);
                     chart
                         .draw_series(std::iter::once(Rectangle::new(
                             [(min_x, min_y), (max_x, max_y)],
@@ -452,13 +705,19 @@ impl Chart {
                         )))
                         .unwrap();
 
-                    // Draw the border as usual
-                    chart
-                        .draw_series(std::iter::once(Rectangle::new(
-                            [(min_x, min_y), (max_x, max_y)],
-                            object.color,
-                        )))
-                        .unwrap();
+                    // Draw the border in the object's style
+                    draw_styled_path(
+                        chart,
+                        vec![
+                            (min_x, min_y),
+                            (max_x, min_y),
+                            (max_x, max_y),
+                            (min_x, max_y),
+                            (min_x, min_y),
+                        ],
+                        object.color,
+                        &object.style,
+                    );
                 }
                 DrawType::Oval((start, end)) => {
                     // Use original object coordinates, not clipped ones
@@ -470,7 +729,7 @@ impl Chart {
                     let ry = (y2 - y1).abs() / 2.0;
 
                     // Draw filled ellipse with alpha (simulate blur) similar to Rectangle
-                    let fill_color = object.color.mix(0.2); // Use original object color
+                    let fill_color = object.color.mix(object.style.fill_opacity as f64);
 
                     // For an ellipse, we'll approximate it using a polygon with many points
                     let num_points = 128; // Balanced for performance and quality
@@ -502,12 +761,7 @@ impl Chart {
                         .collect();
 
                     if visible_points.len() > 1 {
-                        chart
-                            .draw_series(LineSeries::new(
-                                visible_points,
-                                object.color.stroke_width(1),
-                            ))
-                            .unwrap();
+                        draw_styled_path(chart, visible_points, object.color, &object.style);
                     }
                 }
                 DrawType::Ruler((end, start)) => {
@@ -529,11 +783,15 @@ impl Chart {
                     let max_x = x1.max(x2);
                     let min_y = y1.min(y2);
                     let max_y = y1.max(y2);
-                    let percent = (end.1 - start.1) * 100.0 / start.1;
+                    let (from, to) = (
+                        self.price_scale.to_price(start.1),
+                        self.price_scale.to_price(end.1),
+                    );
+                    let percent = (to - from) * 100.0 / from;
                     let percent_string = if percent > 0.0 {
-                        format!("+{:.02} (+{:.02}%)", (end.1 - start.1), percent)
+                        format!("+{:.02} (+{:.02}%)", (to - from), percent)
                     } else {
-                        format!("{:.02} ({:.02}%)", (end.1 - start.1), percent)
+                        format!("{:.02} ({:.02}%)", (to - from), percent)
                     };
 
                     let color = if percent >= 0.0 { BLUE } else { RED };
@@ -543,6 +801,7 @@ impl Chart {
                         &first_arrow_start,
                         &first_arrow_end,
                         color,
+                        1,
                         max_x,
                         min_x,
                         max_y,
@@ -553,6 +812,7 @@ impl Chart {
                         &second_arrow_start,
                         &second_arrow_end,
                         color,
+                        1,
                         max_x,
                         min_x,
                         max_y,
@@ -601,13 +861,45 @@ impl Chart {
                 }
                 DrawType::Arrow((end, start)) => {
                     // Draw the main line
-                    self.draw_arrow(chart, start, end, object.color, max_x, min_x, max_y, min_y);
+                    self.draw_arrow(
+                        chart,
+                        start,
+                        end,
+                        object.color,
+                        object.style.width,
+                        max_x,
+                        min_x,
+                        max_y,
+                        min_y,
+                    );
+                }
+                DrawType::FibRetracement { .. }
+                | DrawType::FibExtension { .. }
+                | DrawType::Channel(_)
+                | DrawType::Pitchfork(_)
+                | DrawType::GannFan(_)
+                | DrawType::Position { .. } => {
+                    if !new_object.is_empty() {
+                        self.chart_draw_tool(chart, &object, (min_x, max_x, min_y, max_y));
+                    }
+                }
+                DrawType::Text {
+                    anchor,
+                    content,
+                    size,
+                } => {
+                    // Skip labels whose anchor is out of the visible area
+                    if new_object.is_empty() {
+                        continue;
+                    }
+                    let _ = chart.plotting_area().draw(&Text::new(
+                        content.clone(),
+                        *anchor,
+                        ("sans-serif", *size).into_font().color(&object.color),
+                    ));
                 }
                 _ => {
-                    chart
-                        .draw_series(LineSeries::new(draw_points, object.color.stroke_width(1)))
-                        .unwrap()
-                        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x, y)], object.color));
+                    draw_styled_path(chart, draw_points, object.color, &object.style);
                 }
             }
         }
@@ -619,6 +911,7 @@ impl Chart {
         start: &(f32, f32),
         end: &(f32, f32),
         color: RGBColor,
+        width: u32,
         max_x: f32,
         min_x: f32,
         max_y: f32,
@@ -626,7 +919,10 @@ impl Chart {
     ) {
         // Draw the main line
         chart
-            .draw_series(LineSeries::new(vec![*start, *end], color.stroke_width(1)))
+            .draw_series(LineSeries::new(
+                vec![*start, *end],
+                color.stroke_width(width),
+            ))
             .unwrap();
 
         // Draw the arrowhead
@@ -651,13 +947,13 @@ impl Chart {
         chart
             .draw_series(std::iter::once(PathElement::new(
                 vec![*end, arrow_point1],
-                color.stroke_width(1),
+                color.stroke_width(width),
             )))
             .unwrap();
         chart
             .draw_series(std::iter::once(PathElement::new(
                 vec![*end, arrow_point2],
-                color.stroke_width(1),
+                color.stroke_width(width),
             )))
             .unwrap();
     }
@@ -697,8 +993,11 @@ impl Chart {
 
     /// Handle different drawing types
     fn update_current_draw(&mut self) {
-        // If cursor is in y-axis field, don't add drawing points
-        if self.is_in_y_field() {
+        // If cursor is in y-axis field or a sub-pane, don't add drawing points
+        if self.is_in_y_field()
+            || self.resizing_pane.is_some()
+            || self.is_in_sub_pane(self.chart_data.ui_data.press_y)
+        {
             return;
         }
 
@@ -748,8 +1047,95 @@ impl Chart {
                     self.current_draw_data =
                         DrawObject::new(DrawType::Ruler(((x, y), press_point)), color);
                 }
+                MouseType::Text => {
+                    // The label follows the cursor until the button is released
+                    let content = self.chart_data.ui_data.text.trim();
+                    if !content.is_empty() {
+                        let size = u32::try_from(self.chart_data.ui_data.text_size)
+                            .ok()
+                            .filter(|&size| size > 0)
+                            .unwrap_or(DEFAULT_TEXT_SIZE);
+                        self.current_draw_data = DrawObject::new(
+                            DrawType::Text {
+                                anchor: (x, y),
+                                content: content.to_string(),
+                                size,
+                            },
+                            color,
+                        );
+                    }
+                }
+                MouseType::FibRetracement => {
+                    let levels = parse_levels(&self.chart_data.ui_data.fib_levels)
+                        .unwrap_or_else(|| FIB_RETRACEMENT_LEVELS.to_vec());
+                    self.current_draw_data = DrawObject::new(
+                        DrawType::FibRetracement {
+                            points: (press_point, (x, y)),
+                            levels,
+                        },
+                        color,
+                    );
+                }
+                MouseType::FibExtension => {
+                    // The third point starts on the second until it is moved
+                    let levels = parse_levels(&self.chart_data.ui_data.fib_levels)
+                        .unwrap_or_else(|| FIB_EXTENSION_LEVELS.to_vec());
+                    self.current_draw_data = DrawObject::new(
+                        DrawType::FibExtension {
+                            points: (press_point, (x, y), (x, y)),
+                            levels,
+                        },
+                        color,
+                    );
+                }
+                MouseType::Channel => {
+                    let width = self.default_tool_width();
+                    self.current_draw_data = DrawObject::new(
+                        DrawType::Channel((
+                            press_point,
+                            (x, y),
+                            (press_point.0, press_point.1 + width),
+                        )),
+                        color,
+                    );
+                }
+                MouseType::Pitchfork => {
+                    // Drag from the pivot to the middle of the two other points
+                    let half = self.default_tool_width() / 2.0;
+                    self.current_draw_data = DrawObject::new(
+                        DrawType::Pitchfork((press_point, (x, y + half), (x, y - half))),
+                        color,
+                    );
+                }
+                MouseType::GannFan => {
+                    self.current_draw_data =
+                        DrawObject::new(DrawType::GannFan((press_point, (x, y))), color);
+                }
+                MouseType::LongPosition | MouseType::ShortPosition => {
+                    // The drag sets the reward; the stop starts at one times the risk
+                    let long =
+                        matches!(self.chart_data.ui_data.mouse_type, MouseType::LongPosition);
+                    let reward = (y - press_point.1).abs();
+                    let sign = if long { 1.0 } else { -1.0 };
+                    self.current_draw_data = DrawObject::new(
+                        DrawType::Position {
+                            entry: press_point,
+                            end_x: x,
+                            target: press_point.1 + sign * reward,
+                            stop: press_point.1 - sign * reward,
+                            long,
+                        },
+                        color,
+                    );
+                }
                 _ => {}
             }
+            // New drawings take the toolbar style but start unlocked and shown
+            self.current_draw_data.style = DrawStyle {
+                locked: false,
+                hidden: false,
+                ..self.ui_style()
+            };
         }
     }
 
@@ -786,13 +1172,23 @@ impl Chart {
         };
 
         // Draw cursor labels
-        self.draw_cursor_labels(chart, max_x, y_position, min, cursor_label_style);
+        if !self.chart_data.ui_data.hide_cursor {
+            self.draw_cursor_labels(chart, max_x, y_position, min, cursor_label_style);
+        }
 
         // Draw latest price label
         self.draw_latest_price_label(chart, max_x, space);
 
         // Draw latest volume label
-        self.draw_latest_volume_label(chart, max_x, last_volume_map_to_y, space, green_label_style);
+        if !self.has_volume_pane() {
+            self.draw_latest_volume_label(
+                chart,
+                max_x,
+                last_volume_map_to_y,
+                space,
+                green_label_style,
+            );
+        }
     }
 
     /// Draw cursor labels
@@ -818,7 +1214,7 @@ impl Chart {
         cursor_rectangle.set_margin(0, 0, 50, 0);
 
         // Show the price of current mouse position
-        let y_position_str = format!("   {y_position:.02}");
+        let y_position_str = format!("   {}", self.format_axis_value(y_position));
         let _ = chart.plotting_area().draw(&cursor_rectangle);
         let _ = chart.plotting_area().draw(&Text::new(
             y_position_str,
@@ -832,28 +1228,18 @@ impl Chart {
         ));
 
         // Draw the label for the cursor date
-        // Calculate the date based on cursor position, including future dates
-        let cursor_index = x_position.round() as i32;
-        let cursor_date = if cursor_index < 0 {
-            // Past dates - go backwards from the first data point
-            let days_back = (-cursor_index) as i64;
-            self.candle_data[0].time - Duration::days(days_back)
-        } else if cursor_index >= self.candle_data.len() as i32 {
-            // Future dates - calculate forward from the last data point
-            let days_forward = (cursor_index - (self.candle_data.len() as i32 - 1)) as i64;
-            self.candle_data[self.candle_data.len() - 1].time + Duration::days(days_forward)
-        } else {
-            // Within data range - use actual data
-            self.candle_data[cursor_index as usize].time
+        // Beyond the bars the date steps along the trading calendar
+        let Some(cursor_date) = self.time_at(x_position.round() as i32) else {
+            return;
         };
 
         let mut rectangle_cursor_x =
             Rectangle::new([(x_position, min), (x_position, min)], cursor_label_style);
         rectangle_cursor_x.set_margin(15, 0, 60, 5);
-        let output = &cursor_date.to_string()[0..11];
+        let output = self.format_cursor_time(cursor_date);
         let _ = chart.plotting_area().draw(&rectangle_cursor_x);
         let _ = chart.plotting_area().draw(&Text::new(
-            output.to_string(),
+            output,
             (x_position, min - 2.0),
             ("Arial-Bold", 14).into_font().color(&WHITE),
         ));
@@ -866,8 +1252,9 @@ impl Chart {
         max_x: f32,
         space: f32,
     ) {
-        let last_price = self.get_last_price();
-        let last_price_str = format!("    {last_price:.02}");
+        let price = self.get_last_price();
+        let last_price_str = format!("    {}", self.price_scale.format(price, self.scale_base()));
+        let last_price = self.price_scale.to_axis(price);
 
         // Determine the color based on the last candle
         let is_up = self.is_last_candle_up();
@@ -965,44 +1352,28 @@ impl Chart {
         ));
     }
 
-    /// Customize the x value from DateTime<Utc> to index to prevent
-    /// the date which has no data of candle stick.
+    /// Draw the axes: prices in the active scale on y, and on x the bar
+    /// index, which leaves no gap for the hours without trading, labelled
+    /// from the trading calendar.
     fn chart_custom_x_axis(
         &self,
         chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
     ) {
-        // Create a mapping of dates to indices
-        let date_to_index: Vec<(DateTime<Utc>, usize)> = self
-            .candle_data
-            .iter()
-            .enumerate()
-            .map(|(idx, x)| (x.time, idx))
-            .collect();
+        // The y axis shows prices in the active scale
+        let (scale, base) = (self.price_scale, self.scale_base());
 
         // Configure the x-axis and y-axis labels with larger font sizes
         chart
             .configure_mesh()
             .disable_x_mesh()
             .disable_y_mesh()
-            .x_label_formatter(&|&idx| {
-                if idx > 0.0 && idx < date_to_index.len() as f32 {
-                    let date = &(date_to_index[idx as usize].0
-                        + Duration::days(
-                            ((self.chart_data.ui_data.move_x as f32
-                                / self.chart_data.candle_distance)
-                                as i32)
-                                .into(),
-                        ));
-                    format!("{}", date.format("Tháng %m\n"))
-                } else {
-                    "".to_string()
-                }
-            })
-            .y_label_formatter(&|&val| format!("{val:.2}")) // Format y-axis labels with 2 decimals
+            .x_labels(0) // Time labels follow the calendar, see `chart_draw_time_axis`
+            .y_label_formatter(&|&val| scale.format(scale.to_price(val), base)) // In the active scale
             .label_style(("Arial-Bold", 16).into_font().color(&WHITE)) // Increased font size
             .axis_style(WHITE.stroke_width(1))
             .draw()
             .expect("error drawing mesh");
+        self.chart_draw_time_axis(chart);
     }
 
     /// show the information of candle stick on the left-top-corner
@@ -1016,9 +1387,6 @@ impl Chart {
         let (min_x, _) = self.update_x_axis_after_moving();
         let y_stock_text = max_y - (max_y - min_y) * (20.0 / self.chart_data.height as f32);
         let y_volumn_text = max_y - (max_y - min_y) * (40.0 / self.chart_data.height as f32);
-        let y_ma_20_text = max_y - (max_y - min_y) * (60.0 / self.chart_data.height as f32);
-        let y_ma_50_text = max_y - (max_y - min_y) * (80.0 / self.chart_data.height as f32);
-        let y_ma_200_text = max_y - (max_y - min_y) * (100.0 / self.chart_data.height as f32);
 
         let (x, _) = self.get_mouse_position();
         // If cursor is exactly at the middle of a candle, show info for that candle
@@ -1063,7 +1431,7 @@ impl Chart {
         };
         let output = format!(
             "  {} - Open: {:.02}, High: {:.02}, Close: {:.02}, Low: {:.02}, {} ({})",
-            self.stock_name,
+            self.series_name(),
             candle_data.open,
             candle_data.high,
             candle_data.close,
@@ -1100,21 +1468,17 @@ impl Chart {
             (min_x, y_volumn_text),
             ("sans-serif", 15).into_font().color(&color),
         ));
-        let _ = chart.plotting_area().draw(&Text::new(
-            "  MA20    _________",
-            (min_x, y_ma_20_text),
-            ("sans-serif", 15).into_font().color(&RED),
-        ));
-        let _ = chart.plotting_area().draw(&Text::new(
-            "  MA50    _________",
-            (min_x, y_ma_50_text),
-            ("sans-serif", 15).into_font().color(&BLUE),
-        ));
-        let _ = chart.plotting_area().draw(&Text::new(
-            "  MA200  _________",
-            (min_x, y_ma_200_text),
-            ("sans-serif", 15).into_font().color(&WHITE),
-        ));
+        // One legend line per overlay, below the volume
+        let overlays = self.indicators.iter().filter(|study| !study.in_pane());
+        for (i, study) in overlays.enumerate() {
+            let y_text = max_y
+                - (max_y - min_y) * ((60.0 + 20.0 * i as f32) / self.chart_data.height as f32);
+            let _ = chart.plotting_area().draw(&Text::new(
+                format!("  {}  _________", study.config.label()),
+                (min_x, y_text),
+                ("sans-serif", 15).into_font().color(&study.config.color),
+            ));
+        }
     }
 
     /// Draw stock name as watermark in the background center of the chart
@@ -1213,73 +1577,6 @@ impl Chart {
         ]
     }
 
-    pub fn calculate_moving_average(
-        &self,
-        period: usize,
-        min_candle_x: usize,
-        max_candle_x: usize,
-    ) -> Vec<f32> {
-        let mut ma_values = Vec::new();
-        let mut sum = 0.0;
-
-        for i in (min_candle_x - period)..max_candle_x {
-            sum += self.candle_data[i].close; // Add the closing price
-
-            if i - (min_candle_x - period) >= period {
-                sum -= self.candle_data[i - period].close; // Remove the price outside the window
-            }
-            if i - (min_candle_x - period) >= period - 1 {
-                ma_values.push(sum / period as f32); // Calculate the moving average
-            }
-        }
-        ma_values
-    }
-
-    fn chart_draw_moving_averages(
-        &self,
-        chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
-    ) {
-        let (min_candle_x, max_candle_x) = self.get_min_max_of_candle_after_moving();
-        // Calculate MA5, MA20, MA100
-        let ma20 = self.calculate_moving_average(20, min_candle_x, max_candle_x);
-        let ma50 = self.calculate_moving_average(50, min_candle_x, max_candle_x);
-        let ma200 = self.calculate_moving_average(200, min_candle_x, max_candle_x);
-
-        // Draw MA5
-        let ma20_points: Vec<(f32, f32)> = self
-            .candle_data
-            .iter()
-            .enumerate()
-            .enumerate()
-            .filter_map(|(i, _)| ma20.get(i).map(|&ma| ((i + min_candle_x) as f32, ma)))
-            .collect();
-        chart
-            .draw_series(LineSeries::new(ma20_points, RED.stroke_width(1)))
-            .expect("Error drawing MA20");
-
-        // Draw MA20
-        let ma50_points: Vec<(f32, f32)> = self
-            .candle_data
-            .iter()
-            .enumerate()
-            .filter_map(|(i, _)| ma50.get(i).map(|&ma| ((i + min_candle_x) as f32, ma)))
-            .collect();
-        chart
-            .draw_series(LineSeries::new(ma50_points, BLUE.stroke_width(1)))
-            .expect("Error drawing MA50");
-
-        // Draw MA100
-        let ma200_points: Vec<(f32, f32)> = self
-            .candle_data
-            .iter()
-            .enumerate()
-            .filter_map(|(i, _)| ma200.get(i).map(|&ma| ((i + min_candle_x) as f32, ma)))
-            .collect();
-        chart
-            .draw_series(LineSeries::new(ma200_points, WHITE.stroke_width(1)))
-            .expect("Error drawing MA200");
-    }
-
     fn cursor_horizontal_line(&self, y_position: f32) -> Vec<(f32, f32)> {
         let converted_move_x =
             self.chart_data.ui_data.move_x as f32 / self.chart_data.candle_distance;
@@ -1302,7 +1599,7 @@ impl Chart {
                 - (self.chart_data.x_offset_min - zoom as f32))
     }
 
-    fn update_y_axis_after_moving(&self) -> (f32, f32, f32) {
+    pub(crate) fn update_y_axis_after_moving(&self) -> (f32, f32, f32) {
         let zoom_ratio = (100.0 + self.chart_data.zoom_y) / 100.0;
 
         // Calculate the real size of the y-axis based on the zoom ratio
@@ -1323,7 +1620,7 @@ impl Chart {
         (real_size / 3.0, max, min)
     }
 
-    fn update_x_axis_after_moving(&self) -> (f32, f32) {
+    pub(crate) fn update_x_axis_after_moving(&self) -> (f32, f32) {
         let min_x = self.chart_data.x_offset_min
             - self.chart_data.ui_data.move_x as f32 / self.chart_data.candle_distance
             - self.chart_data.zoom_x;
@@ -1333,7 +1630,7 @@ impl Chart {
         (min_x, max_x)
     }
 
-    fn get_min_max_of_candle_after_moving(&self) -> (usize, usize) {
+    pub(crate) fn get_min_max_of_candle_after_moving(&self) -> (usize, usize) {
         // convert move x from pixels to real distance in x axis.
         let converted_move_x =
             self.chart_data.ui_data.move_x as f32 / self.chart_data.candle_distance;
@@ -1384,16 +1681,17 @@ impl Chart {
             .fold(f32::INFINITY, |acc, x| acc.min(x))
     }
 
-    /// Get the last price -> close price of last candle stick
+    /// Get the last price -> close price of last bar, also when the chart
+    /// type draws something else
     fn get_last_price(&self) -> f32 {
-        self.candle_data
+        self.source_data
             .last()
             .map_or(0.0, |lastest_data| lastest_data.close)
     }
 
     /// Determine if the last candle is up (green) or down (red)
     fn is_last_candle_up(&self) -> bool {
-        if let Some(last_candle) = self.candle_data.last() {
+        if let Some(last_candle) = self.source_data.last() {
             // In candlestick data, index 2 is open, index 5 is close
             // If close > open, it's an up candle (green)
             last_candle.close > last_candle.open
@@ -1404,7 +1702,7 @@ impl Chart {
 
     /// This function will return the position of the mouse on the chart
     /// return value as (f32, f32) with x, y in chart values.
-    fn get_mouse_position(&self) -> (f32, f32) {
+    pub(crate) fn get_mouse_position(&self) -> (f32, f32) {
         let (_, max_y, min_y) = self.update_y_axis_after_moving();
         let y_position = min_y
             + ((self.chart_data.height as i32
@@ -1421,7 +1719,7 @@ impl Chart {
         (x_position, y_position)
     }
 
-    fn get_press_position(&self) -> (f32, f32) {
+    pub(crate) fn get_press_position(&self) -> (f32, f32) {
         let (_, max_y, min_y) = self.update_y_axis_after_moving();
         let y_position = min_y
             + ((self.chart_data.height as i32
@@ -1445,6 +1743,13 @@ impl Chart {
             .map_or(0.0, |lastest_data| lastest_data.volume)
     }
 
+    /// Starting width of a channel or pitchfork: a tenth of the visible
+    /// price range.
+    fn default_tool_width(&self) -> f32 {
+        let (_, max_y, min_y) = self.update_y_axis_after_moving();
+        (max_y - min_y) * 0.1
+    }
+
     fn pixels_to_y_distance(&self, pixels: f32, y_height: f32) -> f32 {
         (y_height / (self.chart_data.height as f32)) * pixels
     }
@@ -1462,6 +1767,21 @@ impl Chart {
     }
 }
 
+/// Draw a polyline in `color` with the width and dash of `style`.
+pub(crate) fn draw_styled_path(
+    chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+    points: Vec<(f32, f32)>,
+    color: RGBColor,
+    style: &DrawStyle,
+) {
+    let stroke = color.stroke_width(style.width);
+    if style.dashed {
+        let _ = chart.draw_series(DashedLineSeries::new(points, 6, 4, stroke));
+    } else {
+        let _ = chart.draw_series(LineSeries::new(points, stroke));
+    }
+}
+
 /// Interpolates points so that the distance between consecutive points is at most delta_x or delta_y.
 pub fn interpolate_min_distance(points: &[Point], delta_x: f32, delta_y: f32) -> Vec<Point> {
     if points.is_empty() {
@@ -0,0 +1,613 @@
+//! Chart types: how the bars of a chart are drawn on the price pane.
+//!
+//! Bars, line and area draw the candles as they are. Heikin-Ashi, Renko and
+//! point & figure turn them into a series of their own, which then takes the
+//! place of the candles for the axes, indicators, drawings and the crosshair
+//! info. Renko bricks and point & figure columns are numbered like candles
+//! and keyed by time like them, so drawings and the viewport keep their
+//! place: a brick carries the time of the bar that made it, a millisecond
+//! later for each brick the same bar made before it, and a column the time
+//! of the bar that started it.
+//!
+//! The box size is resolved when the series is built and kept while bars
+//! merge in, so a live update only derives the tail of the series again.
+
+use chrono::{DateTime, Duration, Utc};
+use plotters::{
+    backend::BitMapBackend,
+    chart::ChartContext,
+    coord::types::RangedCoordf32,
+    element::{Circle, PathElement},
+    prelude::Cartesian2d,
+    series::{AreaSeries, LineSeries},
+    style::{Color, RGBColor},
+};
+
+use crate::{
+    CandleData, CandleDataVec, Chart,
+    chart::{ChartData, DOWN_COLOR, UP_COLOR},
+    indicator::true_range,
+};
+
+/// Colour of the line and area chart types.
+const SERIES_COLOR: RGBColor = RGBColor(0x29, 0x62, 0xFF);
+/// Boxes a point & figure column needs to turn.
+const REVERSAL: f32 = 3.0;
+/// Smallest box as a share of the last close, so a gap never makes countless
+/// bricks.
+const MIN_BOX_RATIO: f32 = 0.001;
+
+/// Size of a Renko brick or a point & figure box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BoxSize {
+    Fixed(f32),   // in price units
+    Percent(f32), // of the last close
+    Atr(usize),   // mean true range of the last bars
+}
+
+impl Default for BoxSize {
+    fn default() -> Self {
+        BoxSize::Atr(14)
+    }
+}
+
+impl BoxSize {
+    /// Parse a box size as typed in the chart toolbar: `ATR 14`, `2%` or
+    /// `1.5`. Empty text is the default ATR box.
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_uppercase();
+        if text.is_empty() {
+            return Some(BoxSize::default());
+        }
+        let size = if let Some(period) = text.strip_prefix("ATR") {
+            BoxSize::Atr(period.trim().parse().ok().filter(|period| *period > 0)?)
+        } else if let Some(percent) = text.strip_suffix('%') {
+            BoxSize::Percent(percent.trim().parse().ok()?)
+        } else {
+            BoxSize::Fixed(text.parse().ok()?)
+        };
+        match size {
+            BoxSize::Fixed(value) | BoxSize::Percent(value)
+                if value <= 0.0 || !value.is_finite() =>
+            {
+                None
+            }
+            size => Some(size),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self {
+            BoxSize::Fixed(size) => format!("{size}"),
+            BoxSize::Percent(percent) => format!("{percent}%"),
+            BoxSize::Atr(period) => format!("ATR {period}"),
+        }
+    }
+
+    /// Box size in price units for `bars`.
+    ///
+    /// # Returns
+    /// * `Option<f32>` - None when there are no bars to size it from
+    pub fn resolve(&self, bars: &[CandleData]) -> Option<f32> {
+        let last = bars.last()?;
+        let size = match self {
+            BoxSize::Fixed(size) => *size,
+            BoxSize::Percent(percent) => last.close * percent / 100.0,
+            BoxSize::Atr(period) => {
+                let period = (*period).clamp(1, bars.len());
+                (bars.len() - period..bars.len())
+                    .map(|i| true_range(bars, i))
+                    .sum::<f32>()
+                    / period as f32
+            }
+        };
+        let min_size = last.close.abs() * MIN_BOX_RATIO;
+        if min_size <= 0.0 {
+            return None;
+        }
+        Some(if size.is_finite() {
+            size.max(min_size)
+        } else {
+            min_size
+        })
+    }
+}
+
+/// How the bars of a chart are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ChartType {
+    #[default]
+    Candles,
+    Bars,
+    Line,
+    Area,
+    HeikinAshi,
+    Renko(BoxSize),
+    PointFigure(BoxSize),
+}
+
+impl ChartType {
+    /// Parse a type as shown in the chart type picker, with the box size of
+    /// Renko and point & figure.
+    pub fn parse(label: &str, box_size: &str) -> Option<Self> {
+        match label.trim() {
+            "Candles" => Some(ChartType::Candles),
+            "Bars" => Some(ChartType::Bars),
+            "Line" => Some(ChartType::Line),
+            "Area" => Some(ChartType::Area),
+            "Heikin-Ashi" => Some(ChartType::HeikinAshi),
+            "Renko" => Some(ChartType::Renko(BoxSize::parse(box_size)?)),
+            "P&F" => Some(ChartType::PointFigure(BoxSize::parse(box_size)?)),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ChartType::Candles => "Candles",
+            ChartType::Bars => "Bars",
+            ChartType::Line => "Line",
+            ChartType::Area => "Area",
+            ChartType::HeikinAshi => "Heikin-Ashi",
+            ChartType::Renko(_) => "Renko",
+            ChartType::PointFigure(_) => "P&F",
+        }
+    }
+
+    pub fn box_size(&self) -> Option<BoxSize> {
+        match self {
+            ChartType::Renko(box_size) | ChartType::PointFigure(box_size) => Some(*box_size),
+            _ => None,
+        }
+    }
+
+    /// Box size in price units of the series for `bars`, None for the types
+    /// without boxes.
+    pub fn resolve_box(&self, bars: &[CandleData]) -> Option<f32> {
+        self.box_size()?.resolve(bars)
+    }
+
+    /// The series drawn for `bars`.
+    ///
+    /// # Arguments
+    /// * `size` - Box size of Renko and point & figure, see [`Self::resolve_box`];
+    ///   no bricks or columns without one
+    pub fn transform(&self, bars: &[CandleData], size: Option<f32>) -> CandleDataVec {
+        let mut series = Vec::new();
+        self.extend(&mut series, bars, 0, size);
+        series
+    }
+
+    /// Bring `series`, the series for `bars` as they were before the bars
+    /// from `from` on changed or were appended, up to date, deriving only
+    /// its tail again.
+    pub(crate) fn extend(
+        &self,
+        series: &mut CandleDataVec,
+        bars: &[CandleData],
+        from: usize,
+        size: Option<f32>,
+    ) {
+        let Some(changed) = bars.get(from) else {
+            return;
+        };
+        match self {
+            ChartType::HeikinAshi => {
+                series.truncate(from);
+                heikin_ashi(series, &bars[from..]);
+            }
+            ChartType::Renko(_) => {
+                // Bricks of the bars before `from` stay, and the bars
+                // between the last of them and `from` made none
+                let keep = series.partition_point(|brick| brick.time < changed.time);
+                series.truncate(keep);
+                let start = series.last().map_or(0, |brick| {
+                    bars.partition_point(|bar| bar.time <= brick.time)
+                });
+                if let Some(size) = size {
+                    renko(series, &bars[start..], size);
+                }
+            }
+            ChartType::PointFigure(_) => {
+                // The last column started before `from` may have grown since,
+                // so it is derived again from the bar that started it
+                let keep = series
+                    .partition_point(|column| column.time < changed.time)
+                    .saturating_sub(1);
+                let start = match keep {
+                    0 => 0, // bars before the first column count towards it
+                    _ => bars.partition_point(|bar| bar.time < series[keep].time),
+                };
+                series.truncate(keep);
+                if let Some(size) = size {
+                    point_figure(series, &bars[start..], size);
+                }
+            }
+            _ => {
+                series.truncate(from);
+                series.extend_from_slice(&bars[from..]);
+            }
+        }
+    }
+}
+
+/// Append the Heikin-Ashi bars of `bars` to `series`.
+fn heikin_ashi(series: &mut CandleDataVec, bars: &[CandleData]) {
+    for bar in bars {
+        let close = (bar.open + bar.high + bar.low + bar.close) / 4.0;
+        let open = match series.last() {
+            Some(previous) => (previous.open + previous.close) / 2.0,
+            None => (bar.open + bar.close) / 2.0,
+        };
+        series.push(CandleData {
+            open,
+            high: bar.high.max(open).max(close),
+            low: bar.low.min(open).min(close),
+            close,
+            ..bar.clone()
+        });
+    }
+}
+
+/// A brick or column from `open` to `close` at `time`.
+fn block(time: DateTime<Utc>, num: usize, open: f32, close: f32, volume: f32) -> CandleData {
+    CandleData {
+        num: num as f32,
+        time,
+        open,
+        high: open.max(close),
+        low: open.min(close),
+        close,
+        volume,
+    }
+}
+
+/// Append to `bricks` the bricks of `size` on the closes of `bars`, which
+/// follow the bars the bricks were made of, each with the volume traded
+/// since the previous one. A brick against the last one needs the close to move two
+/// sizes, since it starts from the far side of the last brick.
+fn renko(bricks: &mut CandleDataVec, bars: &[CandleData], size: f32) {
+    let (mut last, mut direction) = match (bricks.last(), bars.first()) {
+        (Some(brick), _) => (brick.close, brick.close - brick.open),
+        (None, Some(first)) => (first.close, 0.0), // no direction before the first brick
+        (None, None) => return,
+    };
+    let mut volume = 0.0;
+    for bar in bars {
+        volume += bar.volume;
+        for made in 0.. {
+            let up_from = if direction < 0.0 { last + size } else { last };
+            let down_from = if direction > 0.0 { last - size } else { last };
+            let (open, close) = if bar.close >= up_from + size {
+                (up_from, up_from + size)
+            } else if bar.close <= down_from - size {
+                (down_from, down_from - size)
+            } else {
+                break;
+            };
+            let time = bar.time + Duration::milliseconds(made);
+            bricks.push(block(time, bricks.len(), open, close, volume));
+            volume = 0.0;
+            direction = close - open;
+            last = close;
+        }
+    }
+}
+
+/// Append to `columns` the columns of X (rising, `close` above `open`) or O
+/// boxes of `size` on the closes of `bars`, which follow the bars the
+/// columns were made of, each with the volume traded while it was the last
+/// column. `open` and `close` are the outer edges of the first and last
+/// box. A column turns once the close is `REVERSAL` boxes past the box next
+/// to its last one.
+fn point_figure(columns: &mut CandleDataVec, bars: &[CandleData], size: f32) {
+    let Some(first) = bars.first() else {
+        return;
+    };
+    let base = first.close;
+    let mut volume = 0.0;
+    for bar in bars {
+        volume += bar.volume;
+        let price = bar.close;
+        let turn = match columns.last_mut() {
+            // The first column starts once the close moved a whole box
+            None => {
+                let boxes = ((price - base) / size).trunc();
+                (boxes != 0.0).then_some((base, base + boxes * size))
+            }
+            Some(column) => {
+                let rising = column.close > column.open;
+                let boxes = ((price - column.close) / size).trunc();
+                if (rising && boxes >= 1.0) || (!rising && boxes <= -1.0) {
+                    column.close += boxes * size;
+                    column.high = column.high.max(column.close);
+                    column.low = column.low.min(column.close);
+                    None
+                } else {
+                    let start = if rising {
+                        column.close - size
+                    } else {
+                        column.close + size
+                    };
+                    let boxes = ((price - start) / size).trunc();
+                    ((rising && boxes <= -REVERSAL) || (!rising && boxes >= REVERSAL))
+                        .then_some((start, start + boxes * size))
+                }
+            }
+        };
+        if let Some((open, close)) = turn {
+            columns.push(block(bar.time, columns.len(), open, close, 0.0));
+        }
+        if let Some(column) = columns.last_mut() {
+            column.volume += volume;
+            volume = 0.0;
+        }
+    }
+}
+
+impl Chart {
+    /// Draw the bars as `chart_type`, going back to the latest bars since the
+    /// series may have another length.
+    pub fn set_chart_type(&mut self, chart_type: ChartType) {
+        if chart_type == self.chart_type {
+            return;
+        }
+        self.chart_type = chart_type;
+        self.mark_dirty();
+        self.transform_source();
+        if !self.candle_data.is_empty() {
+            self.chart_data = ChartData {
+                ui_data: self.chart_data.ui_data.clone(),
+                ..Self::latest_view(&self.candle_data, self.price_scale)
+            };
+        }
+        self.backup_chart_offset = None;
+        for study in &mut self.indicators {
+            study.reset();
+        }
+    }
+
+    /// Derive the series drawn from all of `source_data`, resolving the box
+    /// size again.
+    pub(crate) fn transform_source(&mut self) {
+        self.series_box = self.chart_type.resolve_box(&self.source_data);
+        self.candle_data = self
+            .chart_type
+            .transform(&self.source_data, self.series_box);
+    }
+
+    /// Name of the series in the crosshair info: the symbol, followed by the
+    /// chart type and box size when the bars are transformed.
+    pub(crate) fn series_name(&self) -> String {
+        match self.chart_type {
+            ChartType::HeikinAshi => format!("{} Heikin-Ashi", self.stock_name),
+            ChartType::Renko(box_size) | ChartType::PointFigure(box_size) => {
+                let size = self.series_box.unwrap_or_default();
+                format!(
+                    "{} {} {} ({size:.2})",
+                    self.stock_name,
+                    self.chart_type.label(),
+                    box_size.label()
+                )
+            }
+            _ => self.stock_name.clone(),
+        }
+    }
+
+    /// Draw `candles` as OHLC bars: open tick to the left, close to the right.
+    pub(crate) fn chart_draw_bars(
+        &self,
+        chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+        candles: &[CandleData],
+    ) {
+        let tick = 0.4; // half the width of a candle, in candles
+        let _ = chart.draw_series(candles.iter().flat_map(|x| {
+            let color = if x.close >= x.open {
+                UP_COLOR
+            } else {
+                DOWN_COLOR
+            };
+            let style = color.stroke_width(2);
+            [
+                PathElement::new(vec![(x.num, x.low), (x.num, x.high)], style),
+                PathElement::new(vec![(x.num - tick, x.open), (x.num, x.open)], style),
+                PathElement::new(vec![(x.num, x.close), (x.num + tick, x.close)], style),
+            ]
+        }));
+    }
+
+    /// Draw a line through the closes of `candles`, filled down to the
+    /// bottom of the pane for the area type.
+    pub(crate) fn chart_draw_line(
+        &self,
+        chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+        candles: &[CandleData],
+    ) {
+        let points: Vec<(f32, f32)> = candles.iter().map(|x| (x.num, x.close)).collect();
+        if self.chart_type == ChartType::Area {
+            let (_, _, min_y) = self.update_y_axis_after_moving();
+            let _ = chart.draw_series(
+                AreaSeries::new(points, min_y, SERIES_COLOR.mix(0.2))
+                    .border_style(SERIES_COLOR.stroke_width(2)),
+            );
+        } else {
+            let _ = chart.draw_series(LineSeries::new(points, SERIES_COLOR.stroke_width(2)));
+        }
+    }
+
+    /// Draw the point & figure columns in `candles` box by box, in the axis
+    /// units of the price scale.
+    ///
+    /// # Arguments
+    /// * `candles` - Columns in prices
+    /// * `candles_size` - Width of a column in pixels
+    pub(crate) fn chart_draw_point_figure(
+        &self,
+        chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+        candles: &[CandleData],
+        candles_size: f32,
+    ) {
+        let Some(size) = self.series_box else {
+            return;
+        };
+        let (_, max_y, min_y) = self.update_y_axis_after_moving();
+        let scale = self.price_scale;
+        let half_width = 0.35; // of an X, in columns
+        for column in candles {
+            // On a log scale the boxes shrink towards the top of a column
+            let box_pixels = (self.chart_data.height as f32 - self.chart_data.range_x as f32)
+                * (scale.to_axis(column.low + size) - scale.to_axis(column.low))
+                / (max_y - min_y);
+            let radius = (box_pixels.min(candles_size) / 2.0).max(1.0);
+            let boxes = ((column.high - column.low) / size).round() as usize;
+            let bottoms = (0..boxes).map(|i| column.low + i as f32 * size);
+            if column.close > column.open {
+                let _ = chart.draw_series(bottoms.flat_map(|bottom| {
+                    let style = UP_COLOR.stroke_width(2);
+                    let (left, right) = (column.num - half_width, column.num + half_width);
+                    let (low, high) = (scale.to_axis(bottom), scale.to_axis(bottom + size));
+                    [
+                        PathElement::new(vec![(left, low), (right, high)], style),
+                        PathElement::new(vec![(left, high), (right, low)], style),
+                    ]
+                }));
+            } else {
+                let _ = chart.draw_series(bottoms.map(|bottom| {
+                    Circle::new(
+                        (column.num, scale.to_axis(bottom + size / 2.0)),
+                        radius as u32,
+                        DOWN_COLOR.stroke_width(2),
+                    )
+                }));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        draw::time_to_x,
+        tests::{bar, bars},
+    };
+
+    fn closes(series: &[CandleData]) -> Vec<f32> {
+        series.iter().map(|candle| candle.close).collect()
+    }
+
+    /// Closes swinging a few boxes up and down while they drift higher.
+    fn swings(len: usize) -> CandleDataVec {
+        let closes: Vec<f32> = (0..len)
+            .map(|i| (100.0 + 6.0 * (i as f32 * 0.7).sin() + i as f32 * 0.3).round())
+            .collect();
+        bars(&closes)
+    }
+
+    #[test]
+    fn box_size_parse_and_label() {
+        assert_eq!(BoxSize::parse(""), Some(BoxSize::Atr(14)));
+        assert_eq!(BoxSize::parse(" atr 10 "), Some(BoxSize::Atr(10)));
+        assert_eq!(BoxSize::parse("2%"), Some(BoxSize::Percent(2.0)));
+        assert_eq!(BoxSize::parse("1.5"), Some(BoxSize::Fixed(1.5)));
+        for text in ["ATR 0", "ATR", "0", "-1", "0%", "inf", "abc"] {
+            assert_eq!(BoxSize::parse(text), None, "{text}");
+        }
+        for size in [BoxSize::Atr(10), BoxSize::Percent(2.5), BoxSize::Fixed(1.5)] {
+            assert_eq!(BoxSize::parse(&size.label()), Some(size));
+        }
+    }
+
+    #[test]
+    fn box_size_resolve() {
+        let candles = bars(&[100.0, 100.0, 100.0]);
+        assert_eq!(BoxSize::Fixed(2.0).resolve(&candles), Some(2.0));
+        assert_eq!(BoxSize::Percent(2.0).resolve(&candles), Some(2.0));
+        // Every bar ranges from close - 1 to close + 1
+        assert_eq!(BoxSize::Atr(2).resolve(&candles), Some(2.0));
+        assert_eq!(BoxSize::Atr(50).resolve(&candles), Some(2.0));
+        // No box under a thousandth of the last close
+        assert_eq!(BoxSize::Fixed(0.001).resolve(&candles), Some(0.1));
+        assert_eq!(BoxSize::Fixed(2.0).resolve(&[]), None);
+    }
+
+    #[test]
+    fn heikin_ashi_known_values() {
+        let candles = [
+            bar(0, 10.0, 12.0, 8.0, 11.0),
+            bar(1, 11.0, 14.0, 10.0, 13.0),
+        ];
+        let series = ChartType::HeikinAshi.transform(&candles, None);
+        let ohlc: Vec<_> = series
+            .iter()
+            .map(|ha| (ha.open, ha.high, ha.low, ha.close))
+            .collect();
+        assert_eq!(ohlc, [(10.5, 12.0, 8.0, 10.25), (10.375, 14.0, 10.0, 12.0)]);
+        assert_eq!(series[1].time, candles[1].time);
+    }
+
+    #[test]
+    fn renko_bricks_with_unique_times() {
+        let candles = bars(&[100.0, 101.0, 104.0, 99.0, 97.0]);
+        let bricks = ChartType::Renko(BoxSize::Fixed(1.0)).transform(&candles, Some(1.0));
+        // The turn at 99 starts below the top brick, from 103
+        assert_eq!(
+            closes(&bricks),
+            [
+                101.0, 102.0, 103.0, 104.0, 102.0, 101.0, 100.0, 99.0, 98.0, 97.0
+            ]
+        );
+        assert_eq!((bricks[4].open, bricks[4].close), (103.0, 102.0));
+        let volumes: Vec<f32> = bricks.iter().map(|brick| brick.volume).collect();
+        assert_eq!(
+            volumes,
+            [200.0, 100.0, 0.0, 0.0, 100.0, 0.0, 0.0, 0.0, 100.0, 0.0]
+        );
+
+        // Bricks of one bar are a millisecond apart and each keeps its place
+        assert_eq!(bricks[1].time, candles[2].time);
+        assert_eq!(bricks[3].time, candles[2].time + Duration::milliseconds(2));
+        for (i, brick) in bricks.iter().enumerate() {
+            assert_eq!(
+                time_to_x(&bricks, brick.time.timestamp_millis() as f64 / 1000.0),
+                i as f32
+            );
+        }
+    }
+
+    #[test]
+    fn point_figure_columns() {
+        let candles = bars(&[100.0, 102.0, 103.0, 101.0, 100.0, 99.0, 102.0, 104.0]);
+        let columns = ChartType::PointFigure(BoxSize::Fixed(1.0)).transform(&candles, Some(1.0));
+        let boxes: Vec<_> = columns
+            .iter()
+            .map(|column| (column.open, column.close))
+            .collect();
+        // A turn takes three boxes past the box next to the last one
+        assert_eq!(boxes, [(100.0, 103.0), (102.0, 99.0), (100.0, 104.0)]);
+        // Each column at the time of the bar that started it
+        let times: Vec<_> = columns.iter().map(|column| column.time).collect();
+        assert_eq!(times, [candles[1].time, candles[5].time, candles[7].time]);
+        let volumes: Vec<f32> = columns.iter().map(|column| column.volume).collect();
+        assert_eq!(volumes, [500.0, 200.0, 100.0]);
+    }
+
+    #[test]
+    fn extend_matches_a_full_transform() {
+        let full = swings(60);
+        let mut before = full[..40].to_vec();
+        // The last bar was still forming
+        before[39].close -= 3.0;
+        for chart_type in [
+            ChartType::Candles,
+            ChartType::HeikinAshi,
+            ChartType::Renko(BoxSize::Fixed(1.0)),
+            ChartType::PointFigure(BoxSize::Fixed(1.0)),
+        ] {
+            let size = chart_type.resolve_box(&full);
+            let mut series = chart_type.transform(&before, size);
+            chart_type.extend(&mut series, &full, 39, size);
+            assert_eq!(series, chart_type.transform(&full, size), "{chart_type:?}");
+        }
+    }
+}
@@ -0,0 +1,287 @@
+//! Compare mode: other symbols or indices drawn over the price pane.
+//!
+//! Each comparison is rebased to the chart's close at the first visible bar,
+//! so its line shows on the price axis how far it moved in percent. In ratio
+//! mode each line is the chart's symbol divided by the comparison, rebased
+//! the same way: it rises while the symbol outperforms the benchmark.
+
+use aim_data::{OHLCData, Timeframe};
+use chrono::{DateTime, Utc};
+use plotters::{
+    backend::BitMapBackend,
+    chart::ChartContext,
+    coord::types::RangedCoordf32,
+    element::Text,
+    prelude::Cartesian2d,
+    series::LineSeries,
+    style::{Color, IntoFont, RGBColor},
+};
+
+use crate::{CandleData, CandleDataVec, Chart, chart::prepare_candles, merge_candles};
+
+/// Line colours of the comparisons, in the order they were added.
+const COMPARE_COLORS: [RGBColor; 4] = [
+    RGBColor(0xFF, 0x98, 0x00),
+    RGBColor(0x00, 0xBC, 0xD4),
+    RGBColor(0xE9, 0x1E, 0x63),
+    RGBColor(0x8B, 0xC3, 0x4A),
+];
+
+/// Another symbol or index shown over the chart.
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub symbol: String,
+    pub color: RGBColor,
+    candles: CandleDataVec,
+    time_frame: Option<Timeframe>, // of `candles`, None until they are fetched
+}
+
+impl Comparison {
+    fn new(symbol: String, color: RGBColor) -> Self {
+        Self {
+            symbol,
+            color,
+            candles: Vec::new(),
+            time_frame: None,
+        }
+    }
+
+    /// Close of the last bar at or before `time`.
+    fn close_at(&self, time: DateTime<Utc>) -> Option<f32> {
+        let index = self.candles.partition_point(|candle| candle.time <= time);
+        Some(self.candles.get(index.checked_sub(1)?)?.close)
+    }
+
+    /// Price-axis value of the comparison at `candle`, rebased to `base`,
+    /// the first visible bar of the chart.
+    ///
+    /// # Arguments
+    /// * `ratio` - The chart's symbol divided by the comparison instead
+    fn rebase(&self, base: &CandleData, candle: &CandleData, ratio: bool) -> Option<f32> {
+        let base_close = self.close_at(base.time)?;
+        let close = self.close_at(candle.time)?;
+        Some(if ratio {
+            base.close * (candle.close / close) / (base.close / base_close)
+        } else {
+            base.close * close / base_close
+        })
+    }
+}
+
+/// Parse a comparison list such as `VNINDEX, VN30` into upper case symbols.
+pub fn parse_comparisons(spec: &str) -> Vec<String> {
+    let mut symbols: Vec<String> = Vec::new();
+    for symbol in spec.split([',', ';', ' ']).map(str::trim) {
+        let symbol = symbol.to_uppercase();
+        if !symbol.is_empty() && !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    symbols
+}
+
+impl Chart {
+    /// Compare with `symbols`, keeping the bars of those already shown.
+    ///
+    /// # Arguments
+    /// * `ratio` - Draw this symbol divided by each comparison instead of the comparisons
+    pub fn set_comparisons(&mut self, symbols: &[String], ratio: bool) {
+        let mut previous = std::mem::take(&mut self.comparisons);
+        self.comparisons = symbols
+            .iter()
+            .filter(|symbol| **symbol != self.stock_name)
+            .enumerate()
+            .map(
+                |(i, symbol)| match previous.iter().position(|cmp| cmp.symbol == *symbol) {
+                    Some(found) => previous.swap_remove(found),
+                    None => {
+                        Comparison::new(symbol.clone(), COMPARE_COLORS[i % COMPARE_COLORS.len()])
+                    }
+                },
+            )
+            .collect();
+        self.compare_ratio = ratio;
+        self.mark_dirty();
+    }
+
+    /// The comparisons as text for the compare editor.
+    pub fn compare_spec(&self) -> String {
+        self.comparisons
+            .iter()
+            .map(|cmp| cmp.symbol.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Symbols whose bars are missing or of another time frame, none while
+    /// the chart waits for its own bars, where theirs start.
+    pub fn stale_comparisons(&self) -> Vec<String> {
+        if self.source_data.is_empty() {
+            return Vec::new();
+        }
+        self.comparisons
+            .iter()
+            .filter(|cmp| cmp.time_frame != Some(self.time_frame))
+            .map(|cmp| cmp.symbol.clone())
+            .collect()
+    }
+
+    /// Symbols of the comparisons that already have bars, for live updates.
+    pub fn loaded_comparisons(&self) -> Vec<String> {
+        self.comparisons
+            .iter()
+            .filter(|cmp| cmp.time_frame == Some(self.time_frame))
+            .map(|cmp| cmp.symbol.clone())
+            .collect()
+    }
+
+    /// Replace the bars of the comparison `stock.symbol` with `stock`,
+    /// resampled to the chart's time frame.
+    pub fn update_comparison(&mut self, stock: &OHLCData) {
+        let time_frame = self.time_frame;
+        if let Some(cmp) = self
+            .comparisons
+            .iter_mut()
+            .find(|cmp| cmp.symbol == stock.symbol)
+        {
+            cmp.candles = prepare_candles(stock, time_frame, None);
+            cmp.time_frame = Some(time_frame);
+        }
+    }
+
+    /// Merge bars of the comparison `stock.symbol` fetched since its last one.
+    pub fn merge_comparison(&mut self, stock: &OHLCData) {
+        let time_frame = self.time_frame;
+        if let Some(cmp) = self
+            .comparisons
+            .iter_mut()
+            .find(|cmp| cmp.symbol == stock.symbol && cmp.time_frame == Some(time_frame))
+        {
+            merge_candles(&mut cmp.candles, prepare_candles(stock, time_frame, None));
+        }
+    }
+
+    /// Draw the comparison lines over the visible bars, with one legend line
+    /// each under the overlay legends.
+    pub(crate) fn chart_draw_comparisons(
+        &self,
+        chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+    ) {
+        let (min_candle_x, max_candle_x) = self.get_min_max_of_candle_after_moving();
+        let max_candle_x = max_candle_x.min(self.candle_data.len());
+        if min_candle_x >= max_candle_x {
+            return;
+        }
+        let base = &self.candle_data[min_candle_x];
+        let visible = &self.candle_data[min_candle_x..max_candle_x];
+        let (mouse_x, _) = self.get_mouse_position();
+        let cursor = (mouse_x.round().max(0.0) as usize).clamp(min_candle_x, max_candle_x - 1);
+
+        let (_, max_y, min_y) = self.update_y_axis_after_moving();
+        let (min_x, _) = self.update_x_axis_after_moving();
+        let overlays = self
+            .indicators
+            .iter()
+            .filter(|study| !study.in_pane())
+            .count();
+
+        for (i, cmp) in self.comparisons.iter().enumerate() {
+            if cmp.close_at(base.time).is_none() {
+                continue;
+            }
+            let rebase = |candle: &CandleData| cmp.rebase(base, candle, self.compare_ratio);
+            let points: Vec<(f32, f32)> = visible
+                .iter()
+                .filter_map(|candle| Some((candle.num, self.price_scale.to_axis(rebase(candle)?))))
+                .collect();
+            let _ = chart.draw_series(LineSeries::new(points, cmp.color.stroke_width(2)));
+
+            // Change since the first visible bar at the bar under the cursor
+            let change = rebase(&self.candle_data[cursor])
+                .map(|value| format!("{:+.2}%", (value / base.close - 1.0) * 100.0))
+                .unwrap_or_default();
+            let name = if self.compare_ratio {
+                format!("{} / {}", self.stock_name, cmp.symbol)
+            } else {
+                cmp.symbol.clone()
+            };
+            let y_text = max_y
+                - (max_y - min_y)
+                    * ((60.0 + 20.0 * (overlays + i) as f32) / self.chart_data.height as f32);
+            let _ = chart.plotting_area().draw(&Text::new(
+                format!("  {name}  {change}"),
+                (min_x, y_text),
+                ("sans-serif", 15).into_font().color(&cmp.color),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{bar, bars, chart};
+
+    fn comparison(candles: CandleDataVec) -> Comparison {
+        Comparison {
+            candles,
+            time_frame: Some(Timeframe::default()),
+            ..Comparison::new("VNINDEX".to_string(), COMPARE_COLORS[0])
+        }
+    }
+
+    #[test]
+    fn parse_comparisons_upper_cases_and_drops_repeats() {
+        assert_eq!(
+            parse_comparisons(" vnindex, VN30;hpg  VNINDEX,,"),
+            vec!["VNINDEX", "VN30", "HPG"]
+        );
+        assert!(parse_comparisons(" , ; ").is_empty());
+    }
+
+    #[test]
+    fn set_comparisons_skips_own_symbol_and_keeps_fetched_bars() {
+        let mut chart = chart(bars(&[10.0, 11.0]));
+        chart.set_comparisons(&parse_comparisons("VNINDEX"), false);
+        chart.comparisons[0] = comparison(bars(&[100.0, 110.0]));
+
+        chart.set_comparisons(&parse_comparisons("TEST, VN30, VNINDEX"), true);
+        let symbols: Vec<&str> = chart
+            .comparisons
+            .iter()
+            .map(|cmp| cmp.symbol.as_str())
+            .collect();
+        assert_eq!(symbols, ["VN30", "VNINDEX"]);
+        assert_eq!(chart.comparisons[1].candles.len(), 2);
+        assert!(chart.compare_ratio);
+        assert_eq!(chart.stale_comparisons(), ["VN30"]);
+    }
+
+    #[test]
+    fn rebase_follows_the_comparison_from_the_base_bar() {
+        // No bar on day 2: the comparison keeps its close of day 1
+        let cmp = comparison(vec![
+            bar(0, 100.0, 100.0, 100.0, 100.0),
+            bar(1, 110.0, 110.0, 110.0, 110.0),
+            bar(3, 90.0, 90.0, 90.0, 90.0),
+        ]);
+        let candles = bars(&[50.0, 60.0, 40.0, 45.0]);
+        let base = &candles[0];
+
+        let percent: Vec<f32> = candles
+            .iter()
+            .map(|candle| cmp.rebase(base, candle, false).unwrap())
+            .collect();
+        assert_eq!(percent, [50.0, 55.0, 55.0, 45.0]);
+
+        // The symbol over the comparison, equal to the symbol at the base bar
+        let ratio = cmp.rebase(base, &candles[1], true).unwrap();
+        assert!((ratio - 50.0 * (60.0 / 110.0) / (50.0 / 100.0)).abs() < 1e-4);
+        assert_eq!(cmp.rebase(base, base, true), Some(50.0));
+
+        // Nothing before the first bar of the comparison
+        let late = comparison(vec![bar(1, 110.0, 110.0, 110.0, 110.0)]);
+        assert_eq!(late.rebase(base, &candles[1], false), None);
+        assert_eq!(late.rebase(&candles[1], &candles[2], false), Some(60.0));
+    }
+}
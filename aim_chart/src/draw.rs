@@ -1,7 +1,33 @@
 use plotters::style::RGBColor;
+use serde::{Deserialize, Serialize};
+
+use crate::{CandleData, PriceScale, chart::THREAD_HOLD};
 
 pub type Point = (f32, f32);
-#[derive(Debug, Clone, PartialEq, Default)]
+
+/// Font size of a text annotation when none is chosen.
+pub const DEFAULT_TEXT_SIZE: u32 = 14;
+/// Fibonacci retracement levels when none are chosen.
+pub const FIB_RETRACEMENT_LEVELS: [f32; 7] = [0.0, 0.236, 0.382, 0.5, 0.618, 0.786, 1.0];
+/// Fibonacci extension levels when none are chosen.
+pub const FIB_EXTENSION_LEVELS: [f32; 7] = [0.0, 0.618, 1.0, 1.272, 1.618, 2.618, 4.236];
+/// Price-over-time ratios of the Gann fan lines, from flattest to steepest.
+pub const GANN_RATIOS: [(u8, u8); 9] = [
+    (1, 8),
+    (1, 4),
+    (1, 3),
+    (1, 2),
+    (1, 1),
+    (2, 1),
+    (3, 1),
+    (4, 1),
+    (8, 1),
+];
+/// Average glyph width as a share of the font size, to size the hit box of a
+/// text annotation without measuring it.
+const GLYPH_WIDTH: f32 = 0.6;
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
 pub enum DrawType {
     Line((Point, Point)),
     Arrow((Point, Point)),
@@ -11,14 +37,110 @@ pub enum DrawType {
     VerticalLine(f32),
     HorizontalLine(f32),
     Pen(Vec<Point>),
+    /// Label whose top-left corner sits at `anchor`; `size` is in pixels
+    Text {
+        anchor: Point,
+        content: String,
+        size: u32,
+    },
+    /// Levels of the move from the first to the second point, 0 at the end
+    /// of the move and 1 at its start
+    FibRetracement {
+        points: (Point, Point),
+        levels: Vec<f32>,
+    },
+    /// Levels of the first move (points 0 to 1) projected from the third point
+    FibExtension {
+        points: (Point, Point, Point),
+        levels: Vec<f32>,
+    },
+    /// Base line through the first two points and a parallel one through the third
+    Channel((Point, Point, Point)),
+    /// Andrews' pitchfork: pivot, then the two points whose midpoint the
+    /// median line runs through
+    Pitchfork((Point, Point, Point)),
+    /// Fan from the first point; the second one sets the 1x1 line
+    GannFan((Point, Point)),
+    /// Long or short trade from `entry` until `end_x`, with target and stop prices
+    Position {
+        entry: Point,
+        end_x: f32,
+        target: f32,
+        stop: f32,
+        long: bool,
+    },
     #[default]
     Empty,
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct DrawObject {
     pub draw_type: DrawType,
     pub color: RGBColor,
+    pub style: DrawStyle,
+}
+
+/// How a drawing is stroked and filled, and whether the user can move it or
+/// see it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DrawStyle {
+    pub width: u32,        // line width in pixels
+    pub dashed: bool,      // dashed instead of solid lines
+    pub fill_opacity: f32, // of the fill of rectangles, ovals and positions
+    pub locked: bool,      // cannot be moved or reshaped
+    pub hidden: bool,      // neither drawn nor hit by the cursor
+}
+
+impl Default for DrawStyle {
+    fn default() -> Self {
+        Self {
+            width: 1,
+            dashed: false,
+            fill_opacity: 0.2,
+            locked: false,
+            hidden: false,
+        }
+    }
+}
+
+impl DrawStyle {
+    // Manual serialization: width, flag bits and fill opacity
+    pub fn write_to_bytes(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.width.to_le_bytes());
+        let flags = self.dashed as u8 | (self.locked as u8) << 1 | (self.hidden as u8) << 2;
+        buf.push(flags);
+        buf.extend_from_slice(&self.fill_opacity.to_le_bytes());
+    }
+
+    pub fn read_from_bytes(data: &[u8]) -> Option<(Self, usize)> {
+        if data.len() < 9 {
+            return None;
+        }
+        let width = u32::from_le_bytes(data[0..4].try_into().unwrap());
+        let flags = data[4];
+        let fill_opacity = f32::from_le_bytes(data[5..9].try_into().unwrap());
+        let style = Self {
+            width,
+            dashed: flags & 1 != 0,
+            fill_opacity,
+            locked: flags & 2 != 0,
+            hidden: flags & 4 != 0,
+        };
+        Some((style, 9))
+    }
+}
+
+/// Time span of one bar when the history has a single candle.
+const SINGLE_BAR_SECONDS: f64 = 86_400.0;
+
+/// A drawing pinned to (timestamp, price) anchors instead of candle indices,
+/// so it keeps its place when the history is reloaded or shown in another
+/// time frame.
+#[derive(Debug, Clone, Default)]
+pub struct AnchoredDrawing {
+    object: DrawObject, // prices and style; the x coordinates come from `times`
+    times: Vec<f64>,    // unix time in seconds of each x coordinate of `object`
 }
 
 // Manual serialization for DrawType
@@ -76,12 +198,97 @@ pub fn draw_type_write_to_bytes(draw_type: &DrawType, buf: &mut Vec<u8>) {
                 buf.extend_from_slice(&p.1.to_le_bytes());
             }
         }
+        DrawType::Text {
+            anchor,
+            content,
+            size,
+        } => {
+            buf.push(9);
+            buf.extend_from_slice(&anchor.0.to_le_bytes());
+            buf.extend_from_slice(&anchor.1.to_le_bytes());
+            buf.extend_from_slice(&size.to_le_bytes());
+            let bytes = content.as_bytes();
+            buf.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+            buf.extend_from_slice(bytes);
+        }
+        DrawType::FibRetracement {
+            points: (a, b),
+            levels,
+        } => {
+            buf.push(10);
+            write_points(buf, &[*a, *b]);
+            write_levels(buf, levels);
+        }
+        DrawType::FibExtension {
+            points: (a, b, c),
+            levels,
+        } => {
+            buf.push(11);
+            write_points(buf, &[*a, *b, *c]);
+            write_levels(buf, levels);
+        }
+        DrawType::Channel((a, b, c)) => {
+            buf.push(12);
+            write_points(buf, &[*a, *b, *c]);
+        }
+        DrawType::Pitchfork((a, b, c)) => {
+            buf.push(13);
+            write_points(buf, &[*a, *b, *c]);
+        }
+        DrawType::GannFan((a, b)) => {
+            buf.push(14);
+            write_points(buf, &[*a, *b]);
+        }
+        DrawType::Position {
+            entry,
+            end_x,
+            target,
+            stop,
+            long,
+        } => {
+            buf.push(15);
+            write_points(buf, &[*entry]);
+            buf.extend_from_slice(&end_x.to_le_bytes());
+            buf.extend_from_slice(&target.to_le_bytes());
+            buf.extend_from_slice(&stop.to_le_bytes());
+            buf.push(*long as u8);
+        }
         DrawType::Empty => {
             buf.push(0);
         }
     }
 }
 
+fn write_points(buf: &mut Vec<u8>, points: &[Point]) {
+    for point in points {
+        buf.extend_from_slice(&point.0.to_le_bytes());
+        buf.extend_from_slice(&point.1.to_le_bytes());
+    }
+}
+
+fn write_levels(buf: &mut Vec<u8>, levels: &[f32]) {
+    buf.extend_from_slice(&(levels.len() as u32).to_le_bytes());
+    for level in levels {
+        buf.extend_from_slice(&level.to_le_bytes());
+    }
+}
+
+fn read_f32(data: &[u8], pos: &mut usize) -> Option<f32> {
+    let value = f32::from_le_bytes(data.get(*pos..*pos + 4)?.try_into().unwrap());
+    *pos += 4;
+    Some(value)
+}
+
+fn read_point(data: &[u8], pos: &mut usize) -> Option<Point> {
+    Some((read_f32(data, pos)?, read_f32(data, pos)?))
+}
+
+fn read_levels(data: &[u8], pos: &mut usize) -> Option<Vec<f32>> {
+    let count = u32::from_le_bytes(data.get(*pos..*pos + 4)?.try_into().unwrap()) as usize;
+    *pos += 4;
+    (0..count).map(|_| read_f32(data, pos)).collect()
+}
+
 pub fn draw_type_read_from_bytes(data: &[u8]) -> Option<(DrawType, usize)> {
     if data.is_empty() {
         return None;
@@ -147,6 +354,81 @@ pub fn draw_type_read_from_bytes(data: &[u8]) -> Option<(DrawType, usize)> {
             }
             Some((DrawType::Pen(points), pos))
         }
+        9 => {
+            if data.len() < pos + 16 {
+                return None;
+            }
+            let x = f32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+            pos += 4;
+            let y = f32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+            pos += 4;
+            let size = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap());
+            pos += 4;
+            let len = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+            pos += 4;
+            if data.len() < pos + len {
+                return None;
+            }
+            let content = String::from_utf8(data[pos..pos + len].to_vec()).ok()?;
+            pos += len;
+            Some((
+                DrawType::Text {
+                    anchor: (x, y),
+                    content,
+                    size,
+                },
+                pos,
+            ))
+        }
+        10 => {
+            let points = (read_point(data, &mut pos)?, read_point(data, &mut pos)?);
+            let levels = read_levels(data, &mut pos)?;
+            Some((DrawType::FibRetracement { points, levels }, pos))
+        }
+        11 => {
+            let points = (
+                read_point(data, &mut pos)?,
+                read_point(data, &mut pos)?,
+                read_point(data, &mut pos)?,
+            );
+            let levels = read_levels(data, &mut pos)?;
+            Some((DrawType::FibExtension { points, levels }, pos))
+        }
+        12 | 13 => {
+            let points = (
+                read_point(data, &mut pos)?,
+                read_point(data, &mut pos)?,
+                read_point(data, &mut pos)?,
+            );
+            let dt = if tag == 12 {
+                DrawType::Channel(points)
+            } else {
+                DrawType::Pitchfork(points)
+            };
+            Some((dt, pos))
+        }
+        14 => {
+            let points = (read_point(data, &mut pos)?, read_point(data, &mut pos)?);
+            Some((DrawType::GannFan(points), pos))
+        }
+        15 => {
+            let entry = read_point(data, &mut pos)?;
+            let end_x = read_f32(data, &mut pos)?;
+            let target = read_f32(data, &mut pos)?;
+            let stop = read_f32(data, &mut pos)?;
+            let long = *data.get(pos)? != 0;
+            pos += 1;
+            Some((
+                DrawType::Position {
+                    entry,
+                    end_x,
+                    target,
+                    stop,
+                    long,
+                },
+                pos,
+            ))
+        }
         0 => Some((DrawType::Empty, pos)),
         _ => None,
     }
@@ -169,11 +451,149 @@ impl DrawObject {
         let g = data[used1 + 1];
         let b = data[used1 + 2];
         let color = RGBColor(r, g, b);
-        Some((Self { draw_type, color }, used1 + 3))
+        Some((Self::new(draw_type, color), used1 + 3))
     }
 
     pub fn new(draw_type: DrawType, color: RGBColor) -> Self {
-        Self { draw_type, color }
+        Self {
+            draw_type,
+            color,
+            style: DrawStyle::default(),
+        }
+    }
+
+    /// Points the user drags to reshape the object.
+    pub fn handles(&self) -> Vec<Point> {
+        match &self.draw_type {
+            DrawType::Line((a, b))
+            | DrawType::Arrow((a, b))
+            | DrawType::Rectangle((a, b))
+            | DrawType::Oval((a, b))
+            | DrawType::Ruler((a, b))
+            | DrawType::FibRetracement { points: (a, b), .. }
+            | DrawType::GannFan((a, b)) => vec![*a, *b],
+            DrawType::FibExtension {
+                points: (a, b, c), ..
+            }
+            | DrawType::Channel((a, b, c))
+            | DrawType::Pitchfork((a, b, c)) => vec![*a, *b, *c],
+            DrawType::Text { anchor, .. } => vec![*anchor],
+            DrawType::Position {
+                entry,
+                end_x,
+                target,
+                stop,
+                ..
+            } => vec![*entry, (*end_x, *target), (*end_x, *stop)],
+            DrawType::VerticalLine(_)
+            | DrawType::HorizontalLine(_)
+            | DrawType::Pen(_)
+            | DrawType::Empty => vec![],
+        }
+    }
+
+    /// Move the handle at `index` of [`handles`](Self::handles) to `to`.
+    pub fn move_handle(&mut self, index: usize, to: Point) {
+        let point = match &mut self.draw_type {
+            DrawType::Line((a, b))
+            | DrawType::Arrow((a, b))
+            | DrawType::Rectangle((a, b))
+            | DrawType::Oval((a, b))
+            | DrawType::Ruler((a, b))
+            | DrawType::FibRetracement { points: (a, b), .. }
+            | DrawType::GannFan((a, b)) => [a, b].into_iter().nth(index),
+            DrawType::FibExtension {
+                points: (a, b, c), ..
+            }
+            | DrawType::Channel((a, b, c))
+            | DrawType::Pitchfork((a, b, c)) => [a, b, c].into_iter().nth(index),
+            DrawType::Text { anchor, .. } if index == 0 => Some(anchor),
+            DrawType::Position {
+                entry,
+                end_x,
+                target,
+                stop,
+                ..
+            } => {
+                // The target and stop handles also move the end of the position
+                match index {
+                    0 => *entry = to,
+                    1 => (*end_x, *target) = to,
+                    2 => (*end_x, *stop) = to,
+                    _ => {}
+                }
+                None
+            }
+            _ => None,
+        };
+        if let Some(point) = point {
+            *point = to;
+        }
+    }
+
+    /// Index of the handle within the thresholds of `point`, if any.
+    pub fn hit_handle(
+        &self,
+        point: Point,
+        x_thresh_hold: f32,
+        y_thresh_hold: f32,
+    ) -> Option<usize> {
+        self.handles().iter().position(|handle| {
+            (handle.0 - point.0).abs() < x_thresh_hold && (handle.1 - point.1).abs() < y_thresh_hold
+        })
+    }
+
+    /// X coordinates of every point of the object, in storage order.
+    fn xs_mut(&mut self) -> Vec<&mut f32> {
+        match &mut self.draw_type {
+            DrawType::Line((start, end))
+            | DrawType::Arrow((start, end))
+            | DrawType::Rectangle((start, end))
+            | DrawType::Oval((start, end))
+            | DrawType::Ruler((start, end)) => vec![&mut start.0, &mut end.0],
+            DrawType::VerticalLine(x) => vec![x],
+            DrawType::Pen(points) => points.iter_mut().map(|p| &mut p.0).collect(),
+            DrawType::Text { anchor, .. } => vec![&mut anchor.0],
+            DrawType::FibRetracement { points: (a, b), .. } | DrawType::GannFan((a, b)) => {
+                vec![&mut a.0, &mut b.0]
+            }
+            DrawType::FibExtension {
+                points: (a, b, c), ..
+            }
+            | DrawType::Channel((a, b, c))
+            | DrawType::Pitchfork((a, b, c)) => vec![&mut a.0, &mut b.0, &mut c.0],
+            DrawType::Position { entry, end_x, .. } => vec![&mut entry.0, end_x],
+            DrawType::HorizontalLine(_) | DrawType::Empty => vec![],
+        }
+    }
+
+    /// Y coordinates of every point and level price of the object.
+    fn ys_mut(&mut self) -> Vec<&mut f32> {
+        match &mut self.draw_type {
+            DrawType::Line((start, end))
+            | DrawType::Arrow((start, end))
+            | DrawType::Rectangle((start, end))
+            | DrawType::Oval((start, end))
+            | DrawType::Ruler((start, end)) => vec![&mut start.1, &mut end.1],
+            DrawType::HorizontalLine(y) => vec![y],
+            DrawType::Pen(points) => points.iter_mut().map(|p| &mut p.1).collect(),
+            DrawType::Text { anchor, .. } => vec![&mut anchor.1],
+            DrawType::FibRetracement { points: (a, b), .. } | DrawType::GannFan((a, b)) => {
+                vec![&mut a.1, &mut b.1]
+            }
+            DrawType::FibExtension {
+                points: (a, b, c), ..
+            }
+            | DrawType::Channel((a, b, c))
+            | DrawType::Pitchfork((a, b, c)) => vec![&mut a.1, &mut b.1, &mut c.1],
+            DrawType::Position {
+                entry,
+                target,
+                stop,
+                ..
+            } => vec![&mut entry.1, target, stop],
+            DrawType::VerticalLine(_) | DrawType::Empty => vec![],
+        }
     }
 
     pub fn hit_cursor(&self, point: Point, x_thresh_hold: f32, y_thresh_hold: f32) -> bool {
@@ -279,6 +699,45 @@ impl DrawObject {
                     }
                 })
             }
+            DrawType::Text {
+                anchor,
+                content,
+                size,
+            } => {
+                // The thresholds are THREAD_HOLD pixels in chart units
+                let width = content.chars().count() as f32 * *size as f32 * GLYPH_WIDTH;
+                let width = width * x_thresh_hold / THREAD_HOLD;
+                let height = *size as f32 * y_thresh_hold / THREAD_HOLD;
+                let (px, py) = point;
+                px >= anchor.0 - x_thresh_hold
+                    && px <= anchor.0 + width + x_thresh_hold
+                    && py <= anchor.1 + y_thresh_hold
+                    && py >= anchor.1 - height - y_thresh_hold
+            }
+            DrawType::Position {
+                entry,
+                end_x,
+                target,
+                stop,
+                ..
+            } => {
+                let (px, py) = point;
+                let (min_x, max_x) = (entry.0.min(*end_x), entry.0.max(*end_x));
+                let (min_y, max_y) = (target.min(*stop), target.max(*stop));
+                px >= min_x - x_thresh_hold
+                    && px <= max_x + x_thresh_hold
+                    && py >= min_y - y_thresh_hold
+                    && py <= max_y + y_thresh_hold
+            }
+            DrawType::FibRetracement { .. }
+            | DrawType::FibExtension { .. }
+            | DrawType::Channel(_)
+            | DrawType::Pitchfork(_)
+            | DrawType::GannFan(_) => self
+                .draw_type
+                .segments()
+                .into_iter()
+                .any(|(a, b)| near_segment(point, a, b, x_thresh_hold, y_thresh_hold)),
             _ => false,
         }
     }
@@ -309,11 +768,51 @@ impl DrawObject {
                     p.1 += dy;
                 }
             }
+            DrawType::Text { anchor, .. } => {
+                anchor.0 += dx;
+                anchor.1 += dy;
+            }
+            DrawType::FibRetracement { points: (a, b), .. } | DrawType::GannFan((a, b)) => {
+                for p in [a, b] {
+                    p.0 += dx;
+                    p.1 += dy;
+                }
+            }
+            DrawType::FibExtension {
+                points: (a, b, c), ..
+            }
+            | DrawType::Channel((a, b, c))
+            | DrawType::Pitchfork((a, b, c)) => {
+                for p in [a, b, c] {
+                    p.0 += dx;
+                    p.1 += dy;
+                }
+            }
+            DrawType::Position {
+                entry,
+                end_x,
+                target,
+                stop,
+                ..
+            } => {
+                entry.0 += dx;
+                entry.1 += dy;
+                *end_x += dx;
+                *target += dy;
+                *stop += dy;
+            }
             _ => {}
         }
     }
 
     pub fn clipped(&mut self, x_min: f32, x_max: f32, y_min: f32, y_max: f32) {
+        // The multi-line tools are clipped line by line when drawn; hide them
+        // once none of their lines is visible
+        let segments = self.draw_type.segments();
+        if !segments.is_empty() && clip_segments(segments, x_min, x_max, y_min, y_max).is_empty() {
+            self.draw_type = DrawType::Empty;
+            return;
+        }
         match &mut self.draw_type {
             DrawType::Line((start, end)) | DrawType::Arrow((start, end)) => {
                 // Cohen–Sutherland line clipping algorithm (simple version)
@@ -370,6 +869,26 @@ impl DrawObject {
                     self.draw_type = DrawType::Empty;
                 }
             }
+            // Hide the label once its anchor leaves the chart
+            DrawType::Text { anchor, .. }
+                if !(x_min..=x_max).contains(&anchor.0) || !(y_min..=y_max).contains(&anchor.1) =>
+            {
+                self.draw_type = DrawType::Empty;
+            }
+            // Hide a position once its box leaves the chart
+            DrawType::Position {
+                entry,
+                end_x,
+                target,
+                stop,
+                ..
+            } if entry.0.max(*end_x) < x_min
+                || entry.0.min(*end_x) > x_max
+                || target.max(*stop) < y_min
+                || target.min(*stop) > y_max =>
+            {
+                self.draw_type = DrawType::Empty;
+            }
             _ => {}
         }
     }
@@ -409,11 +928,254 @@ impl DrawObject {
             DrawType::VerticalLine(x) => vec![(*x, p1.1), (*x, p2.1)],
             DrawType::HorizontalLine(y) => vec![(p1.0, *y), (p2.0, *y)],
             DrawType::Pen(points) => points.clone(),
+            DrawType::Text { anchor, .. } => vec![*anchor],
             _ => vec![],
         }
     }
 }
 
+impl DrawType {
+    /// Price and x span of each level of a Fibonacci tool, in the order of
+    /// its levels. Empty for other drawings.
+    pub(crate) fn level_lines(&self) -> Vec<(f32, f32, (Point, Point))> {
+        match self {
+            DrawType::FibRetracement {
+                points: (start, end),
+                levels,
+            } => {
+                let (min_x, max_x) = (start.0.min(end.0), start.0.max(end.0));
+                levels
+                    .iter()
+                    .map(|&level| {
+                        let price = end.1 + (start.1 - end.1) * level;
+                        (level, price, ((min_x, price), (max_x, price)))
+                    })
+                    .collect()
+            }
+            DrawType::FibExtension {
+                points: (a, b, c),
+                levels,
+            } => {
+                // Levels run from the third point as far right as the first move
+                let right = c.0 + (b.0 - a.0).abs().max(1.0);
+                levels
+                    .iter()
+                    .map(|&level| {
+                        let price = c.1 + (b.1 - a.1) * level;
+                        (level, price, ((c.0, price), (right, price)))
+                    })
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Straight lines of the multi-line tools, unclipped. Empty for other
+    /// drawings.
+    ///
+    /// Lines that run on past the points (channel, pitchfork tines, fan)
+    /// stop one more tool length to the right.
+    pub(crate) fn segments(&self) -> Vec<(Point, Point)> {
+        match self {
+            DrawType::FibRetracement {
+                points: (start, end),
+                ..
+            } => {
+                let mut lines: Vec<_> = self.level_lines().into_iter().map(|l| l.2).collect();
+                lines.push((*start, *end));
+                lines
+            }
+            DrawType::FibExtension {
+                points: (a, b, c), ..
+            } => {
+                let mut lines: Vec<_> = self.level_lines().into_iter().map(|l| l.2).collect();
+                lines.push((*a, *b));
+                lines.push((*b, *c));
+                lines
+            }
+            DrawType::Channel((a, b, c)) => {
+                // Vertical distance from the base line to the third point
+                let slope = if b.0 != a.0 {
+                    (b.1 - a.1) / (b.0 - a.0)
+                } else {
+                    0.0
+                };
+                let offset = c.1 - (a.1 + slope * (c.0 - a.0));
+                let shift = |p: &Point, by: f32| (p.0, p.1 + by);
+                vec![
+                    (*a, *b),
+                    (shift(a, offset), shift(b, offset)),
+                    (shift(a, offset / 2.0), shift(b, offset / 2.0)),
+                ]
+            }
+            DrawType::Pitchfork((pivot, b, c)) => {
+                let mid = ((b.0 + c.0) / 2.0, (b.1 + c.1) / 2.0);
+                let run = (mid.0 - pivot.0, mid.1 - pivot.1);
+                let extend = |p: &Point| (p.0 + run.0, p.1 + run.1);
+                vec![
+                    (*pivot, extend(&mid)),
+                    (*b, extend(b)),
+                    (*c, extend(c)),
+                    (*b, *c),
+                ]
+            }
+            DrawType::GannFan((origin, unit)) => {
+                let (dx, dy) = (unit.0 - origin.0, unit.1 - origin.1);
+                GANN_RATIOS
+                    .iter()
+                    .map(|&(price, time)| {
+                        let slope = dy * price as f32 / time as f32;
+                        (*origin, (origin.0 + 2.0 * dx, origin.1 + 2.0 * slope))
+                    })
+                    .collect()
+            }
+            _ => vec![],
+        }
+    }
+}
+
+/// Parse a list of Fibonacci levels such as "0 0.382 0.618 1" or
+/// "0, 61.8%, 100%".
+///
+/// # Returns
+/// * `Option<Vec<f32>>` - Levels as ratios, or None when the list is empty or
+///   an entry is not a number
+pub fn parse_levels(text: &str) -> Option<Vec<f32>> {
+    let levels: Option<Vec<f32>> = text
+        .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+        .map(|token| match token.strip_suffix('%') {
+            Some(percent) => percent.parse::<f32>().ok().map(|p| p / 100.0),
+            None => token.parse().ok(),
+        })
+        .collect();
+    levels.filter(|levels| !levels.is_empty())
+}
+
+/// Whether `point` lies within the thresholds of the segment from `a` to `b`.
+fn near_segment(point: Point, a: Point, b: Point, x_thresh_hold: f32, y_thresh_hold: f32) -> bool {
+    let (px, py) = point;
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq == 0.0 {
+        0.0
+    } else {
+        (((px - a.0) * dx + (py - a.1) * dy) / length_sq).clamp(0.0, 1.0)
+    };
+    (px - (a.0 + t * dx)).abs() < x_thresh_hold && (py - (a.1 + t * dy)).abs() < y_thresh_hold
+}
+
+/// Visible parts of `segments` within the bounds.
+pub(crate) fn clip_segments(
+    segments: Vec<(Point, Point)>,
+    x_min: f32,
+    x_max: f32,
+    y_min: f32,
+    y_max: f32,
+) -> Vec<(Point, Point)> {
+    segments
+        .into_iter()
+        .filter_map(|(mut a, mut b)| {
+            cohen_sutherland_clip(&mut a, &mut b, x_min, x_max, y_min, y_max).then_some((a, b))
+        })
+        .collect()
+}
+
+impl AnchoredDrawing {
+    /// Pin `object`, drawn over `candles` on `scale`, to the times and
+    /// prices under its points.
+    pub fn new(mut object: DrawObject, candles: &[CandleData], scale: PriceScale) -> Self {
+        let times = object
+            .xs_mut()
+            .into_iter()
+            .map(|x| x_to_time(candles, *x))
+            .collect();
+        for y in object.ys_mut() {
+            *y = scale.to_price(*y);
+        }
+        Self { object, times }
+    }
+
+    /// Rebuild a drawing from a cached object and the times of its points.
+    ///
+    /// Returns `None` when the number of times does not match the object.
+    pub fn from_parts(mut object: DrawObject, times: Vec<f64>) -> Option<Self> {
+        (object.xs_mut().len() == times.len()).then_some(Self { object, times })
+    }
+
+    /// Unix time in seconds of each x coordinate, in storage order.
+    pub fn times(&self) -> &[f64] {
+        &self.times
+    }
+
+    pub fn style(&self) -> DrawStyle {
+        self.object.style
+    }
+
+    pub fn set_style(&mut self, style: DrawStyle) {
+        self.object.style = style;
+    }
+
+    /// The drawing in the candle-index space of `candles` and the axis
+    /// units of `scale`.
+    pub fn place(&self, candles: &[CandleData], scale: PriceScale) -> DrawObject {
+        let mut object = self.object.clone();
+        for (x, &time) in object.xs_mut().into_iter().zip(&self.times) {
+            *x = time_to_x(candles, time);
+        }
+        for y in object.ys_mut() {
+            *y = scale.to_axis(*y);
+        }
+        object
+    }
+}
+
+/// Time of `candle` in seconds, to the millisecond that sets Renko bricks of
+/// the same bar apart.
+fn candle_seconds(candle: &CandleData) -> f64 {
+    candle.time.timestamp_millis() as f64 / 1000.0
+}
+
+/// Time under the x coordinate `x` of a chart of `candles`.
+///
+/// Between two bars the time is interpolated; before the first and after the
+/// last bar the spacing of the two nearest bars carries on.
+pub(crate) fn x_to_time(candles: &[CandleData], x: f32) -> f64 {
+    let x = x as f64;
+    match candles.len() {
+        0 => x,
+        1 => candle_seconds(&candles[0]) + x * SINGLE_BAR_SECONDS,
+        len => {
+            let i = (x.floor().max(0.0) as usize).min(len - 2);
+            let start = candle_seconds(&candles[i]);
+            start + (x - i as f64) * (candle_seconds(&candles[i + 1]) - start)
+        }
+    }
+}
+
+/// X coordinate of `time` on a chart of `candles`, the inverse of
+/// [`x_to_time`].
+pub(crate) fn time_to_x(candles: &[CandleData], time: f64) -> f32 {
+    match candles.len() {
+        0 => time as f32,
+        1 => ((time - candle_seconds(&candles[0])) / SINGLE_BAR_SECONDS) as f32,
+        len => {
+            let i = candles
+                .partition_point(|candle| candle_seconds(candle) <= time)
+                .saturating_sub(1)
+                .min(len - 2);
+            let start = candle_seconds(&candles[i]);
+            let span = candle_seconds(&candles[i + 1]) - start;
+            let fraction = if span > 0.0 {
+                (time - start) / span
+            } else {
+                0.0
+            };
+            (i as f64 + fraction) as f32
+        }
+    }
+}
+
 // Helper: Cohen–Sutherland line clipping algorithm for 2D lines
 fn cohen_sutherland_clip(
     p1: &mut (f32, f32),
@@ -486,3 +1248,70 @@ fn cohen_sutherland_clip(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{bar, bars};
+
+    const DAY: f64 = 86_400.0;
+
+    fn day(candles: &[CandleData], i: usize) -> f64 {
+        candle_seconds(&candles[i])
+    }
+
+    fn assert_round_trip(candles: &[CandleData], x: f32) {
+        let back = time_to_x(candles, x_to_time(candles, x));
+        assert!((back - x).abs() < 1e-3, "{x} came back as {back}");
+    }
+
+    #[test]
+    fn test_without_candles_x_is_the_time() {
+        assert_eq!(x_to_time(&[], 3.5), 3.5);
+        assert_eq!(time_to_x(&[], 3.5), 3.5);
+    }
+
+    #[test]
+    fn test_single_candle_steps_a_day_per_bar() {
+        let candles = bars(&[10.0]);
+        assert_eq!(x_to_time(&candles, 0.0), day(&candles, 0));
+        assert_eq!(x_to_time(&candles, 2.0), day(&candles, 0) + 2.0 * DAY);
+        assert_eq!(x_to_time(&candles, -1.0), day(&candles, 0) - DAY);
+        for x in [-3.0, -0.5, 0.0, 0.25, 4.0] {
+            assert_round_trip(&candles, x);
+        }
+    }
+
+    #[test]
+    fn test_times_follow_the_candles_across_gaps() {
+        // Friday, Monday and Tuesday: the weekend takes no room on the axis
+        let candles = vec![
+            bar(4, 10.0, 11.0, 9.0, 10.0),
+            bar(7, 10.0, 11.0, 9.0, 10.0),
+            bar(8, 10.0, 11.0, 9.0, 10.0),
+        ];
+        assert_eq!(x_to_time(&candles, 1.0), day(&candles, 1));
+        assert_eq!(x_to_time(&candles, 0.5), day(&candles, 0) + 1.5 * DAY);
+        // Before the first and after the last bar the nearest spacing carries on
+        assert_eq!(x_to_time(&candles, -1.0), day(&candles, 0) - 3.0 * DAY);
+        assert_eq!(x_to_time(&candles, 4.0), day(&candles, 2) + 2.0 * DAY);
+        for x in [-2.0, -0.5, 0.0, 0.5, 1.0, 1.75, 2.0, 3.5] {
+            assert_round_trip(&candles, x);
+        }
+    }
+
+    #[test]
+    fn test_equal_time_neighbours_stay_finite() {
+        let mut candles = bars(&[10.0, 11.0, 12.0, 13.0]);
+        candles[2].time = candles[1].time;
+        let time = day(&candles, 1);
+        assert_eq!(x_to_time(&candles, 1.5), time);
+        let x = time_to_x(&candles, time);
+        assert!((1.0..=2.0).contains(&x), "{x}");
+        // Times around the pair keep their order
+        assert!(time_to_x(&candles, time - DAY / 2.0) < x);
+        assert!(time_to_x(&candles, time + DAY / 2.0) > x);
+        assert_round_trip(&candles, 0.5);
+        assert_round_trip(&candles, 2.5);
+    }
+}
@@ -0,0 +1,282 @@
+//! Selecting, moving, reshaping and restyling the user's drawings, with
+//! undo and redo through the chart's [`DrawHistory`](crate::history::DrawHistory).
+//!
+//! A press on a drawing in move mode selects it and drags it; a press on one
+//! of the handles of the selected drawing drags that point only. Locked
+//! drawings can be selected and restyled but not dragged, and hidden ones
+//! are only drawn while selected.
+
+use plotters::{
+    backend::BitMapBackend,
+    chart::ChartContext,
+    coord::types::RangedCoordf32,
+    element::Circle,
+    prelude::Cartesian2d,
+    style::{Color, RGBColor, WHITE},
+};
+
+use crate::{
+    Chart, MouseType,
+    chart::THREAD_HOLD,
+    draw::{AnchoredDrawing, DrawStyle},
+    history::DrawCommand,
+};
+
+/// Handles are grabbed this many times further away than a line.
+const HANDLE_GRAB: f32 = 3.0;
+/// Radius in pixels of the handles of the selected drawing.
+const HANDLE_RADIUS: i32 = 4;
+
+impl Chart {
+    /// Style of the selected drawing and its colour, for the toolbar.
+    pub fn selected_style(&self) -> Option<(DrawStyle, RGBColor)> {
+        let drawing = self.all_draw_data.get(self.selected?)?;
+        Some((drawing.style, drawing.color))
+    }
+
+    /// Style for new drawings, from the toolbar.
+    pub(crate) fn ui_style(&self) -> DrawStyle {
+        let ui_data = &self.chart_data.ui_data;
+        DrawStyle {
+            width: u32::try_from(ui_data.line_width).unwrap_or(1).max(1),
+            dashed: ui_data.is_dashed,
+            fill_opacity: ui_data.fill_opacity.clamp(0.0, 1.0),
+            locked: ui_data.is_locked,
+            hidden: ui_data.is_hidden,
+        }
+    }
+
+    /// Style and colour the toolbar set for the selected drawing.
+    fn selected_ui_style(&self) -> (DrawStyle, RGBColor) {
+        let ui_data = &self.chart_data.ui_data;
+        let style = DrawStyle {
+            width: u32::try_from(ui_data.selected_line_width)
+                .unwrap_or(1)
+                .max(1),
+            dashed: ui_data.selected_dashed,
+            fill_opacity: ui_data.selected_fill_opacity.clamp(0.0, 1.0),
+            locked: ui_data.selected_locked,
+            hidden: ui_data.selected_hidden,
+        };
+        let color = ui_data.selected_color;
+        (style, RGBColor(color.red(), color.green(), color.blue()))
+    }
+
+    /// Apply the toolbar actions of this frame to the drawings: clear,
+    /// undo, redo, delete, show hidden and style edits.
+    pub(crate) fn apply_drawing_actions(&mut self) {
+        let ui_data = &self.chart_data.ui_data;
+        let (is_clean, is_undo, is_redo, is_delete, is_show_hidden, is_new_style) = (
+            ui_data.is_clean,
+            ui_data.is_undo,
+            ui_data.is_redo,
+            ui_data.is_delete,
+            ui_data.is_show_hidden,
+            ui_data.is_new_style,
+        );
+
+        if is_clean {
+            self.current_draw_data.clear();
+            if !self.drawings.is_empty() {
+                let before = self.drawings.clone();
+                self.history.apply(
+                    &mut self.drawings,
+                    DrawCommand::Reset {
+                        before,
+                        after: Vec::new(),
+                    },
+                );
+            }
+        }
+        if is_undo {
+            self.history.undo(&mut self.drawings);
+        }
+        if is_redo {
+            self.history.redo(&mut self.drawings);
+        }
+        if is_delete && let Some(index) = self.selected.filter(|&i| i < self.drawings.len()) {
+            let drawing = self.drawings[index].clone();
+            self.history
+                .apply(&mut self.drawings, DrawCommand::Remove(index, drawing));
+        }
+        if is_show_hidden && self.drawings.iter().any(|d| d.style().hidden) {
+            let before = self.drawings.clone();
+            let mut after = before.clone();
+            for drawing in &mut after {
+                drawing.set_style(DrawStyle {
+                    hidden: false,
+                    ..drawing.style()
+                });
+            }
+            self.history
+                .apply(&mut self.drawings, DrawCommand::Reset { before, after });
+        }
+        if is_clean || is_undo || is_redo || is_delete || is_show_hidden {
+            self.selected = None;
+            self.dragging_handle = None;
+            self.is_in_object = (0, false, false);
+        }
+
+        // Restyle the selected drawing
+        if is_new_style && let Some(index) = self.selected.filter(|&i| i < self.drawings.len()) {
+            let before = self.drawings[index].clone();
+            let mut object = before.place(&self.candle_data, self.price_scale);
+            (object.style, object.color) = self.selected_ui_style();
+            let after = AnchoredDrawing::new(object, &self.candle_data, self.price_scale);
+            if after.place(&self.candle_data, self.price_scale)
+                != before.place(&self.candle_data, self.price_scale)
+            {
+                self.history.apply(
+                    &mut self.drawings,
+                    DrawCommand::Change {
+                        index,
+                        before,
+                        after,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Select, drag and reshape drawings with the mouse.
+    ///
+    /// While a drawing is dragged the chart does not pan. The drag becomes
+    /// one undo step when the button is released.
+    pub(crate) fn edit_drawings(&mut self) {
+        let is_release = self.chart_data.ui_data.is_release;
+        let (x_thresh_hold, y_thresh_hold) = self.hit_thresholds();
+
+        if self.is_in_object.2 {
+            let index = self.is_in_object.0;
+            if index >= self.drawings.len() {
+                self.is_in_object = (0, false, false);
+                return;
+            }
+            if is_release {
+                let before = AnchoredDrawing::new(
+                    self.moving_object.clone(),
+                    &self.candle_data,
+                    self.price_scale,
+                );
+                if self.all_draw_data[index] != self.moving_object {
+                    self.history.record(DrawCommand::Change {
+                        index,
+                        before,
+                        after: self.drawings[index].clone(),
+                    });
+                }
+                self.is_in_object.2 = false;
+                self.dragging_handle = None;
+                return;
+            }
+
+            // The drag is measured from the press, on the object as it was then
+            let dx = self.chart_data.ui_data.move_x as f32 / self.chart_data.candle_distance;
+            let dy = -(self.chart_data.ui_data.move_y as f32)
+                * (3.0 * (self.chart_data.y_offset_max - self.chart_data.y_offset_min))
+                / (self.chart_data.height - self.chart_data.range_x as u32) as f32;
+            self.chart_data.ui_data.move_x = 0;
+            self.chart_data.ui_data.move_y = 0;
+
+            let mut updated_object = self.moving_object.clone();
+            match self.dragging_handle {
+                Some(handle) => updated_object.move_handle(handle, self.get_mouse_position()),
+                None => updated_object.translate(dx, dy),
+            }
+            self.drawings[index] =
+                AnchoredDrawing::new(updated_object.clone(), &self.candle_data, self.price_scale);
+            self.all_draw_data[index] = updated_object;
+            return;
+        }
+
+        if !is_release && matches!(self.chart_data.ui_data.mouse_type, MouseType::Move) {
+            // A handle of the selected drawing, then any drawing under the press
+            let press = self.get_press_position();
+            let handle = self.selected.and_then(|index| {
+                let object = self.all_draw_data.get(index)?;
+                let handle = object.hit_handle(
+                    press,
+                    HANDLE_GRAB * x_thresh_hold,
+                    HANDLE_GRAB * y_thresh_hold,
+                )?;
+                Some((index, handle))
+            });
+            let grab = match handle {
+                Some((index, handle)) => Some((index, Some(handle))),
+                None => self
+                    .hit_drawing(press, x_thresh_hold, y_thresh_hold)
+                    .map(|index| (index, None)),
+            };
+            self.selected = grab.map(|(index, _)| index);
+            if let Some((index, handle)) = grab
+                && !self.all_draw_data[index].style.locked
+            {
+                self.is_in_object = (index, true, true);
+                self.moving_object = self.all_draw_data[index].clone();
+                self.dragging_handle = handle;
+                self.chart_data.ui_data.move_x = 0;
+                self.chart_data.ui_data.move_y = 0;
+            }
+            return;
+        }
+
+        // Hover: tell the UI to show a pointer over a drawing
+        let mouse = self.get_mouse_position();
+        self.is_in_object = match self.hit_drawing(mouse, x_thresh_hold, y_thresh_hold) {
+            Some(index) => (index, true, false),
+            None => (0, false, false),
+        };
+    }
+
+    /// Draw the handles of the selected drawing.
+    pub(crate) fn chart_draw_handles(
+        &self,
+        chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+    ) {
+        let Some(object) = self
+            .selected
+            .and_then(|index| self.all_draw_data.get(index))
+        else {
+            return;
+        };
+        let handles = object.handles();
+        let _ = chart.draw_series(
+            handles
+                .iter()
+                .map(|&point| Circle::new(point, HANDLE_RADIUS, WHITE.filled())),
+        );
+        let _ = chart.draw_series(
+            handles
+                .iter()
+                .map(|&point| Circle::new(point, HANDLE_RADIUS, object.color.stroke_width(1))),
+        );
+    }
+
+    /// Index of the topmost visible drawing hit by `point`.
+    fn hit_drawing(
+        &self,
+        point: (f32, f32),
+        x_thresh_hold: f32,
+        y_thresh_hold: f32,
+    ) -> Option<usize> {
+        self.all_draw_data
+            .iter()
+            .enumerate()
+            .rev()
+            .find(|(_, object)| {
+                !object.style.hidden && object.hit_cursor(point, x_thresh_hold, y_thresh_hold)
+            })
+            .map(|(index, _)| index)
+    }
+
+    /// `THREAD_HOLD` pixels in chart units along x and y.
+    fn hit_thresholds(&self) -> (f32, f32) {
+        let x_thresh_hold = (self.chart_data.x_offset_max - self.chart_data.x_offset_min)
+            * THREAD_HOLD
+            / (self.chart_data.width as f32 - 60.0);
+        let y_thresh_hold = (3.0 * (self.chart_data.y_offset_max - self.chart_data.y_offset_min))
+            * THREAD_HOLD
+            / (self.chart_data.height as f32 - 60.0);
+        (x_thresh_hold, y_thresh_hold)
+    }
+}
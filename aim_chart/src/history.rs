@@ -0,0 +1,226 @@
+//! Undo and redo of the edits made to a chart's drawings.
+
+use crate::draw::AnchoredDrawing;
+
+/// One edit of the drawing list, with what it takes to take it back.
+#[derive(Debug, Clone)]
+pub enum DrawCommand {
+    /// Insert a drawing at an index
+    Add(usize, AnchoredDrawing),
+    /// Remove the drawing at an index
+    Remove(usize, AnchoredDrawing),
+    /// Replace the drawing at an index, after a move, a reshape or a style edit
+    Change {
+        index: usize,
+        before: AnchoredDrawing,
+        after: AnchoredDrawing,
+    },
+    /// Replace the whole list, e.g. when it is cleared
+    Reset {
+        before: Vec<AnchoredDrawing>,
+        after: Vec<AnchoredDrawing>,
+    },
+}
+
+impl DrawCommand {
+    fn apply(&self, drawings: &mut Vec<AnchoredDrawing>) {
+        match self {
+            DrawCommand::Add(index, drawing) => {
+                drawings.insert((*index).min(drawings.len()), drawing.clone());
+            }
+            DrawCommand::Remove(index, _) => {
+                if *index < drawings.len() {
+                    drawings.remove(*index);
+                }
+            }
+            DrawCommand::Change { index, after, .. } => {
+                if let Some(drawing) = drawings.get_mut(*index) {
+                    *drawing = after.clone();
+                }
+            }
+            DrawCommand::Reset { after, .. } => *drawings = after.clone(),
+        }
+    }
+
+    fn revert(&self, drawings: &mut Vec<AnchoredDrawing>) {
+        match self {
+            DrawCommand::Add(index, _) => {
+                if *index < drawings.len() {
+                    drawings.remove(*index);
+                }
+            }
+            DrawCommand::Remove(index, drawing) => {
+                drawings.insert((*index).min(drawings.len()), drawing.clone());
+            }
+            DrawCommand::Change { index, before, .. } => {
+                if let Some(drawing) = drawings.get_mut(*index) {
+                    *drawing = before.clone();
+                }
+            }
+            DrawCommand::Reset { before, .. } => *drawings = before.clone(),
+        }
+    }
+}
+
+/// Most edits that can be taken back; older ones are forgotten.
+const MAX_UNDO: usize = 100;
+
+/// Undo and redo stacks of [`DrawCommand`]s. A new edit drops the redo stack.
+#[derive(Debug, Clone, Default)]
+pub struct DrawHistory {
+    undo: Vec<DrawCommand>,
+    redo: Vec<DrawCommand>,
+    changed: bool, // the drawings changed since the last `take_changed`
+}
+
+impl DrawHistory {
+    /// Apply `command` to `drawings` and remember it.
+    pub fn apply(&mut self, drawings: &mut Vec<AnchoredDrawing>, command: DrawCommand) {
+        command.apply(drawings);
+        self.record(command);
+    }
+
+    /// Remember a `command` whose effect is already in the drawings, such as
+    /// a drag that updated the drawing while the mouse moved.
+    pub fn record(&mut self, command: DrawCommand) {
+        if self.undo.len() == MAX_UNDO {
+            self.undo.remove(0);
+        }
+        self.undo.push(command);
+        self.redo.clear();
+        self.changed = true;
+    }
+
+    /// Take back the last edit.
+    ///
+    /// # Returns
+    /// * `bool` - Whether there was an edit to take back
+    pub fn undo(&mut self, drawings: &mut Vec<AnchoredDrawing>) -> bool {
+        let Some(command) = self.undo.pop() else {
+            return false;
+        };
+        command.revert(drawings);
+        self.redo.push(command);
+        self.changed = true;
+        true
+    }
+
+    /// Apply the last edit taken back again.
+    ///
+    /// # Returns
+    /// * `bool` - Whether there was an edit to apply
+    pub fn redo(&mut self, drawings: &mut Vec<AnchoredDrawing>) -> bool {
+        let Some(command) = self.redo.pop() else {
+            return false;
+        };
+        command.apply(drawings);
+        self.undo.push(command);
+        self.changed = true;
+        true
+    }
+
+    /// Whether an edit, undo or redo changed the drawings since the last
+    /// call.
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plotters::style::RGBColor;
+
+    use super::*;
+    use crate::draw::{DrawObject, DrawType};
+
+    /// A vertical line at `time`, told apart by its anchor time.
+    fn line(time: f64) -> AnchoredDrawing {
+        let object = DrawObject::new(DrawType::VerticalLine(0.0), RGBColor(255, 0, 0));
+        AnchoredDrawing::from_parts(object, vec![time]).unwrap()
+    }
+
+    fn times(drawings: &[AnchoredDrawing]) -> Vec<f64> {
+        drawings.iter().map(|d| d.times()[0]).collect()
+    }
+
+    #[test]
+    fn test_undo_and_redo_each_command() {
+        let mut history = DrawHistory::default();
+        let mut drawings = Vec::new();
+        history.apply(&mut drawings, DrawCommand::Add(0, line(1.0)));
+        history.apply(&mut drawings, DrawCommand::Add(1, line(2.0)));
+        history.apply(&mut drawings, DrawCommand::Remove(0, line(1.0)));
+        history.apply(
+            &mut drawings,
+            DrawCommand::Change {
+                index: 0,
+                before: line(2.0),
+                after: line(3.0),
+            },
+        );
+        assert_eq!(times(&drawings), [3.0]);
+        assert!(history.take_changed());
+        assert!(!history.take_changed());
+
+        let steps = [vec![2.0], vec![1.0, 2.0], vec![1.0], vec![]];
+        for expected in &steps {
+            assert!(history.undo(&mut drawings));
+            assert_eq!(&times(&drawings), expected);
+        }
+        assert!(!history.undo(&mut drawings));
+        assert!(history.take_changed());
+
+        for expected in steps.iter().rev().skip(1) {
+            assert!(history.redo(&mut drawings));
+            assert_eq!(&times(&drawings), expected);
+        }
+        assert!(history.redo(&mut drawings));
+        assert_eq!(times(&drawings), [3.0]);
+        assert!(!history.redo(&mut drawings));
+    }
+
+    #[test]
+    fn test_reset_is_taken_back_whole() {
+        let mut history = DrawHistory::default();
+        let mut drawings = vec![line(1.0), line(2.0)];
+        let before = drawings.clone();
+        history.apply(
+            &mut drawings,
+            DrawCommand::Reset {
+                before,
+                after: Vec::new(),
+            },
+        );
+        assert!(drawings.is_empty());
+        assert!(history.undo(&mut drawings));
+        assert_eq!(times(&drawings), [1.0, 2.0]);
+        assert!(history.redo(&mut drawings));
+        assert!(drawings.is_empty());
+    }
+
+    #[test]
+    fn test_new_edit_drops_the_redo_stack() {
+        let mut history = DrawHistory::default();
+        let mut drawings = Vec::new();
+        history.apply(&mut drawings, DrawCommand::Add(0, line(1.0)));
+        history.undo(&mut drawings);
+        history.apply(&mut drawings, DrawCommand::Add(0, line(2.0)));
+        assert!(!history.redo(&mut drawings));
+        assert_eq!(times(&drawings), [2.0]);
+    }
+
+    #[test]
+    fn test_history_forgets_the_oldest_edits() {
+        let mut history = DrawHistory::default();
+        let mut drawings = Vec::new();
+        for i in 0..MAX_UNDO + 10 {
+            history.apply(&mut drawings, DrawCommand::Add(i, line(i as f64)));
+        }
+        let mut undone = 0;
+        while history.undo(&mut drawings) {
+            undone += 1;
+        }
+        assert_eq!(undone, MAX_UNDO);
+        assert_eq!(drawings.len(), 10);
+    }
+}
@@ -0,0 +1,798 @@
+//! Technical indicators drawn over the price pane.
+//!
+//! Each chart keeps a list of [`IndicatorConfig`]s that the user edits as
+//! text, e.g. `EMA 20 close #2962FF; BB 20 close x2 #9C27B0`. Configs are
+//! turned into [`Indicator`]s through a registry keyed by name.
+//!
+//! Overlays such as moving averages share the price pane; oscillators such
+//! as RSI get a pane of their own under it, see [`Placement`].
+//!
+//! Indicators compute incrementally: [`Study::update`] only works through
+//! the bars added since the last call, plus the last known bar, which
+//! changes in place while it is still forming.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::LazyLock;
+
+use chrono::{DateTime, Utc};
+use plotters::style::RGBColor;
+
+use crate::CandleData;
+
+mod oscillator;
+mod overlay;
+
+use oscillator::{Atr, Macd, Mfi, Obv, Rsi, Stochastic, Volume};
+use overlay::{Bollinger, Ema, Ichimoku, ParabolicSar, Sma, Supertrend, Vwap, Wma};
+
+/// Price of a candle an indicator is computed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Source {
+    Open,
+    High,
+    Low,
+    #[default]
+    Close,
+    /// (high + low) / 2
+    Hl2,
+    /// (high + low + close) / 3
+    Hlc3,
+    /// (open + high + low + close) / 4
+    Ohlc4,
+}
+
+impl Source {
+    /// All sources, in the order of their cache tag.
+    pub const ALL: [Source; 7] = [
+        Source::Open,
+        Source::High,
+        Source::Low,
+        Source::Close,
+        Source::Hl2,
+        Source::Hlc3,
+        Source::Ohlc4,
+    ];
+
+    pub fn of(&self, candle: &CandleData) -> f32 {
+        match self {
+            Source::Open => candle.open,
+            Source::High => candle.high,
+            Source::Low => candle.low,
+            Source::Close => candle.close,
+            Source::Hl2 => (candle.high + candle.low) / 2.0,
+            Source::Hlc3 => (candle.high + candle.low + candle.close) / 3.0,
+            Source::Ohlc4 => (candle.open + candle.high + candle.low + candle.close) / 4.0,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Source::Open => "open",
+            Source::High => "high",
+            Source::Low => "low",
+            Source::Close => "close",
+            Source::Hl2 => "hl2",
+            Source::Hlc3 => "hlc3",
+            Source::Ohlc4 => "ohlc4",
+        }
+    }
+
+    /// Parse a label such as `close` or `hlc3`, case-insensitive.
+    pub fn parse(label: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|source| source.label().eq_ignore_ascii_case(label))
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.label())
+    }
+}
+
+/// How a [`Series`] is drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeriesStyle {
+    /// Solid line in the indicator colour
+    Line,
+    /// Faded line, e.g. the bands around a Bollinger basis
+    Band,
+    /// Separate dots, e.g. Parabolic SAR
+    Dots,
+    /// Columns from zero, green above and red below, e.g. the MACD histogram
+    Histogram,
+    /// Columns from zero in the colour of their candle, e.g. volume
+    Columns,
+}
+
+/// Where an indicator is drawn.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Placement {
+    /// Over the candles, on the price scale
+    Overlay,
+    /// In a pane of its own under the prices
+    Pane {
+        /// Fixed y range, e.g. 0..100 for RSI; None to fit the visible values
+        range: Option<(f32, f32)>,
+        /// Guide lines, e.g. the 30 and 70 RSI levels
+        levels: &'static [f32],
+    },
+}
+
+/// One output line of an indicator, with a value per bar.
+#[derive(Debug, Clone)]
+pub struct Series {
+    pub values: Vec<Option<f32>>,
+    /// Bars the line is drawn ahead of (positive) or behind (negative) the
+    /// bar it was computed on, e.g. the Ichimoku cloud.
+    pub shift: isize,
+    pub style: SeriesStyle,
+}
+
+impl Series {
+    fn new(style: SeriesStyle) -> Self {
+        Self::shifted(style, 0)
+    }
+
+    fn shifted(style: SeriesStyle, shift: isize) -> Self {
+        Self {
+            values: Vec::new(),
+            shift,
+            style,
+        }
+    }
+}
+
+/// Drop the values from bar `from` onwards so they can be recomputed.
+fn truncate(series: &mut [Series], from: usize) {
+    for line in series {
+        line.values.truncate(from);
+    }
+}
+
+/// Settings of one indicator on a chart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndicatorConfig {
+    /// Registry name, e.g. `EMA`
+    pub kind: String,
+    /// Look-back in bars; 0 when the indicator has none
+    pub period: usize,
+    pub source: Source,
+    /// Band width, ATR factor or SAR step depending on the kind; 0 when unused
+    pub multiplier: f32,
+    pub color: RGBColor,
+}
+
+impl IndicatorConfig {
+    pub fn new(
+        kind: &str,
+        period: usize,
+        source: Source,
+        multiplier: f32,
+        color: RGBColor,
+    ) -> Self {
+        Self {
+            kind: kind.to_uppercase(),
+            period,
+            source,
+            multiplier,
+            color,
+        }
+    }
+
+    /// Short name for the legend, e.g. `EMA 20`.
+    pub fn label(&self) -> String {
+        if self.period > 0 {
+            format!("{} {}", self.kind, self.period)
+        } else {
+            self.kind.clone()
+        }
+    }
+
+    /// Parse one entry such as `EMA 20 close #2962FF` or `BB 20 x2.5`.
+    ///
+    /// The first word is the registry name; the rest may come in any order
+    /// and fields left out take the defaults of that kind.
+    ///
+    /// # Returns
+    /// * `Option<IndicatorConfig>` - None for an unknown kind or a bad field
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut tokens = text.split_whitespace();
+        let mut config = indicator_defaults(tokens.next()?)?;
+        for token in tokens {
+            if let Some(hex) = token.strip_prefix('#') {
+                config.color = parse_color(hex)?;
+            } else if let Some(multiplier) = token.strip_prefix(['x', 'X']) {
+                config.multiplier = multiplier.parse().ok()?;
+            } else if let Ok(period) = token.parse::<usize>() {
+                config.period = period;
+            } else {
+                config.source = Source::parse(token)?;
+            }
+        }
+        Some(config)
+    }
+}
+
+impl fmt::Display for IndicatorConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        if self.period > 0 {
+            write!(f, " {}", self.period)?;
+        }
+        write!(f, " {}", self.source)?;
+        if self.multiplier != 0.0 {
+            write!(f, " x{}", self.multiplier)?;
+        }
+        let RGBColor(r, g, b) = self.color;
+        write!(f, " #{r:02X}{g:02X}{b:02X}")
+    }
+}
+
+pub(crate) fn parse_color(hex: &str) -> Option<RGBColor> {
+    if hex.len() != 6 {
+        return None;
+    }
+    let value = u32::from_str_radix(hex, 16).ok()?;
+    Some(RGBColor(
+        (value >> 16) as u8,
+        (value >> 8) as u8,
+        value as u8,
+    ))
+}
+
+/// Parse a `;`-separated indicator list, skipping entries that do not parse.
+pub fn parse_indicators(spec: &str) -> Vec<IndicatorConfig> {
+    spec.split(';')
+        .filter(|entry| !entry.trim().is_empty())
+        .filter_map(|entry| {
+            let config = IndicatorConfig::parse(entry);
+            if config.is_none() {
+                log::warn!("Ignoring indicator {:?}", entry.trim());
+            }
+            config
+        })
+        .collect()
+}
+
+/// Inverse of [`parse_indicators`].
+pub fn format_indicators(configs: &[IndicatorConfig]) -> String {
+    configs
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Indicators of a new chart: the MA20/MA50/MA200 set the chart always drew.
+pub fn default_indicators() -> Vec<IndicatorConfig> {
+    vec![
+        IndicatorConfig::new("SMA", 20, Source::Close, 0.0, RGBColor(255, 0, 0)),
+        IndicatorConfig::new("SMA", 50, Source::Close, 0.0, RGBColor(0, 0, 255)),
+        IndicatorConfig::new("SMA", 200, Source::Close, 0.0, RGBColor(255, 255, 255)),
+    ]
+}
+
+/// A study computed bar by bar over a chart's candles.
+pub trait Indicator: Send {
+    /// Recompute bars `from..candles.len()`, keeping the output for earlier
+    /// bars.
+    fn compute(&mut self, candles: &[CandleData], from: usize);
+
+    /// Output lines, one value per bar.
+    fn series(&self) -> &[Series];
+}
+
+/// Builds an indicator from its settings.
+type IndicatorFactory = fn(&IndicatorConfig) -> Box<dyn Indicator>;
+
+struct Registration {
+    defaults: IndicatorConfig,
+    placement: Placement,
+    factory: IndicatorFactory,
+}
+
+const OSCILLATOR: Placement = Placement::Pane {
+    range: Some((0.0, 100.0)),
+    levels: &[20.0, 80.0],
+};
+const UNBOUNDED: Placement = Placement::Pane {
+    range: None,
+    levels: &[],
+};
+
+static INDICATORS: LazyLock<BTreeMap<String, Registration>> = LazyLock::new(builtin_indicators);
+
+fn builtin_indicators() -> BTreeMap<String, Registration> {
+    let builtins: [(IndicatorConfig, Placement, IndicatorFactory); 15] = [
+        (
+            IndicatorConfig::new("SMA", 20, Source::Close, 0.0, RGBColor(0xFF, 0x98, 0x00)),
+            Placement::Overlay,
+            |config| Box::new(Sma::new(config)),
+        ),
+        (
+            IndicatorConfig::new("EMA", 20, Source::Close, 0.0, RGBColor(0x29, 0x62, 0xFF)),
+            Placement::Overlay,
+            |config| Box::new(Ema::new(config)),
+        ),
+        (
+            IndicatorConfig::new("WMA", 20, Source::Close, 0.0, RGBColor(0x00, 0xBC, 0xD4)),
+            Placement::Overlay,
+            |config| Box::new(Wma::new(config)),
+        ),
+        (
+            IndicatorConfig::new("BB", 20, Source::Close, 2.0, RGBColor(0x9C, 0x27, 0xB0)),
+            Placement::Overlay,
+            |config| Box::new(Bollinger::new(config)),
+        ),
+        (
+            IndicatorConfig::new("VWAP", 0, Source::Hlc3, 0.0, RGBColor(0xE9, 0x1E, 0x63)),
+            Placement::Overlay,
+            |config| Box::new(Vwap::new(config)),
+        ),
+        (
+            IndicatorConfig::new(
+                "ICHIMOKU",
+                26,
+                Source::Close,
+                0.0,
+                RGBColor(0x4C, 0xAF, 0x50),
+            ),
+            Placement::Overlay,
+            |config| Box::new(Ichimoku::new(config)),
+        ),
+        (
+            IndicatorConfig::new("PSAR", 0, Source::Close, 0.02, RGBColor(0xFF, 0xEB, 0x3B)),
+            Placement::Overlay,
+            |config| Box::new(ParabolicSar::new(config)),
+        ),
+        (
+            IndicatorConfig::new(
+                "SUPERTREND",
+                10,
+                Source::Hl2,
+                3.0,
+                RGBColor(0x2E, 0xBD, 0x85),
+            ),
+            Placement::Overlay,
+            |config| Box::new(Supertrend::new(config)),
+        ),
+        (
+            IndicatorConfig::new("RSI", 14, Source::Close, 0.0, RGBColor(0x7E, 0x57, 0xC2)),
+            Placement::Pane {
+                range: Some((0.0, 100.0)),
+                levels: &[30.0, 70.0],
+            },
+            |config| Box::new(Rsi::new(config)),
+        ),
+        (
+            IndicatorConfig::new("MACD", 12, Source::Close, 0.0, RGBColor(0x29, 0x62, 0xFF)),
+            Placement::Pane {
+                range: None,
+                levels: &[0.0],
+            },
+            |config| Box::new(Macd::new(config)),
+        ),
+        (
+            IndicatorConfig::new("STOCH", 14, Source::Close, 0.0, RGBColor(0x29, 0x62, 0xFF)),
+            OSCILLATOR,
+            |config| Box::new(Stochastic::new(config)),
+        ),
+        (
+            IndicatorConfig::new("ATR", 14, Source::Close, 0.0, RGBColor(0xF4, 0x43, 0x36)),
+            UNBOUNDED,
+            |config| Box::new(Atr::new(config)),
+        ),
+        (
+            IndicatorConfig::new("OBV", 0, Source::Close, 0.0, RGBColor(0x00, 0xBC, 0xD4)),
+            UNBOUNDED,
+            |config| Box::new(Obv::new(config)),
+        ),
+        (
+            IndicatorConfig::new("MFI", 14, Source::Hlc3, 0.0, RGBColor(0xAB, 0x47, 0xBC)),
+            OSCILLATOR,
+            |config| Box::new(Mfi::new(config)),
+        ),
+        (
+            IndicatorConfig::new("VOLUME", 20, Source::Close, 0.0, RGBColor(0xFF, 0x98, 0x00)),
+            UNBOUNDED,
+            |config| Box::new(Volume::new(config)),
+        ),
+    ];
+    builtins
+        .into_iter()
+        .map(|(defaults, placement, factory)| {
+            let registration = Registration {
+                defaults,
+                placement,
+                factory,
+            };
+            (registration.defaults.kind.clone(), registration)
+        })
+        .collect()
+}
+
+/// Default settings of the indicator registered as `kind`.
+pub fn indicator_defaults(kind: &str) -> Option<IndicatorConfig> {
+    INDICATORS
+        .get(&kind.to_uppercase())
+        .map(|registration| registration.defaults.clone())
+}
+
+fn create_indicator(config: &IndicatorConfig) -> Option<(Box<dyn Indicator>, Placement)> {
+    INDICATORS
+        .get(&config.kind)
+        .map(|registration| ((registration.factory)(config), registration.placement))
+}
+
+/// Share of the chart height a new sub-pane takes.
+pub const DEFAULT_PANE_HEIGHT: f32 = 0.2;
+
+/// An indicator on a chart together with how far it has been computed.
+pub struct Study {
+    pub config: IndicatorConfig,
+    pub placement: Placement,
+    /// Share of the chart height when drawn in its own pane
+    pub pane_height: f32,
+    indicator: Option<Box<dyn Indicator>>, // None while the kind is not registered
+    computed: usize,
+    first_time: Option<DateTime<Utc>>,
+}
+
+impl Study {
+    pub fn new(config: IndicatorConfig) -> Self {
+        let (indicator, placement) = match create_indicator(&config) {
+            Some((indicator, placement)) => (Some(indicator), placement),
+            None => {
+                log::warn!("Unknown indicator {}", config.kind);
+                (None, Placement::Overlay)
+            }
+        };
+        Self {
+            config,
+            placement,
+            pane_height: DEFAULT_PANE_HEIGHT,
+            indicator,
+            computed: 0,
+            first_time: None,
+        }
+    }
+
+    /// Whether the study is drawn in a pane of its own.
+    pub fn in_pane(&self) -> bool {
+        matches!(self.placement, Placement::Pane { .. })
+    }
+
+    /// Forget computed values, e.g. after the history was replaced.
+    pub fn reset(&mut self) {
+        self.computed = 0;
+        self.first_time = None;
+    }
+
+    /// Bring the output up to date with `candles`.
+    ///
+    /// Only bars from the last computed one onwards are recomputed, unless
+    /// the history no longer starts at the same bar or got shorter.
+    pub fn update(&mut self, candles: &[CandleData]) {
+        let Some(indicator) = self.indicator.as_mut() else {
+            return;
+        };
+        let first_time = candles.first().map(|candle| candle.time);
+        let from = if first_time != self.first_time || candles.len() < self.computed {
+            0
+        } else {
+            self.computed.saturating_sub(1)
+        };
+        indicator.compute(candles, from);
+        self.computed = candles.len();
+        self.first_time = first_time;
+    }
+
+    pub fn series(&self) -> &[Series] {
+        match &self.indicator {
+            Some(indicator) => indicator.series(),
+            None => &[],
+        }
+    }
+}
+
+impl Clone for Study {
+    // Indicators are rebuilt from the config and recompute on the next update
+    fn clone(&self) -> Self {
+        Self {
+            pane_height: self.pane_height,
+            ..Self::new(self.config.clone())
+        }
+    }
+}
+
+impl fmt::Debug for Study {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Study")
+            .field("config", &self.config)
+            .field("placement", &self.placement)
+            .field("pane_height", &self.pane_height)
+            .field("computed", &self.computed)
+            .finish()
+    }
+}
+
+/// The `period` bars ending at bar `i`, if there are that many.
+fn window(candles: &[CandleData], i: usize, period: usize) -> Option<&[CandleData]> {
+    (period > 0 && i + 1 >= period).then(|| &candles[i + 1 - period..=i])
+}
+
+fn mean(candles: &[CandleData], i: usize, period: usize, source: Source) -> Option<f32> {
+    let bars = window(candles, i, period)?;
+    Some(bars.iter().map(|candle| source.of(candle)).sum::<f32>() / period as f32)
+}
+
+/// Middle of the highest high and lowest low of the window.
+fn midpoint(candles: &[CandleData], i: usize, period: usize) -> Option<f32> {
+    let bars = window(candles, i, period)?;
+    let high = bars.iter().map(|c| c.high).fold(f32::MIN, f32::max);
+    let low = bars.iter().map(|c| c.low).fold(f32::MAX, f32::min);
+    Some((high + low) / 2.0)
+}
+
+/// Mean of the last `period` values up to `i`, if none of them is missing.
+fn mean_of(values: &[Option<f32>], i: usize, period: usize) -> Option<f32> {
+    if period == 0 || i + 1 < period {
+        return None;
+    }
+    let mut sum = 0.0;
+    for value in &values[i + 1 - period..=i] {
+        sum += (*value)?;
+    }
+    Some(sum / period as f32)
+}
+
+/// Next value of a `period` EMA from the previous one, or `seed`, usually
+/// the mean of the first `period` inputs, while there is none yet.
+fn ema_step(
+    previous: Option<f32>,
+    input: f32,
+    period: usize,
+    seed: impl FnOnce() -> Option<f32>,
+) -> Option<f32> {
+    match previous {
+        Some(previous) => Some(previous + 2.0 / (period as f32 + 1.0) * (input - previous)),
+        None => seed(),
+    }
+}
+
+/// Next value of Wilder's smoothing, the running average RSI and ATR use.
+fn wilder_step(
+    previous: Option<f32>,
+    input: f32,
+    period: usize,
+    seed: impl FnOnce() -> Option<f32>,
+) -> Option<f32> {
+    match previous {
+        Some(previous) => Some((previous * (period as f32 - 1.0) + input) / period as f32),
+        None => seed(),
+    }
+}
+
+/// Largest move of bar `i`, including a gap from the previous close.
+pub(crate) fn true_range(candles: &[CandleData], i: usize) -> f32 {
+    let candle = &candles[i];
+    match i.checked_sub(1) {
+        Some(p) => (candle.high - candle.low)
+            .max((candle.high - candles[p].close).abs())
+            .max((candle.low - candles[p].close).abs()),
+        None => candle.high - candle.low,
+    }
+}
+
+/// Mean true range of the `period` bars ending at `i`, where an ATR starts.
+fn atr_seed(candles: &[CandleData], i: usize, period: usize) -> Option<f32> {
+    window(candles, i, period)?;
+    Some(
+        (i + 1 - period..=i)
+            .map(|j| true_range(candles, j))
+            .sum::<f32>()
+            / period as f32,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{bar, bars};
+
+    /// Output lines of the indicator `spec` over `candles`.
+    fn compute(spec: &str, candles: &[CandleData]) -> Vec<Series> {
+        let mut study = Study::new(IndicatorConfig::parse(spec).unwrap());
+        study.update(candles);
+        study.series().to_vec()
+    }
+
+    fn assert_values(actual: &[Option<f32>], expected: &[Option<f32>]) {
+        assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+        for (a, e) in actual.iter().zip(expected) {
+            match (a, e) {
+                (Some(a), Some(e)) => assert!((a - e).abs() < 1e-4, "{actual:?} != {expected:?}"),
+                _ => assert_eq!(a, e, "{actual:?} != {expected:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_moving_averages() {
+        let candles = bars(&[2.0, 4.0, 6.0, 8.0, 12.0]);
+        let sma = compute("SMA 3", &candles);
+        assert_values(
+            &sma[0].values,
+            &[None, None, Some(4.0), Some(6.0), Some(26.0 / 3.0)],
+        );
+        // Seeded with the SMA, then half way to each close
+        let ema = compute("EMA 3", &candles);
+        assert_values(
+            &ema[0].values,
+            &[None, None, Some(4.0), Some(6.0), Some(9.0)],
+        );
+        let wma = compute("WMA 3", &candles);
+        assert_values(
+            &wma[0].values,
+            &[
+                None,
+                None,
+                Some(28.0 / 6.0),
+                Some(40.0 / 6.0),
+                Some(58.0 / 6.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_bollinger_bands() {
+        let series = compute("BB 3 x2", &bars(&[2.0, 4.0, 6.0]));
+        let width = 2.0 * (8.0f32 / 3.0).sqrt();
+        assert_values(&series[0].values, &[None, None, Some(4.0)]);
+        assert_values(&series[1].values, &[None, None, Some(4.0 + width)]);
+        assert_values(&series[2].values, &[None, None, Some(4.0 - width)]);
+    }
+
+    #[test]
+    fn test_vwap_restarts_each_day_or_covers_the_period() {
+        // Three hourly bars from 09:00 local, then one the next day
+        let mut candles = bars(&[10.0, 20.0, 30.0, 40.0]);
+        let start = candles[0].time + chrono::Duration::hours(2);
+        for (i, candle) in candles.iter_mut().enumerate().take(3) {
+            candle.time = start + chrono::Duration::hours(i as i64);
+        }
+        candles[3].time = start + chrono::Duration::days(1);
+        candles[1].volume = 300.0;
+
+        let daily = compute("VWAP 0 close", &candles);
+        assert_values(
+            &daily[0].values,
+            &[Some(10.0), Some(17.5), Some(20.0), Some(40.0)],
+        );
+        let rolling = compute("VWAP 2 close", &candles);
+        assert_values(
+            &rolling[0].values,
+            &[Some(10.0), Some(17.5), Some(22.5), Some(35.0)],
+        );
+    }
+
+    #[test]
+    fn test_ichimoku() {
+        // Period 2: conversion over 1 bar, base over 2, span B over 4
+        let series = compute("ICHIMOKU 2", &bars(&[10.0, 12.0, 14.0, 16.0]));
+        assert_values(
+            &series[0].values,
+            &[Some(10.0), Some(12.0), Some(14.0), Some(16.0)],
+        );
+        assert_values(
+            &series[1].values,
+            &[None, Some(11.0), Some(13.0), Some(15.0)],
+        );
+        assert_values(
+            &series[2].values,
+            &[None, Some(11.5), Some(13.5), Some(15.5)],
+        );
+        assert_values(&series[3].values, &[None, None, None, Some(13.0)]);
+        assert_values(
+            &series[4].values,
+            &[Some(10.0), Some(12.0), Some(14.0), Some(16.0)],
+        );
+        assert_eq!(series[2].shift, 2);
+        assert_eq!(series[4].shift, -2);
+    }
+
+    #[test]
+    fn test_parabolic_sar_accelerates_and_reverses() {
+        let candles = vec![
+            bar(0, 9.0, 10.0, 8.0, 9.0),
+            bar(1, 10.0, 11.0, 9.0, 10.5),
+            bar(2, 11.0, 12.0, 10.0, 11.5),
+            bar(3, 12.0, 13.0, 11.0, 12.5),
+            bar(4, 12.0, 12.0, 7.0, 7.5),
+        ];
+        let series = compute("PSAR x0.02", &candles);
+        // Rising from the first low, held under the two previous lows, then
+        // flipped to the extreme high when the low breaks through
+        assert_values(
+            &series[0].values,
+            &[None, Some(8.0), Some(8.0), Some(8.16), Some(13.0)],
+        );
+    }
+
+    #[test]
+    fn test_supertrend_flips_when_the_close_breaks_the_stop() {
+        let candles = vec![
+            bar(0, 10.0, 11.0, 9.0, 10.0),
+            bar(1, 11.0, 12.0, 10.0, 11.0),
+            bar(2, 12.0, 13.0, 11.0, 12.0),
+            bar(3, 9.0, 10.0, 6.0, 7.0),
+        ];
+        let series = compute("SUPERTREND 2 hl2 x1", &candles);
+        assert_values(&series[0].values, &[None, Some(9.0), Some(10.0), None]);
+        assert_values(&series[1].values, &[None, None, None, Some(12.0)]);
+    }
+
+    #[test]
+    fn test_parse_indicators_round_trips() {
+        let spec = "EMA 20 close #2962FF; BB 20 close x2.5 #9C27B0; OBV close #00BCD4";
+        let configs = parse_indicators(spec);
+        assert_eq!(configs.len(), 3);
+        assert_eq!(format_indicators(&configs), spec);
+        assert_eq!(parse_indicators(&format_indicators(&configs)), configs);
+
+        // Fields in any order, defaults for the rest, unknown kinds skipped
+        let configs = parse_indicators("rsi hl2 7; NOPE 3; ; bb x1");
+        assert_eq!(
+            configs,
+            vec![
+                IndicatorConfig::new("RSI", 7, Source::Hl2, 0.0, RGBColor(0x7E, 0x57, 0xC2)),
+                IndicatorConfig::new("BB", 20, Source::Close, 1.0, RGBColor(0x9C, 0x27, 0xB0)),
+            ]
+        );
+        assert!(IndicatorConfig::parse("SMA 20 #12345").is_none());
+        assert!(IndicatorConfig::parse("SMA 20 median").is_none());
+    }
+
+    #[test]
+    fn test_incremental_update_matches_a_full_recompute() {
+        let closes: Vec<f32> = (0..80)
+            .map(|i| 100.0 + 10.0 * (i as f32 * 0.3).sin() + i as f32 * 0.2)
+            .collect();
+        let full = bars(&closes);
+        let specs = [
+            "SMA 5",
+            "EMA 5",
+            "WMA 5",
+            "BB 5 x2",
+            "VWAP 0",
+            "VWAP 5",
+            "ICHIMOKU 6",
+            "PSAR",
+            "SUPERTREND 4",
+            "RSI 5",
+            "MACD",
+            "STOCH 5",
+            "ATR 5",
+            "OBV",
+            "MFI 5",
+            "VOLUME 5",
+        ];
+        for spec in specs {
+            let mut study = Study::new(IndicatorConfig::parse(spec).unwrap());
+            let mut candles = full[..40].to_vec();
+            // The last bar is still forming when the first batch comes in
+            candles[39].close = 50.0;
+            study.update(&candles);
+            for end in [40, 41, 60, 80] {
+                study.update(&full[..end]);
+            }
+            let expected = compute(spec, &full);
+            for (actual, expected) in study.series().iter().zip(&expected) {
+                assert_values(&actual.values, &expected.values);
+            }
+        }
+    }
+}
@@ -0,0 +1,351 @@
+//! Indicators drawn in a pane of their own under the prices.
+
+use super::{
+    Indicator, IndicatorConfig, Series, SeriesStyle, Source, atr_seed, ema_step, mean, mean_of,
+    true_range, truncate, wilder_step, window,
+};
+use crate::CandleData;
+
+/// 100 - 100 / (1 + gains / losses), the shape shared by RSI and MFI.
+fn strength_index(gains: f32, losses: f32) -> f32 {
+    if losses == 0.0 {
+        100.0
+    } else {
+        100.0 - 100.0 / (1.0 + gains / losses)
+    }
+}
+
+/// Relative Strength Index with Wilder's smoothing
+pub(super) struct Rsi {
+    period: usize,
+    source: Source,
+    averages: Vec<Option<(f32, f32)>>, // average gain and loss per bar
+    series: [Series; 1],
+}
+
+impl Rsi {
+    pub(super) fn new(config: &IndicatorConfig) -> Self {
+        Self {
+            period: config.period,
+            source: config.source,
+            averages: Vec::new(),
+            series: [Series::new(SeriesStyle::Line)],
+        }
+    }
+
+    fn change(&self, candles: &[CandleData], i: usize) -> f32 {
+        self.source.of(&candles[i]) - self.source.of(&candles[i - 1])
+    }
+
+    fn next(&self, candles: &[CandleData], i: usize) -> Option<(f32, f32)> {
+        let previous = self.averages[i.checked_sub(1)?];
+        let change = self.change(candles, i);
+        // The first average covers the changes of bars 1..=period
+        let seed = |pick: fn(f32) -> f32| {
+            (self.period > 0 && i >= self.period).then(|| {
+                (i + 1 - self.period..=i)
+                    .map(|j| pick(self.change(candles, j)))
+                    .sum::<f32>()
+                    / self.period as f32
+            })
+        };
+        let gain = wilder_step(previous.map(|p| p.0), change.max(0.0), self.period, || {
+            seed(|change| change.max(0.0))
+        })?;
+        let loss = wilder_step(
+            previous.map(|p| p.1),
+            (-change).max(0.0),
+            self.period,
+            || seed(|change| (-change).max(0.0)),
+        )?;
+        Some((gain, loss))
+    }
+}
+
+impl Indicator for Rsi {
+    fn compute(&mut self, candles: &[CandleData], from: usize) {
+        truncate(&mut self.series, from);
+        self.averages.truncate(from);
+        for i in from..candles.len() {
+            let averages = self.next(candles, i);
+            self.averages.push(averages);
+            self.series[0]
+                .values
+                .push(averages.map(|(gain, loss)| strength_index(gain, loss)));
+        }
+    }
+
+    fn series(&self) -> &[Series] {
+        &self.series
+    }
+}
+
+/// Moving Average Convergence Divergence.
+///
+/// `period` is the fast EMA (12 by default); the slow EMA uses 13/6 of it
+/// and the signal line 3/4 of it, which gives the usual 12/26/9.
+pub(super) struct Macd {
+    fast: usize,
+    slow: usize,
+    signal: usize,
+    source: Source,
+    emas: Vec<(Option<f32>, Option<f32>)>, // fast and slow EMA per bar
+    series: [Series; 3],                   // MACD, signal, histogram
+}
+
+impl Macd {
+    pub(super) fn new(config: &IndicatorConfig) -> Self {
+        Self {
+            fast: config.period,
+            slow: config.period * 13 / 6,
+            signal: (config.period * 3 / 4).max(1),
+            source: config.source,
+            emas: Vec::new(),
+            series: [
+                Series::new(SeriesStyle::Line),
+                Series::new(SeriesStyle::Band),
+                Series::new(SeriesStyle::Histogram),
+            ],
+        }
+    }
+}
+
+impl Indicator for Macd {
+    fn compute(&mut self, candles: &[CandleData], from: usize) {
+        truncate(&mut self.series, from);
+        self.emas.truncate(from);
+        for i in from..candles.len() {
+            let input = self.source.of(&candles[i]);
+            let (fast, slow) = i.checked_sub(1).map_or((None, None), |p| self.emas[p]);
+            let fast = ema_step(fast, input, self.fast, || {
+                mean(candles, i, self.fast, self.source)
+            });
+            let slow = ema_step(slow, input, self.slow, || {
+                mean(candles, i, self.slow, self.source)
+            });
+            self.emas.push((fast, slow));
+
+            let macd = fast.zip(slow).map(|(fast, slow)| fast - slow);
+            self.series[0].values.push(macd);
+            let previous = i.checked_sub(1).and_then(|p| self.series[1].values[p]);
+            let signal = macd.and_then(|macd| {
+                ema_step(previous, macd, self.signal, || {
+                    mean_of(&self.series[0].values, i, self.signal)
+                })
+            });
+            self.series[1].values.push(signal);
+            self.series[2]
+                .values
+                .push(macd.zip(signal).map(|(macd, signal)| macd - signal));
+        }
+    }
+
+    fn series(&self) -> &[Series] {
+        &self.series
+    }
+}
+
+/// Length of the %K and %D smoothing of the slow stochastic.
+const STOCHASTIC_SMOOTHING: usize = 3;
+
+/// Slow stochastic oscillator: %K over `period` bars smoothed over three
+/// bars, and %D the three-bar average of %K
+pub(super) struct Stochastic {
+    period: usize,
+    source: Source,
+    raw: Vec<Option<f32>>, // fast %K per bar
+    series: [Series; 2],   // %K, %D
+}
+
+impl Stochastic {
+    pub(super) fn new(config: &IndicatorConfig) -> Self {
+        Self {
+            period: config.period,
+            source: config.source,
+            raw: Vec::new(),
+            series: [
+                Series::new(SeriesStyle::Line),
+                Series::new(SeriesStyle::Band),
+            ],
+        }
+    }
+}
+
+impl Indicator for Stochastic {
+    fn compute(&mut self, candles: &[CandleData], from: usize) {
+        truncate(&mut self.series, from);
+        self.raw.truncate(from);
+        for i in from..candles.len() {
+            let raw = window(candles, i, self.period).map(|bars| {
+                let high = bars.iter().map(|c| c.high).fold(f32::MIN, f32::max);
+                let low = bars.iter().map(|c| c.low).fold(f32::MAX, f32::min);
+                if high > low {
+                    100.0 * (self.source.of(&candles[i]) - low) / (high - low)
+                } else {
+                    50.0
+                }
+            });
+            self.raw.push(raw);
+            let k = mean_of(&self.raw, i, STOCHASTIC_SMOOTHING);
+            self.series[0].values.push(k);
+            let d = mean_of(&self.series[0].values, i, STOCHASTIC_SMOOTHING);
+            self.series[1].values.push(d);
+        }
+    }
+
+    fn series(&self) -> &[Series] {
+        &self.series
+    }
+}
+
+/// Average True Range with Wilder's smoothing
+pub(super) struct Atr {
+    period: usize,
+    series: [Series; 1],
+}
+
+impl Atr {
+    pub(super) fn new(config: &IndicatorConfig) -> Self {
+        Self {
+            period: config.period,
+            series: [Series::new(SeriesStyle::Line)],
+        }
+    }
+}
+
+impl Indicator for Atr {
+    fn compute(&mut self, candles: &[CandleData], from: usize) {
+        truncate(&mut self.series, from);
+        for i in from..candles.len() {
+            let previous = i.checked_sub(1).and_then(|p| self.series[0].values[p]);
+            let atr = wilder_step(previous, true_range(candles, i), self.period, || {
+                atr_seed(candles, i, self.period)
+            });
+            self.series[0].values.push(atr);
+        }
+    }
+
+    fn series(&self) -> &[Series] {
+        &self.series
+    }
+}
+
+/// On-Balance Volume: volume added on up closes and taken away on down closes
+pub(super) struct Obv {
+    series: [Series; 1],
+}
+
+impl Obv {
+    pub(super) fn new(_config: &IndicatorConfig) -> Self {
+        Self {
+            series: [Series::new(SeriesStyle::Line)],
+        }
+    }
+}
+
+impl Indicator for Obv {
+    fn compute(&mut self, candles: &[CandleData], from: usize) {
+        truncate(&mut self.series, from);
+        for i in from..candles.len() {
+            let obv = match i.checked_sub(1) {
+                Some(p) => {
+                    let previous = self.series[0].values[p].unwrap_or_default();
+                    let change = candles[i].close - candles[p].close;
+                    if change > 0.0 {
+                        previous + candles[i].volume
+                    } else if change < 0.0 {
+                        previous - candles[i].volume
+                    } else {
+                        previous
+                    }
+                }
+                None => 0.0,
+            };
+            self.series[0].values.push(Some(obv));
+        }
+    }
+
+    fn series(&self) -> &[Series] {
+        &self.series
+    }
+}
+
+/// Money Flow Index: an RSI of the typical price weighted by volume
+pub(super) struct Mfi {
+    period: usize,
+    source: Source,
+    series: [Series; 1],
+}
+
+impl Mfi {
+    pub(super) fn new(config: &IndicatorConfig) -> Self {
+        Self {
+            period: config.period,
+            source: config.source,
+            series: [Series::new(SeriesStyle::Line)],
+        }
+    }
+}
+
+impl Indicator for Mfi {
+    fn compute(&mut self, candles: &[CandleData], from: usize) {
+        truncate(&mut self.series, from);
+        for i in from..candles.len() {
+            // Each bar's flow needs the bar before it
+            let mfi = (self.period > 0 && i >= self.period).then(|| {
+                let (mut positive, mut negative) = (0.0, 0.0);
+                for j in i + 1 - self.period..=i {
+                    let price = self.source.of(&candles[j]);
+                    let previous = self.source.of(&candles[j - 1]);
+                    let flow = price * candles[j].volume;
+                    if price > previous {
+                        positive += flow;
+                    } else if price < previous {
+                        negative += flow;
+                    }
+                }
+                strength_index(positive, negative)
+            });
+            self.series[0].values.push(mfi);
+        }
+    }
+
+    fn series(&self) -> &[Series] {
+        &self.series
+    }
+}
+
+/// Volume columns with a `period` bar average; period 0 leaves out the
+/// average
+pub(super) struct Volume {
+    period: usize,
+    series: [Series; 2], // volume, average
+}
+
+impl Volume {
+    pub(super) fn new(config: &IndicatorConfig) -> Self {
+        Self {
+            period: config.period,
+            series: [
+                Series::new(SeriesStyle::Columns),
+                Series::new(SeriesStyle::Line),
+            ],
+        }
+    }
+}
+
+impl Indicator for Volume {
+    fn compute(&mut self, candles: &[CandleData], from: usize) {
+        truncate(&mut self.series, from);
+        for i in from..candles.len() {
+            self.series[0].values.push(Some(candles[i].volume));
+            let average = window(candles, i, self.period)
+                .map(|bars| bars.iter().map(|c| c.volume).sum::<f32>() / self.period as f32);
+            self.series[1].values.push(average);
+        }
+    }
+
+    fn series(&self) -> &[Series] {
+        &self.series
+    }
+}
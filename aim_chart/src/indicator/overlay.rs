@@ -0,0 +1,474 @@
+//! Indicators drawn over the price pane.
+
+use aim_data::calendar::to_local;
+
+use super::{
+    Indicator, IndicatorConfig, Series, SeriesStyle, Source, atr_seed, ema_step, mean, midpoint,
+    true_range, truncate, wilder_step, window,
+};
+use crate::CandleData;
+
+/// Simple moving average
+pub(super) struct Sma {
+    period: usize,
+    source: Source,
+    series: [Series; 1],
+}
+
+impl Sma {
+    pub(super) fn new(config: &IndicatorConfig) -> Self {
+        Self {
+            period: config.period,
+            source: config.source,
+            series: [Series::new(SeriesStyle::Line)],
+        }
+    }
+}
+
+impl Indicator for Sma {
+    fn compute(&mut self, candles: &[CandleData], from: usize) {
+        truncate(&mut self.series, from);
+        for i in from..candles.len() {
+            let value = mean(candles, i, self.period, self.source);
+            self.series[0].values.push(value);
+        }
+    }
+
+    fn series(&self) -> &[Series] {
+        &self.series
+    }
+}
+
+/// Exponential moving average, seeded with the SMA of the first period
+pub(super) struct Ema {
+    period: usize,
+    source: Source,
+    series: [Series; 1],
+}
+
+impl Ema {
+    pub(super) fn new(config: &IndicatorConfig) -> Self {
+        Self {
+            period: config.period,
+            source: config.source,
+            series: [Series::new(SeriesStyle::Line)],
+        }
+    }
+}
+
+impl Indicator for Ema {
+    fn compute(&mut self, candles: &[CandleData], from: usize) {
+        truncate(&mut self.series, from);
+        for i in from..candles.len() {
+            let previous = i.checked_sub(1).and_then(|p| self.series[0].values[p]);
+            let value = ema_step(previous, self.source.of(&candles[i]), self.period, || {
+                mean(candles, i, self.period, self.source)
+            });
+            self.series[0].values.push(value);
+        }
+    }
+
+    fn series(&self) -> &[Series] {
+        &self.series
+    }
+}
+
+/// Linearly weighted moving average, the newest bar weighing `period`
+pub(super) struct Wma {
+    period: usize,
+    source: Source,
+    series: [Series; 1],
+}
+
+impl Wma {
+    pub(super) fn new(config: &IndicatorConfig) -> Self {
+        Self {
+            period: config.period,
+            source: config.source,
+            series: [Series::new(SeriesStyle::Line)],
+        }
+    }
+}
+
+impl Indicator for Wma {
+    fn compute(&mut self, candles: &[CandleData], from: usize) {
+        truncate(&mut self.series, from);
+        let weights = (self.period * (self.period + 1) / 2) as f32;
+        for i in from..candles.len() {
+            let value = window(candles, i, self.period).map(|bars| {
+                bars.iter()
+                    .enumerate()
+                    .map(|(w, candle)| (w + 1) as f32 * self.source.of(candle))
+                    .sum::<f32>()
+                    / weights
+            });
+            self.series[0].values.push(value);
+        }
+    }
+
+    fn series(&self) -> &[Series] {
+        &self.series
+    }
+}
+
+/// Bollinger Bands: SMA basis with bands `multiplier` standard deviations away
+pub(super) struct Bollinger {
+    period: usize,
+    source: Source,
+    multiplier: f32,
+    series: [Series; 3], // basis, upper, lower
+}
+
+impl Bollinger {
+    pub(super) fn new(config: &IndicatorConfig) -> Self {
+        Self {
+            period: config.period,
+            source: config.source,
+            multiplier: config.multiplier,
+            series: [
+                Series::new(SeriesStyle::Line),
+                Series::new(SeriesStyle::Band),
+                Series::new(SeriesStyle::Band),
+            ],
+        }
+    }
+}
+
+impl Indicator for Bollinger {
+    fn compute(&mut self, candles: &[CandleData], from: usize) {
+        truncate(&mut self.series, from);
+        for i in from..candles.len() {
+            let bands = window(candles, i, self.period).map(|bars| {
+                let basis = bars.iter().map(|c| self.source.of(c)).sum::<f32>() / bars.len() as f32;
+                let variance = bars
+                    .iter()
+                    .map(|c| (self.source.of(c) - basis).powi(2))
+                    .sum::<f32>()
+                    / bars.len() as f32;
+                let width = self.multiplier * variance.sqrt();
+                (basis, basis + width, basis - width)
+            });
+            self.series[0].values.push(bands.map(|b| b.0));
+            self.series[1].values.push(bands.map(|b| b.1));
+            self.series[2].values.push(bands.map(|b| b.2));
+        }
+    }
+
+    fn series(&self) -> &[Series] {
+        &self.series
+    }
+}
+
+/// Volume-weighted average price.
+///
+/// With period 0 the average restarts at the first bar of each local day,
+/// which suits intraday charts; otherwise it covers the last `period` bars.
+pub(super) struct Vwap {
+    period: usize,
+    source: Source,
+    totals: Vec<(f64, f64)>, // running (price * volume, volume) per bar
+    series: [Series; 1],
+}
+
+impl Vwap {
+    pub(super) fn new(config: &IndicatorConfig) -> Self {
+        Self {
+            period: config.period,
+            source: config.source,
+            totals: Vec::new(),
+            series: [Series::new(SeriesStyle::Line)],
+        }
+    }
+}
+
+impl Indicator for Vwap {
+    fn compute(&mut self, candles: &[CandleData], from: usize) {
+        truncate(&mut self.series, from);
+        self.totals.truncate(from);
+        for i in from..candles.len() {
+            let candle = &candles[i];
+            let pv = self.source.of(candle) as f64 * candle.volume as f64;
+            let volume = candle.volume as f64;
+            let (pv, volume) = if self.period > 0 {
+                candles[i + 1 - (i + 1).min(self.period)..=i].iter().fold(
+                    (0.0, 0.0),
+                    |(pv, v), c| {
+                        (
+                            pv + self.source.of(c) as f64 * c.volume as f64,
+                            v + c.volume as f64,
+                        )
+                    },
+                )
+            } else {
+                let same_day = i > 0
+                    && to_local(candles[i - 1].time).date_naive()
+                        == to_local(candle.time).date_naive();
+                match self.totals.last() {
+                    Some((total_pv, total_volume)) if same_day => {
+                        (total_pv + pv, total_volume + volume)
+                    }
+                    _ => (pv, volume),
+                }
+            };
+            self.totals.push((pv, volume));
+            self.series[0]
+                .values
+                .push((volume > 0.0).then(|| (pv / volume) as f32));
+        }
+    }
+
+    fn series(&self) -> &[Series] {
+        &self.series
+    }
+}
+
+/// Ichimoku Kinko Hyo.
+///
+/// `period` is the base line length (26 by default); the conversion line
+/// uses 9/26 of it, the second cloud span twice it, and the cloud and the
+/// lagging span are displaced by `period` bars.
+pub(super) struct Ichimoku {
+    conversion: usize,
+    base: usize,
+    span: usize,
+    series: [Series; 5], // conversion, base, span A, span B, lagging
+}
+
+impl Ichimoku {
+    pub(super) fn new(config: &IndicatorConfig) -> Self {
+        let displacement = config.period as isize;
+        Self {
+            conversion: (config.period * 9 / 26).max(1),
+            base: config.period,
+            span: config.period * 2,
+            series: [
+                Series::new(SeriesStyle::Line),
+                Series::new(SeriesStyle::Line),
+                Series::shifted(SeriesStyle::Band, displacement),
+                Series::shifted(SeriesStyle::Band, displacement),
+                Series::shifted(SeriesStyle::Band, -displacement),
+            ],
+        }
+    }
+}
+
+impl Indicator for Ichimoku {
+    fn compute(&mut self, candles: &[CandleData], from: usize) {
+        truncate(&mut self.series, from);
+        for (i, candle) in candles.iter().enumerate().skip(from) {
+            let conversion = midpoint(candles, i, self.conversion);
+            let base = midpoint(candles, i, self.base);
+            let span_a = conversion.zip(base).map(|(c, b)| (c + b) / 2.0);
+            self.series[0].values.push(conversion);
+            self.series[1].values.push(base);
+            self.series[2].values.push(span_a);
+            self.series[3].values.push(midpoint(candles, i, self.span));
+            self.series[4].values.push(Some(candle.close));
+        }
+    }
+
+    fn series(&self) -> &[Series] {
+        &self.series
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SarState {
+    sar: f32,
+    extreme: f32,
+    factor: f32,
+    rising: bool,
+}
+
+/// Parabolic SAR, accelerating by `multiplier` up to ten times that
+pub(super) struct ParabolicSar {
+    step: f32,
+    states: Vec<Option<SarState>>,
+    series: [Series; 1],
+}
+
+impl ParabolicSar {
+    pub(super) fn new(config: &IndicatorConfig) -> Self {
+        Self {
+            step: config.multiplier,
+            states: Vec::new(),
+            series: [Series::new(SeriesStyle::Dots)],
+        }
+    }
+
+    fn next(&self, candles: &[CandleData], i: usize) -> Option<SarState> {
+        let candle = &candles[i];
+        let prev = &candles[i.checked_sub(1)?];
+        let Some(state) = self.states[i - 1] else {
+            // Take the direction of the first two closes
+            let rising = candle.close >= prev.close;
+            return Some(SarState {
+                sar: if rising { prev.low } else { prev.high },
+                extreme: if rising { candle.high } else { candle.low },
+                factor: self.step,
+                rising,
+            });
+        };
+        let max_factor = self.step * 10.0;
+        let before = &candles[i.saturating_sub(2)];
+        let sar = state.sar + state.factor * (state.extreme - state.sar);
+        let next = if state.rising {
+            let sar = sar.min(prev.low).min(before.low);
+            if candle.low < sar {
+                SarState {
+                    sar: state.extreme,
+                    extreme: candle.low,
+                    factor: self.step,
+                    rising: false,
+                }
+            } else if candle.high > state.extreme {
+                SarState {
+                    sar,
+                    extreme: candle.high,
+                    factor: (state.factor + self.step).min(max_factor),
+                    rising: true,
+                }
+            } else {
+                SarState { sar, ..state }
+            }
+        } else {
+            let sar = sar.max(prev.high).max(before.high);
+            if candle.high > sar {
+                SarState {
+                    sar: state.extreme,
+                    extreme: candle.high,
+                    factor: self.step,
+                    rising: true,
+                }
+            } else if candle.low < state.extreme {
+                SarState {
+                    sar,
+                    extreme: candle.low,
+                    factor: (state.factor + self.step).min(max_factor),
+                    rising: false,
+                }
+            } else {
+                SarState { sar, ..state }
+            }
+        };
+        Some(next)
+    }
+}
+
+impl Indicator for ParabolicSar {
+    fn compute(&mut self, candles: &[CandleData], from: usize) {
+        truncate(&mut self.series, from);
+        self.states.truncate(from);
+        for i in from..candles.len() {
+            let state = self.next(candles, i);
+            self.states.push(state);
+            self.series[0].values.push(state.map(|state| state.sar));
+        }
+    }
+
+    fn series(&self) -> &[Series] {
+        &self.series
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SupertrendState {
+    atr: f32,
+    upper: f32,
+    lower: f32,
+    rising: bool,
+}
+
+/// Supertrend: an ATR trailing stop, `multiplier` ATRs from the source price.
+///
+/// The stop under a rising market is drawn solid, the one above a falling
+/// market faded.
+pub(super) struct Supertrend {
+    period: usize,
+    source: Source,
+    multiplier: f32,
+    states: Vec<Option<SupertrendState>>,
+    series: [Series; 2], // rising, falling
+}
+
+impl Supertrend {
+    pub(super) fn new(config: &IndicatorConfig) -> Self {
+        Self {
+            period: config.period,
+            source: config.source,
+            multiplier: config.multiplier,
+            states: Vec::new(),
+            series: [
+                Series::new(SeriesStyle::Line),
+                Series::new(SeriesStyle::Band),
+            ],
+        }
+    }
+
+    fn next(&self, candles: &[CandleData], i: usize) -> Option<SupertrendState> {
+        let previous = i.checked_sub(1).and_then(|p| self.states[p]);
+        let atr = wilder_step(
+            previous.map(|state| state.atr),
+            true_range(candles, i),
+            self.period,
+            || atr_seed(candles, i, self.period),
+        )?;
+        let candle = &candles[i];
+        let middle = self.source.of(candle);
+        let upper = middle + self.multiplier * atr;
+        let lower = middle - self.multiplier * atr;
+        let Some(state) = previous else {
+            return Some(SupertrendState {
+                atr,
+                upper,
+                lower,
+                rising: true,
+            });
+        };
+        // The stops only move towards the price while the trend holds
+        let prev_close = candles[i - 1].close;
+        let upper = if upper < state.upper || prev_close > state.upper {
+            upper
+        } else {
+            state.upper
+        };
+        let lower = if lower > state.lower || prev_close < state.lower {
+            lower
+        } else {
+            state.lower
+        };
+        let rising = if state.rising {
+            candle.close >= lower
+        } else {
+            candle.close > upper
+        };
+        Some(SupertrendState {
+            atr,
+            upper,
+            lower,
+            rising,
+        })
+    }
+}
+
+impl Indicator for Supertrend {
+    fn compute(&mut self, candles: &[CandleData], from: usize) {
+        truncate(&mut self.series, from);
+        self.states.truncate(from);
+        for i in from..candles.len() {
+            let state = self.next(candles, i);
+            self.states.push(state);
+            let (rising, falling) = match state {
+                Some(state) if state.rising => (Some(state.lower), None),
+                Some(state) => (None, Some(state.upper)),
+                None => (None, None),
+            };
+            self.series[0].values.push(rising);
+            self.series[1].values.push(falling);
+        }
+    }
+
+    fn series(&self) -> &[Series] {
+        &self.series
+    }
+}
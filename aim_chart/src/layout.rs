@@ -0,0 +1,395 @@
+//! Multi-chart layouts of the chart page.
+//!
+//! The first cell always holds the interactive chart of the current stock;
+//! the other cells hold charts of their own, each with a symbol and a time
+//! frame. Linked cells follow the symbol, time frame or crosshair of the
+//! first one.
+
+use aim_data::Timeframe;
+use chrono::{DateTime, Utc};
+
+use crate::{
+    CandleData, Chart, MouseType, UiData,
+    cache::{read_label, write_label},
+};
+
+/// Grid of the chart page, named rows x columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LayoutKind {
+    #[default]
+    Single,
+    OneByTwo,
+    TwoByTwo,
+    ThreeByOne,
+}
+
+impl LayoutKind {
+    /// Parse a label as shown in the layout picker: `1x1`, `1x2`, `2x2` or
+    /// `3x1`.
+    pub fn parse(label: &str) -> Option<Self> {
+        match label.trim() {
+            "1x1" => Some(LayoutKind::Single),
+            "1x2" => Some(LayoutKind::OneByTwo),
+            "2x2" => Some(LayoutKind::TwoByTwo),
+            "3x1" => Some(LayoutKind::ThreeByOne),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            LayoutKind::Single => "1x1",
+            LayoutKind::OneByTwo => "1x2",
+            LayoutKind::TwoByTwo => "2x2",
+            LayoutKind::ThreeByOne => "3x1",
+        }
+    }
+
+    /// Rows and columns of the grid.
+    pub fn grid(&self) -> (usize, usize) {
+        match self {
+            LayoutKind::Single => (1, 1),
+            LayoutKind::OneByTwo => (1, 2),
+            LayoutKind::TwoByTwo => (2, 2),
+            LayoutKind::ThreeByOne => (3, 1),
+        }
+    }
+
+    /// Number of cells, the first chart included.
+    pub fn cell_count(&self) -> usize {
+        let (rows, columns) = self.grid();
+        rows * columns
+    }
+}
+
+/// A cell of the layout after the first one.
+#[derive(Debug, Clone)]
+pub struct LayoutCell {
+    pub symbol: String,
+    pub time_frame: Timeframe,
+    pub chart: Option<Chart>, // None until the bars of `symbol` are fetched
+    rendered: Option<(CellRender, slint::Image)>, // last image of `chart` and what it shows
+}
+
+/// What a cell image shows, to tell when it has to be rendered again.
+#[derive(Debug, Clone, PartialEq)]
+struct CellRender {
+    width: i32,
+    height: i32,
+    crosshair: usize, // index of the bar under the crosshair
+    bars: usize,
+    last_bar: Option<CandleData>, // changes in place while it is still forming
+}
+
+impl LayoutCell {
+    /// Whether the chart is missing or shows another symbol or time frame.
+    pub fn is_stale(&self) -> bool {
+        !self.chart.as_ref().is_some_and(|chart| {
+            chart.stock_name == self.symbol && chart.time_frame == self.time_frame
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ChartLayout {
+    pub kind: LayoutKind,
+    pub cells: Vec<LayoutCell>, // cells after the first one, row by row
+    pub link_symbol: bool,
+    pub link_crosshair: bool,
+    pub link_time_frame: bool,
+}
+
+impl ChartLayout {
+    /// Switch to `kind`, keeping the cells that still fit.
+    ///
+    /// # Arguments
+    /// * `symbol` / `time_frame` - What the first chart shows, for new cells
+    pub fn set_kind(&mut self, kind: LayoutKind, symbol: &str, time_frame: Timeframe) {
+        self.kind = kind;
+        let count = kind.cell_count() - 1;
+        self.cells.truncate(count);
+        while self.cells.len() < count {
+            self.cells.push(LayoutCell {
+                symbol: symbol.to_string(),
+                time_frame,
+                chart: None,
+                rendered: None,
+            });
+        }
+    }
+
+    /// Show `symbol` at `time_frame` in cell `index`, counted after the
+    /// first chart.
+    pub fn set_cell(&mut self, index: usize, symbol: &str, time_frame: Timeframe) {
+        if let Some(cell) = self.cells.get_mut(index) {
+            cell.symbol = symbol.trim().to_uppercase();
+            cell.time_frame = time_frame;
+        }
+    }
+
+    /// Point the linked cells at the symbol and time frame of the first
+    /// chart.
+    pub fn follow(&mut self, symbol: &str, time_frame: Timeframe) {
+        for cell in &mut self.cells {
+            if self.link_symbol {
+                cell.symbol = symbol.to_string();
+            }
+            if self.link_time_frame {
+                cell.time_frame = time_frame;
+            }
+        }
+    }
+
+    /// Index, symbol and time frame of the cells whose bars need fetching.
+    pub fn stale_cells(&self) -> Vec<(usize, String, Timeframe)> {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, cell)| cell.is_stale() && !cell.symbol.is_empty())
+            .map(|(i, cell)| (i, cell.symbol.clone(), cell.time_frame))
+            .collect()
+    }
+
+    /// Put a fetched chart in cell `index`, unless the cell moved on to
+    /// another symbol or time frame while it was fetched.
+    pub fn set_chart(&mut self, index: usize, chart: Chart) {
+        if let Some(cell) = self.cells.get_mut(index)
+            && cell.symbol == chart.stock_name
+            && cell.time_frame == chart.time_frame
+        {
+            cell.chart = Some(chart);
+            cell.rendered = None;
+        }
+    }
+
+    pub fn charts(&self) -> impl Iterator<Item = &Chart> {
+        self.cells.iter().filter_map(|cell| cell.chart.as_ref())
+    }
+
+    pub fn charts_mut(&mut self) -> impl Iterator<Item = &mut Chart> {
+        self.cells.iter_mut().filter_map(|cell| cell.chart.as_mut())
+    }
+
+    /// Images of the cells, each `width` x `height` pixels like every cell
+    /// of the grid.
+    ///
+    /// A cell is only rendered again when its bars, its crosshair or the
+    /// size changed since the last call.
+    ///
+    /// # Arguments
+    /// * `cursor` - Crosshair time of the first chart when the crosshair is
+    ///   linked; otherwise each cell shows its last bar
+    ///
+    /// # Returns
+    /// * `Vec<slint::Image>` - One image per cell, empty for cells still loading
+    pub fn render_cells(
+        &mut self,
+        width: i32,
+        height: i32,
+        cursor: Option<DateTime<Utc>>,
+    ) -> Vec<slint::Image> {
+        let cursor = cursor.filter(|_| self.link_crosshair);
+        self.cells
+            .iter_mut()
+            .map(|cell| {
+                let Some(chart) = &mut cell.chart else {
+                    return slint::Image::default();
+                };
+                let render = CellRender {
+                    width,
+                    height,
+                    crosshair: chart.cell_crosshair(cursor),
+                    bars: chart.candle_data.len(),
+                    last_bar: chart.candle_data.last().cloned(),
+                };
+                match &cell.rendered {
+                    Some((rendered, image)) if *rendered == render => image.clone(),
+                    _ => {
+                        let ui_data = chart.cell_ui_data(width, height, cursor);
+                        let image = chart.render_plot(ui_data).0;
+                        cell.rendered = Some((render, image.clone()));
+                        image
+                    }
+                }
+            })
+            .collect()
+    }
+
+    // Manual serialization: kind, links, then the symbol and time frame of
+    // each cell, whose chart is fetched again
+    pub fn write_to_bytes(&self, buf: &mut Vec<u8>) {
+        buf.push(self.kind as u8);
+        buf.push(self.link_symbol as u8);
+        buf.push(self.link_crosshair as u8);
+        buf.push(self.link_time_frame as u8);
+        buf.extend_from_slice(&(self.cells.len() as u32).to_le_bytes());
+        for cell in &self.cells {
+            write_label(buf, &cell.symbol);
+            write_label(buf, &cell.time_frame.to_string());
+        }
+    }
+
+    // Manual deserialization: read a layout written by cache `version`
+    pub fn read_from_bytes(data: &[u8], version: u32) -> Option<(Self, usize)> {
+        if data.len() < 4 + 4 {
+            return None;
+        }
+        let kind = match data[0] {
+            0 => LayoutKind::Single,
+            1 => LayoutKind::OneByTwo,
+            2 => LayoutKind::TwoByTwo,
+            3 => LayoutKind::ThreeByOne,
+            _ => return None,
+        };
+        let (link_symbol, link_crosshair, link_time_frame) =
+            (data[1] != 0, data[2] != 0, data[3] != 0);
+        let mut pos = 4;
+        let count = u32::from_le_bytes(data[pos..pos + 4].try_into().unwrap()) as usize;
+        pos += 4;
+        let mut cells = Vec::with_capacity(count);
+        for _ in 0..count {
+            let (symbol, used) = read_label(&data[pos..])?;
+            pos += used;
+            let (time_frame, used) = read_label(&data[pos..])?;
+            pos += used;
+            // chart: before version 12 only
+            let has_chart = version < 12 && *data.get(pos)? != 0;
+            if version < 12 {
+                pos += 1;
+            }
+            let chart = if has_chart {
+                let (chart, used) = Chart::read_from_bytes(&data[pos..], version)?;
+                pos += used;
+                Some(chart)
+            } else {
+                None
+            };
+            cells.push(LayoutCell {
+                symbol,
+                time_frame: Timeframe::parse(&time_frame).unwrap_or_default(),
+                chart,
+                rendered: None,
+            });
+        }
+        Some((
+            Self {
+                kind,
+                cells,
+                link_symbol,
+                link_crosshair,
+                link_time_frame,
+            },
+            pos,
+        ))
+    }
+}
+
+impl Chart {
+    /// Time of the bar under the cursor as of the last render.
+    pub fn cursor_time(&self) -> Option<DateTime<Utc>> {
+        let (x, _) = self.get_mouse_position();
+        let last = self.candle_data.len().checked_sub(1)?;
+        let index = (x.round().max(0.0) as usize).min(last);
+        Some(self.candle_data[index].time)
+    }
+
+    /// Index of the bar at `time` as a layout cell shows the crosshair on
+    /// it, the last bar without a time.
+    fn cell_crosshair(&self, time: Option<DateTime<Utc>>) -> usize {
+        match time {
+            Some(time) => self
+                .candle_data
+                .partition_point(|candle| candle.time <= time)
+                .saturating_sub(1),
+            None => self.candle_data.len().saturating_sub(1),
+        }
+    }
+
+    /// Input that renders this chart as a layout cell, with the crosshair on
+    /// the close of the bar at `time`, or of the last bar.
+    pub(crate) fn cell_ui_data(
+        &self,
+        width: i32,
+        height: i32,
+        time: Option<DateTime<Utc>>,
+    ) -> UiData {
+        let mut ui_data = UiData {
+            ticker: self.stock_name.clone(),
+            mouse_type: MouseType::Move,
+            width,
+            height,
+            time_frame: self.time_frame.to_string(),
+            is_release: true,
+            ..UiData::default()
+        };
+        let index = self.cell_crosshair(time);
+        let Some(candle) = self.candle_data.get(index) else {
+            return ui_data;
+        };
+
+        // The inverse of `get_mouse_position` at the view of the last render
+        let (_, max_y, min_y) = self.update_y_axis_after_moving();
+        let plot_height = self.chart_data.height as f32 - self.chart_data.range_x as f32;
+        ui_data.position_x = ((index as f32 - self.chart_data.x_offset_min
+            + self.chart_data.zoom_x)
+            * self.chart_data.candle_distance)
+            .round() as i32;
+        if max_y > min_y {
+            ui_data.position_y = (plot_height
+                - (self.price_scale.to_axis(candle.close) - min_y) / (max_y - min_y) * plot_height)
+                .round() as i32;
+        }
+        ui_data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{bar, bars, chart};
+
+    /// Swap the image kept for the first cell for an empty one, to tell a
+    /// reused image from a new render.
+    fn forget_image(layout: &mut ChartLayout) {
+        if let Some((_, image)) = &mut layout.cells[0].rendered {
+            *image = slint::Image::default();
+        }
+    }
+
+    #[test]
+    fn render_cells_only_renders_changed_cells() {
+        let chart = chart(bars(&[10.0, 11.0, 12.0, 13.0]));
+        let mut layout = ChartLayout::default();
+        layout.set_kind(LayoutKind::OneByTwo, &chart.stock_name, chart.time_frame);
+        layout.set_chart(0, chart);
+        assert!(layout.render_cells(400, 300, None)[0].to_rgb8().is_some());
+
+        forget_image(&mut layout);
+        assert!(layout.render_cells(400, 300, None)[0].to_rgb8().is_none());
+
+        // A new size
+        assert!(layout.render_cells(500, 300, None)[0].to_rgb8().is_some());
+
+        // A new bar
+        forget_image(&mut layout);
+        let candle_data = &mut layout.cells[0].chart.as_mut().unwrap().candle_data;
+        candle_data.push(bar(4, 13.0, 15.0, 12.0, 14.0));
+        assert!(layout.render_cells(500, 300, None)[0].to_rgb8().is_some());
+
+        // The last bar still forming
+        forget_image(&mut layout);
+        let candle_data = &mut layout.cells[0].chart.as_mut().unwrap().candle_data;
+        candle_data.last_mut().unwrap().close = 14.5;
+        assert!(layout.render_cells(500, 300, None)[0].to_rgb8().is_some());
+
+        // A linked crosshair on another bar
+        forget_image(&mut layout);
+        layout.link_crosshair = true;
+        let time = layout.cells[0].chart.as_ref().unwrap().candle_data[1].time;
+        assert!(
+            layout.render_cells(500, 300, Some(time))[0]
+                .to_rgb8()
+                .is_some()
+        );
+    }
+}
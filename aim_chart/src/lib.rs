@@ -1,8 +1,27 @@
+mod alert;
 mod cache;
+pub use cache::{CHART_CACHE_LEGACY_VERSIONS, CHART_CACHE_MAGIC, CHART_CACHE_VERSION};
 mod chart;
+mod chart_type;
+pub use chart_type::{BoxSize, ChartType};
 mod company_info;
+mod compare;
+pub use compare::{Comparison, parse_comparisons};
 mod draw;
+pub use draw::DrawStyle;
+mod edit;
+mod history;
+pub mod indicator;
+mod layout;
+pub use layout::{ChartLayout, LayoutCell, LayoutKind};
 pub mod mini_chart;
+mod pane;
+mod scale;
+pub use scale::PriceScale;
+mod share;
+pub use share::{CHART_STATE_VERSION, ChartState, SharedDrawing, Viewport};
+mod time_axis;
+mod tool;
 pub use chart::Chart;
 use chrono::{DateTime, Utc};
 pub use company_info::CompanyInfo;
@@ -22,6 +41,13 @@ pub enum MouseType {
     Text,
     HorizontalLine,
     VerticalLine,
+    FibRetracement,
+    FibExtension,
+    Channel,
+    Pitchfork,
+    GannFan,
+    LongPosition,
+    ShortPosition,
 }
 
 #[derive(Debug, Clone)]
@@ -41,11 +67,45 @@ pub struct UiData {
     pub height: i32,
     pub time_frame: String,
     pub is_new_time_frame: bool,
+    pub is_adjusted: bool,
+    pub indicators: String, // indicator list as text, see `indicator::parse_indicators`
+    pub is_new_indicators: bool,
     pub is_new_stock: bool,
     pub is_in_object: bool,
     pub is_undo: bool,
     pub is_in_update: bool,
     pub color: Color,
+    pub text: String,       // content of the next text annotation
+    pub text_size: i32,     // font size of the next text annotation, in pixels
+    pub fib_levels: String, // levels of the next Fibonacci tool, see `draw::parse_levels`
+    pub line_width: i32,    // line width of new drawings and style edits, in pixels
+    pub is_dashed: bool,
+    pub fill_opacity: f32, // fill opacity of rectangles, ovals and positions, 0 to 1
+    pub is_locked: bool,
+    pub is_hidden: bool,
+    pub is_new_style: bool, // apply the selected style below to the selected drawing
+    pub is_redo: bool,
+    pub is_delete: bool, // delete the selected drawing
+    pub is_show_hidden: bool,
+    pub has_selection: bool, // set by the chart when a drawing is selected
+    // Style of the selected drawing, set by the chart and edited in the
+    // toolbar while there is a selection; the style above stays for new drawings
+    pub selected_color: Color,
+    pub selected_line_width: i32,
+    pub selected_dashed: bool,
+    pub selected_fill_opacity: f32,
+    pub selected_locked: bool,
+    pub selected_hidden: bool,
+    pub compare: String, // symbols to compare with, see `compare::parse_comparisons`
+    pub is_compare_ratio: bool, // draw the ratios to the comparisons
+    pub chart_type: String, // label of a `ChartType`, see `ChartType::parse`
+    pub box_size: String, // Renko and P&F box size, see `BoxSize::parse`
+    pub is_new_chart_type: bool,
+    pub price_scale: String, // label of a `PriceScale`, see `PriceScale::parse`
+    pub is_auto_fit: bool,   // fit the y axis to the visible bars
+    pub is_lock_scale: bool, // keep the y axis as it is
+    pub is_new_scale: bool,
+    pub hide_cursor: bool, // leave out the crosshair and its labels, as in a snapshot
 }
 
 impl Default for UiData {
@@ -66,17 +126,49 @@ impl Default for UiData {
             height: 0,
             time_frame: String::new(),
             is_new_time_frame: false,
+            is_adjusted: false,
+            indicators: String::new(),
+            is_new_indicators: false,
             is_new_stock: false,
             is_in_object: false,
             is_undo: false,
             is_in_update: false,
             color: Color::default(),
+            text: String::new(),
+            text_size: draw::DEFAULT_TEXT_SIZE as i32,
+            fib_levels: String::new(),
+            line_width: 1,
+            is_dashed: false,
+            fill_opacity: 0.2,
+            is_locked: false,
+            is_hidden: false,
+            is_new_style: false,
+            is_redo: false,
+            is_delete: false,
+            is_show_hidden: false,
+            has_selection: false,
+            selected_color: Color::default(),
+            selected_line_width: 1,
+            selected_dashed: false,
+            selected_fill_opacity: 0.2,
+            selected_locked: false,
+            selected_hidden: false,
+            compare: String::new(),
+            is_compare_ratio: false,
+            chart_type: String::new(),
+            box_size: String::new(),
+            is_new_chart_type: false,
+            price_scale: String::new(),
+            is_auto_fit: false,
+            is_lock_scale: false,
+            is_new_scale: false,
+            hide_cursor: false,
         }
     }
 }
 
 type CandleDataVec = Vec<CandleData>;
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct CandleData {
     num: f32,
     time: DateTime<Utc>,
@@ -170,3 +262,153 @@ pub fn convert_candlesticks(is_stock: bool, data: Vec<Candlestick>) -> CandleDat
             .collect()
     }
 }
+
+/// Merge `candles` into `existing`, both sorted by time.
+///
+/// A bar whose timestamp is already cached replaces it and keeps its `num`;
+/// newer bars are appended with consecutive `num` values. Older bars missing
+/// from the cache are dropped, since inserting them would shift every index
+/// that drawings refer to.
+///
+/// # Returns
+/// * `usize` - Number of bars appended
+fn merge_candles(existing: &mut CandleDataVec, candles: CandleDataVec) -> usize {
+    let mut appended = 0;
+    for mut candle in candles {
+        // A bar without a trade yet comes back as all zeros
+        if candle.close <= 0.0 {
+            continue;
+        }
+        match existing.last() {
+            Some(last) if candle.time > last.time => {
+                candle.num = last.num + 1.0;
+                existing.push(candle);
+                appended += 1;
+            }
+            Some(_) => {
+                if let Ok(i) = existing.binary_search_by(|cached| cached.time.cmp(&candle.time)) {
+                    candle.num = existing[i].num;
+                    // The feed may not know the open of a forming bar yet
+                    if candle.open <= 0.0 {
+                        candle.open = existing[i].open;
+                    }
+                    existing[i] = candle;
+                }
+            }
+            None => {
+                candle.num = 0.0;
+                existing.push(candle);
+                appended += 1;
+            }
+        }
+    }
+    appended
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// A bar `day` days after 2024-01-01, numbered by its day.
+    pub(crate) fn bar(day: i64, open: f32, high: f32, low: f32, close: f32) -> CandleData {
+        CandleData {
+            num: day as f32,
+            time: DateTime::from_timestamp(1_704_067_200 + day * 86_400, 0).unwrap(),
+            open,
+            high,
+            low,
+            close,
+            volume: 100.0,
+        }
+    }
+
+    /// Bars closing at `closes`, one a day from 2024-01-01.
+    pub(crate) fn bars(closes: &[f32]) -> CandleDataVec {
+        closes
+            .iter()
+            .enumerate()
+            .map(|(day, &close)| bar(day as i64, close, close + 1.0, close - 1.0, close))
+            .collect()
+    }
+
+    /// A chart of `candles` showing the latest of them, with the default
+    /// indicators.
+    pub(crate) fn chart(candles: CandleDataVec) -> Chart {
+        let first = Candlestick {
+            timestamp: candles[0].time,
+            open: candles[0].open as f64,
+            high: candles[0].high as f64,
+            low: candles[0].low as f64,
+            close: candles[0].close as f64,
+            volume: candles[0].volume as i64,
+        };
+        let stock = aim_data::OHLCData::from_candlesticks("TEST", &[first]);
+        let mut chart = Chart::new_default(
+            "TEST".to_string(),
+            stock,
+            CompanyInfo::default(),
+            aim_data::Timeframe::Days(1),
+        );
+        chart.chart_data = Chart::latest_view(&candles, chart.price_scale);
+        chart.candle_data = candles.clone();
+        chart.source_data = candles;
+        chart
+    }
+
+    #[test]
+    fn test_merge_replaces_by_timestamp_and_keeps_num() {
+        let mut existing = bars(&[10.0, 11.0, 12.0]);
+        let mut update = bar(1, 0.0, 14.0, 10.5, 13.0);
+        update.num = 42.0;
+        assert_eq!(merge_candles(&mut existing, vec![update]), 0);
+
+        assert_eq!(existing.len(), 3);
+        assert_eq!(existing[1].num, 1.0);
+        assert_eq!(existing[1].close, 13.0);
+        assert_eq!(existing[1].high, 14.0);
+        // An unknown open keeps the cached one
+        assert_eq!(existing[1].open, 11.0);
+    }
+
+    #[test]
+    fn test_merge_appends_newer_bars_with_consecutive_num() {
+        let mut existing = bars(&[10.0, 11.0]);
+        let mut newer = vec![
+            bar(5, 12.0, 13.0, 11.0, 12.5),
+            bar(6, 12.5, 14.0, 12.0, 13.5),
+        ];
+        newer[0].num = 0.0;
+        newer[1].num = 0.0;
+        assert_eq!(merge_candles(&mut existing, newer), 2);
+
+        let nums: Vec<f32> = existing.iter().map(|c| c.num).collect();
+        assert_eq!(nums, [0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(existing[3].close, 13.5);
+
+        // Into an empty cache the first bar is numbered 0
+        let mut empty = CandleDataVec::new();
+        assert_eq!(
+            merge_candles(&mut empty, vec![bar(3, 1.0, 1.0, 1.0, 1.0)]),
+            1
+        );
+        assert_eq!(empty[0].num, 0.0);
+    }
+
+    #[test]
+    fn test_merge_drops_empty_and_unknown_older_bars() {
+        let mut existing = bars(&[10.0, 11.0]);
+        existing[0] = bar(2, 10.0, 11.0, 9.0, 10.0);
+        existing[1] = bar(4, 11.0, 12.0, 10.0, 11.0);
+        let incoming = vec![
+            bar(1, 9.0, 9.0, 9.0, 9.0),     // older than the cache and not in it
+            bar(3, 10.0, 10.0, 10.0, 10.0), // between cached bars
+            bar(5, 0.0, 0.0, 0.0, 0.0),     // no trade yet
+            bar(6, 12.0, 12.0, 12.0, -1.0),
+        ];
+        assert_eq!(merge_candles(&mut existing, incoming), 0);
+
+        assert_eq!(existing.len(), 2);
+        assert_eq!(existing[0].close, 10.0);
+        assert_eq!(existing[1].close, 11.0);
+    }
+}
@@ -46,25 +46,23 @@ fn render_simple_trading_chart(
     height: u32,
     ref_price: f32,
 ) {
-    use chrono::{Local, NaiveTime};
+    use aim_data::calendar::{AFTERNOON_SESSION, MORNING_SESSION, minute_of_day};
     use plotters::prelude::*;
 
     if data.is_empty() {
         return;
     }
 
-    // Trading sessions: 9:00-11:30 and 13:00-14:45
-    let morning_start = NaiveTime::from_hms_opt(9, 0, 0).unwrap();
-    let morning_end = NaiveTime::from_hms_opt(11, 30, 0).unwrap();
-    let afternoon_start = NaiveTime::from_hms_opt(13, 0, 0).unwrap();
-    let afternoon_end = NaiveTime::from_hms_opt(14, 45, 0).unwrap();
+    // Trading sessions of the calendar, as the resampled bars use them
+    let (morning_start, morning_end) = (MORNING_SESSION.open, MORNING_SESSION.close);
+    let (afternoon_start, afternoon_end) = (AFTERNOON_SESSION.open, AFTERNOON_SESSION.close);
 
     // Separate data into trading sessions
     let mut morning_data = Vec::new();
     let mut afternoon_data = Vec::new();
 
     for candle in data {
-        let time = candle.time.with_timezone(&Local).time();
+        let time = minute_of_day(candle.time);
         if time >= morning_start && time <= morning_end {
             morning_data.push(candle);
         } else if time >= afternoon_start && time <= afternoon_end {
@@ -134,7 +132,7 @@ fn render_simple_trading_chart(
         all_price_points.push((240.0, first_afternoon_price));
     }
 
-    // Afternoon session: map to 240-390 (representing 13:00-14:45)
+    // Afternoon session: map to 240-390 (representing 13:00-15:00)
     for (i, candle) in afternoon_data.iter().enumerate() {
         let x = 240.0 + (i as f32 / afternoon_data.len().max(1) as f32) * 150.0;
         all_price_points.push((x, candle.close));
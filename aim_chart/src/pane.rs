@@ -0,0 +1,402 @@
+//! Indicator drawing and the sub-panes under the price chart.
+//!
+//! Studies placed in a pane stack under the prices in list order. They use
+//! the x range of the price pane and follow its crosshair, and each takes a
+//! share of the chart height that the user changes by dragging its top
+//! border.
+
+use plotters::{
+    backend::BitMapBackend,
+    chart::{ChartBuilder, ChartContext},
+    coord::{Shift, types::RangedCoordf32},
+    drawing::DrawingArea,
+    element::{Circle, PathElement, Rectangle, Text},
+    prelude::Cartesian2d,
+    series::LineSeries,
+    style::{Color, IntoFont, WHITE, full_palette::GREY},
+};
+
+use crate::{
+    CandleData, Chart, PriceScale,
+    chart::{DOWN_COLOR, UP_COLOR},
+    indicator::{Placement, SeriesStyle, Study},
+};
+
+/// Smallest share of the chart height a sub-pane can be dragged to.
+pub(crate) const MIN_PANE_HEIGHT: f32 = 0.08;
+/// Largest share all sub-panes take together; the prices keep the rest.
+pub(crate) const MAX_PANES_HEIGHT: f32 = 0.6;
+/// Distance in pixels from a pane border where a press starts a resize.
+const BORDER_GRAB: i32 = 4;
+
+impl Chart {
+    /// Index and pixel height of each sub-pane, top to bottom, for a chart
+    /// `total` pixels high.
+    pub(crate) fn sub_pane_heights(&self, total: u32) -> Vec<(usize, u32)> {
+        let panes: Vec<usize> = (0..self.indicators.len())
+            .filter(|&i| self.indicators[i].in_pane())
+            .collect();
+        let sum: f32 = panes.iter().map(|&i| self.indicators[i].pane_height).sum();
+        let scale = if sum > MAX_PANES_HEIGHT {
+            MAX_PANES_HEIGHT / sum
+        } else {
+            1.0
+        };
+        panes
+            .into_iter()
+            .map(|i| {
+                let share = self.indicators[i].pane_height * scale;
+                (i, (share * total as f32) as u32)
+            })
+            .collect()
+    }
+
+    /// Whether volume has a pane of its own instead of the bars at the
+    /// bottom of the price pane.
+    pub(crate) fn has_volume_pane(&self) -> bool {
+        self.indicators
+            .iter()
+            .any(|study| study.in_pane() && study.config.kind == "VOLUME")
+    }
+
+    /// Whether a pixel row of the image lies under the price pane.
+    pub(crate) fn is_in_sub_pane(&self, y: i32) -> bool {
+        self.indicators.iter().any(Study::in_pane) && y >= self.chart_data.height as i32
+    }
+
+    /// Start, follow or end a drag on the top border of a sub-pane.
+    ///
+    /// While a pane is resized, or the press started inside a sub-pane, the
+    /// mouse movement does not pan the prices vertically.
+    pub(crate) fn resize_sub_panes(&mut self) {
+        let ui_data = &self.chart_data.ui_data;
+        if ui_data.is_release || ui_data.height <= 0 {
+            if self.resizing_pane.take().is_some() {
+                self.mark_dirty();
+            }
+            return;
+        }
+        let total = ui_data.height as u32;
+        if self.resizing_pane.is_none() {
+            let mut top = total as i32;
+            let panes = self.sub_pane_heights(total);
+            let tops: Vec<(usize, i32)> = panes
+                .iter()
+                .rev()
+                .map(|&(index, height)| {
+                    top -= height as i32;
+                    (index, top)
+                })
+                .collect();
+            self.resizing_pane = tops
+                .into_iter()
+                .find(|(_, top)| (ui_data.press_y - top).abs() <= BORDER_GRAB)
+                .map(|(index, _)| (index, self.indicators[index].pane_height));
+        }
+        if let Some((index, start)) = self.resizing_pane {
+            // Dragging the border up grows the pane
+            let dragged = (ui_data.position_y - ui_data.press_y) as f32 / total as f32;
+            self.indicators[index].pane_height =
+                (start - dragged).clamp(MIN_PANE_HEIGHT, MAX_PANES_HEIGHT);
+            self.chart_data.ui_data.move_x = 0;
+            self.chart_data.ui_data.move_y = 0;
+        } else if self.is_in_sub_pane(ui_data.press_y) {
+            self.chart_data.ui_data.move_y = 0;
+        }
+    }
+
+    /// Draw the studies placed over the candles.
+    pub(crate) fn chart_draw_indicators(
+        &mut self,
+        chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+    ) {
+        let (min_candle_x, max_candle_x) = self.get_min_max_of_candle_after_moving();
+        for study in self.indicators.iter_mut().filter(|study| !study.in_pane()) {
+            study.update(&self.candle_data);
+            draw_study(
+                chart,
+                study,
+                &self.candle_data,
+                min_candle_x,
+                max_candle_x,
+                self.price_scale,
+            );
+        }
+    }
+
+    /// Draw the sub-panes into `area`, the part of the image under the
+    /// price pane.
+    ///
+    /// # Arguments
+    /// * `area` - Drawing area under the price pane
+    /// * `panes` - Study index and pixel height of each pane, from [`sub_pane_heights`](Self::sub_pane_heights)
+    /// * `min_x`, `max_x` - X range of the price pane
+    pub(crate) fn chart_draw_sub_panes(
+        &mut self,
+        area: &DrawingArea<BitMapBackend, Shift>,
+        panes: &[(usize, u32)],
+        min_x: f32,
+        max_x: f32,
+    ) {
+        let mut rest = area.clone();
+        let mut top = self.chart_data.height as i32;
+        for &(index, height) in panes {
+            let (pane, below) = rest.split_vertically(height);
+            self.chart_draw_sub_pane(&pane, index, (top, height), min_x, max_x);
+            rest = below;
+            top += height as i32;
+        }
+    }
+
+    /// Draw one sub-pane: divider, guide levels, the study, its legend and
+    /// the crosshair.
+    fn chart_draw_sub_pane(
+        &mut self,
+        pane: &DrawingArea<BitMapBackend, Shift>,
+        index: usize,
+        (top, height): (i32, u32),
+        min_x: f32,
+        max_x: f32,
+    ) {
+        let (min_candle_x, max_candle_x) = self.get_min_max_of_candle_after_moving();
+        let (mouse_x, _) = self.get_mouse_position();
+        let position_y = self.chart_data.ui_data.position_y;
+        let hide_cursor = self.chart_data.ui_data.hide_cursor;
+        let range_y = self.chart_data.range_y;
+        let width = self.chart_data.width as i32;
+
+        let study = &mut self.indicators[index];
+        study.update(&self.candle_data);
+        let Placement::Pane { range, levels } = study.placement else {
+            return;
+        };
+        let (low, high) = range.unwrap_or_else(|| visible_range(study, min_candle_x, max_candle_x));
+
+        let Ok(mut chart) = ChartBuilder::on(pane)
+            .right_y_label_area_size(range_y)
+            .build_cartesian_2d(min_x..max_x, low..high)
+        else {
+            return;
+        };
+        let _ = chart
+            .configure_mesh()
+            .disable_x_mesh()
+            .disable_y_mesh()
+            .y_labels(3)
+            .y_label_formatter(&|&val| format_value(val))
+            .label_style(("Arial-Bold", 12).into_font().color(&WHITE))
+            .axis_style(WHITE.stroke_width(1))
+            .draw();
+
+        for &level in levels {
+            let _ = chart.draw_series(LineSeries::new(
+                vec![(min_x, level), (max_x, level)],
+                GREY.mix(0.6).stroke_width(1),
+            ));
+        }
+        draw_study(
+            &mut chart,
+            study,
+            &self.candle_data,
+            min_candle_x,
+            max_candle_x,
+            PriceScale::Linear,
+        );
+
+        // Legend with the values under the cursor
+        let candle_idx = mouse_x
+            .round()
+            .clamp(0.0, self.candle_data.len() as f32 - 1.0) as usize;
+        let values: Vec<String> = study
+            .series()
+            .iter()
+            .filter_map(|series| {
+                let i = candle_idx as isize - series.shift;
+                let value = series.values.get(usize::try_from(i).ok()?)?;
+                value.map(format_value)
+            })
+            .collect();
+        let _ = pane.draw(&Text::new(
+            format!("  {}   {}", study.config.label(), values.join("  ")),
+            (0, 5),
+            ("sans-serif", 15).into_font().color(&study.config.color),
+        ));
+
+        // Crosshair: the vertical line always, the horizontal one over this pane
+        if !hide_cursor && (min_x..max_x).contains(&mouse_x) {
+            let _ = chart.draw_series(LineSeries::new(
+                vec![(mouse_x, low), (mouse_x, high)],
+                GREY.stroke_width(1),
+            ));
+        }
+        if !hide_cursor && (top..top + height as i32).contains(&position_y) {
+            let y = high - (position_y - top) as f32 / height as f32 * (high - low);
+            let _ = chart.draw_series(LineSeries::new(
+                vec![(min_x, y), (max_x, y)],
+                GREY.stroke_width(1),
+            ));
+            let _ = chart.plotting_area().draw(&Text::new(
+                format!("   {}", format_value(y)),
+                (max_x, y),
+                ("Arial-Bold", 14).into_font().color(&WHITE),
+            ));
+        }
+
+        // Border the user drags to resize the pane
+        let _ = pane.draw(&PathElement::new(
+            vec![(0, 0), (width, 0)],
+            GREY.stroke_width(1),
+        ));
+    }
+}
+
+/// Y range that fits the visible values of `study`, with a little room
+/// above and below. Columns always start at zero.
+fn visible_range(study: &Study, min_candle_x: usize, max_candle_x: usize) -> (f32, f32) {
+    let mut low = f32::INFINITY;
+    let mut high = f32::NEG_INFINITY;
+    for series in study.series() {
+        if matches!(series.style, SeriesStyle::Histogram | SeriesStyle::Columns) {
+            low = low.min(0.0);
+            high = high.max(0.0);
+        }
+        for (i, value) in series.values.iter().enumerate() {
+            let x = i as isize + series.shift;
+            if let Some(value) = value
+                && (min_candle_x as isize..max_candle_x as isize).contains(&x)
+            {
+                low = low.min(*value);
+                high = high.max(*value);
+            }
+        }
+    }
+    if low > high {
+        return (0.0, 1.0);
+    }
+    let margin = if high > low {
+        (high - low) * 0.05
+    } else {
+        low.abs().max(1.0) * 0.05
+    };
+    (low - margin, high + margin)
+}
+
+/// Short label for a value, e.g. 1.25M for a volume.
+fn format_value(value: f32) -> String {
+    if value.abs() >= 1_000_000.0 {
+        format!("{:.2}M", value / 1_000_000.0)
+    } else if value.abs() >= 1_000.0 {
+        format!("{:.1}K", value / 1_000.0)
+    } else {
+        format!("{value:.2}")
+    }
+}
+
+/// Draw the visible part of every series of `study`.
+///
+/// # Arguments
+/// * `scale` - Scale of the y axis: that of the price pane for an overlay,
+///   linear in a sub-pane
+fn draw_study(
+    chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+    study: &Study,
+    candles: &[CandleData],
+    min_candle_x: usize,
+    max_candle_x: usize,
+    scale: PriceScale,
+) {
+    let color = study.config.color;
+    let min_x = min_candle_x as isize - 1;
+    let max_x = max_candle_x as isize + 1;
+    for series in study.series() {
+        // Bar index and point of each visible bar, None where there is no value
+        let points = series.values.iter().enumerate().filter_map(|(i, value)| {
+            let x = i as isize + series.shift;
+            (min_x..=max_x)
+                .contains(&x)
+                .then_some((i, value.map(|y| (x as f32, scale.to_axis(y)))))
+        });
+        match series.style {
+            SeriesStyle::Dots => {
+                let _ = chart.draw_series(
+                    points
+                        .filter_map(|(_, point)| point)
+                        .map(|point| Circle::new(point, 2, color.filled())),
+                );
+            }
+            SeriesStyle::Histogram | SeriesStyle::Columns => {
+                let _ = chart.draw_series(points.filter_map(|(i, point)| {
+                    let (x, y) = point?;
+                    let up = if series.style == SeriesStyle::Histogram {
+                        y >= 0.0
+                    } else {
+                        candles.get(i).is_some_and(|c| c.open < c.close)
+                    };
+                    let fill = if up { UP_COLOR } else { DOWN_COLOR };
+                    Some(Rectangle::new(
+                        [(x - 0.4, 0.0), (x + 0.4, y)],
+                        fill.mix(0.8).filled(),
+                    ))
+                }));
+            }
+            SeriesStyle::Line | SeriesStyle::Band => {
+                let style = if series.style == SeriesStyle::Line {
+                    color.stroke_width(1)
+                } else {
+                    color.mix(0.5).stroke_width(1)
+                };
+                // Break the line where the indicator has no value
+                let mut runs: Vec<Vec<(f32, f32)>> = vec![Vec::new()];
+                for (_, point) in points {
+                    match point {
+                        Some(point) => runs.last_mut().unwrap().push(point),
+                        None if !runs.last().unwrap().is_empty() => runs.push(Vec::new()),
+                        None => {}
+                    }
+                }
+                for run in runs.into_iter().filter(|run| run.len() > 1) {
+                    let _ = chart.draw_series(LineSeries::new(run, style));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        UiData,
+        indicator::parse_indicators,
+        tests::{bars, chart},
+    };
+
+    #[test]
+    fn test_sub_panes_stack_in_list_order() {
+        let mut chart = chart(bars(&[10.0, 11.0, 12.0]));
+        assert!(chart.sub_pane_heights(1000).is_empty());
+
+        chart.set_indicators(parse_indicators("SMA 20; RSI 14; MACD"));
+        assert_eq!(chart.sub_pane_heights(1000), vec![(1, 200), (2, 200)]);
+    }
+
+    #[test]
+    fn test_sub_panes_shrink_to_leave_the_prices_room() {
+        let mut chart = chart(bars(&[10.0, 11.0, 12.0]));
+        chart.set_indicators(parse_indicators("RSI 14; MACD"));
+        chart.indicators[0].pane_height = 0.5;
+        chart.indicators[1].pane_height = 0.25;
+        // 0.75 of the height scaled down to 0.6
+        assert_eq!(chart.sub_pane_heights(1000), vec![(0, 400), (1, 200)]);
+    }
+
+    #[test]
+    fn test_price_pane_keeps_its_height_without_a_window_size() {
+        let mut chart = chart(bars(&[10.0, 11.0, 12.0]));
+        chart.set_indicators(parse_indicators("RSI 14"));
+        for _ in 0..3 {
+            chart.render_plot(UiData::default());
+        }
+        assert_eq!(chart.chart_data.image_height, 800);
+        assert_eq!(chart.chart_data.height, 640);
+    }
+}
@@ -0,0 +1,231 @@
+//! Price scales of the y axis.
+//!
+//! The price pane is laid out in axis units: the price itself on the linear,
+//! percentage and indexed scales, and its natural log on the logarithmic one.
+//! Percentage and indexed scales only relabel a linear axis against the close
+//! of the first visible bar, so scrolling changes their labels but not the
+//! picture. Bars, drawings, overlays and the crosshair go through
+//! [`PriceScale::to_axis`] and [`PriceScale::to_price`], while the view and
+//! the saved drawings stay in prices.
+
+use crate::{CandleData, Chart};
+
+/// Lowest price a logarithmic axis shows, instead of the log of zero.
+const MIN_LOG_PRICE: f32 = 1e-3;
+
+/// How prices map to the y axis of the price pane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PriceScale {
+    #[default]
+    Linear,
+    Log,
+    Percent, // change from the close of the first visible bar
+    Indexed, // the close of the first visible bar is 100
+}
+
+impl PriceScale {
+    /// Parse a scale as shown in the scale picker.
+    pub fn parse(label: &str) -> Option<Self> {
+        match label.trim() {
+            "Linear" => Some(PriceScale::Linear),
+            "Log" => Some(PriceScale::Log),
+            "%" => Some(PriceScale::Percent),
+            "Indexed" => Some(PriceScale::Indexed),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            PriceScale::Linear => "Linear",
+            PriceScale::Log => "Log",
+            PriceScale::Percent => "%",
+            PriceScale::Indexed => "Indexed",
+        }
+    }
+
+    /// Axis value of `price`.
+    pub fn to_axis(&self, price: f32) -> f32 {
+        match self {
+            PriceScale::Log => price.max(MIN_LOG_PRICE).ln(),
+            _ => price,
+        }
+    }
+
+    /// Price at the axis value `y`, the inverse of [`to_axis`](Self::to_axis).
+    pub fn to_price(&self, y: f32) -> f32 {
+        match self {
+            PriceScale::Log => y.exp(),
+            _ => y,
+        }
+    }
+
+    /// Text of `price` on the axis and its labels.
+    ///
+    /// # Arguments
+    /// * `base` - Close of the first visible bar, the 0% and 100 of the
+    ///   percentage and indexed scales
+    pub fn format(&self, price: f32, base: f32) -> String {
+        match self {
+            PriceScale::Linear | PriceScale::Log => format!("{price:.2}"),
+            _ if base == 0.0 => format!("{price:.2}"),
+            PriceScale::Percent => format!("{:+.2}%", (price / base - 1.0) * 100.0),
+            PriceScale::Indexed => format!("{:.2}", price / base * 100.0),
+        }
+    }
+
+    /// `candle` with its prices in axis units, to draw it.
+    pub(crate) fn candle_to_axis(&self, candle: &CandleData) -> CandleData {
+        CandleData {
+            open: self.to_axis(candle.open),
+            high: self.to_axis(candle.high),
+            low: self.to_axis(candle.low),
+            close: self.to_axis(candle.close),
+            ..candle.clone()
+        }
+    }
+}
+
+impl Chart {
+    /// Switch the y axis to `scale`, keeping the visible prices, and set
+    /// whether it fits the visible bars or stays where it is.
+    ///
+    /// # Arguments
+    /// * `auto_fit` - Fit the visible bars on every render, ignoring vertical
+    ///   drags and y zoom
+    /// * `lock` - Keep the y range as it is: no vertical drags, y zoom or
+    ///   auto-fit
+    pub fn set_price_scale(&mut self, scale: PriceScale, auto_fit: bool, lock: bool) {
+        if (scale, auto_fit, lock) == (self.price_scale, self.auto_fit, self.lock_scale) {
+            return;
+        }
+        if scale != self.price_scale {
+            let (min, max) = (
+                self.price_scale.to_price(self.chart_data.y_offset_min),
+                self.price_scale.to_price(self.chart_data.y_offset_max),
+            );
+            self.price_scale = scale;
+            self.chart_data.y_offset_min = scale.to_axis(min);
+            self.chart_data.y_offset_max = scale.to_axis(max);
+            self.backup_chart_offset = None;
+        }
+        self.auto_fit = auto_fit;
+        self.lock_scale = lock;
+        self.mark_dirty();
+    }
+
+    /// Whether vertical drags and y zoom leave the y range alone.
+    pub(crate) fn is_y_fixed(&self) -> bool {
+        self.auto_fit || self.lock_scale
+    }
+
+    /// Close of the first visible bar, the base of the percentage and
+    /// indexed scales.
+    pub(crate) fn scale_base(&self) -> f32 {
+        let (min_candle_x, _) = self.get_min_max_of_candle_after_moving();
+        self.candle_data
+            .get(min_candle_x)
+            .or(self.candle_data.last())
+            .map_or(0.0, |candle| candle.close)
+    }
+
+    /// Text of the price at the axis value `y`, in the active scale.
+    pub(crate) fn format_axis_value(&self, y: f32) -> String {
+        self.price_scale
+            .format(self.price_scale.to_price(y), self.scale_base())
+    }
+
+    /// Fit the y range to the visible bars, as a newly opened chart shows
+    /// them, unless the scale is locked.
+    pub(crate) fn fit_visible_range(&mut self) {
+        if !self.auto_fit || self.lock_scale {
+            return;
+        }
+        let (min_candle_x, max_candle_x) = self.get_min_max_of_candle_after_moving();
+        let Some(visible) = self.candle_data.get(min_candle_x..max_candle_x) else {
+            return;
+        };
+        let low = visible.iter().map(|c| c.low).fold(f32::INFINITY, f32::min);
+        let high = visible
+            .iter()
+            .map(|c| c.high)
+            .fold(f32::NEG_INFINITY, f32::max);
+        if low < high {
+            self.chart_data.y_offset_min = self.price_scale.to_axis(low);
+            self.chart_data.y_offset_max = self.price_scale.to_axis(high);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{bars, chart};
+
+    const SCALES: [PriceScale; 4] = [
+        PriceScale::Linear,
+        PriceScale::Log,
+        PriceScale::Percent,
+        PriceScale::Indexed,
+    ];
+
+    #[test]
+    fn test_to_price_inverts_to_axis() {
+        for scale in SCALES {
+            for price in [0.01, 1.0, 12.35, 98_500.0] {
+                let back = scale.to_price(scale.to_axis(price));
+                assert!((back - price).abs() <= price * 1e-5, "{scale:?} {price}");
+            }
+        }
+        // No log of zero or less: those sit at the bottom of the axis
+        let log = PriceScale::Log;
+        assert_eq!(log.to_axis(0.0), MIN_LOG_PRICE.ln());
+        assert_eq!(log.to_axis(-5.0), MIN_LOG_PRICE.ln());
+        assert!((log.to_price(log.to_axis(0.0)) - MIN_LOG_PRICE).abs() < 1e-6);
+        assert_eq!(PriceScale::Linear.to_axis(-5.0), -5.0);
+    }
+
+    #[test]
+    fn test_format_against_the_base() {
+        assert_eq!(PriceScale::Percent.format(110.0, 100.0), "+10.00%");
+        assert_eq!(PriceScale::Percent.format(95.0, 100.0), "-5.00%");
+        assert_eq!(PriceScale::Percent.format(100.0, 100.0), "+0.00%");
+        assert_eq!(PriceScale::Indexed.format(110.0, 100.0), "110.00");
+        assert_eq!(PriceScale::Indexed.format(25.0, 50.0), "50.00");
+        // Prices as they are without a base, and on the other scales
+        assert_eq!(PriceScale::Percent.format(110.0, 0.0), "110.00");
+        assert_eq!(PriceScale::Indexed.format(110.0, 0.0), "110.00");
+        assert_eq!(PriceScale::Linear.format(110.0, 100.0), "110.00");
+        assert_eq!(PriceScale::Log.format(110.0, 100.0), "110.00");
+    }
+
+    #[test]
+    fn test_fit_visible_range_pads_like_a_new_chart() {
+        let candles = bars(&[10.0, 14.0, 12.0, 20.0, 16.0, 18.0]);
+        for scale in SCALES {
+            let mut fitted = chart(candles.clone());
+            fitted.chart_data.candle_distance = 10.0;
+            fitted.set_price_scale(scale, true, false);
+            fitted.chart_data.y_offset_min = 0.5;
+            fitted.chart_data.y_offset_max = 900.0;
+            fitted.fit_visible_range();
+
+            let mut fresh = chart(candles.clone());
+            fresh.chart_data = Chart::latest_view(&candles, scale);
+            fresh.price_scale = scale;
+            assert_eq!(
+                fitted.update_y_axis_after_moving(),
+                fresh.update_y_axis_after_moving(),
+                "{scale:?}"
+            );
+        }
+
+        // A locked scale stays where it is
+        let mut locked = chart(candles);
+        locked.chart_data.candle_distance = 10.0;
+        locked.set_price_scale(PriceScale::Linear, true, true);
+        locked.chart_data.y_offset_min = 0.5;
+        locked.fit_visible_range();
+        assert_eq!(locked.chart_data.y_offset_min, 0.5);
+    }
+}
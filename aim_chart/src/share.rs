@@ -0,0 +1,333 @@
+//! Sharing a chart: PNG snapshots and a portable JSON chart state.
+//!
+//! The state holds what a teammate needs to open the same view on their own
+//! data: symbol, time frame, chart type, price scale, indicators,
+//! comparisons, the visible range and the drawings. Times rather than candle
+//! indices place the range and the drawings, since the other side may have a
+//! longer or shorter history. Prices rather than axis units place them
+//! vertically, so the view reads the same on any scale.
+
+use std::{io, path::Path};
+
+use plotters::{
+    backend::{BitMapBackend, DrawingBackend},
+    drawing::IntoDrawingArea,
+    element::Text,
+    style::{Color, IntoFont, RGBColor, WHITE},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    Chart, ChartType, PriceScale, UiData,
+    draw::{AnchoredDrawing, DrawObject, DrawStyle, DrawType, time_to_x, x_to_time},
+    history::DrawCommand,
+    indicator::{parse_color, parse_indicators},
+};
+
+/// Version of the JSON written by [`Chart::export_state`].
+///
+/// * 1 - initial layout
+/// * 2 - price scale with its auto-fit and lock flags, and the price range of
+///   the viewport in prices on any scale
+pub const CHART_STATE_VERSION: u32 = 2;
+
+/// Everything needed to open the same view of a chart elsewhere.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChartState {
+    pub version: u32,
+    pub symbol: String,
+    pub time_frame: String, // label such as `1D`
+    #[serde(default)]
+    pub adjusted: bool,
+    #[serde(default)]
+    pub chart_type: String, // label of a `ChartType`
+    #[serde(default)]
+    pub box_size: String,
+    #[serde(default)]
+    pub price_scale: String, // label of a `PriceScale`
+    #[serde(default)]
+    pub auto_fit: bool,
+    #[serde(default)]
+    pub lock_scale: bool,
+    #[serde(default)]
+    pub indicators: String, // as in the indicator editor
+    #[serde(default)]
+    pub compare: Vec<String>,
+    #[serde(default)]
+    pub compare_ratio: bool,
+    pub viewport: Viewport,
+    #[serde(default)]
+    pub drawings: Vec<SharedDrawing>,
+}
+
+/// The visible part of the chart.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Viewport {
+    pub from: f64,         // unix time in seconds at the left edge
+    pub to: f64,           // and at the right edge
+    pub y_offset_min: f32, // prices, whatever the scale
+    pub y_offset_max: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedDrawing {
+    pub shape: DrawType, // x coordinates are candle indices of the exporting chart
+    pub color: String,   // `#RRGGBB`
+    #[serde(default)]
+    pub style: DrawStyle,
+    pub times: Vec<f64>, // unix time in seconds of each x coordinate, which places it
+}
+
+impl Chart {
+    /// The current view as a portable state.
+    pub fn export_state(&self) -> ChartState {
+        let drawings = self
+            .drawings
+            .iter()
+            .map(|drawing| {
+                let object = drawing.place(&self.candle_data, PriceScale::Linear);
+                let RGBColor(r, g, b) = object.color;
+                SharedDrawing {
+                    shape: object.draw_type,
+                    color: format!("#{r:02X}{g:02X}{b:02X}"),
+                    style: object.style,
+                    times: drawing.times().to_vec(),
+                }
+            })
+            .collect();
+        ChartState {
+            version: CHART_STATE_VERSION,
+            symbol: self.stock_name.clone(),
+            time_frame: self.time_frame.to_string(),
+            adjusted: self.adjusted,
+            chart_type: self.chart_type.label().to_string(),
+            box_size: self
+                .chart_type
+                .box_size()
+                .map(|box_size| box_size.label())
+                .unwrap_or_default(),
+            price_scale: self.price_scale.label().to_string(),
+            auto_fit: self.auto_fit,
+            lock_scale: self.lock_scale,
+            indicators: self.indicator_spec(),
+            compare: self
+                .comparisons
+                .iter()
+                .map(|cmp| cmp.symbol.clone())
+                .collect(),
+            compare_ratio: self.compare_ratio,
+            viewport: self.viewport(),
+            drawings,
+        }
+    }
+
+    /// Show `state` on this chart, which should already hold the bars of its
+    /// symbol and time frame. The drawings replace the current ones in one
+    /// step that can be undone.
+    ///
+    /// Drawings whose number of times does not match their shape are skipped.
+    pub fn import_state(&mut self, state: &ChartState) {
+        let chart_type = ChartType::parse(&state.chart_type, &state.box_size).unwrap_or_default();
+        self.set_chart_type(chart_type);
+        let scale = PriceScale::parse(&state.price_scale).unwrap_or_default();
+        self.set_price_scale(scale, state.auto_fit, state.lock_scale);
+        self.set_indicators(parse_indicators(&state.indicators));
+        self.set_comparisons(&state.compare, state.compare_ratio);
+
+        let after: Vec<AnchoredDrawing> = state
+            .drawings
+            .iter()
+            .filter_map(|shared| {
+                let object = DrawObject {
+                    draw_type: shared.shape.clone(),
+                    color: parse_color(shared.color.trim_start_matches('#'))?,
+                    style: shared.style,
+                };
+                AnchoredDrawing::from_parts(object, shared.times.clone())
+            })
+            .collect();
+        let before = self.drawings.clone();
+        self.history
+            .apply(&mut self.drawings, DrawCommand::Reset { before, after });
+        self.selected = None;
+        self.dragging_handle = None;
+
+        self.show_viewport(state.viewport);
+    }
+
+    /// The visible part of the chart, or the view still waiting for the bars.
+    pub(crate) fn viewport(&self) -> Viewport {
+        if self.source_data.is_empty()
+            && let Some(viewport) = self.pending_view
+        {
+            return viewport;
+        }
+        Viewport {
+            from: x_to_time(&self.candle_data, self.chart_data.x_offset_min),
+            to: x_to_time(&self.candle_data, self.chart_data.x_offset_max),
+            y_offset_min: self.price_scale.to_price(self.chart_data.y_offset_min),
+            y_offset_max: self.price_scale.to_price(self.chart_data.y_offset_max),
+        }
+    }
+
+    /// Scroll and zoom to `viewport`; an empty range is ignored.
+    pub(crate) fn show_viewport(&mut self, viewport: Viewport) {
+        let (from, to) = (
+            time_to_x(&self.candle_data, viewport.from),
+            time_to_x(&self.candle_data, viewport.to),
+        );
+        if to > from && viewport.y_offset_max > viewport.y_offset_min {
+            self.chart_data.x_offset_min = from;
+            self.chart_data.x_offset_max = to;
+            self.chart_data.y_offset_min = self.price_scale.to_axis(viewport.y_offset_min);
+            self.chart_data.y_offset_max = self.price_scale.to_axis(viewport.y_offset_max);
+            self.backup_chart_offset = None;
+        }
+    }
+
+    /// The current view rendered off screen at `width` x `height` pixels,
+    /// without the crosshair and with the info of the last bar.
+    fn snapshot(&self, width: u32, height: u32) -> slint::Image {
+        let mut chart = self.clone();
+        let ui_data = UiData {
+            hide_cursor: true,
+            // Past the right edge, where the legends show the last bar
+            position_x: width as i32 * 10,
+            ..chart.cell_ui_data(width as i32, height as i32, None)
+        };
+        chart.render_plot(ui_data).0
+    }
+
+    /// Save a [snapshot](Self::snapshot) of the current view as a PNG.
+    ///
+    /// # Arguments
+    /// * `watermark` - Text written faintly in the bottom left corner
+    pub fn export_png(
+        &self,
+        path: &Path,
+        width: u32,
+        height: u32,
+        watermark: Option<&str>,
+    ) -> io::Result<()> {
+        let Some(pixels) = self.snapshot(width, height).to_rgb8() else {
+            return Err(io::Error::other(format!(
+                "no bars of {} to export",
+                self.stock_name
+            )));
+        };
+
+        let size = (pixels.width(), pixels.height());
+        let mut backend = BitMapBackend::new(path, size);
+        backend
+            .blit_bitmap((0, 0), size, pixels.as_bytes())
+            .map_err(io::Error::other)?;
+        let root = backend.into_drawing_area();
+        if let Some(watermark) = watermark {
+            root.draw(&Text::new(
+                watermark.to_string(),
+                (12, size.1 as i32 - 28),
+                ("sans-serif", 18).into_font().color(&WHITE.mix(0.35)),
+            ))
+            .map_err(io::Error::other)?;
+        }
+        root.present().map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::{bars, chart};
+
+    /// A chart of 40 bars scrolled back from the latest ones, with a line
+    /// from the fifth to the twentieth bar.
+    fn shared_chart() -> Chart {
+        let closes: Vec<f32> = (0..40).map(|i| 100.0 + i as f32).collect();
+        let mut chart = chart(bars(&closes));
+        chart.chart_data.x_offset_min = 10.0;
+        chart.chart_data.x_offset_max = 30.0;
+        chart.chart_data.y_offset_min = 105.0;
+        chart.chart_data.y_offset_max = 135.0;
+        let object = DrawObject::new(
+            DrawType::Line(((4.0, 104.0), (19.0, 119.0))),
+            RGBColor(0x26, 0xA6, 0x9A),
+        );
+        chart.drawings = vec![AnchoredDrawing::new(
+            object,
+            &chart.candle_data,
+            PriceScale::Linear,
+        )];
+        chart
+    }
+
+    #[test]
+    fn chart_state_serde_round_trip() {
+        let state = shared_chart().export_state();
+        let json = serde_json::to_string(&state).unwrap();
+        let back: ChartState = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            serde_json::to_value(&back).unwrap(),
+            serde_json::to_value(&state).unwrap()
+        );
+
+        let drawing = &back.drawings[0];
+        assert_eq!(drawing.shape, DrawType::Line(((4.0, 104.0), (19.0, 119.0))));
+        assert_eq!(drawing.color, "#26A69A");
+        assert_eq!(drawing.times, state.drawings[0].times);
+
+        // Fields added since version 1 fall back to their defaults
+        let old: SharedDrawing = serde_json::from_str(
+            r##"{"shape":{"VerticalLine":3.0},"color":"#FF0000","times":[0.0]}"##,
+        )
+        .unwrap();
+        assert_eq!(old.style, DrawStyle::default());
+    }
+
+    #[test]
+    fn import_state_restores_drawings_and_viewport() {
+        let state = shared_chart().export_state();
+
+        // The same bars, without the drawing and showing the latest bars
+        let closes: Vec<f32> = (0..40).map(|i| 100.0 + i as f32).collect();
+        let mut other = chart(bars(&closes));
+        other.import_state(&state);
+
+        assert_eq!(other.drawings.len(), 1);
+        assert_eq!(
+            other.drawings[0].times(),
+            state.drawings[0].times.as_slice()
+        );
+        let placed = other.drawings[0].place(&other.candle_data, other.price_scale);
+        assert_eq!(
+            placed.draw_type,
+            DrawType::Line(((4.0, 104.0), (19.0, 119.0)))
+        );
+
+        let viewport = other.viewport();
+        assert_eq!(
+            (viewport.from, viewport.to),
+            (state.viewport.from, state.viewport.to)
+        );
+        assert_eq!(
+            (other.chart_data.x_offset_min, other.chart_data.x_offset_max),
+            (10.0, 30.0)
+        );
+        assert!((viewport.y_offset_min - 105.0).abs() < 1e-3);
+        assert!((viewport.y_offset_max - 135.0).abs() < 1e-3);
+
+        // One step back to the drawings before the import
+        other.history.undo(&mut other.drawings);
+        assert!(other.drawings.is_empty());
+    }
+
+    #[test]
+    fn snapshot_leaves_out_the_crosshair() {
+        let chart = shared_chart();
+        let pixels = |image: slint::Image| image.to_rgb8().unwrap().as_bytes().to_vec();
+        let snapshot = pixels(chart.snapshot(400, 300));
+
+        let mut live = chart.clone();
+        let ui_data = live.cell_ui_data(400, 300, None);
+        assert_ne!(snapshot, pixels(live.render_plot(ui_data).0));
+    }
+}
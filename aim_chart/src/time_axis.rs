@@ -0,0 +1,412 @@
+//! Time axis of the price pane.
+//!
+//! The x axis counts bars, so nights, weekends and the lunch break take no
+//! room on it. This module puts the time back from the trading calendar in
+//! [`aim_data::calendar`]: labels and grid lines where a session, day, week,
+//! month or year starts, shading over the lunch break and the closing
+//! auction of intraday charts, and the time under the cursor, also before
+//! the first and after the last bar.
+
+use aim_data::{
+    Timeframe,
+    calendar::{
+        AFTERNOON_SESSION, CLOSING_AUCTION, MORNING_SESSION, Session, at_minute, minute_of_day,
+        next_trading_day, previous_trading_day, session_of, to_local,
+    },
+};
+use chrono::{DateTime, Datelike, Months, Timelike, Utc};
+use plotters::{
+    backend::BitMapBackend,
+    chart::ChartContext,
+    coord::types::RangedCoordf32,
+    element::{PathElement, Rectangle, Text},
+    prelude::Cartesian2d,
+    style::{
+        Color, IntoFont, RGBColor, WHITE,
+        text_anchor::{HPos, Pos, VPos},
+    },
+};
+
+use crate::Chart;
+
+/// Fewest pixels between two time labels.
+const MIN_LABEL_SPACING: f32 = 90.0;
+/// Fewest pixels between two boundary lines.
+const MIN_LINE_SPACING: f32 = 6.0;
+/// Colour of the lunch break and closing auction shading.
+const SESSION_SHADE: RGBColor = RGBColor(0x2A, 0x2E, 0x39);
+/// Width of the lunch break band, in bars.
+const LUNCH_BAND: f32 = 0.4;
+
+/// What a bar starts that the bar before it did not, from the least to the
+/// most significant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Boundary {
+    Hour,
+    Session,
+    Day,
+    Week,
+    Month,
+    Year,
+}
+
+const BOUNDARIES: [Boundary; 6] = [
+    Boundary::Hour,
+    Boundary::Session,
+    Boundary::Day,
+    Boundary::Week,
+    Boundary::Month,
+    Boundary::Year,
+];
+
+/// The most significant boundary between a bar at `previous` and the next
+/// one at `time`, None within the same hour.
+pub(crate) fn boundary(previous: DateTime<Utc>, time: DateTime<Utc>) -> Option<Boundary> {
+    let (before, after) = (to_local(previous), to_local(time));
+    if before.year() != after.year() {
+        Some(Boundary::Year)
+    } else if before.month() != after.month() {
+        Some(Boundary::Month)
+    } else if before.iso_week() != after.iso_week() {
+        Some(Boundary::Week)
+    } else if before.date_naive() != after.date_naive() {
+        Some(Boundary::Day)
+    } else if session_of(minute_of_day(previous)) != session_of(minute_of_day(time)) {
+        Some(Boundary::Session)
+    } else if before.hour() != after.hour() {
+        Some(Boundary::Hour)
+    } else {
+        None
+    }
+}
+
+/// Label of a bar at `time` that starts `boundary`, for a chart of
+/// `time_frame`.
+fn boundary_label(time: DateTime<Utc>, boundary: Boundary, time_frame: Timeframe) -> String {
+    let local = to_local(time);
+    let format = match boundary {
+        Boundary::Year => "%Y",
+        Boundary::Month if matches!(time_frame, Timeframe::Months(_)) => "%m/%Y",
+        Boundary::Month if !is_intraday(time_frame) => "Tháng %m",
+        Boundary::Month | Boundary::Week | Boundary::Day => "%d/%m",
+        Boundary::Session | Boundary::Hour => "%H:%M",
+    };
+    local.format(format).to_string()
+}
+
+/// Least significant boundary drawn as a grid line: days on intraday
+/// charts, then weeks, months and years.
+fn grid_boundary(time_frame: Timeframe) -> Boundary {
+    match time_frame {
+        Timeframe::Minutes(_) | Timeframe::Hours(_) => Boundary::Day,
+        Timeframe::Days(_) => Boundary::Week,
+        Timeframe::Weeks(_) => Boundary::Month,
+        Timeframe::Months(_) => Boundary::Year,
+    }
+}
+
+fn is_intraday(time_frame: Timeframe) -> bool {
+    bar_minutes(time_frame).is_some()
+}
+
+/// Length of an intraday bar in minutes, None from daily bars up.
+fn bar_minutes(time_frame: Timeframe) -> Option<u32> {
+    match time_frame {
+        Timeframe::Minutes(n) => Some(n),
+        Timeframe::Hours(n) => Some(n * 60),
+        _ => None,
+    }
+}
+
+/// Start of the last bar of `minutes` in `session`, since bars are
+/// anchored at the session open.
+fn last_bar_of(session: Session, minutes: u32) -> u32 {
+    session.open + (session.minutes() - 1) / minutes * minutes
+}
+
+/// Time of the bar next to the one at `time`, skipping the lunch break,
+/// nights, weekends and the rest of the calendar's closed time.
+///
+/// # Arguments
+/// * `forward` - The next bar, else the previous one
+pub(crate) fn step_bar(time: DateTime<Utc>, time_frame: Timeframe, forward: bool) -> DateTime<Utc> {
+    let date = to_local(time).date_naive();
+    match time_frame {
+        Timeframe::Minutes(_) | Timeframe::Hours(_) => {
+            let minutes = bar_minutes(time_frame).unwrap_or(1).max(1);
+            let minute = minute_of_day(time);
+            let session = session_of(minute);
+            if forward {
+                if minute + minutes < session.close {
+                    time + chrono::Duration::minutes(minutes as i64)
+                } else if session == MORNING_SESSION {
+                    at_minute(date, AFTERNOON_SESSION.open)
+                } else {
+                    at_minute(next_trading_day(date), MORNING_SESSION.open)
+                }
+            } else if minute >= session.open + minutes {
+                time - chrono::Duration::minutes(minutes as i64)
+            } else if session == AFTERNOON_SESSION {
+                at_minute(date, last_bar_of(MORNING_SESSION, minutes))
+            } else {
+                at_minute(
+                    previous_trading_day(date),
+                    last_bar_of(AFTERNOON_SESSION, minutes),
+                )
+            }
+        }
+        Timeframe::Days(n) => {
+            let mut day = date;
+            for _ in 0..n.max(1) {
+                day = if forward {
+                    next_trading_day(day)
+                } else {
+                    previous_trading_day(day)
+                };
+            }
+            time + (day - date)
+        }
+        Timeframe::Weeks(n) => {
+            let days = chrono::Duration::weeks(n.max(1) as i64);
+            if forward { time + days } else { time - days }
+        }
+        Timeframe::Months(n) => {
+            let months = Months::new(n.max(1));
+            let stepped = if forward {
+                time.checked_add_months(months)
+            } else {
+                time.checked_sub_months(months)
+            };
+            stepped.unwrap_or(time)
+        }
+    }
+}
+
+impl Chart {
+    /// Time of the bar at `index`, stepping along the calendar before the
+    /// first and after the last bar.
+    pub(crate) fn time_at(&self, index: i32) -> Option<DateTime<Utc>> {
+        let last = self.candle_data.len() as i32 - 1;
+        let (mut time, mut offset) = if index < 0 {
+            (self.candle_data.first()?.time, index)
+        } else if index > last {
+            (self.candle_data.last()?.time, index - last)
+        } else {
+            return Some(self.candle_data[index as usize].time);
+        };
+        while offset != 0 {
+            time = step_bar(time, self.time_frame, offset > 0);
+            offset -= offset.signum();
+        }
+        Some(time)
+    }
+
+    /// Text of the cursor time: with the time of day on intraday charts.
+    pub(crate) fn format_cursor_time(&self, time: DateTime<Utc>) -> String {
+        let format = if is_intraday(self.time_frame) {
+            "%d/%m/%Y %H:%M"
+        } else {
+            "%d/%m/%Y"
+        };
+        to_local(time).format(format).to_string()
+    }
+
+    /// Boundaries started by the visible bars: index of the bar and what it
+    /// starts.
+    fn visible_boundaries(&self) -> Vec<(usize, Boundary)> {
+        let (min_candle_x, max_candle_x) = self.get_min_max_of_candle_after_moving();
+        (min_candle_x.max(1)..max_candle_x.min(self.candle_data.len()))
+            .filter_map(|i| {
+                let previous = self.candle_data[i - 1].time;
+                Some((i, boundary(previous, self.candle_data[i].time)?))
+            })
+            .collect()
+    }
+
+    /// Shade the lunch break and the closing auction of intraday charts,
+    /// under the bars.
+    pub(crate) fn chart_draw_sessions(
+        &self,
+        chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+    ) {
+        let Some(minutes) = bar_minutes(self.time_frame) else {
+            return;
+        };
+        let (_, max_y, min_y) = self.update_y_axis_after_moving();
+        let style = SESSION_SHADE.mix(0.8).filled();
+
+        // The lunch break falls between the last morning and first afternoon bar
+        let _ = chart.draw_series(
+            self.visible_boundaries()
+                .into_iter()
+                .filter(|(_, boundary)| *boundary == Boundary::Session)
+                .map(|(i, _)| {
+                    let x = i as f32 - 0.5;
+                    Rectangle::new(
+                        [(x - LUNCH_BAND / 2.0, min_y), (x + LUNCH_BAND / 2.0, max_y)],
+                        style,
+                    )
+                }),
+        );
+
+        // Bars that trade in the closing auction
+        let (min_candle_x, max_candle_x) = self.get_min_max_of_candle_after_moving();
+        let Some(visible) = self
+            .candle_data
+            .get(min_candle_x..max_candle_x.min(self.candle_data.len()))
+        else {
+            return;
+        };
+        let _ = chart.draw_series(
+            visible
+                .iter()
+                .filter(|candle| {
+                    let start = minute_of_day(candle.time);
+                    start < CLOSING_AUCTION.close && start + minutes > CLOSING_AUCTION.open
+                })
+                .map(|candle| {
+                    Rectangle::new(
+                        [(candle.num - 0.5, min_y), (candle.num + 0.5, max_y)],
+                        style,
+                    )
+                }),
+        );
+    }
+
+    /// Label the x axis where the visible bars start a new hour, session,
+    /// day, week, month or year, as many as fit, and draw grid lines at the
+    /// day, week, month or year boundaries the time frame calls for.
+    pub(crate) fn chart_draw_time_axis(
+        &self,
+        chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+    ) {
+        let boundaries = self.visible_boundaries();
+        let (_, max_y, min_y) = self.update_y_axis_after_moving();
+        let bar_pixels = self.chart_data.candle_distance.max(f32::EPSILON);
+
+        // Grid lines, brighter for the more significant boundaries
+        let grid = grid_boundary(self.time_frame);
+        let mut last_line: Option<usize> = None;
+        for &(i, boundary) in boundaries.iter().filter(|(_, b)| *b >= grid) {
+            if last_line.is_some_and(|last| ((i - last) as f32) * bar_pixels < MIN_LINE_SPACING) {
+                continue;
+            }
+            last_line = Some(i);
+            let opacity = match boundary {
+                Boundary::Year => 0.5,
+                Boundary::Month => 0.35,
+                Boundary::Week => 0.25,
+                _ => 0.15,
+            };
+            let x = i as f32 - 0.5;
+            let _ = chart.draw_series(std::iter::once(PathElement::new(
+                vec![(x, min_y), (x, max_y)],
+                WHITE.mix(opacity).stroke_width(1),
+            )));
+        }
+
+        // Labels: the least significant boundary that leaves room for all of
+        // its labels, then drop any that would still touch the one before
+        let plot_width = self.chart_data.width as f32 - self.chart_data.range_y as f32;
+        let room = (plot_width / MIN_LABEL_SPACING).max(1.0) as usize;
+        let level = BOUNDARIES
+            .into_iter()
+            .find(|level| boundaries.iter().filter(|(_, b)| b >= level).count() <= room)
+            .unwrap_or(Boundary::Year);
+        let style = ("Arial-Bold", 14)
+            .into_font()
+            .color(&WHITE)
+            .pos(Pos::new(HPos::Center, VPos::Top));
+        let mut last_label: Option<usize> = None;
+        for &(i, boundary) in boundaries.iter().filter(|(_, b)| *b >= level) {
+            if last_label.is_some_and(|last| ((i - last) as f32) * bar_pixels < MIN_LABEL_SPACING) {
+                continue;
+            }
+            last_label = Some(i);
+            let candle = &self.candle_data[i];
+            let _ = chart.plotting_area().draw(&Text::new(
+                boundary_label(candle.time, boundary, self.time_frame),
+                (candle.num, min_y),
+                style.clone(),
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// Local `hour:minute` on 2024-01-`day`, a Monday on the 1st.
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        let date = NaiveDate::from_ymd_opt(2024, 1, day).unwrap();
+        at_minute(date, hour * 60 + minute)
+    }
+
+    #[test]
+    fn test_last_bar_of_a_session() {
+        assert_eq!(last_bar_of(MORNING_SESSION, 1), 11 * 60 + 29);
+        assert_eq!(last_bar_of(MORNING_SESSION, 30), 11 * 60);
+        // The morning is no whole number of hours: its last hour bar starts at 11:00
+        assert_eq!(last_bar_of(MORNING_SESSION, 60), 11 * 60);
+        assert_eq!(last_bar_of(AFTERNOON_SESSION, 30), 14 * 60 + 30);
+        assert_eq!(last_bar_of(AFTERNOON_SESSION, 60), 14 * 60);
+    }
+
+    #[test]
+    fn test_step_bar_over_the_lunch_break() {
+        let (m1, m30, h1) = (
+            Timeframe::Minutes(1),
+            Timeframe::Minutes(30),
+            Timeframe::Hours(1),
+        );
+        assert_eq!(step_bar(at(2, 11, 29), m1, true), at(2, 13, 0));
+        // The 11:30 close print is the last of the morning
+        assert_eq!(step_bar(at(2, 11, 30), m1, true), at(2, 13, 0));
+        assert_eq!(step_bar(at(2, 11, 0), m30, true), at(2, 13, 0));
+        assert_eq!(step_bar(at(2, 10, 30), m30, true), at(2, 11, 0));
+
+        // And back to the last morning bar
+        assert_eq!(step_bar(at(2, 13, 0), m1, false), at(2, 11, 29));
+        assert_eq!(step_bar(at(2, 13, 0), m30, false), at(2, 11, 0));
+        assert_eq!(step_bar(at(2, 13, 0), h1, false), at(2, 11, 0));
+        assert_eq!(step_bar(at(2, 13, 30), m30, false), at(2, 13, 0));
+    }
+
+    #[test]
+    fn test_step_bar_over_the_weekend() {
+        let (m1, m30, h1) = (
+            Timeframe::Minutes(1),
+            Timeframe::Minutes(30),
+            Timeframe::Hours(1),
+        );
+        // Friday 5th to Monday 8th
+        assert_eq!(step_bar(at(5, 14, 30), m30, true), at(8, 9, 0));
+        assert_eq!(step_bar(at(5, 14, 0), h1, true), at(8, 9, 0));
+        assert_eq!(step_bar(at(5, 7, 0), Timeframe::DAILY, true), at(8, 7, 0));
+        assert_eq!(step_bar(at(5, 7, 0), Timeframe::Days(2), true), at(9, 7, 0));
+
+        // Back from the Monday open to the last bar of Friday
+        assert_eq!(step_bar(at(8, 9, 0), m30, false), at(5, 14, 30));
+        assert_eq!(step_bar(at(8, 9, 0), m1, false), at(5, 14, 59));
+        assert_eq!(step_bar(at(8, 9, 0), h1, false), at(5, 14, 0));
+        assert_eq!(step_bar(at(8, 7, 0), Timeframe::DAILY, false), at(5, 7, 0));
+    }
+
+    #[test]
+    fn test_boundary_between_bars() {
+        assert_eq!(boundary(at(2, 9, 0), at(2, 9, 30)), None);
+        assert_eq!(boundary(at(2, 9, 30), at(2, 10, 0)), Some(Boundary::Hour));
+        assert_eq!(
+            boundary(at(2, 11, 0), at(2, 13, 0)),
+            Some(Boundary::Session)
+        );
+        assert_eq!(boundary(at(2, 14, 30), at(3, 9, 0)), Some(Boundary::Day));
+        assert_eq!(boundary(at(5, 14, 30), at(8, 9, 0)), Some(Boundary::Week));
+        let february = at_minute(NaiveDate::from_ymd_opt(2024, 2, 1).unwrap(), 9 * 60);
+        assert_eq!(boundary(at(31, 14, 30), february), Some(Boundary::Month));
+        let last_year = at_minute(NaiveDate::from_ymd_opt(2023, 12, 29).unwrap(), 9 * 60);
+        assert_eq!(boundary(last_year, at(2, 9, 0)), Some(Boundary::Year));
+    }
+}
@@ -0,0 +1,194 @@
+//! Drawing of the analysis tools: Fibonacci levels, channels, pitchforks,
+//! Gann fans and long/short positions.
+
+use plotters::{
+    backend::BitMapBackend,
+    chart::ChartContext,
+    coord::types::RangedCoordf32,
+    element::{Rectangle, Text},
+    prelude::Cartesian2d,
+    style::{
+        Color, IntoFont, RGBColor, WHITE,
+        text_anchor::{HPos, Pos, VPos},
+    },
+};
+
+use crate::{
+    CandleData, Chart,
+    chart::{DOWN_COLOR, UP_COLOR, draw_styled_path},
+    draw::{DrawObject, DrawType, GANN_RATIOS, clip_segments},
+};
+
+/// Visible area of the price pane: min x, max x, min y, max y.
+type Bounds = (f32, f32, f32, f32);
+
+impl Chart {
+    /// Draw a Fibonacci, channel, pitchfork, Gann fan or position object.
+    ///
+    /// # Arguments
+    /// * `chart` - Price chart to draw on
+    /// * `object` - Drawing, unclipped
+    /// * `bounds` - Visible area of the chart
+    pub(crate) fn chart_draw_tool(
+        &self,
+        chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+        object: &DrawObject,
+        bounds: Bounds,
+    ) {
+        let (min_x, max_x, min_y, max_y) = bounds;
+        for (a, b) in clip_segments(object.draw_type.segments(), min_x, max_x, min_y, max_y) {
+            draw_styled_path(chart, vec![a, b], object.color, &object.style);
+        }
+        let visible =
+            |(x, y): (f32, f32)| (min_x..=max_x).contains(&x) && (min_y..=max_y).contains(&y);
+
+        match &object.draw_type {
+            DrawType::FibRetracement { .. } | DrawType::FibExtension { .. } => {
+                for (level, y, (start, _)) in object.draw_type.level_lines() {
+                    let at = (start.0.max(min_x), y);
+                    if visible(at) {
+                        let price = self.price_scale.to_price(y);
+                        draw_label(
+                            chart,
+                            format!("{level} ({price:.2})"),
+                            at,
+                            object.color,
+                            VPos::Bottom,
+                        );
+                    }
+                }
+            }
+            DrawType::GannFan(_) => {
+                for ((_, end), (price, time)) in
+                    object.draw_type.segments().into_iter().zip(GANN_RATIOS)
+                {
+                    if visible(end) {
+                        draw_label(
+                            chart,
+                            format!("{price}/{time}"),
+                            end,
+                            object.color,
+                            VPos::Center,
+                        );
+                    }
+                }
+            }
+            DrawType::Position {
+                entry,
+                end_x,
+                target,
+                stop,
+                long,
+            } => {
+                let clamp_x = |x: f32| x.clamp(min_x, max_x);
+                let clamp_y = |y: f32| y.clamp(min_y, max_y);
+                let (left, right) = (clamp_x(entry.0.min(*end_x)), clamp_x(entry.0.max(*end_x)));
+                for (price, color) in [(*target, UP_COLOR), (*stop, DOWN_COLOR)] {
+                    let _ = chart.draw_series(std::iter::once(Rectangle::new(
+                        [(left, clamp_y(entry.1)), (right, clamp_y(price))],
+                        color.mix(object.style.fill_opacity as f64).filled(),
+                    )));
+                }
+                draw_styled_path(
+                    chart,
+                    vec![(left, clamp_y(entry.1)), (right, clamp_y(entry.1))],
+                    object.color,
+                    &object.style,
+                );
+
+                // Prices of the entry, target and stop, drawn in axis units
+                let scale = self.price_scale;
+                let price = |y: f32| scale.to_price(y);
+                let entry_price = price(entry.1);
+                let change = |exit: f32| (exit - entry_price) / entry_price * 100.0;
+                let risk = (entry_price - price(*stop)).abs();
+                let reward = (price(*target) - entry_price).abs();
+                let ratio = if risk > 0.0 { reward / risk } else { 0.0 };
+                // Target above the box for a long and below it for a short
+                let (top, bottom) = if *long {
+                    (*target, *stop)
+                } else {
+                    (*stop, *target)
+                };
+                let labels = [(top, VPos::Bottom, *long), (bottom, VPos::Top, !*long)];
+                for (y, pos, is_target) in labels {
+                    let name = if is_target { "Target" } else { "Stop" };
+                    let at = (left, clamp_y(y));
+                    draw_label(
+                        chart,
+                        format!("{name}: {:.2} ({:+.2}%)", price(y), change(price(y))),
+                        at,
+                        WHITE,
+                        pos,
+                    );
+                }
+                let mut summary = format!("R/R {ratio:.2}");
+                if let Some((status, exit)) = position_result(
+                    &self.candle_data,
+                    (entry.0, entry_price),
+                    *end_x,
+                    price(*target),
+                    price(*stop),
+                    *long,
+                ) {
+                    let pnl = if *long { change(exit) } else { -change(exit) };
+                    summary.push_str(&format!("  {status} P&L {pnl:+.2}%"));
+                }
+                if visible((left, entry.1)) {
+                    draw_label(chart, summary, (left, entry.1), WHITE, VPos::Bottom);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Draw `text` at a point of the chart with its left side on the point and
+/// `vertical` telling which of its edges sits there.
+fn draw_label(
+    chart: &mut ChartContext<BitMapBackend, Cartesian2d<RangedCoordf32, RangedCoordf32>>,
+    text: String,
+    at: (f32, f32),
+    color: RGBColor,
+    vertical: VPos,
+) {
+    let style = ("sans-serif", 12)
+        .into_font()
+        .color(&color)
+        .pos(Pos::new(HPos::Left, vertical));
+    let _ = chart.plotting_area().draw(&Text::new(text, at, style));
+}
+
+/// Outcome of a position over the candles between its entry and `end_x`.
+///
+/// # Returns
+/// * `Option<(&str, f32)>` - "Target" or "Stopped" with the exit price when
+///   a bar reached either, else "Open" with the last close; None when no bar
+///   follows the entry
+fn position_result(
+    candles: &[CandleData],
+    entry: (f32, f32),
+    end_x: f32,
+    target: f32,
+    stop: f32,
+    long: bool,
+) -> Option<(&'static str, f32)> {
+    let first = entry.0.min(end_x).floor().max(0.0) as usize + 1;
+    let last = (entry.0.max(end_x).floor().max(0.0) as usize).min(candles.len().checked_sub(1)?);
+    let bars = candles.get(first..=last)?;
+    for bar in bars {
+        // A bar that spans both is counted as stopped out
+        let (stopped, reached) = if long {
+            (bar.low <= stop, bar.high >= target)
+        } else {
+            (bar.high >= stop, bar.low <= target)
+        };
+        if stopped {
+            return Some(("Stopped", stop));
+        }
+        if reached {
+            return Some(("Target", target));
+        }
+    }
+    Some(("Open", bars.last()?.close))
+}
@@ -0,0 +1,642 @@
+//! Price alerts evaluated against the live market watch.
+//!
+//! An [`Alert`] watches one symbol, or every symbol of the user's watchlist,
+//! for an [`AlertCondition`]. The app feeds an [`AlertBook`] one
+//! [`Snapshot`] per symbol whenever the market watch updates, and the book
+//! answers with the alerts that fired, which the app shows in the UI and
+//! passes on to the desktop or a webhook.
+//!
+//! Prices are in thousands of VND, as shown on the charts and the watchlist.
+//!
+//! Crossing conditions fire when the price (or the fast indicator) moves from
+//! one side of the level to the other between two snapshots, so the first
+//! snapshot after a launch only tells the book which side it is on.
+//! Threshold conditions fire when they start to hold.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::error::{Error, Result, check_status};
+use crate::explorer::vci::market_watch::VCIMarketWatch;
+use crate::http::HttpClient;
+
+/// Name of the alert file inside the app cache directory.
+pub const ALERTS_FILE_NAME: &str = "alerts.json";
+
+/// Which way a crossing has to go to fire.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Cross {
+    /// From below to above
+    Up,
+    /// From above to below
+    Down,
+    #[default]
+    Either,
+}
+
+impl Cross {
+    /// Whether going from `previous` to `current`, both signed distances to
+    /// the level, crosses it in this direction.
+    fn crossed(self, previous: f64, current: f64) -> bool {
+        let up = previous < 0.0 && current >= 0.0;
+        let down = previous > 0.0 && current <= 0.0;
+        match self {
+            Cross::Up => up,
+            Cross::Down => down,
+            Cross::Either => up || down,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Cross::Up => "crossed above",
+            Cross::Down => "crossed below",
+            Cross::Either => "crossed",
+        }
+    }
+}
+
+/// What an alert waits for.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// The price crosses a fixed level, e.g. a horizontal line on the chart.
+    Level { price: f64, cross: Cross },
+    /// The price crosses a trendline through two (unix seconds, price)
+    /// points, extended past both ends.
+    Trendline {
+        from: (i64, f64),
+        to: (i64, f64),
+        cross: Cross,
+    },
+    /// The change from the reference price reaches `percent`; a negative
+    /// value waits for a drop of that size.
+    PercentChange { percent: f64 },
+    /// The session volume reaches `multiple` times the average daily volume.
+    VolumeSpike { multiple: f64 },
+    /// Indicator `fast` crosses indicator `slow`, both named by their chart
+    /// legend, e.g. `EMA 20` and `SMA 50`.
+    IndicatorCross {
+        fast: String,
+        slow: String,
+        cross: Cross,
+    },
+}
+
+impl AlertCondition {
+    /// Price of the trendline at `time`, unix seconds.
+    fn trendline_price(from: (i64, f64), to: (i64, f64), time: i64) -> f64 {
+        if from.0 == to.0 {
+            return to.1;
+        }
+        let slope = (to.1 - from.1) / (to.0 - from.0) as f64;
+        from.1 + slope * (time - from.0) as f64
+    }
+
+    /// Signed distance of a crossing condition from its level, or None for
+    /// threshold conditions and missing inputs.
+    fn distance(&self, snapshot: &Snapshot) -> Option<f64> {
+        match self {
+            AlertCondition::Level { price, .. } => Some(snapshot.price - price),
+            AlertCondition::Trendline { from, to, .. } => {
+                Some(snapshot.price - Self::trendline_price(*from, *to, snapshot.time))
+            }
+            AlertCondition::IndicatorCross { fast, slow, .. } => {
+                Some(snapshot.indicators.get(fast)? - snapshot.indicators.get(slow)?)
+            }
+            AlertCondition::PercentChange { .. } | AlertCondition::VolumeSpike { .. } => None,
+        }
+    }
+
+    /// Whether a threshold condition holds, or None for crossing conditions
+    /// and missing inputs.
+    fn holds(&self, snapshot: &Snapshot) -> Option<bool> {
+        match self {
+            AlertCondition::PercentChange { percent } => {
+                let change = snapshot.change_percent()?;
+                Some(if *percent < 0.0 {
+                    change <= *percent
+                } else {
+                    change >= *percent
+                })
+            }
+            AlertCondition::VolumeSpike { multiple } => {
+                let average = snapshot.average_volume.filter(|average| *average > 0.0)?;
+                Some(snapshot.volume >= multiple * average)
+            }
+            _ => None,
+        }
+    }
+
+    /// Names of the indicator values a snapshot needs for this condition.
+    pub fn indicators(&self) -> Vec<&str> {
+        match self {
+            AlertCondition::IndicatorCross { fast, slow, .. } => vec![fast, slow],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Short description, e.g. `crossed above 25.5`.
+    pub fn describe(&self) -> String {
+        match self {
+            AlertCondition::Level { price, cross } => format!("{} {price}", cross.label()),
+            AlertCondition::Trendline { cross, .. } => format!("{} the trendline", cross.label()),
+            AlertCondition::PercentChange { percent } => format!("changed {percent:+}%"),
+            AlertCondition::VolumeSpike { multiple } => {
+                format!("volume above {multiple}x the daily average")
+            }
+            AlertCondition::IndicatorCross { fast, slow, cross } => {
+                format!("{fast} {} {slow}", cross.label())
+            }
+        }
+    }
+}
+
+/// What an alert watches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "target", content = "symbol", rename_all = "snake_case")]
+pub enum AlertTarget {
+    Symbol(String),
+    /// Every symbol of the user's watchlist at the time of the snapshot
+    Watchlist,
+}
+
+/// Where a fired alert is sent besides the in-app list.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Delivery {
+    pub desktop: bool,
+    /// URL that gets a JSON POST of the [`Triggered`] alert.
+    pub webhook: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: u64,
+    pub target: AlertTarget,
+    pub condition: AlertCondition,
+    /// Added to the notification text.
+    #[serde(default)]
+    pub note: String,
+    pub enabled: bool,
+    /// Keep the alert after it fires instead of disabling it.
+    #[serde(default)]
+    pub repeat: bool,
+    #[serde(default)]
+    pub delivery: Delivery,
+    /// Unix seconds of the last time it fired.
+    #[serde(default)]
+    pub triggered_at: Option<i64>,
+}
+
+impl Alert {
+    pub fn new(target: AlertTarget, condition: AlertCondition) -> Self {
+        Self {
+            id: 0,
+            target,
+            condition,
+            note: String::new(),
+            enabled: true,
+            repeat: false,
+            delivery: Delivery::default(),
+            triggered_at: None,
+        }
+    }
+
+    fn watches(&self, symbol: &str, watchlist: &[String]) -> bool {
+        match &self.target {
+            AlertTarget::Symbol(target) => target.eq_ignore_ascii_case(symbol),
+            AlertTarget::Watchlist => watchlist
+                .iter()
+                .any(|item| item.eq_ignore_ascii_case(symbol)),
+        }
+    }
+}
+
+/// State of one symbol that alerts are evaluated on.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Snapshot {
+    pub symbol: String,
+    pub price: f64,
+    /// Previous close the day's change is measured from.
+    pub reference_price: f64,
+    /// Volume traded in the session so far.
+    pub volume: f64,
+    /// Average daily volume, for [`AlertCondition::VolumeSpike`].
+    pub average_volume: Option<f64>,
+    /// Latest value of chart indicators by legend name, for
+    /// [`AlertCondition::IndicatorCross`].
+    pub indicators: BTreeMap<String, f64>,
+    /// Unix seconds.
+    pub time: i64,
+}
+
+impl Snapshot {
+    /// Snapshot of a market watch row at `time`, unix seconds.
+    pub fn from_market_watch(row: &VCIMarketWatch, time: i64) -> Self {
+        Self {
+            symbol: row.listing_info.symbol.clone(),
+            price: row.match_price.match_price / 1000.0,
+            reference_price: row.listing_info.ref_price / 1000.0,
+            volume: row.match_price.accumulated_volume as f64,
+            average_volume: None,
+            indicators: BTreeMap::new(),
+            time,
+        }
+    }
+
+    fn change_percent(&self) -> Option<f64> {
+        (self.reference_price > 0.0)
+            .then(|| (self.price - self.reference_price) / self.reference_price * 100.0)
+    }
+}
+
+/// An alert that fired, as shown to the user and posted to webhooks.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Triggered {
+    pub alert_id: u64,
+    pub symbol: String,
+    pub message: String,
+    pub price: f64,
+    /// Unix seconds.
+    pub time: i64,
+    #[serde(skip)]
+    pub delivery: Delivery,
+}
+
+/// Last evaluation of an alert on one symbol.
+#[derive(Debug, Clone, Copy)]
+enum Observed {
+    Distance(f64),
+    Holds(bool),
+}
+
+/// The user's alerts with what is needed to evaluate them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AlertBook {
+    next_id: u64,
+    alerts: Vec<Alert>,
+    #[serde(skip)]
+    observed: HashMap<(u64, String), Observed>,
+    /// Set by every change that should be saved
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl AlertBook {
+    /// Read the book saved at `path`; an empty book if there is none.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(Error::cache_io(path.display().to_string(), e)),
+        };
+        serde_json::from_str(&text).map_err(|e| Error::Config(format!("{}: {e}", path.display())))
+    }
+
+    /// Write the book to `path` through a temporary file, so a crash never
+    /// leaves half a file behind.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| Error::Config(format!("alerts: {e}")))?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json).map_err(|e| Error::cache_io(tmp.display().to_string(), e))?;
+        std::fs::rename(&tmp, path).map_err(|e| Error::cache_io(path.display().to_string(), e))
+    }
+
+    pub fn alerts(&self) -> &[Alert] {
+        &self.alerts
+    }
+
+    /// Add `alert` under a new id.
+    ///
+    /// # Returns
+    /// * `u64` - The id given to the alert
+    pub fn add(&mut self, mut alert: Alert) -> u64 {
+        self.next_id += 1;
+        alert.id = self.next_id;
+        self.alerts.push(alert);
+        self.dirty = true;
+        self.next_id
+    }
+
+    pub fn remove(&mut self, id: u64) -> Option<Alert> {
+        let index = self.alerts.iter().position(|alert| alert.id == id)?;
+        self.observed.retain(|(alert_id, _), _| *alert_id != id);
+        self.dirty = true;
+        Some(self.alerts.remove(index))
+    }
+
+    /// Turn an alert on or off; turning it on forgets the side it was on.
+    pub fn set_enabled(&mut self, id: u64, enabled: bool) {
+        if let Some(alert) = self.alerts.iter_mut().find(|alert| alert.id == id) {
+            alert.enabled = enabled;
+            self.observed.retain(|(alert_id, _), _| *alert_id != id);
+            self.dirty = true;
+        }
+    }
+
+    /// Whether anything changed since the last call.
+    pub fn take_dirty(&mut self) -> bool {
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// Save again on the next check, e.g. after a save failed.
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Indicator names enabled alerts on `symbol` need in its snapshot.
+    pub fn indicators_for(&self, symbol: &str, watchlist: &[String]) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .alerts
+            .iter()
+            .filter(|alert| alert.enabled && alert.watches(symbol, watchlist))
+            .flat_map(|alert| alert.condition.indicators())
+            .map(str::to_string)
+            .collect();
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Whether an enabled alert on `symbol` needs its average volume.
+    pub fn needs_average_volume(&self, symbol: &str, watchlist: &[String]) -> bool {
+        self.alerts.iter().any(|alert| {
+            alert.enabled
+                && alert.watches(symbol, watchlist)
+                && matches!(alert.condition, AlertCondition::VolumeSpike { .. })
+        })
+    }
+
+    /// Evaluate the enabled alerts on the symbol of `snapshot`.
+    ///
+    /// Alerts that fire are disabled unless they repeat.
+    ///
+    /// # Arguments
+    /// * `snapshot` - Latest state of one symbol
+    /// * `watchlist` - Symbols of the user's watchlist, for watchlist alerts
+    ///
+    /// # Returns
+    /// * `Vec<Triggered>` - The alerts that fired, in the order they were added
+    pub fn evaluate(&mut self, snapshot: &Snapshot, watchlist: &[String]) -> Vec<Triggered> {
+        let mut triggered = Vec::new();
+        for alert in &mut self.alerts {
+            if !alert.enabled || !alert.watches(&snapshot.symbol, watchlist) {
+                continue;
+            }
+            let current = match (
+                alert.condition.distance(snapshot),
+                alert.condition.holds(snapshot),
+            ) {
+                (Some(distance), _) => Observed::Distance(distance),
+                (None, Some(holds)) => Observed::Holds(holds),
+                (None, None) => continue,
+            };
+            let key = (alert.id, snapshot.symbol.to_uppercase());
+            let previous = self.observed.insert(key, current);
+            let fired = match (&alert.condition, previous, current) {
+                (
+                    AlertCondition::Level { cross, .. }
+                    | AlertCondition::Trendline { cross, .. }
+                    | AlertCondition::IndicatorCross { cross, .. },
+                    Some(Observed::Distance(previous)),
+                    Observed::Distance(current),
+                ) => cross.crossed(previous, current),
+                (_, Some(Observed::Holds(true)), Observed::Holds(_)) => false,
+                (_, _, Observed::Holds(holds)) => holds,
+                _ => false,
+            };
+            if !fired {
+                continue;
+            }
+
+            let mut message = format!("{} {}", snapshot.symbol, alert.condition.describe());
+            if !alert.note.is_empty() {
+                message = format!("{message}: {}", alert.note);
+            }
+            triggered.push(Triggered {
+                alert_id: alert.id,
+                symbol: snapshot.symbol.clone(),
+                message,
+                price: snapshot.price,
+                time: snapshot.time,
+                delivery: alert.delivery.clone(),
+            });
+            alert.triggered_at = Some(snapshot.time);
+            alert.enabled = alert.repeat;
+            self.dirty = true;
+        }
+        triggered
+    }
+}
+
+/// Average daily volume over the last `bars` of `volumes`, oldest first.
+pub fn average_volume(volumes: &[f64], bars: usize) -> Option<f64> {
+    let recent = &volumes[volumes.len().saturating_sub(bars)..];
+    (!recent.is_empty()).then(|| recent.iter().sum::<f64>() / recent.len() as f64)
+}
+
+/// POST `triggered` as JSON to a webhook.
+pub async fn post_webhook(client: &HttpClient, url: &str, triggered: &Triggered) -> Result<()> {
+    let request = client.client().post(url).json(triggered);
+    check_status(client.send(request).await?).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture_server::{FixtureServer, Route, test_client};
+
+    fn snapshot(price: f64, time: i64) -> Snapshot {
+        Snapshot {
+            symbol: "VCI".to_string(),
+            price,
+            reference_price: 20.0,
+            volume: 1_000.0,
+            time,
+            ..Snapshot::default()
+        }
+    }
+
+    #[test]
+    fn test_level_fires_once_on_crossing() {
+        let mut book = AlertBook::default();
+        let condition = AlertCondition::Level {
+            price: 21.0,
+            cross: Cross::Up,
+        };
+        let id = book.add(Alert::new(
+            AlertTarget::Symbol("vci".to_string()),
+            condition,
+        ));
+
+        // The first snapshot only tells which side the price is on
+        assert!(book.evaluate(&snapshot(21.5, 1), &[]).is_empty());
+        assert!(book.evaluate(&snapshot(20.5, 2), &[]).is_empty());
+        let fired = book.evaluate(&snapshot(21.0, 3), &[]);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].alert_id, id);
+        assert_eq!(fired[0].message, "VCI crossed above 21");
+
+        // Disabled after firing
+        assert!(!book.alerts()[0].enabled);
+        assert_eq!(book.alerts()[0].triggered_at, Some(3));
+        assert!(book.evaluate(&snapshot(20.0, 4), &[]).is_empty());
+        assert!(book.evaluate(&snapshot(22.0, 5), &[]).is_empty());
+    }
+
+    #[test]
+    fn test_trendline_is_extended() {
+        let mut book = AlertBook::default();
+        let mut alert = Alert::new(
+            AlertTarget::Symbol("VCI".to_string()),
+            AlertCondition::Trendline {
+                from: (0, 20.0),
+                to: (100, 21.0),
+                cross: Cross::Down,
+            },
+        );
+        alert.repeat = true;
+        book.add(alert);
+
+        // The line is at 22 by t = 200
+        assert!(book.evaluate(&snapshot(22.5, 150), &[]).is_empty());
+        assert_eq!(book.evaluate(&snapshot(21.9, 200), &[]).len(), 1);
+        assert!(book.evaluate(&snapshot(21.0, 300), &[]).is_empty());
+        assert!(book.alerts()[0].enabled);
+    }
+
+    #[test]
+    fn test_threshold_rearms_only_when_repeating() {
+        let mut book = AlertBook::default();
+        let mut alert = Alert::new(
+            AlertTarget::Watchlist,
+            AlertCondition::PercentChange { percent: -5.0 },
+        );
+        alert.repeat = true;
+        alert.note = "check news".to_string();
+        book.add(alert);
+        let watchlist = vec!["VCI".to_string()];
+
+        assert!(book.evaluate(&snapshot(19.5, 1), &watchlist).is_empty());
+        let fired = book.evaluate(&snapshot(18.9, 2), &watchlist);
+        assert_eq!(fired[0].message, "VCI changed -5%: check news");
+        // Still down: no repeat until it recovers
+        assert!(book.evaluate(&snapshot(18.5, 3), &watchlist).is_empty());
+        assert!(book.evaluate(&snapshot(19.5, 4), &watchlist).is_empty());
+        assert_eq!(book.evaluate(&snapshot(18.0, 5), &watchlist).len(), 1);
+
+        // Not in the watchlist
+        assert!(book.evaluate(&snapshot(18.0, 6), &[]).is_empty());
+    }
+
+    #[test]
+    fn test_volume_and_indicator_inputs() {
+        let mut book = AlertBook::default();
+        book.add(Alert::new(
+            AlertTarget::Symbol("VCI".to_string()),
+            AlertCondition::VolumeSpike { multiple: 2.0 },
+        ));
+        book.add(Alert::new(
+            AlertTarget::Symbol("VCI".to_string()),
+            AlertCondition::IndicatorCross {
+                fast: "EMA 20".to_string(),
+                slow: "SMA 50".to_string(),
+                cross: Cross::Either,
+            },
+        ));
+        assert!(book.needs_average_volume("VCI", &[]));
+        assert_eq!(book.indicators_for("VCI", &[]), vec!["EMA 20", "SMA 50"]);
+
+        // Missing inputs are skipped rather than treated as zero
+        assert!(book.evaluate(&snapshot(20.0, 1), &[]).is_empty());
+
+        let mut snap = snapshot(20.0, 2);
+        snap.average_volume = average_volume(&[100.0, 300.0, 500.0, 500.0], 2);
+        snap.indicators =
+            BTreeMap::from([("EMA 20".to_string(), 19.0), ("SMA 50".to_string(), 19.5)]);
+        let fired = book.evaluate(&snap, &[]);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].message, "VCI volume above 2x the daily average");
+
+        snap.indicators.insert("EMA 20".to_string(), 19.6);
+        let fired = book.evaluate(&snap, &[]);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].message, "VCI EMA 20 crossed SMA 50");
+    }
+
+    #[test]
+    fn test_book_round_trips_through_file() {
+        let dir = std::env::temp_dir().join(format!("aim-alerts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(ALERTS_FILE_NAME);
+        let _ = std::fs::remove_file(&path);
+        assert!(AlertBook::load(&path).unwrap().alerts().is_empty());
+
+        let mut book = AlertBook::default();
+        let mut alert = Alert::new(
+            AlertTarget::Symbol("FPT".to_string()),
+            AlertCondition::Level {
+                price: 120.5,
+                cross: Cross::Either,
+            },
+        );
+        alert.delivery.webhook = Some("http://127.0.0.1/hook".to_string());
+        book.add(alert);
+        book.add(Alert::new(
+            AlertTarget::Watchlist,
+            AlertCondition::VolumeSpike { multiple: 3.0 },
+        ));
+        assert!(book.take_dirty());
+        assert!(!book.take_dirty());
+        book.mark_dirty();
+        assert!(book.take_dirty());
+        book.save(&path).unwrap();
+
+        let mut loaded = AlertBook::load(&path).unwrap();
+        assert_eq!(loaded.alerts(), book.alerts());
+        // Ids keep counting from where they were
+        let id = loaded.add(Alert::new(
+            AlertTarget::Watchlist,
+            AlertCondition::PercentChange { percent: 7.0 },
+        ));
+        assert_eq!(id, 3);
+        assert!(loaded.remove(1).is_some());
+        assert_eq!(loaded.alerts().len(), 2);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_webhook_posts_json() {
+        let server =
+            FixtureServer::start(vec![Route::new("POST", "/hook", 200, "{}".to_string())]).await;
+        let triggered = Triggered {
+            alert_id: 7,
+            symbol: "VCI".to_string(),
+            message: "VCI crossed 21".to_string(),
+            price: 21.0,
+            time: 1_700_000_000,
+            delivery: Delivery::default(),
+        };
+        let url = format!("{}/hook", server.url());
+        post_webhook(&test_client(), &url, &triggered)
+            .await
+            .unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        let body: Triggered = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body, triggered);
+
+        let missing = format!("{}/missing", server.url());
+        assert!(
+            post_webhook(&test_client(), &missing, &triggered)
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,356 @@
+//! Versioned cache files that are checked on read and replaced atomically.
+//!
+//! A file is a header followed by the payload:
+//!
+//! * magic - 4 bytes naming what the file holds
+//! * version - `u32`, the layout of the payload, migrated by the reader
+//! * length - `u64`, bytes of payload
+//! * checksum - `u64`, FNV-1a of the payload
+//!
+//! A write goes to `<file>.tmp` first and is renamed over the file, so a
+//! crash leaves either the old file or the new one. Before that the old file
+//! may move to `<file>.1`, `<file>.2`, ... (newest first), which is where
+//! [`CacheFile::read`] looks when the file is missing, truncated or fails its
+//! checksum, and [`CacheFile::read_with`] also when its payload does not
+//! decode.
+//!
+//! Files written before this header existed started straight with the `u32`
+//! version; they are read without a checksum when the version is one of
+//! [`CacheFile::with_legacy_versions`], and are damaged otherwise.
+
+use std::fs;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::error::{Error, Result};
+
+/// magic, version, length, checksum
+const HEADER_LEN: usize = 4 + 4 + 8 + 8;
+
+/// A cache file with its backup generations.
+#[derive(Debug, Clone)]
+pub struct CacheFile {
+    path: PathBuf,
+    magic: [u8; 4],
+    generations: usize,                           // backups kept next to the file
+    backup_interval: Duration, // minimum age of the newest backup before the next one
+    legacy_versions: Option<RangeInclusive<u32>>, // of files written without the header
+}
+
+/// The payload of a cache file and where it was read from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub version: u32,
+    pub payload: Vec<u8>,
+    pub path: PathBuf, // the file itself or one of its backups
+}
+
+impl CacheFile {
+    /// A cache file at `path` holding payloads tagged with `magic`, without backups.
+    pub fn new(path: impl Into<PathBuf>, magic: [u8; 4]) -> Self {
+        Self {
+            path: path.into(),
+            magic,
+            generations: 0,
+            backup_interval: Duration::ZERO,
+            legacy_versions: None,
+        }
+    }
+
+    /// Keep `generations` older copies of the file, taking a new one on a
+    /// write only once the newest is `interval` old.
+    pub fn with_backups(mut self, generations: usize, interval: Duration) -> Self {
+        self.generations = generations;
+        self.backup_interval = interval;
+        self
+    }
+
+    /// Also read files written before the header existed, which start with
+    /// a bare version in `versions`.
+    pub fn with_legacy_versions(mut self, versions: RangeInclusive<u32>) -> Self {
+        self.legacy_versions = Some(versions);
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path of backup `generation`, 1 being the newest.
+    pub fn backup_path(&self, generation: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{generation}"));
+        PathBuf::from(name)
+    }
+
+    /// Read the file, or its newest backup that is intact when the file is
+    /// missing or damaged. `None` when there is nothing to read at all.
+    pub fn read(&self) -> Result<Option<Frame>> {
+        self.read_with(Ok)
+    }
+
+    /// Read the file like [`Self::read`] and decode it, taking a payload
+    /// that `decode` rejects for damaged as well.
+    ///
+    /// # Arguments
+    /// * `decode` - The value held by a frame, or why the frame is damaged
+    pub fn read_with<T>(
+        &self,
+        mut decode: impl FnMut(Frame) -> std::result::Result<T, String>,
+    ) -> Result<Option<T>> {
+        let mut first_error = None;
+        for path in std::iter::once(self.path.clone())
+            .chain((1..=self.generations).map(|generation| self.backup_path(generation)))
+        {
+            let decoded = self.read_path(&path).and_then(|frame| {
+                frame
+                    .map(|frame| decode(frame).map_err(|reason| invalid_data(&path, &reason)))
+                    .transpose()
+            });
+            match decoded {
+                Ok(Some(value)) => {
+                    if let Some(e) = &first_error {
+                        log::warn!("Using cache backup {}: {e}", path.display());
+                    }
+                    return Ok(Some(value));
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    log::warn!("Skipping damaged cache file {}: {e}", path.display());
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        first_error.map_or(Ok(None), Err)
+    }
+
+    fn read_path(&self, path: &Path) -> Result<Option<Frame>> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::cache_io(path.display().to_string(), e)),
+        };
+        let invalid = |reason: &str| invalid_data(path, reason);
+        if bytes.len() < 4 {
+            return Err(invalid("too short for a version"));
+        }
+        if bytes[..4] != self.magic {
+            // Written before the header: a bare version, then the payload
+            let version = u32::from_le_bytes(bytes[..4].try_into().unwrap());
+            if !self
+                .legacy_versions
+                .as_ref()
+                .is_some_and(|versions| versions.contains(&version))
+            {
+                return Err(invalid("unknown magic"));
+            }
+            return Ok(Some(Frame {
+                version,
+                payload: bytes[4..].to_vec(),
+                path: path.to_path_buf(),
+            }));
+        }
+        if bytes.len() < HEADER_LEN {
+            return Err(invalid("truncated header"));
+        }
+        let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        let len = u64::from_le_bytes(bytes[8..16].try_into().unwrap()) as usize;
+        let checksum = u64::from_le_bytes(bytes[16..24].try_into().unwrap());
+        let payload = &bytes[HEADER_LEN..];
+        if payload.len() != len {
+            return Err(invalid("truncated payload"));
+        }
+        if fnv1a(payload) != checksum {
+            return Err(invalid("checksum mismatch"));
+        }
+        Ok(Some(Frame {
+            version,
+            payload: payload.to_vec(),
+            path: path.to_path_buf(),
+        }))
+    }
+
+    /// Replace the file with `payload` written by layout `version`, backing
+    /// up the previous file when a backup is due.
+    pub fn write(&self, version: u32, payload: &[u8]) -> Result<()> {
+        let io_error = |path: &Path| {
+            let path = path.display().to_string();
+            move |e| Error::cache_io(path, e)
+        };
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir).map_err(io_error(dir))?;
+        }
+
+        let mut tmp_name = self.path.as_os_str().to_owned();
+        tmp_name.push(".tmp");
+        let tmp = PathBuf::from(tmp_name);
+        let mut file = fs::File::create(&tmp).map_err(io_error(&tmp))?;
+        file.write_all(&self.magic).map_err(io_error(&tmp))?;
+        file.write_all(&version.to_le_bytes())
+            .map_err(io_error(&tmp))?;
+        file.write_all(&(payload.len() as u64).to_le_bytes())
+            .map_err(io_error(&tmp))?;
+        file.write_all(&fnv1a(payload).to_le_bytes())
+            .map_err(io_error(&tmp))?;
+        file.write_all(payload).map_err(io_error(&tmp))?;
+        file.sync_all().map_err(io_error(&tmp))?;
+        drop(file);
+
+        if self.backup_due() {
+            self.rotate_backups()?;
+        }
+        fs::rename(&tmp, &self.path).map_err(io_error(&self.path))
+    }
+
+    /// Whether the current file should become the newest backup.
+    fn backup_due(&self) -> bool {
+        if self.generations == 0 || !self.path.exists() {
+            return false;
+        }
+        let newest = fs::metadata(self.backup_path(1)).and_then(|meta| meta.modified());
+        match newest {
+            Ok(modified) => SystemTime::now()
+                .duration_since(modified)
+                .is_ok_and(|age| age >= self.backup_interval),
+            Err(_) => true,
+        }
+    }
+
+    /// Shift every backup one generation older, dropping the oldest, and move
+    /// the file to the first generation.
+    fn rotate_backups(&self) -> Result<()> {
+        for generation in (1..self.generations).rev() {
+            let from = self.backup_path(generation);
+            if from.exists() {
+                let to = self.backup_path(generation + 1);
+                fs::rename(&from, &to).map_err(|e| Error::cache_io(to.display().to_string(), e))?;
+            }
+        }
+        let to = self.backup_path(1);
+        fs::rename(&self.path, &to).map_err(|e| Error::cache_io(to.display().to_string(), e))
+    }
+}
+
+/// Error for the file at `path` that holds something else than a cache of
+/// this kind.
+fn invalid_data(path: &Path, reason: &str) -> Error {
+    Error::cache_io(
+        path.display().to_string(),
+        io::Error::new(io::ErrorKind::InvalidData, reason.to_string()),
+    )
+}
+
+/// 64-bit FNV-1a hash of `bytes`.
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAGIC: [u8; 4] = *b"TEST";
+
+    fn temp_cache(name: &str, generations: usize) -> CacheFile {
+        let dir = std::env::temp_dir().join(format!("aim-cache-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        CacheFile::new(dir.join("cache.bin"), MAGIC).with_backups(generations, Duration::ZERO)
+    }
+
+    #[test]
+    fn test_round_trip_and_missing_file() {
+        let cache = temp_cache("round-trip", 0);
+        assert_eq!(cache.read().unwrap(), None);
+        cache.write(3, b"payload").unwrap();
+        let frame = cache.read().unwrap().unwrap();
+        assert_eq!(frame.version, 3);
+        assert_eq!(frame.payload, b"payload");
+        assert_eq!(frame.path, cache.path());
+        assert!(!cache.backup_path(1).exists());
+    }
+
+    #[test]
+    fn test_damaged_file_falls_back_to_backup() {
+        let cache = temp_cache("fallback", 2);
+        cache.write(1, b"first").unwrap();
+        cache.write(2, b"second").unwrap();
+        cache.write(3, b"third").unwrap();
+        // The oldest write is dropped after two backups
+        assert_eq!(
+            fs::read(cache.backup_path(2)).unwrap()[HEADER_LEN..],
+            *b"first"
+        );
+        assert!(!cache.backup_path(3).exists());
+
+        let mut bytes = fs::read(cache.path()).unwrap();
+        *bytes.last_mut().unwrap() ^= 0xff;
+        fs::write(cache.path(), &bytes).unwrap();
+        let frame = cache.read().unwrap().unwrap();
+        assert_eq!(
+            (frame.version, frame.payload.as_slice()),
+            (2, &b"second"[..])
+        );
+        assert_eq!(frame.path, cache.backup_path(1));
+
+        fs::write(cache.path(), &bytes[..HEADER_LEN + 2]).unwrap();
+        fs::remove_file(cache.backup_path(1)).unwrap();
+        fs::remove_file(cache.backup_path(2)).unwrap();
+        assert!(cache.read().is_err());
+    }
+
+    #[test]
+    fn test_backups_wait_for_the_interval() {
+        let cache = temp_cache("interval", 2).with_backups(2, Duration::from_secs(3600));
+        cache.write(1, b"first").unwrap();
+        cache.write(2, b"second").unwrap();
+        cache.write(3, b"third").unwrap();
+        // Only the first replacement was backed up, the newest backup is too young
+        assert_eq!(
+            fs::read(cache.backup_path(1)).unwrap()[HEADER_LEN..],
+            *b"first"
+        );
+        assert!(!cache.backup_path(2).exists());
+        assert_eq!(cache.read().unwrap().unwrap().payload, b"third");
+    }
+
+    #[test]
+    fn test_reads_files_without_header() {
+        let cache = temp_cache("legacy", 0);
+        fs::create_dir_all(cache.path().parent().unwrap()).unwrap();
+        let mut bytes = 7u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(b"old layout");
+        fs::write(cache.path(), bytes).unwrap();
+        assert!(cache.read().is_err());
+        let cache = cache.with_legacy_versions(1..=10);
+        let frame = cache.read().unwrap().unwrap();
+        assert_eq!(
+            (frame.version, frame.payload.as_slice()),
+            (7, &b"old layout"[..])
+        );
+
+        // Anything else without the magic is no cache of this kind
+        let mut bytes = 11u32.to_le_bytes().to_vec();
+        bytes.extend_from_slice(b"garbage");
+        fs::write(cache.path(), bytes).unwrap();
+        assert!(cache.read().is_err());
+    }
+
+    #[test]
+    fn test_payload_that_does_not_decode_falls_back_to_backup() {
+        let cache = temp_cache("decode", 2);
+        cache.write(1, b"good").unwrap();
+        cache.write(2, b"bad").unwrap();
+        let decode = |frame: Frame| match frame.payload.as_slice() {
+            b"good" => Ok(frame.version),
+            _ => Err("does not decode".to_string()),
+        };
+        assert_eq!(cache.read_with(decode).unwrap(), Some(1));
+
+        fs::remove_file(cache.backup_path(1)).unwrap();
+        assert!(cache.read_with(decode).is_err());
+    }
+}
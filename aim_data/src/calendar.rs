@@ -0,0 +1,211 @@
+//! Trading calendar of HOSE and HNX, shared by resampling, the chart axis,
+//! the intraday mini charts and the data updates.
+//!
+//! Times are Vietnam local time (UTC+7, no daylight saving). Trading runs in
+//! two sessions with a lunch break from 11:30 to 13:00. The morning opens
+//! with the ATO call auction until 9:15; the afternoon trades continuously
+//! until the ATC call auction from 14:30 to 14:45, then put-through deals
+//! until 15:00.
+
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, Timelike, Utc, Weekday};
+
+/// Offset of Vietnam local time from UTC.
+pub const VIETNAM_UTC_OFFSET_SECS: i32 = 7 * 3600;
+
+/// A trading session, in minutes after local midnight.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Session {
+    pub open: u32,
+    pub close: u32,
+}
+
+impl Session {
+    pub fn minutes(&self) -> u32 {
+        self.close - self.open
+    }
+}
+
+pub const MORNING_SESSION: Session = Session {
+    open: 9 * 60,
+    close: 11 * 60 + 30,
+};
+
+pub const AFTERNOON_SESSION: Session = Session {
+    open: 13 * 60,
+    close: 15 * 60,
+};
+
+pub const SESSIONS: [Session; 2] = [MORNING_SESSION, AFTERNOON_SESSION];
+
+/// Opening call auction (ATO) at the start of the morning session.
+pub const OPENING_AUCTION: Session = Session {
+    open: 9 * 60,
+    close: 9 * 60 + 15,
+};
+
+/// Closing call auction (ATC) at the end of continuous trading.
+pub const CLOSING_AUCTION: Session = Session {
+    open: 14 * 60 + 30,
+    close: 14 * 60 + 45,
+};
+
+/// What the exchange is doing at a time of a trading day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Closed,
+    OpeningAuction,
+    Continuous,
+    LunchBreak,
+    ClosingAuction,
+    PutThrough,
+}
+
+pub fn vietnam_offset() -> FixedOffset {
+    FixedOffset::east_opt(VIETNAM_UTC_OFFSET_SECS).unwrap()
+}
+
+pub fn to_local(time: DateTime<Utc>) -> DateTime<FixedOffset> {
+    time.with_timezone(&vietnam_offset())
+}
+
+/// Minutes after local midnight.
+pub fn minute_of_day(time: DateTime<Utc>) -> u32 {
+    let local = to_local(time);
+    local.hour() * 60 + local.minute()
+}
+
+/// Session a local minute of day belongs to.
+///
+/// Prints before the open belong to the morning, prints during the lunch
+/// break (such as the 11:30 close) to the morning, and later ones to the
+/// afternoon.
+pub fn session_of(minute: u32) -> Session {
+    if minute < AFTERNOON_SESSION.open {
+        MORNING_SESSION
+    } else {
+        AFTERNOON_SESSION
+    }
+}
+
+/// Whether `minute` falls inside a trading session, auctions included.
+pub fn is_in_session(minute: u32) -> bool {
+    SESSIONS
+        .iter()
+        .any(|session| (session.open..session.close).contains(&minute))
+}
+
+/// Phase of trading at a local minute of day.
+pub fn phase_of(minute: u32) -> Phase {
+    if (OPENING_AUCTION.open..OPENING_AUCTION.close).contains(&minute) {
+        Phase::OpeningAuction
+    } else if (CLOSING_AUCTION.open..CLOSING_AUCTION.close).contains(&minute) {
+        Phase::ClosingAuction
+    } else if (CLOSING_AUCTION.close..AFTERNOON_SESSION.close).contains(&minute) {
+        Phase::PutThrough
+    } else if is_in_session(minute) {
+        Phase::Continuous
+    } else if (MORNING_SESSION.close..AFTERNOON_SESSION.open).contains(&minute) {
+        Phase::LunchBreak
+    } else {
+        Phase::Closed
+    }
+}
+
+/// Weekdays trade; public holidays are not modelled.
+pub fn is_trading_day(date: NaiveDate) -> bool {
+    !matches!(date.weekday(), Weekday::Sat | Weekday::Sun)
+}
+
+/// Whether the exchange is in one of its sessions at `time`.
+pub fn is_open(time: DateTime<Utc>) -> bool {
+    is_trading_day(to_local(time).date_naive()) && is_in_session(minute_of_day(time))
+}
+
+/// The first trading day after `date`.
+pub fn next_trading_day(date: NaiveDate) -> NaiveDate {
+    let mut day = date + Duration::days(1);
+    while !is_trading_day(day) {
+        day += Duration::days(1);
+    }
+    day
+}
+
+/// The last trading day before `date`.
+pub fn previous_trading_day(date: NaiveDate) -> NaiveDate {
+    let mut day = date - Duration::days(1);
+    while !is_trading_day(day) {
+        day -= Duration::days(1);
+    }
+    day
+}
+
+/// Monday of the first week counted by [`trading_day_number`].
+const TRADING_DAY_EPOCH: NaiveDate = NaiveDate::from_ymd_opt(2000, 1, 3).unwrap();
+
+/// Trading days from Monday 2000-01-03 to `date`; a weekend day gets the
+/// number of the Friday before it.
+pub fn trading_day_number(date: NaiveDate) -> i64 {
+    let days = (date - TRADING_DAY_EPOCH).num_days();
+    days.div_euclid(7) * 5 + days.rem_euclid(7).min(4)
+}
+
+/// The instant of a local minute of day on `date`.
+pub fn at_minute(date: NaiveDate, minute: u32) -> DateTime<Utc> {
+    let midnight = date.and_hms_opt(0, 0, 0).unwrap();
+    let local = midnight + Duration::minutes(minute as i64);
+    local.and_utc() - Duration::seconds(VIETNAM_UTC_OFFSET_SECS as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lunch_break_belongs_to_morning() {
+        // 04:30 UTC is 11:30 in Hanoi
+        let close = DateTime::from_timestamp(1727238600, 0).unwrap();
+        assert_eq!(minute_of_day(close), 11 * 60 + 30);
+        assert_eq!(session_of(minute_of_day(close)), MORNING_SESSION);
+        assert!(!is_in_session(minute_of_day(close)));
+        assert_eq!(session_of(13 * 60), AFTERNOON_SESSION);
+        assert!(is_in_session(14 * 60 + 59));
+        assert!(!is_in_session(15 * 60));
+    }
+
+    #[test]
+    fn test_phases_and_trading_days() {
+        assert_eq!(phase_of(8 * 60 + 59), Phase::Closed);
+        assert_eq!(phase_of(9 * 60), Phase::OpeningAuction);
+        assert_eq!(phase_of(9 * 60 + 15), Phase::Continuous);
+        assert_eq!(phase_of(11 * 60 + 30), Phase::LunchBreak);
+        assert_eq!(phase_of(14 * 60 + 29), Phase::Continuous);
+        assert_eq!(phase_of(14 * 60 + 30), Phase::ClosingAuction);
+        assert_eq!(phase_of(14 * 60 + 50), Phase::PutThrough);
+        assert_eq!(phase_of(15 * 60), Phase::Closed);
+
+        // Friday 2024-09-27 and Monday 2024-09-30
+        let friday = NaiveDate::from_ymd_opt(2024, 9, 27).unwrap();
+        let monday = NaiveDate::from_ymd_opt(2024, 9, 30).unwrap();
+        assert_eq!(next_trading_day(friday), monday);
+        assert_eq!(previous_trading_day(monday), friday);
+
+        let open = at_minute(monday, MORNING_SESSION.open);
+        assert_eq!(to_local(open).date_naive(), monday);
+        assert_eq!(minute_of_day(open), 9 * 60);
+        assert!(is_open(open));
+        assert!(!is_open(at_minute(monday, 12 * 60)));
+        assert!(!is_open(at_minute(friday + Duration::days(1), 10 * 60)));
+    }
+
+    #[test]
+    fn test_trading_day_numbers_skip_weekends() {
+        let monday = NaiveDate::from_ymd_opt(2000, 1, 3).unwrap();
+        assert_eq!(trading_day_number(monday), 0);
+        assert_eq!(trading_day_number(monday + Duration::days(4)), 4);
+        // Saturday and Sunday share Friday's number, and Monday follows it
+        assert_eq!(trading_day_number(monday + Duration::days(5)), 4);
+        assert_eq!(trading_day_number(monday + Duration::days(6)), 4);
+        assert_eq!(trading_day_number(monday + Duration::days(7)), 5);
+        assert_eq!(trading_day_number(monday - Duration::days(3)), -1);
+    }
+}
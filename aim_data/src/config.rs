@@ -0,0 +1,252 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::error::{Error, Result};
+use crate::http::{HttpConfig, ProxyConfig};
+use crate::stream::StreamConfig;
+use crate::tls::parse_fingerprint;
+
+/// Name of the configuration file inside the app cache directory.
+pub const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// Runtime configuration, read from [`CONFIG_FILE_NAME`] and overridden by
+/// environment variables.
+///
+/// ```toml
+/// [aim]
+/// base_url = "https://staging.example.com:4443"
+/// token = "..."                    # required, there is no default token
+/// cert_fingerprint = "AB:CD:..."   # or ca_cert = "/path/to/ca.pem",
+///                                  # or accept_invalid_certs = true
+///
+/// [proxy]
+/// url = "http://proxy.local:3128"
+///
+/// [market_data]
+/// provider = "vci"
+///
+/// [stream]
+/// url = "wss://feed.example.com/stream"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub aim: AimServerConfig,
+    pub proxy: Option<ProxyConfig>,
+    pub market_data: MarketDataConfig,
+    /// Real-time feed; data is polled when this is not set.
+    pub stream: Option<StreamConfig>,
+}
+
+/// Where the AIM server lives and how to authenticate and trust it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AimServerConfig {
+    pub base_url: String,
+    /// Bearer token; requests to the server fail while it is empty.
+    pub token: String,
+    /// PEM file of the CA that signed the server certificate.
+    pub ca_cert: Option<PathBuf>,
+    /// SHA-256 of the server certificate; takes precedence over `ca_cert`.
+    pub cert_fingerprint: Option<String>,
+    /// Skip certificate checks, for a self-signed certificate. Only used
+    /// when neither `ca_cert` nor `cert_fingerprint` is set, and off unless
+    /// the config file turns it on.
+    pub accept_invalid_certs: bool,
+}
+
+impl Default for AimServerConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "https://103.48.84.52:4443".to_string(),
+            token: String::new(),
+            ca_cert: None,
+            cert_fingerprint: None,
+            accept_invalid_certs: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarketDataConfig {
+    /// Name of the [`crate::MarketDataProvider`] picked at startup.
+    pub provider: String,
+}
+
+impl Default for MarketDataConfig {
+    fn default() -> Self {
+        Self {
+            provider: "vci".to_string(),
+        }
+    }
+}
+
+impl Config {
+    /// Read the file at `path` (defaults if it does not exist), then apply
+    /// the `AIM_*` environment variables.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut config = Self::from_file(path)?;
+        config.apply_overrides(|key| std::env::var(key).ok());
+        Ok(config)
+    }
+
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(Error::cache_io(path.display().to_string(), e)),
+        };
+        toml::from_str(&text).map_err(|e| Error::Config(format!("{}: {e}", path.display())))
+    }
+
+    /// Apply overrides looked up by variable name.
+    ///
+    /// | Variable | Field |
+    /// |---|---|
+    /// | `AIM_SERVER_URL` | `aim.base_url` |
+    /// | `AIM_SERVER_TOKEN` | `aim.token` |
+    /// | `AIM_SERVER_CA_CERT` | `aim.ca_cert` |
+    /// | `AIM_SERVER_CERT_FINGERPRINT` | `aim.cert_fingerprint` |
+    /// | `AIM_PROXY_URL` | `proxy.url` |
+    /// | `AIM_MARKET_DATA_PROVIDER` | `market_data.provider` |
+    /// | `AIM_STREAM_URL` | `stream.url` |
+    pub fn apply_overrides(&mut self, lookup: impl Fn(&str) -> Option<String>) {
+        if let Some(url) = lookup("AIM_SERVER_URL") {
+            self.aim.base_url = url;
+        }
+        if let Some(token) = lookup("AIM_SERVER_TOKEN") {
+            self.aim.token = token;
+        }
+        if let Some(ca_cert) = lookup("AIM_SERVER_CA_CERT") {
+            self.aim.ca_cert = Some(PathBuf::from(ca_cert));
+        }
+        if let Some(fingerprint) = lookup("AIM_SERVER_CERT_FINGERPRINT") {
+            self.aim.cert_fingerprint = Some(fingerprint);
+        }
+        if let Some(url) = lookup("AIM_PROXY_URL") {
+            self.proxy.get_or_insert_with(ProxyConfig::default).url = url;
+        }
+        if let Some(provider) = lookup("AIM_MARKET_DATA_PROVIDER") {
+            self.market_data.provider = provider;
+        }
+        if let Some(url) = lookup("AIM_STREAM_URL") {
+            self.stream.get_or_insert_with(StreamConfig::default).url = url;
+        }
+    }
+
+    /// Feed settings, authenticating with the AIM token, when one is set,
+    /// unless the feed has its own.
+    pub fn stream_config(&self) -> Option<StreamConfig> {
+        let mut stream = self
+            .stream
+            .clone()
+            .filter(|stream| !stream.url.is_empty())?;
+        if stream.token.is_none() && !self.aim.token.is_empty() {
+            stream.token = Some(self.aim.token.clone());
+        }
+        Some(stream)
+    }
+
+    /// HTTP settings for brokers and other public endpoints.
+    pub fn http_config(&self) -> HttpConfig {
+        HttpConfig {
+            proxy: self.proxy.clone(),
+            ..HttpConfig::default()
+        }
+    }
+
+    /// HTTP settings for the AIM server, with its trust settings applied.
+    pub fn aim_http_config(&self) -> Result<HttpConfig> {
+        let mut config = self.http_config();
+        if let Some(fingerprint) = &self.aim.cert_fingerprint {
+            config.pinned_fingerprint = Some(parse_fingerprint(fingerprint)?);
+        } else if let Some(path) = &self.aim.ca_cert {
+            let pem =
+                std::fs::read(path).map_err(|e| Error::cache_io(path.display().to_string(), e))?;
+            config.root_ca_pem = Some(pem);
+        } else {
+            config.accept_invalid_certs = self.aim.accept_invalid_certs;
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_file_values_are_overridden_by_env() {
+        let text = r#"
+            [aim]
+            base_url = "https://staging.local:4443"
+            token = "from-file"
+
+            [market_data]
+            provider = "aim"
+        "#;
+        let mut config: Config = toml::from_str(text).unwrap();
+        assert_eq!(config.aim.base_url, "https://staging.local:4443");
+        assert!(!config.aim.accept_invalid_certs);
+        assert_eq!(config.proxy, None);
+
+        let env = HashMap::from([
+            ("AIM_SERVER_TOKEN", "from-env"),
+            ("AIM_PROXY_URL", "http://proxy.local:3128"),
+        ]);
+        config.apply_overrides(|key| env.get(key).map(|v| v.to_string()));
+
+        assert_eq!(config.aim.base_url, "https://staging.local:4443");
+        assert_eq!(config.aim.token, "from-env");
+        assert_eq!(config.market_data.provider, "aim");
+        assert_eq!(config.proxy.unwrap().url, "http://proxy.local:3128");
+    }
+
+    #[test]
+    fn test_stream_uses_aim_token_by_default() {
+        let mut config = Config::default();
+        assert_eq!(config.stream_config(), None);
+
+        config.apply_overrides(|key| {
+            (key == "AIM_STREAM_URL").then(|| "ws://127.0.0.1:9000".to_string())
+        });
+        let stream = config.stream_config().unwrap();
+        assert_eq!(stream.url, "ws://127.0.0.1:9000");
+        assert_eq!(stream.token, None);
+
+        config.aim.token = "from-file".to_string();
+        let stream = config.stream_config().unwrap();
+        assert_eq!(stream.token.as_deref(), Some("from-file"));
+    }
+
+    #[test]
+    fn test_defaults_hold_no_secrets_and_verify_certs() {
+        let config = Config::default();
+        assert_eq!(config.aim.token, "");
+        assert!(!config.aim.accept_invalid_certs);
+        assert!(!config.aim_http_config().unwrap().accept_invalid_certs);
+
+        let config: Config = toml::from_str("[aim]\naccept_invalid_certs = true").unwrap();
+        assert!(config.aim_http_config().unwrap().accept_invalid_certs);
+    }
+
+    #[test]
+    fn test_missing_file_gives_defaults() {
+        let config = Config::from_file(Path::new("does/not/exist/config.toml")).unwrap();
+        assert_eq!(config, Config::default());
+    }
+
+    #[test]
+    fn test_fingerprint_takes_precedence_over_invalid_certs() {
+        let mut config = Config::default();
+        config.aim.cert_fingerprint = Some("00".repeat(32));
+        let http = config.aim_http_config().unwrap();
+        assert_eq!(http.pinned_fingerprint, Some([0u8; 32]));
+        assert!(!http.accept_invalid_certs);
+
+        config.aim.cert_fingerprint = Some("not-hex".to_string());
+        assert!(config.aim_http_config().is_err());
+    }
+}
@@ -0,0 +1,319 @@
+//! Corporate actions and back-adjusted price history.
+//!
+//! On an ex-date the reference price drops by the value handed to
+//! shareholders, which shows up as a gap in raw candles. Back-adjusting
+//! multiplies every bar before the ex-date by the ratio of the theoretical
+//! ex-price to the last close, so the latest prices stay as traded and the
+//! history becomes continuous.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::calendar::to_local;
+use crate::error::{Error, Result};
+use crate::explorer::vci::ohlc::Candlestick;
+use crate::store::{CandleStore, candle_store};
+
+/// What shareholders receive per share held on the record date.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ActionKind {
+    /// Cash paid per share, in VND.
+    CashDividend { amount: f64 },
+    /// New shares per share held, e.g. `0.15` for a 15% stock dividend.
+    StockDividend { ratio: f64 },
+    /// Shares after the split per share before, e.g. `2.0` for 2-for-1.
+    Split { ratio: f64 },
+    /// Rights per share held and the subscription price in VND.
+    Rights { ratio: f64, price: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CorporateAction {
+    pub symbol: String,
+    /// First trading day without the entitlement, local date.
+    pub ex_date: NaiveDate,
+    #[serde(flatten)]
+    pub kind: ActionKind,
+}
+
+impl CorporateAction {
+    /// Multiplier for prices before the ex-date, given the last close before it.
+    ///
+    /// Returns 1.0 when the action would not move the price, e.g. a cash
+    /// dividend larger than the close or a rights price above it. Only a
+    /// reverse split raises the earlier prices.
+    pub fn price_factor(&self, prev_close: f64) -> f64 {
+        // Dividends and rights can only lower the price
+        let discount = |factor: f64| {
+            if factor > 0.0 && factor < 1.0 {
+                factor
+            } else {
+                1.0
+            }
+        };
+        match self.kind {
+            ActionKind::CashDividend { amount } if prev_close > 0.0 => {
+                discount((prev_close - amount) / prev_close)
+            }
+            ActionKind::StockDividend { ratio } => discount(1.0 / (1.0 + ratio)),
+            ActionKind::Split { ratio } if ratio > 0.0 => 1.0 / ratio,
+            ActionKind::Rights { ratio, price } if prev_close > 0.0 => {
+                discount((prev_close + ratio * price) / ((1.0 + ratio) * prev_close))
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// Multiplier for volumes before the ex-date. Only actions that change
+    /// the share count touch volume.
+    pub fn volume_factor(&self) -> f64 {
+        match self.kind {
+            ActionKind::StockDividend { ratio } => 1.0 + ratio,
+            ActionKind::Split { ratio } if ratio > 0.0 => ratio,
+            ActionKind::CashDividend { .. }
+            | ActionKind::Rights { .. }
+            | ActionKind::Split { .. } => 1.0,
+        }
+    }
+}
+
+/// Back-adjust `candles`, sorted by time, for `actions`.
+///
+/// Actions without a bar before their ex-date are ignored, since there is
+/// no close to derive the factor from.
+///
+/// # Arguments
+/// * `candles` - Raw candles of one symbol
+/// * `actions` - Corporate actions of that symbol, in any order
+///
+/// # Returns
+/// * `Vec<Candlestick>` - Candles in the price terms of the latest bar
+pub fn adjust(candles: &[Candlestick], actions: &[CorporateAction]) -> Vec<Candlestick> {
+    let dates: Vec<NaiveDate> = candles
+        .iter()
+        .map(|candle| to_local(candle.timestamp).date_naive())
+        .collect();
+    // Cumulative factors applied to bars before each ex-date
+    let mut factors: Vec<(NaiveDate, f64, f64)> = actions
+        .iter()
+        .filter_map(|action| {
+            let before = dates.partition_point(|date| *date < action.ex_date);
+            let prev = candles[..before].iter().rev().find(|c| c.close > 0.0)?;
+            Some((
+                action.ex_date,
+                action.price_factor(prev.close),
+                action.volume_factor(),
+            ))
+        })
+        .collect();
+    factors.sort_by_key(|(ex_date, _, _)| *ex_date);
+
+    candles
+        .iter()
+        .zip(&dates)
+        .map(|(candle, date)| {
+            let (price, volume) = factors
+                .iter()
+                .filter(|(ex_date, _, _)| date < ex_date)
+                .fold((1.0, 1.0), |(price, volume), (_, p, v)| {
+                    (price * p, volume * v)
+                });
+            Candlestick {
+                timestamp: candle.timestamp,
+                open: candle.open * price,
+                high: candle.high * price,
+                low: candle.low * price,
+                close: candle.close * price,
+                volume: (candle.volume as f64 * volume).round() as i64,
+            }
+        })
+        .collect()
+}
+
+/// Read actions from a JSON file, e.g. one exported from a broker.
+pub fn load_actions(path: &Path) -> Result<Vec<CorporateAction>> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| Error::cache_io(path.display().to_string(), e))?;
+    serde_json::from_str(&text).map_err(|e| {
+        Error::Config(format!(
+            "invalid corporate actions in {}: {e}",
+            path.display()
+        ))
+    })
+}
+
+/// Write actions as JSON, replacing the file.
+pub fn save_actions(path: &Path, actions: &[CorporateAction]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| Error::cache_io(dir.display().to_string(), e))?;
+    }
+    let text = serde_json::to_string_pretty(actions).unwrap_or_else(|_| "[]".to_string());
+    std::fs::write(path, text).map_err(|e| Error::cache_io(path.display().to_string(), e))
+}
+
+/// Add `imported` to `actions`, replacing events of the same kind on the
+/// same ex-date. The result is sorted by ex-date.
+pub fn merge_actions(actions: &mut Vec<CorporateAction>, imported: Vec<CorporateAction>) {
+    for action in imported {
+        let same_event = |existing: &CorporateAction| {
+            existing.ex_date == action.ex_date
+                && std::mem::discriminant(&existing.kind) == std::mem::discriminant(&action.kind)
+        };
+        match actions.iter_mut().find(|existing| same_event(existing)) {
+            Some(existing) => *existing = action,
+            None => actions.push(action),
+        }
+    }
+    actions.sort_by_key(|action| action.ex_date);
+}
+
+fn actions_path(store: &CandleStore, symbol: &str) -> PathBuf {
+    store.series_dir(symbol).join("actions.json")
+}
+
+/// Actions of `symbol` kept next to its candles in the local store.
+pub fn stored_actions(symbol: &str) -> Result<Vec<CorporateAction>> {
+    let Some(store) = candle_store() else {
+        return Ok(Vec::new());
+    };
+    let path = actions_path(&store, symbol);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    load_actions(&path)
+}
+
+/// Merge `actions` into the local store, grouped by symbol.
+pub fn store_actions(actions: Vec<CorporateAction>) -> Result<()> {
+    let Some(store) = candle_store() else {
+        return Err(Error::Config("no local candle store installed".to_string()));
+    };
+    let mut by_symbol: BTreeMap<String, Vec<CorporateAction>> = BTreeMap::new();
+    for action in actions {
+        by_symbol
+            .entry(action.symbol.to_uppercase())
+            .or_default()
+            .push(action);
+    }
+    for (symbol, imported) in by_symbol {
+        let mut stored = stored_actions(&symbol)?;
+        merge_actions(&mut stored, imported);
+        save_actions(&actions_path(&store, &symbol), &stored)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+
+    /// Daily bar stamped 00:00 UTC, like the Vietcap feed.
+    fn bar(date: &str, close: f64, volume: i64) -> Candlestick {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        Candlestick {
+            timestamp: DateTime::from_naive_utc_and_offset(
+                date.and_hms_opt(0, 0, 0).unwrap(),
+                chrono::Utc,
+            ),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume,
+        }
+    }
+
+    fn action(ex_date: &str, kind: ActionKind) -> CorporateAction {
+        CorporateAction {
+            symbol: "VCI".to_string(),
+            ex_date: NaiveDate::parse_from_str(ex_date, "%Y-%m-%d").unwrap(),
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_split_and_dividend_remove_the_gap() {
+        let candles = [
+            bar("2024-09-23", 40000.0, 1000),
+            bar("2024-09-24", 40000.0, 1000),
+            bar("2024-09-25", 20000.0, 2000),
+            bar("2024-09-26", 19000.0, 2000),
+        ];
+        let actions = [
+            action("2024-09-25", ActionKind::Split { ratio: 2.0 }),
+            action("2024-09-26", ActionKind::CashDividend { amount: 1000.0 }),
+        ];
+        let adjusted = adjust(&candles, &actions);
+        let closes: Vec<f64> = adjusted.iter().map(|c| c.close).collect();
+        assert_eq!(closes, [19000.0, 19000.0, 19000.0, 19000.0]);
+        assert_eq!(adjusted[0].volume, 2000);
+        assert_eq!(adjusted[3].volume, 2000);
+
+        // A reverse split: two shares become one at twice the price
+        let candles = [
+            bar("2024-09-24", 10000.0, 1000),
+            bar("2024-09-25", 20000.0, 500),
+        ];
+        let reverse = action("2024-09-25", ActionKind::Split { ratio: 0.5 });
+        let adjusted = adjust(&candles, &[reverse]);
+        let closes: Vec<f64> = adjusted.iter().map(|c| c.close).collect();
+        assert_eq!(closes, [20000.0, 20000.0]);
+        assert_eq!(adjusted[0].volume, 500);
+    }
+
+    #[test]
+    fn test_rights_and_stock_dividend_factors() {
+        let rights = action(
+            "2024-09-25",
+            ActionKind::Rights {
+                ratio: 0.5,
+                price: 10000.0,
+            },
+        );
+        // (30000 + 0.5 * 10000) / (1.5 * 30000)
+        assert!((rights.price_factor(30000.0) - 35000.0 / 45000.0).abs() < 1e-12);
+        let dividend = action("2024-09-25", ActionKind::StockDividend { ratio: 0.25 });
+        assert_eq!(dividend.price_factor(30000.0), 0.8);
+        // A rights price above the market does not adjust anything
+        let expensive = action(
+            "2024-09-25",
+            ActionKind::Rights {
+                ratio: 0.5,
+                price: 50000.0,
+            },
+        );
+        assert_eq!(expensive.price_factor(30000.0), 1.0);
+    }
+
+    #[test]
+    fn test_imported_actions_replace_same_event() {
+        let mut actions = vec![action(
+            "2024-09-25",
+            ActionKind::CashDividend { amount: 500.0 },
+        )];
+        merge_actions(
+            &mut actions,
+            vec![
+                action("2024-09-25", ActionKind::CashDividend { amount: 700.0 }),
+                action("2024-06-01", ActionKind::Split { ratio: 2.0 }),
+            ],
+        );
+        assert_eq!(actions.len(), 2);
+        assert_eq!(actions[0].kind, ActionKind::Split { ratio: 2.0 });
+        assert_eq!(actions[1].kind, ActionKind::CashDividend { amount: 700.0 });
+
+        let json = r#"[{"symbol":"VCI","ex_date":"2024-09-25","kind":"rights","ratio":0.2,"price":12000}]"#;
+        let parsed: Vec<CorporateAction> = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            parsed[0].kind,
+            ActionKind::Rights {
+                ratio: 0.2,
+                price: 12000.0
+            }
+        );
+    }
+}
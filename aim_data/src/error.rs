@@ -0,0 +1,168 @@
+use reqwest::StatusCode;
+use std::fmt;
+
+/// Longest slice of a response body kept in [`Error::Decode`] and [`Error::Http`].
+const BODY_SNIPPET_LEN: usize = 256;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Errors returned by every fetcher in `aim_data`.
+#[derive(Debug)]
+pub enum Error {
+    /// The request never produced a response (DNS, TLS, connect, timeout, ...).
+    Transport(reqwest::Error),
+    /// The server answered with a non-success status.
+    Http {
+        url: String,
+        status: StatusCode,
+        body: String,
+    },
+    /// The server rejected our credentials (401 / 403).
+    Auth { url: String, status: StatusCode },
+    /// The body could not be decoded into the expected type.
+    Decode {
+        url: String,
+        source: serde_json::Error,
+        body: String,
+    },
+    /// Reading or writing the local cache failed.
+    CacheIo {
+        path: String,
+        source: std::io::Error,
+    },
+    /// The configuration file or an override is invalid.
+    Config(String),
+}
+
+impl Error {
+    /// Whether retrying the same request later can succeed.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Transport(_) => true,
+            Error::Http { status, .. } => {
+                status.is_server_error() || *status == StatusCode::TOO_MANY_REQUESTS
+            }
+            Error::Auth { .. }
+            | Error::Decode { .. }
+            | Error::CacheIo { .. }
+            | Error::Config(_) => false,
+        }
+    }
+
+    /// Short text suitable for a status line in the UI.
+    pub fn status_message(&self) -> String {
+        match self {
+            Error::Transport(e) if e.is_timeout() => "Request timed out".to_string(),
+            Error::Transport(_) => "Network unavailable".to_string(),
+            Error::Http { status, .. } => format!("Server error ({})", status.as_u16()),
+            Error::Auth { .. } => "Not authorized, check the access token".to_string(),
+            Error::Decode { .. } => "Unexpected data format from server".to_string(),
+            Error::CacheIo { .. } => "Could not write local cache".to_string(),
+            Error::Config(_) => "Invalid configuration".to_string(),
+        }
+    }
+
+    pub(crate) fn cache_io(path: impl Into<String>, source: std::io::Error) -> Self {
+        Error::CacheIo {
+            path: path.into(),
+            source,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Transport(e) => write!(f, "transport error: {e}"),
+            Error::Http { url, status, body } => {
+                write!(f, "HTTP {status} from {url}: {body}")
+            }
+            Error::Auth { url, status } => write!(f, "not authorized ({status}) for {url}"),
+            Error::Decode { url, source, body } => {
+                write!(
+                    f,
+                    "failed to decode response from {url}: {source}; body: {body}"
+                )
+            }
+            Error::CacheIo { path, source } => write!(f, "cache I/O error on {path}: {source}"),
+            Error::Config(message) => write!(f, "configuration error: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Transport(e) => Some(e),
+            Error::Decode { source, .. } => Some(source),
+            Error::CacheIo { source, .. } => Some(source),
+            Error::Http { .. } | Error::Auth { .. } | Error::Config(_) => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Error::Transport(e)
+    }
+}
+
+/// Keep at most [`BODY_SNIPPET_LEN`] bytes of a body, cut on a char boundary.
+fn snippet(body: &str) -> String {
+    if body.len() <= BODY_SNIPPET_LEN {
+        return body.to_string();
+    }
+    let mut end = BODY_SNIPPET_LEN;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...", &body[..end])
+}
+
+/// Map a non-success status to [`Error::Auth`] or [`Error::Http`].
+pub(crate) async fn check_status(response: reqwest::Response) -> Result<reqwest::Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let url = response.url().to_string();
+    if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
+        return Err(Error::Auth { url, status });
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(Error::Http {
+        url,
+        status,
+        body: snippet(&body),
+    })
+}
+
+/// Check the status, then decode the JSON body keeping a snippet on failure.
+pub(crate) async fn decode_json<T>(response: reqwest::Response) -> Result<T>
+where
+    T: for<'de> serde::Deserialize<'de>,
+{
+    let response = check_status(response).await?;
+    let url = response.url().to_string();
+    let body = response.text().await?;
+    serde_json::from_str(&body).map_err(|source| Error::Decode {
+        url,
+        source,
+        body: snippet(&body),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snippet_cuts_on_char_boundary() {
+        let body = "ồ".repeat(200);
+        let cut = snippet(&body);
+        assert!(cut.ends_with("..."));
+        assert!(cut.len() <= BODY_SNIPPET_LEN + 3);
+
+        assert_eq!(snippet("[]"), "[]");
+    }
+}
@@ -10,30 +10,131 @@ pub struct SjcPriceData {
     pub sell_price: String,
     pub updated_at: i64,
 }
-use reqwest::ClientBuilder;
+use crate::config::Config;
+use crate::error::{Error, Result, check_status, decode_json};
+use crate::explorer::vci::VCIExplorer;
+use crate::http::HttpClient;
 use serde::{Deserialize, Serialize};
-const TOKEN: &str = "SUPER_SECRET_ADMIN_TOKEN";
-const SERVER_URL: &str = "https://103.48.84.52:4443";
+use std::sync::{LazyLock, RwLock};
+
+/// Connection to the AIM server: HTTP client, base URL and bearer token.
+#[derive(Clone)]
+pub struct AimServer {
+    client: HttpClient,
+    base_url: String,
+    token: String,
+}
+
+impl AimServer {
+    pub fn new(client: HttpClient, base_url: &str, token: &str) -> Self {
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+        }
+    }
+
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let client = HttpClient::new(config.aim_http_config()?)?;
+        Ok(Self::new(client, &config.aim.base_url, &config.aim.token))
+    }
+
+    pub fn client(&self) -> &HttpClient {
+        &self.client
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Authorized GET request for an endpoint relative to the base URL, or a
+    /// configuration error while no token is set.
+    fn get(&self, endpoint: &str) -> Result<reqwest::RequestBuilder> {
+        if self.token.is_empty() {
+            return Err(Error::Config(
+                "no AIM server token, set `token` under [aim] or AIM_SERVER_TOKEN".to_string(),
+            ));
+        }
+        Ok(self
+            .client
+            .client()
+            .get(format!("{}/{endpoint}", self.base_url))
+            .header("Authorization", format!("Bearer {}", self.token)))
+    }
+}
+
+static AIM_SERVER: LazyLock<RwLock<AimServer>> = LazyLock::new(|| {
+    let server = AimServer::from_config(&Config::default()).unwrap_or_else(|e| {
+        log::error!("Invalid default AIM server settings: {e}");
+        AimServer::new(
+            HttpClient::default(),
+            &Config::default().aim.base_url,
+            &Config::default().aim.token,
+        )
+    });
+    RwLock::new(server)
+});
+
+/// Server used by the `fetch_*` functions of this module.
+pub fn aim_server() -> AimServer {
+    AIM_SERVER.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Replace the server used by the `fetch_*` functions of this module.
+pub fn set_aim_server(server: AimServer) {
+    *AIM_SERVER.write().unwrap_or_else(|e| e.into_inner()) = server;
+}
+
+/// Point the `fetch_*` functions at the server described by `config`.
+pub fn configure(config: &Config) -> Result<()> {
+    set_aim_server(AimServer::from_config(config)?);
+    Ok(())
+}
 
 // Generic function to fetch data from API endpoints
-async fn fetch_api_data<T>(endpoint: &str) -> Result<T, reqwest::Error>
+async fn fetch_api_data<T>(endpoint: &str) -> Result<T>
+where
+    T: for<'de> serde::Deserialize<'de>,
+{
+    fetch_api_data_with(&aim_server(), endpoint).await
+}
+
+/// Fetch and decode an AIM endpoint from the given server.
+pub async fn fetch_api_data_with<T>(server: &AimServer, endpoint: &str) -> Result<T>
 where
     T: for<'de> serde::Deserialize<'de>,
 {
-    let url = format!("{SERVER_URL}/{endpoint}");
-    let client = ClientBuilder::new()
-        .danger_accept_invalid_certs(true)
-        .build()?;
-    let resp = client
-        .get(url)
-        .header("Authorization", format!("Bearer {TOKEN}"))
-        .send()
-        .await?;
+    let resp = server.client.send_idempotent(server.get(endpoint)?).await?;
 
-    let data: T = resp.json().await?;
-    Ok(data)
+    decode_json(resp).await
 }
 
+/// Market data backed by the AIM server.
+///
+/// Chart history and the trade tape are not served by the AIM server, so
+/// those requests go through the wrapped [`VCIExplorer`].
+pub struct AimExplorer {
+    server: AimServer,
+    vci: VCIExplorer,
+}
+
+impl AimExplorer {
+    pub fn new(server: AimServer, vci: VCIExplorer) -> Self {
+        Self { server, vci }
+    }
+
+    pub fn vci(&self) -> &VCIExplorer {
+        &self.vci
+    }
+
+    pub async fn get_stock_by_gics(&self) -> Result<Vec<StockByGics>> {
+        fetch_api_data_with(&self.server, "stock-by-gics").await
+    }
+
+    pub async fn get_financial_data(&self, symbol: &str) -> Result<Vec<FinancialData>> {
+        fetch_api_data_with(&self.server, &format!("financial-data/{symbol}")).await
+    }
+}
 
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct IcbIndex {
@@ -998,7 +1099,7 @@ pub struct PdfReport {
     pub file_path: String,
 }
 
-pub async fn fetch_api_finance_report_pdf(symbol: &str) -> Result<PdfReport, reqwest::Error> {
+pub async fn fetch_api_finance_report_pdf(symbol: &str) -> Result<PdfReport> {
 
     let cache_dir = "cache";
     let file_path = format!("{cache_dir}/{symbol}.pdf");
@@ -1009,28 +1110,25 @@ pub async fn fetch_api_finance_report_pdf(symbol: &str) -> Result<PdfReport, req
         });
     }
 
-    let endpoint = format!("report-file/{symbol}");
-    let url = format!("{SERVER_URL}/{endpoint}");
-
-    let client = ClientBuilder::new()
-        .danger_accept_invalid_certs(true)
-        .build()?; // <- tự động trả reqwest::Error nếu lỗi
-
-    let resp = client
-        .get(&url)
-        .header("Authorization", format!("Bearer {TOKEN}"))
+    let server = aim_server();
+    let request = server
+        .get(&format!("report-file/{symbol}"))?
         .header("Accept", "application/pdf")
-        .send()
-        .await?; // <- reqwest::Error nếu lỗi mạng
+        // Reports can be tens of megabytes
+        .timeout(std::time::Duration::from_secs(120));
+    let resp = server.client.send_idempotent(request).await?;
 
-    let bytes = resp.bytes().await?; // <- reqwest::Error nếu lỗi đọc
+    let bytes = check_status(resp).await?.bytes().await?;
 
-    fs::create_dir_all("cache").ok(); // không cần xử lý lỗi tạo thư mục
-    let file_path = format!("cache/{}.pdf", symbol);
-    fs::write(&file_path, &bytes).ok(); // lỗi ghi file bỏ qua
+    fs::create_dir_all(cache_dir).map_err(|e| Error::cache_io(cache_dir, e))?;
+    fs::write(&file_path, &bytes).map_err(|e| Error::cache_io(&file_path, e))?;
 
     Ok(PdfReport {
         symbol: symbol.to_string(),
         file_path,
     })
 }
+
+#[cfg(test)]
+#[path = "tests/api_test.rs"]
+mod api_test;
@@ -0,0 +1,91 @@
+use super::*;
+use crate::corporate_actions::ActionKind;
+use crate::error::Error;
+use crate::fixture_server::{FixtureServer, Route, test_client};
+
+/// Every `fetch_*` goes through the global server, so they share one test
+/// instead of racing on [`set_aim_server`].
+#[tokio::test]
+async fn test_fetch_functions_decode_fixtures() {
+    let server = FixtureServer::start(vec![
+        Route::get("/balance-sheet/VCI/Y", "aim/balance_sheet.json"),
+        Route::get("/cash-flow-indirect/VCI/Y", "aim/balance_sheet.json"),
+        Route::get("/cash-flow-direct/VCI/Y", "aim/balance_sheet.json"),
+        Route::get("/income-statement/VCI/Y", "aim/balance_sheet.json"),
+        Route::get("/financial-data/VCI", "aim/financial_data.json"),
+        Route::get("/shareholder/VCI", "aim/shareholder.json"),
+        Route::get("/institution-profile/VCI", "aim/institution_profile.json"),
+        Route::get("/subsidiaries/VCI", "aim/subsidiaries.json"),
+        Route::get("/officer/VCI", "aim/officer.json"),
+        Route::get("/insider-transactions/VCI", "aim/insider_transactions.json"),
+        Route::get("/corporate-actions/VCI", "aim/corporate_actions.json"),
+        Route::get("/top-stock-influence", "aim/top_stock_influence.json"),
+        Route::get("/exchange-index", "aim/exchange_index.json"),
+        Route::get("/stock-by-gics", "aim/stock_by_gics.json"),
+        Route::get("/icb-index", "aim/icb_index.json"),
+        Route::get("/abnormal-trades", "aim/abnormal_trades.json"),
+        Route::get("/KQGD-TD-chart", "aim/kqgd_chart.json"),
+        Route::get("/KQGD-NN-chart", "aim/kqgd_chart.json"),
+        Route::get("/sjc-price", "aim/sjc_price.json"),
+        Route::get("/reports", "aim/reports.json"),
+        Route::get("/reports?source=9999", "aim/strategy_reports.json"),
+        Route::new("GET", "/report-file/NOAUTH", 401, String::new()),
+    ])
+    .await;
+    set_aim_server(AimServer::new(test_client(), &server.url(), "test-token"));
+
+    for sheet in [
+        fetch_balance_sheet_data("VCI", "Y").await.unwrap(),
+        fetch_cash_flow_gt_sheet_data("VCI", "Y").await.unwrap(),
+        fetch_cash_flow_tt_sheet_data("VCI", "Y").await.unwrap(),
+        fetch_income_statement_sheet_data("VCI", "Y").await.unwrap(),
+    ] {
+        assert_eq!(sheet.len(), 2);
+    }
+    assert_eq!(fetch_financial_data("VCI").await.unwrap().len(), 2);
+    assert_eq!(fetch_sharedholder_data("VCI").await.unwrap().len(), 2);
+    fetch_institution_data("VCI").await.unwrap();
+    // The fixture repeats one institution_id
+    assert_eq!(fetch_subsidiaries_data("VCI").await.unwrap().len(), 1);
+    assert_eq!(fetch_officers_data("VCI").await.unwrap().len(), 2);
+    assert_eq!(
+        fetch_insider_transactions_data("VCI").await.unwrap().len(),
+        2
+    );
+    let actions = fetch_corporate_actions_data("VCI").await.unwrap();
+    assert_eq!(actions.len(), 2);
+    assert_eq!(actions[1].kind, ActionKind::Split { ratio: 2.0 });
+    assert_eq!(fetch_top_stock_influencer_data().await.unwrap().len(), 2);
+    assert_eq!(fetch_exchange_index_data().await.unwrap().len(), 2);
+    let gics = fetch_stock_by_gics_data().await.unwrap();
+    assert_eq!(gics[1].stock_code, "FPT");
+    assert_eq!(fetch_icb_index_data().await.unwrap().len(), 2);
+    assert_eq!(fetch_abnormal_trade_data().await.unwrap().len(), 2);
+    assert_eq!(fetch_kqgd_td_chart_data().await.unwrap().len(), 2);
+    assert_eq!(fetch_kqgd_nn_chart_data().await.unwrap().len(), 2);
+    assert_eq!(fetch_sjc_price_data().await.unwrap().len(), 2);
+    assert_eq!(fetch_finance_report_list().await.unwrap().len(), 2);
+    assert_eq!(fetch_strategy_report_list().await.unwrap().len(), 2);
+
+    let error = fetch_finance_report_pdf("NOAUTH").await.unwrap_err();
+    assert!(matches!(error, Error::Auth { .. }), "{error:?}");
+    assert!(!error.is_transient());
+
+    let requests = server.requests();
+    assert!(
+        requests
+            .iter()
+            .all(|r| r.header("authorization") == Some("Bearer test-token"))
+    );
+}
+
+#[tokio::test]
+async fn test_requests_need_a_token() {
+    let server = FixtureServer::start(vec![Route::get("/sjc-price", "aim/sjc_price.json")]).await;
+    let aim = AimServer::new(test_client(), &server.url(), "");
+    let error = fetch_api_data_with::<Vec<SjcPriceData>>(&aim, "sjc-price")
+        .await
+        .unwrap_err();
+    assert!(matches!(error, Error::Config(_)), "{error:?}");
+    assert!(server.requests().is_empty());
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::stream::StreamEvent;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct MarketWatchResponse(pub Vec<VCIMarketWatch>);
 
@@ -13,6 +15,39 @@ pub struct VCIMarketWatch {
     pub match_price: MatchPrice,
 }
 
+impl VCIMarketWatch {
+    /// Update the snapshot from a feed event for the same symbol.
+    ///
+    /// # Returns
+    /// * `bool` - Whether anything shown in the market watch changed
+    pub fn apply(&mut self, event: &StreamEvent) -> bool {
+        if event.symbol() != self.listing_info.symbol {
+            return false;
+        }
+        match event {
+            StreamEvent::Tick(tick) => {
+                let changed = self.match_price.match_price != tick.price
+                    || self.match_price.accumulated_volume != tick.accumulated_volume;
+                self.match_price.match_price = tick.price;
+                self.match_price.match_vol = tick.volume;
+                self.match_price.accumulated_volume = tick.accumulated_volume;
+                self.match_price.highest = tick.highest;
+                self.match_price.lowest = tick.lowest;
+                changed
+            }
+            StreamEvent::Quote(quote) => {
+                let changed = self.bid_ask.bid_prices != quote.bid_prices
+                    || self.bid_ask.ask_prices != quote.ask_prices;
+                self.bid_ask.bid_prices = quote.bid_prices.clone();
+                self.bid_ask.ask_prices = quote.ask_prices.clone();
+                changed
+            }
+            // Trades only feed the order list; their totals arrive as ticks
+            StreamEvent::Trade(_) => false,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BidAsk {
     pub code: String,
@@ -24,7 +59,7 @@ pub struct BidAsk {
     // pub time: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct PriceVolume {
     pub price: f64,
     pub volume: i64,
@@ -81,3 +116,42 @@ pub struct MatchPrice {
     #[serde(rename = "referencePrice")]
     pub reference_price: f64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture_server::fixture;
+    use crate::stream::{Quote, Tick};
+
+    #[test]
+    fn test_apply_tick_and_quote() {
+        let mut watch: Vec<VCIMarketWatch> =
+            serde_json::from_str(&fixture("vci/market_watch.json")).unwrap();
+        let tick = StreamEvent::Tick(Tick {
+            symbol: "FPT".to_string(),
+            price: 134000.0,
+            volume: 300,
+            accumulated_volume: 1_000_000,
+            highest: 134500.0,
+            lowest: 132000.0,
+            time: 1727250299,
+        });
+        assert!(!watch[0].apply(&tick), "VCI must ignore FPT ticks");
+        assert!(watch[1].apply(&tick));
+        assert_eq!(watch[1].match_price.match_price, 134000.0);
+        assert!(!watch[1].apply(&tick), "same tick twice is not a change");
+
+        let quote = StreamEvent::Quote(Quote {
+            symbol: "VCI".to_string(),
+            bid_prices: vec![PriceVolume {
+                price: 36150.0,
+                volume: 500,
+            }],
+            ask_prices: vec![],
+            time: 1727250299,
+        });
+        assert!(watch[0].apply(&quote));
+        assert_eq!(watch[0].bid_ask.bid_prices.len(), 1);
+        assert!(watch[0].bid_ask.ask_prices.is_empty());
+    }
+}
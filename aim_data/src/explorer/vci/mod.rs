@@ -3,23 +3,36 @@ use company_info::CompanyInfo;
 use market_watch::{MarketWatchResponse, VCIMarketWatch};
 use ohlc::OHLCResponse;
 pub use order_data::{OrderData, OrderList, VCIOderBook};
-use reqwest::Error;
 use serde_json::json;
 
 use super::get_headers;
+use crate::error::{Result, decode_json};
+use crate::http::HttpClient;
 
 pub mod company_info;
 pub mod market_watch;
 pub mod ohlc;
 mod order_data;
 
+/// Base URL of the Vietcap trading API.
+pub const VCI_BASE_URL: &str = "https://trading.vietcap.com.vn";
+
 pub struct VCIExplorer {
-    client: reqwest::Client,
+    client: HttpClient,
+    base_url: String,
 }
 
 impl VCIExplorer {
-    pub fn new(client: reqwest::Client) -> Self {
-        Self { client }
+    pub fn new(client: HttpClient) -> Self {
+        Self::with_base_url(client, VCI_BASE_URL)
+    }
+
+    /// Point the explorer at another host, e.g. a local fixture server.
+    pub fn with_base_url(client: HttpClient, base_url: &str) -> Self {
+        Self {
+            client,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
     }
 
     pub async fn get_quote(
@@ -28,8 +41,8 @@ impl VCIExplorer {
         time_frame: &str,
         start_time: Option<DateTime<Utc>>,
         end_time: Option<DateTime<Utc>>,
-    ) -> Result<OHLCResponse, Error> {
-        let url = "https://trading.vietcap.com.vn/api/chart/OHLCChart/gap";
+    ) -> Result<OHLCResponse> {
+        let url = format!("{}/api/chart/OHLCChart/gap", self.base_url);
         let headers = get_headers("VCI");
 
         // Use provided times or default to 30-day range
@@ -47,41 +60,42 @@ impl VCIExplorer {
             "to": to
         });
 
-        let response = self
+        // Read-only query despite being a POST, so it is safe to retry
+        let request = self
             .client
-            .request(reqwest::Method::POST, url)
+            .client()
+            .request(reqwest::Method::POST, &url)
             .headers(headers)
-            .json(&payload)
-            .send()
-            .await?;
+            .json(&payload);
+        let response = self.client.send_idempotent(request).await?;
 
-        let res = response.json::<OHLCResponse>().await?;
+        let res: OHLCResponse = decode_json(response).await?;
 
         Ok(res)
     }
 
-    pub async fn get_market_watch(&self, symbols: &[&str]) -> Result<MarketWatchResponse, Error> {
-        let url = "https://trading.vietcap.com.vn/api/price/symbols/getList";
+    pub async fn get_market_watch(&self, symbols: &[&str]) -> Result<MarketWatchResponse> {
+        let url = format!("{}/api/price/symbols/getList", self.base_url);
         let headers = get_headers("VCI");
 
         // Build JSON payload with the provided symbol
         let payload = json!({
             "symbols": symbols,
         });
-        let response = self
+        let request = self
             .client
-            .request(reqwest::Method::POST, url)
+            .client()
+            .request(reqwest::Method::POST, &url)
             .headers(headers)
-            .json(&payload)
-            .send()
-            .await?;
+            .json(&payload);
+        let response = self.client.send_idempotent(request).await?;
 
-        let data: Vec<VCIMarketWatch> = response.json().await?;
+        let data: Vec<VCIMarketWatch> = decode_json(response).await?;
         Ok(MarketWatchResponse(data))
     }
 
-    pub async fn get_company_info(&self, symbol: &str, period: &str) -> Result<CompanyInfo, Error> {
-        let url = "https://trading.vietcap.com.vn/data-mt/graphql";
+    pub async fn get_company_info(&self, symbol: &str, period: &str) -> Result<CompanyInfo> {
+        let url = format!("{}/data-mt/graphql", self.base_url);
         let headers = get_headers("VCI");
 
         // Build JSON payload with the provided symbol
@@ -93,20 +107,20 @@ impl VCIExplorer {
             },
         });
 
-        let response = self
+        let request = self
             .client
-            .request(reqwest::Method::POST, url)
+            .client()
+            .request(reqwest::Method::POST, &url)
             .headers(headers)
-            .json(&payload)
-            .send()
-            .await?;
+            .json(&payload);
+        let response = self.client.send_idempotent(request).await?;
 
-        let data: CompanyInfo = response.json().await?;
+        let data: CompanyInfo = decode_json(response).await?;
         Ok(data)
     }
 
-    pub async fn get_order_list(&self, symbol: &str, limit: u32) -> Result<OrderList, Error> {
-        let url = "https://trading.vietcap.com.vn/api/market-watch/LEData/getAll";
+    pub async fn get_order_list(&self, symbol: &str, limit: u32) -> Result<OrderList> {
+        let url = format!("{}/api/market-watch/LEData/getAll", self.base_url);
         let headers = get_headers("VCI");
 
         // Build JSON payload with the provided symbol
@@ -115,15 +129,15 @@ impl VCIExplorer {
             "limit": limit
         });
 
-        let response = self
+        let request = self
             .client
-            .request(reqwest::Method::POST, url)
+            .client()
+            .request(reqwest::Method::POST, &url)
             .headers(headers)
-            .json(&payload)
-            .send()
-            .await?;
+            .json(&payload);
+        let response = self.client.send_idempotent(request).await?;
 
-        let data: Vec<OrderData> = response.json().await?;
+        let data: Vec<OrderData> = decode_json(response).await?;
         let converted_data = data
             .into_iter()
             .map(|od| VCIOderBook {
@@ -139,28 +153,5 @@ impl VCIExplorer {
 }
 
 #[cfg(test)]
-mod tests {
-    use super::*;
-    use reqwest::Client;
-
-    #[tokio::test]
-    async fn test_get_order_list() {
-        let client = Client::new();
-        let explorer = VCIExplorer::new(client);
-
-        // Use a known symbol and a small limit for testing
-        let symbol = "API";
-        let limit = 10000;
-
-        let result = explorer.get_order_list(symbol, limit).await;
-        assert!(result.is_ok(), "API call failed: {result:?}");
-
-        let orders = result.unwrap();
-        println!("output: {orders:?}");
-        assert!(!orders.is_empty(), "Order list should not be empty");
-        for order in &orders {
-            assert!(order.price > 0.0);
-            assert!(order.volume > 0);
-        }
-    }
-}
+#[path = "tests/explorer_test.rs"]
+mod explorer_test;
@@ -111,7 +111,7 @@ pub struct OHLCData {
     pub min_batch_trunc_time: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct Candlestick {
     pub timestamp: DateTime<Utc>,
     pub open: f64,
@@ -157,6 +157,29 @@ impl OHLCData {
 
         candlesticks
     }
+
+    /// Build the response shape back from candles, e.g. ones read from the
+    /// local store.
+    pub fn from_candlesticks(symbol: &str, candles: &[Candlestick]) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            o: candles.iter().map(|c| Some(c.open)).collect(),
+            h: candles.iter().map(|c| Some(c.high)).collect(),
+            l: candles.iter().map(|c| Some(c.low)).collect(),
+            c: candles.iter().map(|c| Some(c.close)).collect(),
+            v: candles.iter().map(|c| Some(c.volume)).collect(),
+            t: candles
+                .iter()
+                .map(|c| c.timestamp.timestamp().to_string())
+                .collect(),
+            accumulated_volume: vec![None; candles.len()],
+            accumulated_value: vec![None; candles.len()],
+            min_batch_trunc_time: candles
+                .first()
+                .map(|c| c.timestamp.timestamp().to_string())
+                .unwrap_or_default(),
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
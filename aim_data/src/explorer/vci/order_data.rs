@@ -1,6 +1,8 @@
 use serde::{Deserialize, Deserializer};
 use std::fmt;
 
+use crate::stream::Trade;
+
 pub type OrderList = Vec<VCIOderBook>;
 
 #[allow(dead_code)]
@@ -13,6 +15,18 @@ pub struct VCIOderBook {
     pub match_type: String,
 }
 
+impl From<&Trade> for VCIOderBook {
+    fn from(trade: &Trade) -> Self {
+        Self {
+            id: trade.id,
+            timestamp: trade.time.to_string(),
+            price: trade.price,
+            volume: trade.volume,
+            match_type: trade.match_type.clone(),
+        }
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, Deserialize)]
 pub struct OrderData {
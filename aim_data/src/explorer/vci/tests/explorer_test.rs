@@ -0,0 +1,108 @@
+use super::VCIExplorer;
+use crate::error::Error;
+use crate::fixture_server::{FixtureServer, Route, test_client};
+
+#[tokio::test]
+async fn test_get_market_watch_multiple_symbols() {
+    let server = FixtureServer::vci().await;
+    let explorer = VCIExplorer::with_base_url(test_client(), &server.url());
+
+    let market_watch = explorer.get_market_watch(&["VCI", "FPT"]).await.unwrap();
+    assert_eq!(market_watch.0.len(), 2, "Expected two stocks in response");
+
+    let symbols: Vec<&str> = market_watch
+        .0
+        .iter()
+        .map(|stock| stock.listing_info.symbol.as_str())
+        .collect();
+    assert_eq!(symbols, ["VCI", "FPT"]);
+
+    let stock = &market_watch.0[0];
+    assert_eq!(stock.match_price.match_price, 36200.0);
+    assert!(stock.bid_ask.bid_prices.iter().all(|v| v.price >= 0.0));
+    assert!(stock.bid_ask.ask_prices.iter().all(|v| v.price >= 0.0));
+
+    let request = &server.requests()[0];
+    assert_eq!(request.method, "POST");
+    assert_eq!(request.path, "/api/price/symbols/getList");
+    let body: serde_json::Value = serde_json::from_str(&request.body).unwrap();
+    assert_eq!(body["symbols"], serde_json::json!(["VCI", "FPT"]));
+}
+
+#[tokio::test]
+async fn test_get_quote_fills_missing_values() {
+    let server = FixtureServer::vci().await;
+    let explorer = VCIExplorer::with_base_url(test_client(), &server.url());
+
+    let response = explorer
+        .get_quote(&["VCI"], "ONE_DAY", None, None)
+        .await
+        .unwrap();
+    let candles = response.0[0].to_candlesticks();
+    assert_eq!(candles.len(), 3);
+    assert_eq!(candles[0].timestamp.timestamp(), 1727049600);
+    assert_eq!(candles[1].close, 35950.0);
+    // Null open and volume of the forming bar come back as zero
+    assert_eq!(candles[2].open, 0.0);
+    assert_eq!(candles[2].volume, 0);
+}
+
+#[tokio::test]
+async fn test_get_company_info() {
+    let server = FixtureServer::vci().await;
+    let explorer = VCIExplorer::with_base_url(test_client(), &server.url());
+
+    let info = explorer.get_company_info("VCI", "Y").await.unwrap();
+    let ratio = &info.data.company_financial_ratio.ratio[0];
+    assert_eq!(ratio.roe, Some(0.0806));
+    assert_eq!(ratio.pe, Some(31.2));
+
+    let body: serde_json::Value = serde_json::from_str(&server.requests()[0].body).unwrap();
+    assert_eq!(body["variables"]["ticker"], "VCI");
+    assert_eq!(body["variables"]["period"], "Y");
+}
+
+#[tokio::test]
+async fn test_get_order_list_accepts_strings_and_numbers() {
+    let server = FixtureServer::vci().await;
+    let explorer = VCIExplorer::with_base_url(test_client(), &server.url());
+
+    let orders = explorer.get_order_list("VCI", 30000).await.unwrap();
+    assert_eq!(orders.len(), 2);
+    assert_eq!(orders[0].id, 9102338);
+    assert_eq!(orders[0].price, 36200.0);
+    assert_eq!(orders[0].volume, 1500);
+    assert_eq!(orders[1].price, 36150.0);
+    assert_eq!(orders[1].volume, 200);
+}
+
+#[tokio::test]
+async fn test_malformed_body_is_a_decode_error() {
+    let server = FixtureServer::start(vec![Route::new(
+        "POST",
+        "/api/price/symbols/getList",
+        200,
+        "{\"not\": \"a list\"}".to_string(),
+    )])
+    .await;
+    let explorer = VCIExplorer::with_base_url(test_client(), &server.url());
+
+    let result = explorer.get_market_watch(&["VCI"]).await;
+    assert!(matches!(result, Err(Error::Decode { .. })), "{result:?}");
+}
+
+#[tokio::test]
+async fn test_server_error_is_transient() {
+    let server = FixtureServer::start(vec![Route::new(
+        "POST",
+        "/api/market-watch/LEData/getAll",
+        503,
+        String::new(),
+    )])
+    .await;
+    let explorer = VCIExplorer::with_base_url(test_client(), &server.url());
+
+    let error = explorer.get_order_list("VCI", 10).await.unwrap_err();
+    assert!(matches!(error, Error::Http { .. }), "{error:?}");
+    assert!(error.is_transient());
+}
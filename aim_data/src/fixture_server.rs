@@ -0,0 +1,340 @@
+//! Local stand-ins for the network: an HTTP server that serves the JSON
+//! fixtures under `fixtures/`, and a WebSocket server for the price feed.
+
+use futures_util::{SinkExt, StreamExt};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+
+use crate::http::{HttpClient, HttpConfig};
+use crate::stream::{ClientMessage, StreamEvent};
+
+/// Read `fixtures/<name>` from the crate root.
+pub fn fixture(name: &str) -> String {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("fixtures")
+        .join(name);
+    std::fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("missing fixture {}: {e}", path.display()))
+}
+
+/// Client without rate limiting or retries, so tests run fast and fail fast.
+pub fn test_client() -> HttpClient {
+    HttpClient::new(HttpConfig {
+        rate_per_host: 0.0,
+        max_retries: 0,
+        ..HttpConfig::default()
+    })
+    .unwrap()
+}
+
+/// Canned answer for one method and path.
+#[derive(Clone)]
+pub struct Route {
+    pub method: &'static str,
+    /// Path with query string, e.g. `/reports?source=9999`.
+    pub path: String,
+    pub status: u16,
+    pub body: String,
+}
+
+impl Route {
+    pub fn get(path: &str, fixture_name: &str) -> Self {
+        Self::new("GET", path, 200, fixture(fixture_name))
+    }
+
+    pub fn post(path: &str, fixture_name: &str) -> Self {
+        Self::new("POST", path, 200, fixture(fixture_name))
+    }
+
+    pub fn new(method: &'static str, path: &str, status: u16, body: String) -> Self {
+        Self {
+            method,
+            path: path.to_string(),
+            status,
+            body,
+        }
+    }
+}
+
+/// A request as received by the server.
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub struct FixtureServer {
+    addr: SocketAddr,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    handle: JoinHandle<()>,
+}
+
+impl FixtureServer {
+    /// Bind to a free local port and answer with `routes`; anything else gets a 404.
+    pub async fn start(routes: Vec<Route>) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let routes = Arc::new(routes);
+
+        let recorded = requests.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let routes = routes.clone();
+                let recorded = recorded.clone();
+                tokio::spawn(async move {
+                    if let Some(request) = serve(stream, &routes).await {
+                        recorded.lock().unwrap().push(request);
+                    }
+                });
+            }
+        });
+
+        Self {
+            addr,
+            requests,
+            handle,
+        }
+    }
+
+    /// Routes for every Vietcap endpoint used by [`crate::VCIExplorer`].
+    pub async fn vci() -> Self {
+        Self::start(vec![
+            Route::post("/api/chart/OHLCChart/gap", "vci/ohlc.json"),
+            Route::post("/api/price/symbols/getList", "vci/market_watch.json"),
+            Route::post("/data-mt/graphql", "vci/company_ratio.json"),
+            Route::post("/api/market-watch/LEData/getAll", "vci/order_list.json"),
+        ])
+        .await
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for FixtureServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Answer a single request and close the connection.
+async fn serve(mut stream: TcpStream, routes: &[Route]) -> Option<RecordedRequest> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 4096];
+    let head_end = loop {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            return None;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(pos) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos + 4;
+        }
+    };
+
+    let head = String::from_utf8_lossy(&buf[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next()?.split_whitespace();
+    let method = request_line.next()?.to_string();
+    let path = request_line.next()?.to_string();
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    let content_length = headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .unwrap_or(0);
+    while buf.len() < head_end + content_length {
+        let n = stream.read(&mut chunk).await.ok()?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+    }
+    let body = String::from_utf8_lossy(&buf[head_end..]).to_string();
+
+    let (status, response_body) = routes
+        .iter()
+        .find(|route| route.method == method && route.path == path)
+        .map(|route| (route.status, route.body.as_str()))
+        .unwrap_or((404, "not found"));
+    let response = format!(
+        "HTTP/1.1 {status} Fixture\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response_body}",
+        response_body.len()
+    );
+    stream.write_all(response.as_bytes()).await.ok()?;
+    stream.shutdown().await.ok();
+
+    Some(RecordedRequest {
+        method,
+        path,
+        headers,
+        body,
+    })
+}
+
+/// Poll `condition` until it holds, failing the test after five seconds.
+pub async fn wait_until(condition: impl Fn() -> bool) {
+    let deadline = tokio::time::Instant::now() + std::time::Duration::from_secs(5);
+    while !condition() {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "condition not met within 5s"
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+}
+
+enum StreamCommand {
+    Send(String),
+    Close,
+}
+
+#[derive(Default)]
+struct StreamState {
+    clients: Mutex<Vec<tokio::sync::mpsc::UnboundedSender<StreamCommand>>>,
+    messages: Mutex<Vec<ClientMessage>>,
+    authorizations: Mutex<Vec<Option<String>>>,
+}
+
+/// WebSocket stand-in for the price feed: records what clients send and
+/// pushes events to every connected client.
+pub struct StreamServer {
+    addr: SocketAddr,
+    state: Arc<StreamState>,
+    handle: JoinHandle<()>,
+}
+
+impl StreamServer {
+    pub async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let state = Arc::new(StreamState::default());
+
+        let shared = state.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve_stream(stream, shared.clone()));
+            }
+        });
+
+        Self {
+            addr,
+            state,
+            handle,
+        }
+    }
+
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.addr)
+    }
+
+    /// Send `events` as one text frame to every connected client.
+    pub fn push(&self, events: &[StreamEvent]) {
+        let text = serde_json::to_string(events).unwrap();
+        for client in self.state.clients.lock().unwrap().iter() {
+            let _ = client.send(StreamCommand::Send(text.clone()));
+        }
+    }
+
+    /// Close every connection, as a server restart would.
+    pub fn disconnect_all(&self) {
+        for client in self.state.clients.lock().unwrap().drain(..) {
+            let _ = client.send(StreamCommand::Close);
+        }
+    }
+
+    /// Messages received from clients, across all connections.
+    pub fn messages(&self) -> Vec<ClientMessage> {
+        self.state.messages.lock().unwrap().clone()
+    }
+
+    /// `Authorization` header of each accepted connection.
+    pub fn authorizations(&self) -> Vec<Option<String>> {
+        self.state.authorizations.lock().unwrap().clone()
+    }
+
+    pub fn connections(&self) -> usize {
+        self.state.authorizations.lock().unwrap().len()
+    }
+}
+
+impl Drop for StreamServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+// The handshake callback signature is fixed by tungstenite
+#[allow(clippy::result_large_err)]
+async fn serve_stream(stream: TcpStream, state: Arc<StreamState>) {
+    let mut authorization = None;
+    let callback = |request: &Request, response: Response| {
+        authorization = request
+            .headers()
+            .get("authorization")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        Ok(response)
+    };
+    let Ok(socket) = tokio_tungstenite::accept_hdr_async(stream, callback).await else {
+        return;
+    };
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    // Register before recording the connection so a test that saw the
+    // connection can push to it
+    state.clients.lock().unwrap().push(tx);
+    state.authorizations.lock().unwrap().push(authorization);
+
+    let (mut sink, mut source) = socket.split();
+    loop {
+        tokio::select! {
+            message = source.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    if let Ok(message) = serde_json::from_str(&text) {
+                        state.messages.lock().unwrap().push(message);
+                    }
+                }
+                Some(Ok(_)) => {}
+                _ => return,
+            },
+            command = rx.recv() => match command {
+                Some(StreamCommand::Send(text)) => {
+                    if sink.send(Message::Text(text.into())).await.is_err() {
+                        return;
+                    }
+                }
+                Some(StreamCommand::Close) | None => {
+                    let _ = sink.close().await;
+                    return;
+                }
+            },
+        }
+    }
+}
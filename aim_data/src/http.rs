@@ -0,0 +1,314 @@
+use reqwest::{Request, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::error::{Error, Result};
+use crate::tls::pinned_tls_config;
+
+/// Outgoing proxy, applied to every scheme.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProxyConfig {
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Comma separated hosts that bypass the proxy, as in `NO_PROXY`.
+    pub no_proxy: Option<String>,
+}
+
+impl ProxyConfig {
+    fn to_reqwest(&self) -> Result<reqwest::Proxy> {
+        let mut proxy = reqwest::Proxy::all(&self.url)
+            .map_err(|e| Error::Config(format!("invalid proxy url {:?}: {e}", self.url)))?;
+        if let Some(username) = &self.username {
+            proxy = proxy.basic_auth(username, self.password.as_deref().unwrap_or_default());
+        }
+        if let Some(no_proxy) = &self.no_proxy {
+            proxy = proxy.no_proxy(reqwest::NoProxy::from_string(no_proxy));
+        }
+        Ok(proxy)
+    }
+}
+
+/// Tuning knobs for [`HttpClient`].
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    pub connect_timeout: Duration,
+    /// Timeout for a whole request, body included.
+    pub request_timeout: Duration,
+    /// Retries after the first attempt, only for idempotent requests.
+    pub max_retries: u32,
+    /// Delay before the first retry; doubled on every further retry.
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    /// Sustained requests per second allowed towards a single host.
+    pub rate_per_host: f64,
+    /// Requests a host may receive in a burst before `rate_per_host` applies.
+    pub burst_per_host: u32,
+    pub pool_max_idle_per_host: usize,
+    pub pool_idle_timeout: Duration,
+    /// Skip TLS verification; ignored when a CA or a fingerprint is pinned.
+    pub accept_invalid_certs: bool,
+    /// PEM encoded CA that replaces the system roots.
+    pub root_ca_pem: Option<Vec<u8>>,
+    /// SHA-256 of the only server certificate to trust.
+    pub pinned_fingerprint: Option<[u8; 32]>,
+    pub proxy: Option<ProxyConfig>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(20),
+            max_retries: 3,
+            backoff_base: Duration::from_millis(250),
+            backoff_max: Duration::from_secs(5),
+            rate_per_host: 10.0,
+            burst_per_host: 10,
+            pool_max_idle_per_host: 8,
+            pool_idle_timeout: Duration::from_secs(90),
+            accept_invalid_certs: false,
+            root_ca_pem: None,
+            pinned_fingerprint: None,
+            proxy: None,
+        }
+    }
+}
+
+impl HttpConfig {
+    /// Delay before retry number `attempt` (1-based).
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.backoff_base
+            .saturating_mul(factor)
+            .min(self.backoff_max)
+    }
+}
+
+/// Token bucket guarding one host.
+#[derive(Debug)]
+struct TokenBucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(burst: u32, now: Instant) -> Self {
+        Self {
+            tokens: burst as f64,
+            last_refill: now,
+        }
+    }
+
+    /// Take one token, or return how long to wait until one is available.
+    fn try_take(&mut self, rate: f64, burst: u32, now: Instant) -> Option<Duration> {
+        let elapsed = now
+            .saturating_duration_since(self.last_refill)
+            .as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(burst as f64);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        }
+    }
+}
+
+struct Inner {
+    client: reqwest::Client,
+    config: HttpConfig,
+    buckets: Mutex<HashMap<String, TokenBucket>>,
+}
+
+/// Shared HTTP layer used by every fetcher in `aim_data`.
+///
+/// Cloning is cheap: clones share the connection pool and the per-host rate limiters.
+#[derive(Clone)]
+pub struct HttpClient {
+    inner: Arc<Inner>,
+}
+
+static SHARED: LazyLock<HttpClient> = LazyLock::new(HttpClient::default);
+
+impl HttpClient {
+    pub fn new(config: HttpConfig) -> Result<Self> {
+        let mut builder = reqwest::ClientBuilder::new()
+            .connect_timeout(config.connect_timeout)
+            .timeout(config.request_timeout)
+            .pool_max_idle_per_host(config.pool_max_idle_per_host)
+            .pool_idle_timeout(config.pool_idle_timeout)
+            .tcp_keepalive(Duration::from_secs(60));
+
+        if let Some(fingerprint) = config.pinned_fingerprint {
+            builder = builder.use_preconfigured_tls(pinned_tls_config(fingerprint)?);
+        } else if let Some(pem) = &config.root_ca_pem {
+            let ca = reqwest::Certificate::from_pem(pem)
+                .map_err(|e| Error::Config(format!("invalid CA certificate: {e}")))?;
+            builder = builder
+                .tls_built_in_root_certs(false)
+                .add_root_certificate(ca);
+        } else {
+            builder = builder.danger_accept_invalid_certs(config.accept_invalid_certs);
+        }
+
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(proxy.to_reqwest()?);
+        }
+
+        let client = builder.build()?;
+        Ok(Self::with_client(client, config))
+    }
+
+    /// Wrap an already configured `reqwest::Client`; only the retry and rate
+    /// limit settings of `config` are used.
+    pub fn with_client(client: reqwest::Client, config: HttpConfig) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                client,
+                config,
+                buckets: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// The process-wide client with the default configuration.
+    pub fn shared() -> Self {
+        SHARED.clone()
+    }
+
+    pub fn config(&self) -> &HttpConfig {
+        &self.inner.config
+    }
+
+    /// The underlying client, used to build requests.
+    pub fn client(&self) -> &reqwest::Client {
+        &self.inner.client
+    }
+
+    /// Send a request once, after waiting for the host's rate limiter.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response> {
+        let request = request.build()?;
+        self.acquire(&request).await;
+        Ok(self.inner.client.execute(request).await?)
+    }
+
+    /// Send a request that is safe to repeat, retrying transport failures,
+    /// 5xx and 429 answers with exponential backoff.
+    pub async fn send_idempotent(&self, request: RequestBuilder) -> Result<Response> {
+        let request = request.build()?;
+        let max_retries = self.inner.config.max_retries;
+        let mut attempt = 0;
+
+        loop {
+            // Streaming bodies cannot be cloned; send those once
+            let Some(current) = request.try_clone() else {
+                return self.execute_once(request).await;
+            };
+            self.acquire(&current).await;
+            let url = current.url().clone();
+
+            match self.inner.client.execute(current).await {
+                Ok(response) if is_retryable_status(response.status()) && attempt < max_retries => {
+                    log::warn!("{url} answered {}, retrying", response.status());
+                }
+                Ok(response) => return Ok(response),
+                Err(e) if is_retryable_error(&e) && attempt < max_retries => {
+                    log::warn!("request to {url} failed: {e}, retrying");
+                }
+                Err(e) => return Err(e.into()),
+            }
+
+            attempt += 1;
+            tokio::time::sleep(self.inner.config.backoff(attempt)).await;
+        }
+    }
+
+    async fn execute_once(&self, request: Request) -> Result<Response> {
+        self.acquire(&request).await;
+        Ok(self.inner.client.execute(request).await?)
+    }
+
+    /// Wait until the request's host has a token left.
+    async fn acquire(&self, request: &Request) {
+        let config = &self.inner.config;
+        if config.rate_per_host <= 0.0 {
+            return;
+        }
+        let host = request.url().host_str().unwrap_or_default().to_string();
+
+        loop {
+            let wait = {
+                let mut buckets = self.inner.buckets.lock().unwrap_or_else(|e| e.into_inner());
+                let now = Instant::now();
+                buckets
+                    .entry(host.clone())
+                    .or_insert_with(|| TokenBucket::new(config.burst_per_host, now))
+                    .try_take(config.rate_per_host, config.burst_per_host, now)
+            };
+            match wait {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => return,
+            }
+        }
+    }
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new(HttpConfig::default()).unwrap_or_else(|e| {
+            log::error!("Failed to build HTTP client, using reqwest defaults: {e}");
+            Self::with_client(reqwest::Client::new(), HttpConfig::default())
+        })
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS
+}
+
+/// Only errors of the network: a request that cannot be built or sent as
+/// it is fails the same way every time.
+fn is_retryable_error(e: &reqwest::Error) -> bool {
+    e.is_timeout() || e.is_connect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_max() {
+        let config = HttpConfig {
+            backoff_base: Duration::from_millis(100),
+            backoff_max: Duration::from_millis(500),
+            ..HttpConfig::default()
+        };
+        assert_eq!(config.backoff(1), Duration::from_millis(100));
+        assert_eq!(config.backoff(2), Duration::from_millis(200));
+        assert_eq!(config.backoff(3), Duration::from_millis(400));
+        assert_eq!(config.backoff(4), Duration::from_millis(500));
+        assert_eq!(config.backoff(40), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_token_bucket_limits_burst_then_refills() {
+        let start = Instant::now();
+        let mut bucket = TokenBucket::new(2, start);
+
+        assert!(bucket.try_take(4.0, 2, start).is_none());
+        assert!(bucket.try_take(4.0, 2, start).is_none());
+        let wait = bucket.try_take(4.0, 2, start).unwrap();
+        assert!((wait.as_secs_f64() - 0.25).abs() < 1e-9);
+
+        // A quarter second later one token is back, but not two
+        let later = start + Duration::from_millis(250);
+        assert!(bucket.try_take(4.0, 2, later).is_none());
+        assert!(bucket.try_take(4.0, 2, later).is_some());
+    }
+}
@@ -1,10 +1,28 @@
 // pub mod btc;
+pub mod alert;
+pub mod cache_file;
+pub mod calendar;
+pub mod config;
+pub mod corporate_actions;
+pub mod error;
 pub mod explorer;
+#[cfg(test)]
+mod fixture_server;
+pub mod http;
+pub mod provider;
+pub mod resample;
+pub mod store;
+pub mod stream;
+mod tls;
 
 // pub use btc::get_btc_price;
 use chrono::{DateTime, Utc};
 use explorer::vci::VCIOderBook;
-use reqwest::Client;
+
+pub use config::Config;
+pub use corporate_actions::{ActionKind, CorporateAction};
+pub use error::{Error, Result};
+pub use http::{HttpClient, HttpConfig, ProxyConfig};
 
 /// Get stock quote data for a given symbol
 ///
@@ -14,18 +32,16 @@ use reqwest::Client;
 /// * `end_time` - Optional end time for the data range
 ///
 /// # Returns
-/// * `Result<OHLCResponse, reqwest::Error>` - The OHLC data or an error
+/// * `Result<OHLCResponse>` - The OHLC data or an error
 ///
 /// # Example
-/// ```
-/// use aim_stock;
-/// use chrono::{DateTime, Utc};
-///
+/// ```no_run
 /// #[tokio::main]
 /// async fn main() {
-///     aim_stock::init();
-///     let data = aim_stock::get_quote("VCI", None, None).await.unwrap();
-///     
+///     let data = aim_data::get_quote(&["VCI"], "ONE_DAY", None, None)
+///         .await
+///         .unwrap();
+///
 ///     // Access the first (and only) symbol's data
 ///     if let Some(ohlc) = data.0.first() {
 ///         println!("Symbol: {}", ohlc.symbol);
@@ -42,40 +58,98 @@ pub async fn get_quote(
     time_frame: &str,
     start_time: Option<DateTime<Utc>>,
     end_time: Option<DateTime<Utc>>,
-) -> Result<explorer::vci::ohlc::OHLCResponse, reqwest::Error> {
-    let client = Client::new();
-    let explorer = explorer::VCIExplorer::new(client);
-    explorer
+) -> Result<explorer::vci::ohlc::OHLCResponse> {
+    provider()
         .get_quote(symbol, time_frame, start_time, end_time)
         .await
 }
 
+/// Candle history of one symbol, served from the local [`store`] when one is
+/// installed.
+///
+/// Gaps in the stored range are fetched from the provider first. When that
+/// fails, e.g. offline, whatever is stored is returned instead of the error.
+///
+/// # Arguments
+/// * `symbol` - Ticker, e.g. "VCI"
+/// * `time_frame` - Provider resolution, e.g. "ONE_DAY"
+/// * `start_time` / `end_time` - Optional bounds; `None` means unbounded / now
+///
+/// # Returns
+/// * `Result<Vec<Candlestick>>` - Candles sorted by time
+pub async fn get_history(
+    symbol: &str,
+    time_frame: &str,
+    start_time: Option<DateTime<Utc>>,
+    end_time: Option<DateTime<Utc>>,
+) -> Result<Vec<Candlestick>> {
+    let Some(store) = store::candle_store() else {
+        let data = get_quote(&[symbol], time_frame, start_time, end_time).await?;
+        return Ok(data
+            .0
+            .iter()
+            .flat_map(|ohlc| ohlc.to_candlesticks())
+            .collect());
+    };
+    let from = start_time.unwrap_or(DateTime::UNIX_EPOCH);
+    if let Err(e) = store
+        .backfill(provider().as_ref(), symbol, time_frame, from, end_time)
+        .await
+    {
+        let stored = store.range(symbol, time_frame, start_time, end_time)?;
+        if stored.is_empty() {
+            return Err(e);
+        }
+        log::warn!("Serving stored {symbol} {time_frame} history, backfill failed: {e}");
+        return Ok(stored);
+    }
+    store.range(symbol, time_frame, start_time, end_time)
+}
+
+/// Corporate actions of one symbol from the AIM server, merged with any
+/// imported into the local store.
+///
+/// When the server cannot be reached, the stored actions are returned.
+pub async fn get_corporate_actions(symbol: &str) -> Result<Vec<CorporateAction>> {
+    match explorer::aim::fetch_corporate_actions_data(symbol).await {
+        Ok(actions) if store::candle_store().is_some() => {
+            corporate_actions::store_actions(actions)?;
+            corporate_actions::stored_actions(symbol)
+        }
+        Ok(actions) => Ok(actions),
+        Err(e) => {
+            let stored = corporate_actions::stored_actions(symbol)?;
+            if stored.is_empty() && store::candle_store().is_none() {
+                return Err(e);
+            }
+            log::warn!("Using stored corporate actions of {symbol}: {e}");
+            Ok(stored)
+        }
+    }
+}
+
 pub async fn get_market_watch(
     symbols: &[&str],
-) -> Result<explorer::vci::market_watch::MarketWatchResponse, reqwest::Error> {
-    let client = Client::new();
-    let explorer = explorer::VCIExplorer::new(client);
-    explorer.get_market_watch(symbols).await
+) -> Result<explorer::vci::market_watch::MarketWatchResponse> {
+    provider().get_market_watch(symbols).await
 }
 
-pub async fn get_company_info(
-    symbols: &str,
-) -> Result<explorer::vci::company_info::CompanyInfo, reqwest::Error> {
-    let client = Client::new();
-    let explorer = explorer::VCIExplorer::new(client);
-    explorer.get_company_info(symbols, "Y").await
+pub async fn get_company_info(symbols: &str) -> Result<explorer::vci::company_info::CompanyInfo> {
+    provider().get_company_info(symbols, "Y").await
 }
 
 #[allow(dead_code)]
-pub async fn get_order_list(symbol: &str) -> Result<Vec<VCIOderBook>, reqwest::Error> {
-    let client = Client::new();
-    let explorer = explorer::VCIExplorer::new(client);
-    explorer.get_order_list(symbol, 30000).await
+pub async fn get_order_list(symbol: &str) -> Result<Vec<VCIOderBook>> {
+    provider().get_order_list(symbol, 30000).await
 }
 
 /// Re-export types for direct usage
 pub use explorer::vci::ohlc::{Candlestick, OHLCData};
 pub use explorer::*;
+pub use provider::{MarketDataProvider, ProviderFuture, provider, provider_by_name, set_provider};
+pub use resample::{Timeframe, resample};
+pub use store::{CandleStore, candle_store, set_candle_store};
+pub use stream::{MarketStream, StreamEvent, market_stream, set_market_stream};
 
 // mod test {
 //     #[cfg(test)]
@@ -0,0 +1,336 @@
+use chrono::{DateTime, Utc};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, LazyLock, RwLock};
+
+use crate::error::Result;
+use crate::explorer::aim::{AimExplorer, StockByGics, aim_server};
+use crate::explorer::vci::company_info::{
+    CompanyFinancialRatio, CompanyFinancialRatioData, CompanyFinancialRatioPeriod, CompanyInfo,
+};
+use crate::explorer::vci::market_watch::{
+    BidAsk, ListingInfo, MarketWatchResponse, MatchPrice, VCIMarketWatch,
+};
+use crate::explorer::vci::ohlc::OHLCResponse;
+use crate::explorer::vci::{OrderList, VCIExplorer};
+use crate::http::HttpClient;
+
+/// Boxed future returned by every [`MarketDataProvider`] method.
+///
+/// The trait is used behind `Arc<dyn MarketDataProvider>`, so the futures are
+/// boxed by hand instead of relying on `async fn` in traits.
+pub type ProviderFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T>> + Send + 'a>>;
+
+/// A source of market data: quotes, market watch, company ratios and the trade tape.
+///
+/// The app installs one provider at startup with [`set_provider`]; the `get_*`
+/// helpers at the crate root always go through the installed provider.
+pub trait MarketDataProvider: Send + Sync {
+    /// Short identifier used in logs and in the startup selection (e.g. "vci").
+    fn name(&self) -> &'static str;
+
+    /// OHLC history for one or more symbols.
+    fn get_quote<'a>(
+        &'a self,
+        symbols: &'a [&'a str],
+        time_frame: &'a str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+    ) -> ProviderFuture<'a, OHLCResponse>;
+
+    /// Latest price board snapshot for the given symbols.
+    fn get_market_watch<'a>(
+        &'a self,
+        symbols: &'a [&'a str],
+    ) -> ProviderFuture<'a, MarketWatchResponse>;
+
+    /// Financial ratios of a company for the given period ("Y" or "Q").
+    fn get_company_info<'a>(
+        &'a self,
+        symbol: &'a str,
+        period: &'a str,
+    ) -> ProviderFuture<'a, CompanyInfo>;
+
+    /// Matched trades of the current session, newest first.
+    fn get_order_list<'a>(&'a self, symbol: &'a str, limit: u32) -> ProviderFuture<'a, OrderList>;
+}
+
+impl MarketDataProvider for VCIExplorer {
+    fn name(&self) -> &'static str {
+        "vci"
+    }
+
+    fn get_quote<'a>(
+        &'a self,
+        symbols: &'a [&'a str],
+        time_frame: &'a str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+    ) -> ProviderFuture<'a, OHLCResponse> {
+        Box::pin(VCIExplorer::get_quote(
+            self, symbols, time_frame, start_time, end_time,
+        ))
+    }
+
+    fn get_market_watch<'a>(
+        &'a self,
+        symbols: &'a [&'a str],
+    ) -> ProviderFuture<'a, MarketWatchResponse> {
+        Box::pin(VCIExplorer::get_market_watch(self, symbols))
+    }
+
+    fn get_company_info<'a>(
+        &'a self,
+        symbol: &'a str,
+        period: &'a str,
+    ) -> ProviderFuture<'a, CompanyInfo> {
+        Box::pin(VCIExplorer::get_company_info(self, symbol, period))
+    }
+
+    fn get_order_list<'a>(&'a self, symbol: &'a str, limit: u32) -> ProviderFuture<'a, OrderList> {
+        Box::pin(VCIExplorer::get_order_list(self, symbol, limit))
+    }
+}
+
+/// The AIM server serves fundamentals and the GICS price board, but neither
+/// chart history nor the trade tape, so those two go to Vietcap.
+impl MarketDataProvider for AimExplorer {
+    fn name(&self) -> &'static str {
+        "aim"
+    }
+
+    fn get_quote<'a>(
+        &'a self,
+        symbols: &'a [&'a str],
+        time_frame: &'a str,
+        start_time: Option<DateTime<Utc>>,
+        end_time: Option<DateTime<Utc>>,
+    ) -> ProviderFuture<'a, OHLCResponse> {
+        Box::pin(
+            self.vci()
+                .get_quote(symbols, time_frame, start_time, end_time),
+        )
+    }
+
+    fn get_market_watch<'a>(
+        &'a self,
+        symbols: &'a [&'a str],
+    ) -> ProviderFuture<'a, MarketWatchResponse> {
+        Box::pin(async move {
+            let board = self.get_stock_by_gics().await?;
+            // Keep the order of the requested symbols, like the VCI endpoint does
+            let data = symbols
+                .iter()
+                .filter_map(|symbol| board.iter().find(|stock| stock.stock_code == *symbol))
+                .map(stock_by_gics_to_market_watch)
+                .collect();
+            Ok(MarketWatchResponse(data))
+        })
+    }
+
+    fn get_company_info<'a>(
+        &'a self,
+        symbol: &'a str,
+        _period: &'a str,
+    ) -> ProviderFuture<'a, CompanyInfo> {
+        // financial-data has no period filter; the latest report comes first
+        Box::pin(async move {
+            let mut reports = self.get_financial_data(symbol).await?;
+            reports.sort_by_key(|report| std::cmp::Reverse((report.year, report.quarter)));
+
+            let ratio = reports
+                .iter()
+                .map(|report| CompanyFinancialRatio {
+                    roe: report.financial_values.roe,
+                    roic: None,
+                    roa: report.financial_values.roa,
+                    pe: report.financial_values.pe,
+                    pb: report.financial_values.pb,
+                    eps: report.financial_values.basic_eps,
+                })
+                .collect();
+            let period = reports
+                .iter()
+                .map(|report| format!("{}-{}", report.year, report.quarter))
+                .collect();
+
+            Ok(CompanyInfo {
+                data: CompanyFinancialRatioData {
+                    company_financial_ratio: CompanyFinancialRatioPeriod { ratio, period },
+                },
+            })
+        })
+    }
+
+    fn get_order_list<'a>(&'a self, symbol: &'a str, limit: u32) -> ProviderFuture<'a, OrderList> {
+        Box::pin(self.vci().get_order_list(symbol, limit))
+    }
+}
+
+/// The GICS board carries no intraday high/low, so they are bounded by the open and last price.
+fn stock_by_gics_to_market_watch(stock: &StockByGics) -> VCIMarketWatch {
+    VCIMarketWatch {
+        listing_info: ListingInfo {
+            code: stock.stock_code.clone(),
+            symbol: stock.stock_code.clone(),
+            ceiling: stock.ceiling_price as f64,
+            floor: stock.floor_price as f64,
+            ref_price: stock.basic_price as f64,
+            board: String::new(),
+            en_organ_name: stock.stockname.clone(),
+            en_organ_short_name: stock.stockname.clone(),
+            organ_name: stock.stockname.clone(),
+            organ_short_name: stock.stockname.clone(),
+            ticker: stock.stock_code.clone(),
+            trading_date: String::new(),
+        },
+        bid_ask: BidAsk {
+            code: stock.stock_code.clone(),
+            symbol: stock.stock_code.clone(),
+            bid_prices: vec![],
+            ask_prices: vec![],
+        },
+        match_price: MatchPrice {
+            code: stock.stock_code.clone(),
+            symbol: stock.stock_code.clone(),
+            match_price: stock.last_price as f64,
+            match_vol: 0,
+            accumulated_volume: stock.total_vol,
+            highest: stock.open_price.max(stock.last_price) as f64,
+            lowest: stock.open_price.min(stock.last_price) as f64,
+            reference_price: stock.basic_price as f64,
+        },
+    }
+}
+
+static PROVIDER: LazyLock<RwLock<Arc<dyn MarketDataProvider>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(VCIExplorer::new(HttpClient::shared()))));
+
+/// Build a provider from its [`MarketDataProvider::name`].
+///
+/// # Arguments
+/// * `name` - Provider name, e.g. "vci" or "aim"
+/// * `client` - Client for the broker endpoints; the AIM server keeps its own
+///
+/// # Returns
+/// * `None` if no provider with that name is known
+pub fn provider_by_name(name: &str, client: HttpClient) -> Option<Arc<dyn MarketDataProvider>> {
+    match name.to_lowercase().as_str() {
+        "vci" => Some(Arc::new(VCIExplorer::new(client))),
+        "aim" => Some(Arc::new(AimExplorer::new(
+            aim_server(),
+            VCIExplorer::new(client),
+        ))),
+        _ => None,
+    }
+}
+
+/// Install the provider used by the crate-level `get_*` functions.
+pub fn set_provider(provider: Arc<dyn MarketDataProvider>) {
+    let mut current = PROVIDER.write().unwrap_or_else(|e| e.into_inner());
+    *current = provider;
+}
+
+/// The provider currently installed; defaults to [`VCIExplorer`].
+pub fn provider() -> Arc<dyn MarketDataProvider> {
+    PROVIDER.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::explorer::vci::VCIOderBook;
+
+    struct ReplayProvider;
+
+    impl MarketDataProvider for ReplayProvider {
+        fn name(&self) -> &'static str {
+            "replay"
+        }
+
+        fn get_quote<'a>(
+            &'a self,
+            symbols: &'a [&'a str],
+            _time_frame: &'a str,
+            _start_time: Option<DateTime<Utc>>,
+            _end_time: Option<DateTime<Utc>>,
+        ) -> ProviderFuture<'a, OHLCResponse> {
+            Box::pin(async move {
+                let data = symbols
+                    .iter()
+                    .map(|symbol| crate::OHLCData {
+                        symbol: symbol.to_string(),
+                        o: vec![Some(1.0)],
+                        h: vec![Some(2.0)],
+                        l: vec![Some(0.5)],
+                        c: vec![Some(1.5)],
+                        v: vec![Some(100)],
+                        t: vec!["1700000000".to_string()],
+                        accumulated_volume: vec![Some(100)],
+                        accumulated_value: vec![Some(150.0)],
+                        min_batch_trunc_time: String::new(),
+                    })
+                    .collect();
+                Ok(OHLCResponse(data))
+            })
+        }
+
+        fn get_market_watch<'a>(
+            &'a self,
+            _symbols: &'a [&'a str],
+        ) -> ProviderFuture<'a, MarketWatchResponse> {
+            Box::pin(async { Ok(MarketWatchResponse(vec![])) })
+        }
+
+        fn get_company_info<'a>(
+            &'a self,
+            _symbol: &'a str,
+            _period: &'a str,
+        ) -> ProviderFuture<'a, CompanyInfo> {
+            Box::pin(async {
+                Ok(CompanyInfo {
+                    data: CompanyFinancialRatioData {
+                        company_financial_ratio: CompanyFinancialRatioPeriod {
+                            ratio: vec![],
+                            period: vec![],
+                        },
+                    },
+                })
+            })
+        }
+
+        fn get_order_list<'a>(
+            &'a self,
+            symbol: &'a str,
+            _limit: u32,
+        ) -> ProviderFuture<'a, OrderList> {
+            Box::pin(async move {
+                Ok(vec![VCIOderBook {
+                    id: 1,
+                    timestamp: "1700000000".to_string(),
+                    price: 10_000.0,
+                    volume: 100,
+                    match_type: format!("{symbol}-buy"),
+                }])
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_crate_helpers_use_installed_provider() {
+        set_provider(Arc::new(ReplayProvider));
+        assert_eq!(provider().name(), "replay");
+
+        let quote = crate::get_quote(&["AAA", "VCI"], "ONE_DAY", None, None)
+            .await
+            .unwrap();
+        assert_eq!(quote.0.len(), 2);
+        assert_eq!(quote.0[1].symbol, "VCI");
+
+        let orders = crate::get_order_list("AAA").await.unwrap();
+        assert_eq!(orders[0].match_type, "AAA-buy");
+
+        set_provider(provider_by_name("vci", HttpClient::shared()).unwrap());
+        assert_eq!(provider().name(), "vci");
+    }
+}
@@ -0,0 +1,304 @@
+//! Aggregate candles into coarser time frames.
+//!
+//! Intraday bars are anchored at the open of each trading session and never
+//! span the lunch break, so a 30m chart has bars at 9:00 ... 11:00 and
+//! 13:00 ... 14:30, and a 4H chart has one bar per session.
+
+use chrono::{DateTime, Datelike, Utc};
+use std::fmt;
+
+use crate::calendar::{minute_of_day, session_of, to_local, trading_day_number};
+use crate::explorer::vci::ohlc::Candlestick;
+
+/// Time frame selected on a chart, e.g. `15m`, `4H`, `2D`, `1W` or `1M`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Timeframe {
+    Minutes(u32),
+    Hours(u32),
+    Days(u32),
+    Weeks(u32),
+    Months(u32),
+}
+
+impl Default for Timeframe {
+    fn default() -> Self {
+        Timeframe::DAILY
+    }
+}
+
+impl fmt::Display for Timeframe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Timeframe::Minutes(n) => write!(f, "{n}m"),
+            Timeframe::Hours(n) => write!(f, "{n}H"),
+            Timeframe::Days(n) => write!(f, "{n}D"),
+            Timeframe::Weeks(n) => write!(f, "{n}W"),
+            Timeframe::Months(n) => write!(f, "{n}M"),
+        }
+    }
+}
+
+impl Timeframe {
+    pub const DAILY: Timeframe = Timeframe::Days(1);
+
+    /// Parse a label as shown in the time frame picker.
+    ///
+    /// Note the case: `m` is minutes and `M` is months.
+    pub fn parse(label: &str) -> Option<Self> {
+        let label = label.trim();
+        let unit = label.chars().last()?;
+        let count: u32 = label[..label.len() - unit.len_utf8()].parse().ok()?;
+        if count == 0 {
+            return None;
+        }
+        match unit {
+            'm' => Some(Timeframe::Minutes(count)),
+            'H' | 'h' => Some(Timeframe::Hours(count)),
+            'D' | 'd' => Some(Timeframe::Days(count)),
+            'W' | 'w' => Some(Timeframe::Weeks(count)),
+            'M' => Some(Timeframe::Months(count)),
+            _ => None,
+        }
+    }
+
+    /// Resolution to request from the provider before resampling.
+    pub fn base_resolution(&self) -> &'static str {
+        match self {
+            Timeframe::Minutes(_) => "ONE_MINUTE",
+            Timeframe::Hours(_) => "ONE_HOUR",
+            Timeframe::Days(_) | Timeframe::Weeks(_) | Timeframe::Months(_) => "ONE_DAY",
+        }
+    }
+
+    /// Whether bars of the base resolution are already the requested bars.
+    pub fn is_base(&self) -> bool {
+        matches!(
+            self,
+            Timeframe::Minutes(1) | Timeframe::Hours(1) | Timeframe::Days(1)
+        )
+    }
+}
+
+/// Which bar a candle falls into. Equal keys on consecutive candles mean
+/// the same bar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BucketKey {
+    Intraday { day: i32, session: u32, slot: u32 },
+    Ordinal(i64),
+}
+
+/// Aggregate `candles`, sorted by time, into bars of `timeframe`.
+///
+/// Each bar is stamped with the time of its first candle, so fetching again
+/// from the last bar's timestamp reproduces that bar. N-day bars count
+/// trading days from a fixed Monday, see
+/// [`trading_day_number`](crate::calendar::trading_day_number), so they line
+/// up whichever candle a fetch starts from. Candles without a trade (close
+/// of zero) are skipped.
+///
+/// # Arguments
+/// * `candles` - Bars of [`Timeframe::base_resolution`]
+/// * `timeframe` - Requested time frame
+///
+/// # Returns
+/// * `Vec<Candlestick>` - One candlestick per bar
+pub fn resample(candles: &[Candlestick], timeframe: Timeframe) -> Vec<Candlestick> {
+    let mut bars: Vec<Candlestick> = Vec::new();
+    let mut current: Option<BucketKey> = None;
+
+    for candle in candles.iter().filter(|candle| candle.close > 0.0) {
+        let key = bucket_key(candle.timestamp, timeframe);
+
+        match bars.last_mut() {
+            Some(bar) if current == Some(key) => {
+                bar.high = bar.high.max(candle.high);
+                if candle.low > 0.0 {
+                    bar.low = if bar.low > 0.0 {
+                        bar.low.min(candle.low)
+                    } else {
+                        candle.low
+                    };
+                }
+                bar.close = candle.close;
+                bar.volume += candle.volume;
+            }
+            _ => {
+                current = Some(key);
+                let mut bar = candle.clone();
+                // A missing open falls back to the first price we know
+                if bar.open <= 0.0 {
+                    bar.open = bar.close;
+                }
+                bars.push(bar);
+            }
+        }
+    }
+    bars
+}
+
+fn bucket_key(time: DateTime<Utc>, timeframe: Timeframe) -> BucketKey {
+    let date = to_local(time).date_naive();
+    let intraday = |size: u32| {
+        let minute = minute_of_day(time);
+        let session = session_of(minute);
+        // The 11:30 and 15:00 closing prints join the last bar of their session
+        let offset = minute.clamp(session.open, session.close - 1) - session.open;
+        BucketKey::Intraday {
+            day: date.num_days_from_ce(),
+            session: session.open,
+            slot: offset / size.max(1),
+        }
+    };
+    match timeframe {
+        Timeframe::Minutes(n) => intraday(n),
+        Timeframe::Hours(n) => intraday(n * 60),
+        Timeframe::Days(n) => BucketKey::Ordinal(trading_day_number(date).div_euclid(n as i64)),
+        Timeframe::Weeks(n) => {
+            // Day 1 of the common era is a Monday
+            let week = (date.num_days_from_ce() as i64 - 1).div_euclid(7);
+            BucketKey::Ordinal(week.div_euclid(n as i64))
+        }
+        Timeframe::Months(n) => {
+            let month = date.year() as i64 * 12 + date.month0() as i64;
+            BucketKey::Ordinal(month.div_euclid(n as i64))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-09-25 00:00 in Hanoi.
+    const DAY_START: i64 = 1727222400 - 7 * 3600;
+
+    fn candle(local_minute: i64, price: f64, volume: i64) -> Candlestick {
+        Candlestick {
+            timestamp: DateTime::from_timestamp(DAY_START + local_minute * 60, 0).unwrap(),
+            open: price,
+            high: price + 1.0,
+            low: price - 1.0,
+            close: price,
+            volume,
+        }
+    }
+
+    fn daily(days: i64, price: f64) -> Candlestick {
+        candle(days * 24 * 60, price, 10)
+    }
+
+    fn local_minutes(bars: &[Candlestick]) -> Vec<u32> {
+        bars.iter()
+            .map(|bar| minute_of_day(bar.timestamp))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_labels() {
+        assert_eq!(Timeframe::parse("15m"), Some(Timeframe::Minutes(15)));
+        assert_eq!(Timeframe::parse("4H"), Some(Timeframe::Hours(4)));
+        assert_eq!(Timeframe::parse("1M"), Some(Timeframe::Months(1)));
+        assert_eq!(Timeframe::parse("2W"), Some(Timeframe::Weeks(2)));
+        assert_eq!(Timeframe::parse("0D"), None);
+        assert_eq!(Timeframe::parse("D"), None);
+        assert_eq!(Timeframe::Minutes(30).base_resolution(), "ONE_MINUTE");
+        assert_eq!(Timeframe::Months(1).base_resolution(), "ONE_DAY");
+        assert_eq!(Timeframe::Hours(2).to_string(), "2H");
+    }
+
+    #[test]
+    fn test_thirty_minutes_stop_at_lunch() {
+        let candles = [
+            candle(9 * 60, 100.0, 1),
+            candle(9 * 60 + 29, 101.0, 2),
+            candle(9 * 60 + 30, 102.0, 3),
+            candle(11 * 60 + 29, 103.0, 4),
+            candle(11 * 60 + 30, 104.0, 5),
+            candle(13 * 60, 105.0, 6),
+        ];
+        let bars = resample(&candles, Timeframe::Minutes(30));
+        assert_eq!(
+            local_minutes(&bars),
+            [9 * 60, 9 * 60 + 30, 11 * 60 + 29, 13 * 60]
+        );
+        assert_eq!(bars[0].volume, 3);
+        assert_eq!(bars[0].close, 101.0);
+        // The 11:30 closing print belongs to the 11:00 bar
+        assert_eq!(bars[2].volume, 9);
+        assert_eq!(bars[2].close, 104.0);
+    }
+
+    #[test]
+    fn test_four_hours_is_one_bar_per_session() {
+        let candles = [
+            candle(9 * 60, 100.0, 1),
+            candle(10 * 60, 110.0, 1),
+            candle(11 * 60, 90.0, 1),
+            candle(13 * 60, 95.0, 1),
+            candle(14 * 60, 97.0, 1),
+        ];
+        let bars = resample(&candles, Timeframe::Hours(4));
+        assert_eq!(bars.len(), 2);
+        assert_eq!((bars[0].open, bars[0].close), (100.0, 90.0));
+        assert_eq!((bars[0].high, bars[0].low), (111.0, 89.0));
+        assert_eq!(bars[1].volume, 2);
+    }
+
+    #[test]
+    fn test_days_weeks_and_months() {
+        // Wed 25, Thu 26, Fri 27 Sep, Mon 30 Sep, Tue 1 Oct
+        let candles = [
+            daily(0, 10.0),
+            daily(1, 11.0),
+            daily(2, 12.0),
+            daily(5, 13.0),
+            daily(6, 14.0),
+        ];
+
+        let two_days = resample(&candles, Timeframe::Days(2));
+        let closes: Vec<f64> = two_days.iter().map(|bar| bar.close).collect();
+        assert_eq!(closes, [11.0, 13.0, 14.0]);
+
+        let weeks = resample(&candles, Timeframe::Weeks(1));
+        assert_eq!(weeks.len(), 2);
+        assert_eq!(weeks[0].volume, 30);
+        assert_eq!(weeks[1].open, 13.0);
+
+        let months = resample(&candles, Timeframe::Months(1));
+        assert_eq!(months.len(), 2);
+        assert_eq!(months[0].close, 13.0);
+    }
+
+    #[test]
+    fn test_refetch_from_last_bar_reproduces_it() {
+        let candles: Vec<Candlestick> = (0..7).map(|d| daily(d, 10.0 + d as f64)).collect();
+        let full = resample(&candles, Timeframe::Days(3));
+        let last = full.last().unwrap();
+        let tail: Vec<Candlestick> = candles
+            .iter()
+            .filter(|c| c.timestamp >= last.timestamp)
+            .cloned()
+            .collect();
+        let again = resample(&tail, Timeframe::Days(3));
+        assert_eq!(again.len(), 1);
+        assert_eq!(again[0].timestamp, last.timestamp);
+        assert_eq!(again[0].close, last.close);
+    }
+
+    #[test]
+    fn test_n_day_bars_do_not_depend_on_the_first_candle() {
+        // Wed 25 Sep to Fri 4 Oct, skipping the weekend
+        let candles: Vec<Candlestick> = [0, 1, 2, 5, 6, 7, 8, 9]
+            .into_iter()
+            .map(|d| daily(d, 10.0 + d as f64))
+            .collect();
+        let full = resample(&candles, Timeframe::Days(3));
+        let later = resample(&candles[1..], Timeframe::Days(3));
+        let starts = |bars: &[Candlestick]| -> Vec<i64> {
+            bars.iter().map(|bar| bar.timestamp.timestamp()).collect()
+        };
+        // Past the first, partial bar they start on the same candles
+        assert!(later.len() > 1);
+        assert!(starts(&full).ends_with(&starts(&later)[1..]));
+    }
+}
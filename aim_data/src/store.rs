@@ -0,0 +1,671 @@
+//! On-disk candle history, one append-only series per (symbol, resolution).
+//!
+//! Each series lives under `<root>/<SYMBOL>/` as two files:
+//!
+//! * `<RESOLUTION>.bin` - fixed-size candle records. A record for a timestamp
+//!   that is already stored replaces it on read, so the still-forming bar of
+//!   the day is simply appended again. Bars that did not change are not
+//!   appended, and the file is compacted once superseded records outnumber
+//!   the bars, so polling the same bars does not grow it.
+//! * `<RESOLUTION>.ranges` - time ranges already fetched from the provider.
+//!   Anything outside them is a gap, which is how holidays and trading halts
+//!   (no bars, but covered) differ from days the app never fetched.
+
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
+
+use crate::error::{Error, Result};
+use crate::explorer::vci::ohlc::Candlestick;
+use crate::provider::MarketDataProvider;
+
+const MAGIC: &[u8; 4] = b"AIMC";
+const FORMAT_VERSION: u32 = 1;
+const HEADER_LEN: usize = 8;
+/// timestamp, open, high, low, close, volume
+const RECORD_LEN: usize = 8 * 6;
+const RANGE_LEN: usize = 8 * 2;
+/// Superseded records tolerated on top of one per bar before an append
+/// compacts the series
+const COMPACT_SLACK: usize = 64;
+
+/// A time range, in UTC seconds, with no stored coverage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gap {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+/// Candle history persisted under a root directory, usually `<cache>/Aim/candles`.
+pub struct CandleStore {
+    root: PathBuf,
+    // Serializes appends and compaction; reads go straight to the files
+    write_lock: Mutex<()>,
+}
+
+impl CandleStore {
+    /// Open the store at `root`, creating the directory when missing.
+    pub fn open(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root).map_err(|e| Error::cache_io(root.display().to_string(), e))?;
+        Ok(Self {
+            root,
+            write_lock: Mutex::new(()),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Symbols with at least one stored series, sorted.
+    pub fn symbols(&self) -> Result<Vec<String>> {
+        let io_error = |e| Error::cache_io(self.root.display().to_string(), e);
+        let mut symbols = Vec::new();
+        for entry in fs::read_dir(&self.root).map_err(io_error)? {
+            let entry = entry.map_err(io_error)?;
+            if entry.file_type().map_err(io_error)?.is_dir() {
+                symbols.push(entry.file_name().to_string_lossy().into_owned());
+            }
+        }
+        symbols.sort();
+        Ok(symbols)
+    }
+
+    /// Directory holding every series of `symbol`.
+    pub fn series_dir(&self, symbol: &str) -> PathBuf {
+        self.root.join(symbol.to_uppercase())
+    }
+
+    fn candle_path(&self, symbol: &str, resolution: &str) -> PathBuf {
+        self.series_dir(symbol).join(format!("{resolution}.bin"))
+    }
+
+    fn range_path(&self, symbol: &str, resolution: &str) -> PathBuf {
+        self.series_dir(symbol).join(format!("{resolution}.ranges"))
+    }
+
+    /// Stored candles of a series between `from` and `to`, both inclusive.
+    ///
+    /// # Arguments
+    /// * `symbol` - Ticker, case-insensitive
+    /// * `resolution` - Provider resolution such as `ONE_DAY`
+    /// * `from` / `to` - Optional bounds; `None` means unbounded
+    ///
+    /// # Returns
+    /// * `Result<Vec<Candlestick>>` - Candles sorted by time, one per timestamp
+    pub fn range(
+        &self,
+        symbol: &str,
+        resolution: &str,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Candlestick>> {
+        let candles = self.read_series(symbol, resolution)?;
+        Ok(candles
+            .into_values()
+            .filter(|candle| from.is_none_or(|from| candle.timestamp >= from))
+            .filter(|candle| to.is_none_or(|to| candle.timestamp <= to))
+            .collect())
+    }
+
+    /// Time of the newest stored candle of a series.
+    pub fn last_time(&self, symbol: &str, resolution: &str) -> Result<Option<DateTime<Utc>>> {
+        Ok(self
+            .read_series(symbol, resolution)?
+            .into_values()
+            .next_back()
+            .map(|candle| candle.timestamp))
+    }
+
+    /// Append candles to a series. Bars without a trade, and bars stored
+    /// with the same values already, are not written.
+    pub fn append(&self, symbol: &str, resolution: &str, candles: &[Candlestick]) -> Result<()> {
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let (mut stored, records) = self.read_records(symbol, resolution)?;
+        let mut buf = Vec::with_capacity(candles.len() * RECORD_LEN);
+        let mut written = 0;
+        for candle in candles.iter().filter(|candle| candle.close > 0.0) {
+            let timestamp = candle.timestamp.timestamp();
+            if stored.get(&timestamp) != Some(candle) {
+                write_record(&mut buf, candle);
+                stored.insert(timestamp, candle.clone());
+                written += 1;
+            }
+        }
+        if buf.is_empty() {
+            return Ok(());
+        }
+
+        if records + written > 2 * stored.len() + COMPACT_SLACK {
+            return self.rewrite(symbol, resolution, &stored);
+        }
+        let path = self.candle_path(symbol, resolution);
+        let mut file = open_append(&path, &header(), RECORD_LEN)?;
+        file.write_all(&buf)
+            .map_err(|e| Error::cache_io(path.display().to_string(), e))
+    }
+
+    /// Record that `from..=to` was fetched from the provider.
+    ///
+    /// When the range merges with those already stored, the ranges are
+    /// rewritten merged, so backfilling the same series again and again does
+    /// not grow the file.
+    pub fn mark_covered(
+        &self,
+        symbol: &str,
+        resolution: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<()> {
+        if to < from {
+            return Ok(());
+        }
+        let range = (from.timestamp(), to.timestamp());
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let path = self.range_path(symbol, resolution);
+        let (mut ranges, records) = self.read_ranges(symbol, resolution)?;
+        ranges.push(range);
+        let merged = merge_ranges(ranges);
+        if merged.len() <= records {
+            return replace_file(&path, &range_bytes(&merged));
+        }
+        let mut file = open_append(&path, &[], RANGE_LEN)?;
+        file.write_all(&range_bytes(&[range]))
+            .map_err(|e| Error::cache_io(path.display().to_string(), e))
+    }
+
+    /// Parts of `from..=to` that were never fetched, oldest first.
+    pub fn gaps(
+        &self,
+        symbol: &str,
+        resolution: &str,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<Gap>> {
+        let (covered, _) = self.read_ranges(symbol, resolution)?;
+        let (from, to) = (from.timestamp(), to.timestamp());
+        let mut gaps = Vec::new();
+        let mut cursor = from;
+        for (start, end) in covered {
+            if end < cursor {
+                continue;
+            }
+            if start > to {
+                break;
+            }
+            if start > cursor {
+                gaps.push((cursor, start - 1));
+            }
+            cursor = cursor.max(end.saturating_add(1));
+        }
+        if cursor <= to {
+            gaps.push((cursor, to));
+        }
+        Ok(gaps
+            .into_iter()
+            .filter_map(|(from, to)| {
+                Some(Gap {
+                    from: DateTime::from_timestamp(from, 0)?,
+                    to: DateTime::from_timestamp(to, 0)?,
+                })
+            })
+            .collect())
+    }
+
+    /// Fetch every gap of `from..=to` from `provider` and store the result.
+    ///
+    /// With `to` left open the range ends now. That last gap is only covered
+    /// up to its newest bar, so the forming bar is fetched again next time.
+    ///
+    /// # Returns
+    /// * `Result<usize>` - Number of candles fetched
+    pub async fn backfill(
+        &self,
+        provider: &dyn MarketDataProvider,
+        symbol: &str,
+        resolution: &str,
+        from: DateTime<Utc>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<usize> {
+        let end = to.unwrap_or_else(Utc::now);
+        let mut fetched = 0;
+        for gap in self.gaps(symbol, resolution, from, end)? {
+            let response = provider
+                .get_quote(&[symbol], resolution, Some(gap.from), Some(gap.to))
+                .await?;
+            let candles: Vec<Candlestick> = response
+                .0
+                .iter()
+                .filter(|data| data.symbol.eq_ignore_ascii_case(symbol))
+                .flat_map(|data| data.to_candlesticks())
+                .collect();
+            self.append(symbol, resolution, &candles)?;
+            fetched += candles.len();
+
+            let covered_to = if to.is_none() && gap.to == end {
+                // Leave the newest bar uncovered, it may still change
+                candles
+                    .iter()
+                    .map(|candle| candle.timestamp - chrono::Duration::seconds(1))
+                    .max()
+            } else {
+                Some(gap.to)
+            };
+            if let Some(covered_to) = covered_to {
+                self.mark_covered(symbol, resolution, gap.from, covered_to)?;
+            }
+        }
+        Ok(fetched)
+    }
+
+    /// Rewrite a series without superseded records and merge its ranges.
+    ///
+    /// The new files are written next to the old ones and renamed over them.
+    pub fn compact(&self, symbol: &str, resolution: &str) -> Result<()> {
+        let _guard = self.write_lock.lock().unwrap_or_else(|e| e.into_inner());
+        let (candles, _) = self.read_records(symbol, resolution)?;
+        self.rewrite(symbol, resolution, &candles)
+    }
+
+    /// Replace the files of a series with `candles` and its merged ranges.
+    /// The caller holds the write lock.
+    fn rewrite(
+        &self,
+        symbol: &str,
+        resolution: &str,
+        candles: &BTreeMap<i64, Candlestick>,
+    ) -> Result<()> {
+        let mut buf = header();
+        for candle in candles.values() {
+            write_record(&mut buf, candle);
+        }
+        replace_file(&self.candle_path(symbol, resolution), &buf)?;
+
+        let (ranges, _) = self.read_ranges(symbol, resolution)?;
+        replace_file(&self.range_path(symbol, resolution), &range_bytes(&ranges))
+    }
+
+    fn read_series(&self, symbol: &str, resolution: &str) -> Result<BTreeMap<i64, Candlestick>> {
+        Ok(self.read_records(symbol, resolution)?.0)
+    }
+
+    /// Candles of a series by timestamp, and the number of records on disk
+    /// including the superseded ones.
+    fn read_records(
+        &self,
+        symbol: &str,
+        resolution: &str,
+    ) -> Result<(BTreeMap<i64, Candlestick>, usize)> {
+        let path = self.candle_path(symbol, resolution);
+        let mut candles = BTreeMap::new();
+        let Some(bytes) = read_file(&path)? else {
+            return Ok((candles, 0));
+        };
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+            return Err(Error::cache_io(
+                path.display().to_string(),
+                std::io::Error::new(std::io::ErrorKind::InvalidData, "not a candle series"),
+            ));
+        }
+        let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+        if version != FORMAT_VERSION {
+            return Err(Error::cache_io(
+                path.display().to_string(),
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("unsupported candle series version {version}"),
+                ),
+            ));
+        }
+        // A torn trailing record from an interrupted append is skipped here
+        // and cut off by the next append
+        let records = bytes[HEADER_LEN..].chunks_exact(RECORD_LEN);
+        let count = records.len();
+        for record in records {
+            if let Some(candle) = read_record(record) {
+                candles.insert(candle.timestamp.timestamp(), candle);
+            }
+        }
+        Ok((candles, count))
+    }
+
+    /// Covered ranges, sorted and merged, and the number of range records
+    /// on disk.
+    fn read_ranges(&self, symbol: &str, resolution: &str) -> Result<(Vec<(i64, i64)>, usize)> {
+        let Some(bytes) = read_file(&self.range_path(symbol, resolution))? else {
+            return Ok((Vec::new(), 0));
+        };
+        let ranges: Vec<(i64, i64)> = bytes
+            .chunks_exact(RANGE_LEN)
+            .map(|chunk| {
+                (
+                    i64::from_le_bytes(chunk[..8].try_into().unwrap()),
+                    i64::from_le_bytes(chunk[8..].try_into().unwrap()),
+                )
+            })
+            .collect();
+        let count = ranges.len();
+        Ok((merge_ranges(ranges), count))
+    }
+}
+
+/// `ranges` sorted, with overlapping and adjacent ones joined.
+fn merge_ranges(mut ranges: Vec<(i64, i64)>) -> Vec<(i64, i64)> {
+    ranges.sort_unstable();
+    let mut merged: Vec<(i64, i64)> = Vec::with_capacity(ranges.len());
+    for (from, to) in ranges {
+        match merged.last_mut() {
+            Some(last) if from <= last.1.saturating_add(1) => last.1 = last.1.max(to),
+            _ => merged.push((from, to)),
+        }
+    }
+    merged
+}
+
+fn range_bytes(ranges: &[(i64, i64)]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(ranges.len() * RANGE_LEN);
+    for (from, to) in ranges {
+        buf.extend_from_slice(&from.to_le_bytes());
+        buf.extend_from_slice(&to.to_le_bytes());
+    }
+    buf
+}
+
+fn header() -> Vec<u8> {
+    let mut buf = Vec::with_capacity(HEADER_LEN);
+    buf.extend_from_slice(MAGIC);
+    buf.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    buf
+}
+
+fn write_record(buf: &mut Vec<u8>, candle: &Candlestick) {
+    buf.extend_from_slice(&candle.timestamp.timestamp().to_le_bytes());
+    buf.extend_from_slice(&candle.open.to_le_bytes());
+    buf.extend_from_slice(&candle.high.to_le_bytes());
+    buf.extend_from_slice(&candle.low.to_le_bytes());
+    buf.extend_from_slice(&candle.close.to_le_bytes());
+    buf.extend_from_slice(&candle.volume.to_le_bytes());
+}
+
+fn read_record(record: &[u8]) -> Option<Candlestick> {
+    let field = |i: usize| -> [u8; 8] { record[i * 8..(i + 1) * 8].try_into().unwrap() };
+    Some(Candlestick {
+        timestamp: DateTime::from_timestamp(i64::from_le_bytes(field(0)), 0)?,
+        open: f64::from_le_bytes(field(1)),
+        high: f64::from_le_bytes(field(2)),
+        low: f64::from_le_bytes(field(3)),
+        close: f64::from_le_bytes(field(4)),
+        volume: i64::from_le_bytes(field(5)),
+    })
+}
+
+fn read_file(path: &Path) -> Result<Option<Vec<u8>>> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::cache_io(path.display().to_string(), e)),
+    };
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)
+        .map_err(|e| Error::cache_io(path.display().to_string(), e))?;
+    Ok(Some(bytes))
+}
+
+/// Open `path` for appending records of `record_len` bytes, creating it
+/// (and `header`) when missing. A torn trailing record is cut off, so the
+/// next record starts on a record boundary again.
+fn open_append(path: &Path, header: &[u8], record_len: usize) -> Result<File> {
+    let io_error = |e| Error::cache_io(path.display().to_string(), e);
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(io_error)?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(io_error)?;
+    let len = file.metadata().map_err(io_error)?.len() as usize;
+    if len == 0 {
+        file.write_all(header).map_err(io_error)?;
+    } else if let Some(records) = len.checked_sub(header.len()) {
+        let torn = records % record_len;
+        if torn > 0 {
+            file.set_len((len - torn) as u64).map_err(io_error)?;
+        }
+    }
+    Ok(file)
+}
+
+fn replace_file(path: &Path, bytes: &[u8]) -> Result<()> {
+    let io_error = |e| Error::cache_io(path.display().to_string(), e);
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes).map_err(io_error)?;
+    fs::rename(&tmp, path).map_err(io_error)
+}
+
+static CANDLE_STORE: LazyLock<RwLock<Option<Arc<CandleStore>>>> =
+    LazyLock::new(|| RwLock::new(None));
+
+/// Store installed at startup, or `None` when history is only kept in memory.
+pub fn candle_store() -> Option<Arc<CandleStore>> {
+    CANDLE_STORE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+pub fn set_candle_store(store: Option<CandleStore>) {
+    *CANDLE_STORE.write().unwrap_or_else(|e| e.into_inner()) = store.map(Arc::new);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::explorer::vci::VCIExplorer;
+    use crate::fixture_server::{FixtureServer, test_client};
+
+    fn temp_store(name: &str) -> CandleStore {
+        let root = std::env::temp_dir().join(format!("aim-store-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        CandleStore::open(root).unwrap()
+    }
+
+    fn candle(timestamp: i64, close: f64) -> Candlestick {
+        Candlestick {
+            timestamp: DateTime::from_timestamp(timestamp, 0).unwrap(),
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 100,
+        }
+    }
+
+    fn at(timestamp: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp(timestamp, 0).unwrap()
+    }
+
+    #[test]
+    fn test_later_records_replace_earlier_ones() {
+        let store = temp_store("replace");
+        store
+            .append("aaa", "ONE_DAY", &[candle(100, 1.0), candle(200, 2.0)])
+            .unwrap();
+        store
+            .append(
+                "AAA",
+                "ONE_DAY",
+                &[candle(200, 2.5), candle(50, 0.5), candle(300, 0.0)],
+            )
+            .unwrap();
+
+        let closes: Vec<f64> = store
+            .range("AAA", "ONE_DAY", None, None)
+            .unwrap()
+            .iter()
+            .map(|candle| candle.close)
+            .collect();
+        assert_eq!(closes, [0.5, 1.0, 2.5]);
+        let ranged = store
+            .range("AAA", "ONE_DAY", Some(at(100)), Some(at(150)))
+            .unwrap();
+        assert_eq!(ranged.len(), 1);
+        assert_eq!(store.last_time("AAA", "ONE_DAY").unwrap(), Some(at(200)));
+        assert_eq!(store.symbols().unwrap(), ["AAA"]);
+
+        store.compact("AAA", "ONE_DAY").unwrap();
+        let bytes = fs::read(store.candle_path("AAA", "ONE_DAY")).unwrap();
+        assert_eq!(bytes.len(), HEADER_LEN + 3 * RECORD_LEN);
+        assert_eq!(store.range("AAA", "ONE_DAY", None, None).unwrap().len(), 3);
+        fs::remove_dir_all(store.root()).unwrap();
+    }
+
+    #[test]
+    fn test_polling_the_same_bars_does_not_grow_the_file() {
+        let store = temp_store("polling");
+        let path = store.candle_path("AAA", "ONE_MINUTE");
+        let bars: Vec<Candlestick> = (0..10).map(|i| candle(60 * i, 1.0)).collect();
+        store.append("AAA", "ONE_MINUTE", &bars).unwrap();
+        let len = fs::metadata(&path).unwrap().len();
+        for _ in 0..5 {
+            store.append("AAA", "ONE_MINUTE", &bars).unwrap();
+        }
+        assert_eq!(fs::metadata(&path).unwrap().len(), len);
+
+        // A forming bar that changes on every poll is compacted away
+        for i in 0..500 {
+            let mut polled = bars.clone();
+            polled.push(candle(600, 1.0 + i as f64));
+            store.append("AAA", "ONE_MINUTE", &polled).unwrap();
+        }
+        let records = (fs::metadata(&path).unwrap().len() as usize - HEADER_LEN) / RECORD_LEN;
+        assert!(records <= 2 * 11 + COMPACT_SLACK, "{records} records");
+        let stored = store.range("AAA", "ONE_MINUTE", None, None).unwrap();
+        assert_eq!(stored.len(), 11);
+        assert_eq!(stored[10].close, 500.0);
+        fs::remove_dir_all(store.root()).unwrap();
+    }
+
+    #[test]
+    fn test_append_cuts_off_a_torn_record() {
+        let store = temp_store("torn");
+        let path = store.candle_path("AAA", "ONE_DAY");
+        store
+            .append("AAA", "ONE_DAY", &[candle(100, 1.0), candle(200, 2.0)])
+            .unwrap();
+        // An append interrupted halfway through a record
+        let mut half = Vec::new();
+        write_record(&mut half, &candle(300, 3.0));
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&half[..RECORD_LEN / 2]).unwrap();
+        drop(file);
+        assert_eq!(store.range("AAA", "ONE_DAY", None, None).unwrap().len(), 2);
+
+        store.append("AAA", "ONE_DAY", &[candle(400, 4.0)]).unwrap();
+        let stored = store.range("AAA", "ONE_DAY", None, None).unwrap();
+        assert_eq!(
+            stored,
+            [candle(100, 1.0), candle(200, 2.0), candle(400, 4.0)]
+        );
+        assert_eq!(
+            fs::metadata(&path).unwrap().len() as usize,
+            HEADER_LEN + 3 * RECORD_LEN
+        );
+        fs::remove_dir_all(store.root()).unwrap();
+    }
+
+    #[test]
+    fn test_gaps_are_the_uncovered_parts() {
+        let store = temp_store("gaps");
+        assert_eq!(
+            store.gaps("AAA", "ONE_DAY", at(0), at(100)).unwrap(),
+            [Gap {
+                from: at(0),
+                to: at(100)
+            }]
+        );
+        store
+            .mark_covered("AAA", "ONE_DAY", at(10), at(20))
+            .unwrap();
+        store
+            .mark_covered("AAA", "ONE_DAY", at(21), at(40))
+            .unwrap();
+        store
+            .mark_covered("AAA", "ONE_DAY", at(60), at(200))
+            .unwrap();
+        let gaps = store.gaps("AAA", "ONE_DAY", at(0), at(100)).unwrap();
+        assert_eq!(
+            gaps,
+            [
+                Gap {
+                    from: at(0),
+                    to: at(9)
+                },
+                Gap {
+                    from: at(41),
+                    to: at(59)
+                },
+            ]
+        );
+        assert!(
+            store
+                .gaps("AAA", "ONE_DAY", at(60), at(100))
+                .unwrap()
+                .is_empty()
+        );
+        // The two joined ranges are stored as one
+        let path = store.range_path("AAA", "ONE_DAY");
+        assert_eq!(fs::metadata(&path).unwrap().len() as usize, 2 * RANGE_LEN);
+
+        // Covering the newest bars again and again does not grow the file
+        for i in 0..100 {
+            store
+                .mark_covered("AAA", "ONE_DAY", at(150), at(200 + i))
+                .unwrap();
+        }
+        assert_eq!(fs::metadata(&path).unwrap().len() as usize, 2 * RANGE_LEN);
+        assert_eq!(
+            store.gaps("AAA", "ONE_DAY", at(0), at(400)).unwrap().last(),
+            Some(&Gap {
+                from: at(300),
+                to: at(400)
+            })
+        );
+        fs::remove_dir_all(store.root()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_backfill_fetches_only_gaps() {
+        let server = FixtureServer::vci().await;
+        let explorer = VCIExplorer::with_base_url(test_client(), &server.url());
+        let store = temp_store("backfill");
+
+        let from = at(1727049600);
+        let to = Some(at(1727308800));
+        let fetched = store
+            .backfill(&explorer, "VCI", "ONE_DAY", from, to)
+            .await
+            .unwrap();
+        assert!(fetched > 0);
+        assert_eq!(server.requests().len(), 1);
+        assert!(
+            !store
+                .range("VCI", "ONE_DAY", None, None)
+                .unwrap()
+                .is_empty()
+        );
+
+        // Fully covered now, so nothing is requested again
+        let fetched = store
+            .backfill(&explorer, "VCI", "ONE_DAY", from, to)
+            .await
+            .unwrap();
+        assert_eq!(fetched, 0);
+        assert_eq!(server.requests().len(), 1);
+        fs::remove_dir_all(store.root()).unwrap();
+    }
+}
@@ -0,0 +1,549 @@
+//! Real-time market data over a WebSocket feed.
+//!
+//! [`MarketStream`] keeps one connection open, reconnects with backoff and
+//! re-sends the current subscriptions after every reconnect. Incoming events
+//! are fanned out to every receiver on a [`broadcast`] channel.
+//!
+//! Wire protocol, one JSON object (or an array of them) per text frame:
+//!
+//! ```json
+//! {"action": "subscribe", "symbols": ["VCI", "FPT"]}
+//! {"type": "tick", "symbol": "VCI", "price": 36200.0, ...}
+//! ```
+
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, LazyLock, Mutex, RwLock};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc, watch};
+use tokio::task::JoinHandle;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream};
+
+use crate::error::{Error, Result};
+use crate::explorer::vci::market_watch::PriceVolume;
+use crate::tls::{parse_fingerprint, websocket_tls_config};
+
+/// Where the feed lives and how to keep the connection alive.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StreamConfig {
+    /// `ws://` or `wss://` URL of the feed.
+    pub url: String,
+    /// Sent as a bearer token when connecting.
+    pub token: Option<String>,
+    /// SHA-256 of the feed certificate, for self-signed `wss://` servers.
+    pub cert_fingerprint: Option<String>,
+    pub reconnect_delay_ms: u64,
+    pub max_reconnect_delay_ms: u64,
+    /// Silence after which the connection is pinged, and dropped if the
+    /// ping goes unanswered for as long again.
+    pub idle_timeout_secs: u64,
+    /// Events buffered per receiver before slow receivers start lagging.
+    pub channel_capacity: usize,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            token: None,
+            cert_fingerprint: None,
+            reconnect_delay_ms: 500,
+            max_reconnect_delay_ms: 30_000,
+            idle_timeout_secs: 30,
+            channel_capacity: 4096,
+        }
+    }
+}
+
+impl StreamConfig {
+    /// Delay before reconnect number `attempt` (0-based).
+    fn reconnect_delay(&self, attempt: u32) -> Duration {
+        let factor = 2u64.saturating_pow(attempt.min(16));
+        Duration::from_millis(
+            self.reconnect_delay_ms
+                .saturating_mul(factor)
+                .min(self.max_reconnect_delay_ms),
+        )
+    }
+
+    fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_timeout_secs.max(1))
+    }
+}
+
+/// Last match of a symbol with the running session totals.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Tick {
+    pub symbol: String,
+    pub price: f64,
+    pub volume: i64,
+    pub accumulated_volume: i64,
+    pub highest: f64,
+    pub lowest: f64,
+    /// Unix seconds.
+    pub time: i64,
+}
+
+/// Top of the order book.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Quote {
+    pub symbol: String,
+    pub bid_prices: Vec<PriceVolume>,
+    pub ask_prices: Vec<PriceVolume>,
+    pub time: i64,
+}
+
+/// A single matched trade, as shown in the order list.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Trade {
+    pub id: u64,
+    pub symbol: String,
+    pub price: f64,
+    pub volume: i64,
+    /// `b` for buy-initiated, `s` for sell-initiated.
+    pub match_type: String,
+    pub time: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum StreamEvent {
+    Tick(Tick),
+    Quote(Quote),
+    Trade(Trade),
+}
+
+impl StreamEvent {
+    pub fn symbol(&self) -> &str {
+        match self {
+            StreamEvent::Tick(tick) => &tick.symbol,
+            StreamEvent::Quote(quote) => &quote.symbol,
+            StreamEvent::Trade(trade) => &trade.symbol,
+        }
+    }
+}
+
+/// Messages sent from the client to the feed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum ClientMessage {
+    Subscribe { symbols: Vec<String> },
+    Unsubscribe { symbols: Vec<String> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamStatus {
+    Connecting,
+    Connected,
+    Disconnected,
+}
+
+/// Handle to the feed connection. Cheap to clone; the connection closes
+/// when the last clone is dropped.
+#[derive(Clone)]
+pub struct MarketStream {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    events: broadcast::Sender<StreamEvent>,
+    status: watch::Receiver<StreamStatus>,
+    /// Subscribed symbols with the number of subscribers for each.
+    symbols: Arc<Mutex<BTreeMap<String, usize>>>,
+    commands: mpsc::UnboundedSender<ClientMessage>,
+    task: JoinHandle<()>,
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl MarketStream {
+    /// Start the connection task. Must be called inside a Tokio runtime.
+    pub fn spawn(config: StreamConfig) -> Result<Self> {
+        let tls = if config.url.starts_with("wss://") {
+            let fingerprint = config
+                .cert_fingerprint
+                .as_deref()
+                .map(parse_fingerprint)
+                .transpose()?;
+            Some(Arc::new(websocket_tls_config(fingerprint)?))
+        } else if config.url.starts_with("ws://") {
+            None
+        } else {
+            return Err(Error::Config(format!(
+                "stream URL must start with ws:// or wss://, got {:?}",
+                config.url
+            )));
+        };
+
+        let (events, _) = broadcast::channel(config.channel_capacity.max(1));
+        let (status_tx, status) = watch::channel(StreamStatus::Connecting);
+        let (commands, commands_rx) = mpsc::unbounded_channel();
+        let symbols = Arc::new(Mutex::new(BTreeMap::new()));
+
+        let connection = Connection {
+            config,
+            tls,
+            events: events.clone(),
+            status: status_tx,
+            symbols: symbols.clone(),
+            commands: commands_rx,
+        };
+        let task = tokio::spawn(connection.run());
+
+        Ok(Self {
+            inner: Arc::new(Inner {
+                events,
+                status,
+                symbols,
+                commands,
+                task,
+            }),
+        })
+    }
+
+    /// Receive every event from the feed, whoever subscribed to it.
+    pub fn events(&self) -> broadcast::Receiver<StreamEvent> {
+        self.inner.events.subscribe()
+    }
+
+    /// Ask the feed for `symbols` and receive events.
+    ///
+    /// Subscriptions are counted per symbol, so every call should be paired
+    /// with an [`unsubscribe`](Self::unsubscribe) of the same symbols.
+    /// The receiver also gets events for symbols subscribed by others.
+    pub fn subscribe(&self, symbols: &[&str]) -> broadcast::Receiver<StreamEvent> {
+        let receiver = self.inner.events.subscribe();
+        let added: Vec<String> = {
+            let mut subscribed = self.inner.symbols.lock().unwrap_or_else(|e| e.into_inner());
+            symbols
+                .iter()
+                .filter(|symbol| {
+                    let count = subscribed.entry(symbol.to_string()).or_insert(0);
+                    *count += 1;
+                    *count == 1
+                })
+                .map(|symbol| symbol.to_string())
+                .collect()
+        };
+        if !added.is_empty() {
+            let _ = self
+                .inner
+                .commands
+                .send(ClientMessage::Subscribe { symbols: added });
+        }
+        receiver
+    }
+
+    pub fn unsubscribe(&self, symbols: &[&str]) {
+        let removed: Vec<String> = {
+            let mut subscribed = self.inner.symbols.lock().unwrap_or_else(|e| e.into_inner());
+            symbols
+                .iter()
+                .filter(|symbol| match subscribed.get_mut(**symbol) {
+                    Some(count) if *count > 1 => {
+                        *count -= 1;
+                        false
+                    }
+                    Some(_) => {
+                        subscribed.remove(**symbol);
+                        true
+                    }
+                    None => false,
+                })
+                .map(|symbol| symbol.to_string())
+                .collect()
+        };
+        if !removed.is_empty() {
+            let _ = self
+                .inner
+                .commands
+                .send(ClientMessage::Unsubscribe { symbols: removed });
+        }
+    }
+
+    pub fn status(&self) -> watch::Receiver<StreamStatus> {
+        self.inner.status.clone()
+    }
+
+    pub fn is_connected(&self) -> bool {
+        *self.inner.status.borrow() == StreamStatus::Connected
+    }
+}
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// State owned by the connection task.
+struct Connection {
+    config: StreamConfig,
+    tls: Option<Arc<rustls::ClientConfig>>,
+    events: broadcast::Sender<StreamEvent>,
+    status: watch::Sender<StreamStatus>,
+    symbols: Arc<Mutex<BTreeMap<String, usize>>>,
+    commands: mpsc::UnboundedReceiver<ClientMessage>,
+}
+
+impl Connection {
+    async fn run(mut self) {
+        let mut attempt = 0;
+        loop {
+            let _ = self.status.send(StreamStatus::Connecting);
+            match self.connect().await {
+                Ok(socket) => {
+                    attempt = 0;
+                    log::info!("Connected to market stream {}", self.config.url);
+                    let _ = self.status.send(StreamStatus::Connected);
+                    let reason = self.pump(socket).await;
+                    log::warn!("Market stream disconnected: {reason}");
+                }
+                Err(e) => log::warn!("Market stream connection failed: {e}"),
+            }
+            let _ = self.status.send(StreamStatus::Disconnected);
+
+            tokio::time::sleep(self.config.reconnect_delay(attempt)).await;
+            attempt = attempt.saturating_add(1);
+        }
+    }
+
+    async fn connect(&self) -> std::result::Result<Socket, String> {
+        let mut request = self
+            .config
+            .url
+            .as_str()
+            .into_client_request()
+            .map_err(|e| e.to_string())?;
+        if let Some(token) = &self.config.token {
+            let value = format!("Bearer {token}")
+                .parse()
+                .map_err(|_| "token is not a valid header value".to_string())?;
+            request.headers_mut().insert("Authorization", value);
+        }
+
+        let connector = self.tls.clone().map(Connector::Rustls);
+        let connect =
+            tokio_tungstenite::connect_async_tls_with_config(request, None, false, connector);
+        match tokio::time::timeout(self.config.idle_timeout(), connect).await {
+            Ok(Ok((socket, _))) => Ok(socket),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err("connect timed out".to_string()),
+        }
+    }
+
+    /// Forward events until the connection ends, returning why it ended.
+    async fn pump(&mut self, socket: Socket) -> String {
+        let (mut sink, mut source) = socket.split();
+
+        // Changes queued while offline are already reflected in `symbols`
+        while self.commands.try_recv().is_ok() {}
+        let symbols: Vec<String> = self
+            .symbols
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .keys()
+            .cloned()
+            .collect();
+        if !symbols.is_empty() {
+            let resubscribe = ClientMessage::Subscribe { symbols };
+            if let Err(e) = sink.send(encode(&resubscribe)).await {
+                return e.to_string();
+            }
+        }
+
+        let idle_timeout = self.config.idle_timeout();
+        let mut awaiting_pong = false;
+        loop {
+            tokio::select! {
+                message = tokio::time::timeout(idle_timeout, source.next()) => {
+                    let message = match message {
+                        Err(_) if awaiting_pong => return "no answer to ping".to_string(),
+                        Err(_) => {
+                            awaiting_pong = true;
+                            if let Err(e) = sink.send(Message::Ping(Vec::new().into())).await {
+                                return e.to_string();
+                            }
+                            continue;
+                        }
+                        Ok(None) => return "connection closed".to_string(),
+                        Ok(Some(Err(e))) => return e.to_string(),
+                        Ok(Some(Ok(message))) => message,
+                    };
+                    awaiting_pong = false;
+                    match message {
+                        Message::Text(text) => self.dispatch(&text),
+                        Message::Close(frame) => return format!("closed by server {frame:?}"),
+                        _ => {}
+                    }
+                }
+                Some(command) = self.commands.recv() => {
+                    if let Err(e) = sink.send(encode(&command)).await {
+                        return e.to_string();
+                    }
+                }
+            }
+        }
+    }
+
+    fn dispatch(&self, text: &str) {
+        let events = if text.trim_start().starts_with('[') {
+            serde_json::from_str::<Vec<StreamEvent>>(text)
+        } else {
+            serde_json::from_str::<StreamEvent>(text).map(|event| vec![event])
+        };
+        match events {
+            // Sending only fails when nobody is listening
+            Ok(events) => events.into_iter().for_each(|event| {
+                let _ = self.events.send(event);
+            }),
+            Err(e) => log::debug!("Ignoring market stream message: {e}: {text}"),
+        }
+    }
+}
+
+fn encode(message: &ClientMessage) -> Message {
+    Message::Text(serde_json::to_string(message).unwrap_or_default().into())
+}
+
+static MARKET_STREAM: LazyLock<RwLock<Option<MarketStream>>> = LazyLock::new(|| RwLock::new(None));
+
+/// Stream installed at startup, or `None` when no feed is configured and
+/// callers have to poll.
+pub fn market_stream() -> Option<MarketStream> {
+    MARKET_STREAM
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+}
+
+pub fn set_market_stream(stream: Option<MarketStream>) {
+    *MARKET_STREAM.write().unwrap_or_else(|e| e.into_inner()) = stream;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixture_server::{StreamServer, wait_until};
+
+    fn test_config(url: String) -> StreamConfig {
+        StreamConfig {
+            url,
+            token: Some("stream-token".to_string()),
+            reconnect_delay_ms: 10,
+            max_reconnect_delay_ms: 50,
+            ..StreamConfig::default()
+        }
+    }
+
+    fn tick(symbol: &str, price: f64) -> StreamEvent {
+        StreamEvent::Tick(Tick {
+            symbol: symbol.to_string(),
+            price,
+            volume: 100,
+            accumulated_volume: 1000,
+            highest: price,
+            lowest: price,
+            time: 1727250299,
+        })
+    }
+
+    async fn recv(receiver: &mut broadcast::Receiver<StreamEvent>) -> StreamEvent {
+        tokio::time::timeout(Duration::from_secs(5), receiver.recv())
+            .await
+            .expect("no event within 5s")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_events_fan_out_to_every_receiver() {
+        let server = StreamServer::start().await;
+        let stream = MarketStream::spawn(test_config(server.url())).unwrap();
+
+        let mut ticks = stream.subscribe(&["VCI"]);
+        let mut everything = stream.events();
+        wait_until(|| server.messages().contains(&subscribe(&["VCI"]))).await;
+        assert_eq!(
+            server.authorizations(),
+            [Some("Bearer stream-token".to_string())]
+        );
+
+        let trade = StreamEvent::Trade(Trade {
+            id: 9102338,
+            symbol: "VCI".to_string(),
+            price: 36200.0,
+            volume: 1500,
+            match_type: "b".to_string(),
+            time: 1727250299,
+        });
+        server.push(&[tick("VCI", 36200.0), trade.clone()]);
+
+        assert_eq!(recv(&mut ticks).await, tick("VCI", 36200.0));
+        assert_eq!(recv(&mut ticks).await, trade);
+        assert_eq!(recv(&mut everything).await, tick("VCI", 36200.0));
+        assert!(stream.is_connected());
+    }
+
+    #[tokio::test]
+    async fn test_reconnect_resubscribes_current_symbols() {
+        let server = StreamServer::start().await;
+        let stream = MarketStream::spawn(test_config(server.url())).unwrap();
+
+        let mut receiver = stream.subscribe(&["VCI", "FPT"]);
+        wait_until(|| !server.messages().is_empty()).await;
+
+        // Only the last subscriber of a symbol unsubscribes it
+        let _shared = stream.subscribe(&["FPT"]);
+        stream.unsubscribe(&["FPT"]);
+        stream.unsubscribe(&["VCI"]);
+        let _again = stream.subscribe(&["VCI"]);
+        wait_until(|| server.messages().len() == 3).await;
+        assert_eq!(
+            server.messages()[1..],
+            [
+                ClientMessage::Unsubscribe {
+                    symbols: vec!["VCI".to_string()]
+                },
+                subscribe(&["VCI"]),
+            ]
+        );
+
+        server.disconnect_all();
+        wait_until(|| server.connections() == 2 && server.messages().len() == 4).await;
+        assert_eq!(server.messages()[3], subscribe(&["FPT", "VCI"]));
+
+        server.push(&[tick("FPT", 133500.0)]);
+        assert_eq!(recv(&mut receiver).await, tick("FPT", 133500.0));
+    }
+
+    #[test]
+    fn test_reconnect_delay_is_capped() {
+        let config = StreamConfig::default();
+        assert_eq!(config.reconnect_delay(0), Duration::from_millis(500));
+        assert_eq!(config.reconnect_delay(2), Duration::from_millis(2000));
+        assert_eq!(config.reconnect_delay(40), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_rejects_non_websocket_url() {
+        let config = StreamConfig {
+            url: "https://example.com".to_string(),
+            ..StreamConfig::default()
+        };
+        assert!(matches!(MarketStream::spawn(config), Err(Error::Config(_))));
+    }
+
+    fn subscribe(symbols: &[&str]) -> ClientMessage {
+        ClientMessage::Subscribe {
+            symbols: symbols.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
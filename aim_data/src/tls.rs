@@ -0,0 +1,143 @@
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{CryptoProvider, verify_tls12_signature, verify_tls13_signature};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{CertificateError, DigitallySignedStruct, SignatureScheme};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+
+/// Parse a SHA-256 certificate fingerprint written as hex, with or without `:` separators.
+pub fn parse_fingerprint(text: &str) -> Result<[u8; 32]> {
+    let hex: String = text.chars().filter(|c| !matches!(c, ':' | ' ')).collect();
+    if hex.len() != 64 || !hex.is_ascii() {
+        return Err(Error::Config(format!(
+            "certificate fingerprint must be 32 bytes of hex, got {text:?}"
+        )));
+    }
+
+    let mut fingerprint = [0u8; 32];
+    for (i, byte) in fingerprint.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+            .map_err(|_| Error::Config(format!("certificate fingerprint is not hex: {text:?}")))?;
+    }
+    Ok(fingerprint)
+}
+
+/// Accepts exactly one server certificate, identified by the SHA-256 of its DER encoding.
+///
+/// Chain and host name are not checked: the pin replaces them, which is what
+/// a self-signed server reached by IP needs. Handshake signatures are still verified.
+#[derive(Debug)]
+struct PinnedCertVerifier {
+    fingerprint: [u8; 32],
+    provider: Arc<CryptoProvider>,
+}
+
+impl ServerCertVerifier for PinnedCertVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        let digest = Sha256::digest(end_entity.as_ref());
+        if digest.as_slice() == self.fingerprint {
+            Ok(ServerCertVerified::assertion())
+        } else {
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::ApplicationVerificationFailure,
+            ))
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.provider.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.provider
+            .signature_verification_algorithms
+            .supported_schemes()
+    }
+}
+
+/// TLS configuration that only trusts the certificate with the given fingerprint.
+pub(crate) fn pinned_tls_config(fingerprint: [u8; 32]) -> Result<rustls::ClientConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let verifier = PinnedCertVerifier {
+        fingerprint,
+        provider: provider.clone(),
+    };
+    let config = rustls::ClientConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| Error::Config(format!("TLS setup failed: {e}")))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(verifier))
+        .with_no_client_auth();
+    Ok(config)
+}
+
+/// TLS configuration for WebSocket feeds: the pinned certificate if given,
+/// otherwise the bundled web PKI roots.
+pub(crate) fn websocket_tls_config(fingerprint: Option<[u8; 32]>) -> Result<rustls::ClientConfig> {
+    if let Some(fingerprint) = fingerprint {
+        return pinned_tls_config(fingerprint);
+    }
+    let roots = rustls::RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config = rustls::ClientConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()
+    .map_err(|e| Error::Config(format!("TLS setup failed: {e}")))?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fingerprint_accepts_colon_separated_hex() {
+        let text = "AB:".repeat(31) + "CD";
+        let fingerprint = parse_fingerprint(&text).unwrap();
+        assert_eq!(fingerprint[0], 0xAB);
+        assert_eq!(fingerprint[31], 0xCD);
+
+        let plain = "ab".repeat(32);
+        assert_eq!(parse_fingerprint(&plain).unwrap()[5], 0xAB);
+
+        assert!(parse_fingerprint("ABCD").is_err());
+        assert!(parse_fingerprint(&"zz".repeat(32)).is_err());
+    }
+}
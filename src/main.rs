@@ -9,11 +9,13 @@ mod monte_carlo;
 use crate::{
     slint_generatedAppWindow::StockData as SlintStockData,
     tasks::{
-        sort_market_watch, sort_stocks, spawn_cache_storage_task, ChartMetaData, ALL_STOCK_LIST,
+        alert_items, setup_alert_callbacks, setup_layout_callbacks, setup_share_callbacks,
+        sort_market_watch, sort_stocks, spawn_cache_storage_task, spawn_compare_task,
+        spawn_layout_task, ChartMetaData, ALL_STOCK_LIST,
     },
 };
 use aim_chart::Chart;
-use aim_data::{get_company_info, get_market_watch, get_quote};
+use aim_data::{get_company_info, get_history, get_market_watch, OHLCData, Timeframe};
 use dirs_next::cache_dir;
 use slint::{Model, SharedString, VecModel};
 use std::{path::PathBuf, sync::Arc};
@@ -55,9 +57,51 @@ async fn main() {
     std::fs::create_dir_all(&app_cache_dir).unwrap();
     let cache_file: PathBuf = app_cache_dir.join("cache.bin");
     let user_list: PathBuf = app_cache_dir.join("user_list.json");
+    let alert_file: PathBuf = app_cache_dir.join(aim_data::alert::ALERTS_FILE_NAME);
+
+    // Load server settings and pick the market data provider before any data is fetched
+    let config_file = app_cache_dir.join(aim_data::config::CONFIG_FILE_NAME);
+    let config = aim_data::Config::load(&config_file).unwrap_or_else(|e| {
+        log::error!(
+            "Failed to load {}: {e}. Using defaults",
+            config_file.display()
+        );
+        let mut config = aim_data::Config::default();
+        config.apply_overrides(|key| std::env::var(key).ok());
+        config
+    });
+    if let Err(e) = aim_data::aim::configure(&config) {
+        log::error!(
+            "Failed to configure AIM server {}: {e}",
+            config.aim.base_url
+        );
+    }
+    let http_client = aim_data::HttpClient::new(config.http_config()).unwrap_or_else(|e| {
+        log::error!("Failed to build HTTP client: {e}");
+        aim_data::HttpClient::shared()
+    });
+    let provider_name = &config.market_data.provider;
+    match aim_data::provider_by_name(provider_name, http_client) {
+        Some(provider) => aim_data::set_provider(provider),
+        None => log::error!("Unknown market data provider {provider_name}, using VCI"),
+    }
+    log::info!("Market data provider: {}", aim_data::provider().name());
+    if let Some(stream_config) = config.stream_config() {
+        match aim_data::MarketStream::spawn(stream_config) {
+            Ok(stream) => aim_data::set_market_stream(Some(stream)),
+            Err(e) => log::error!("Market stream disabled, polling instead: {e}"),
+        }
+    }
+
+    match aim_data::CandleStore::open(app_cache_dir.join("candles")) {
+        Ok(store) => aim_data::set_candle_store(Some(store)),
+        Err(e) => log::error!("Candle store disabled, history is fetched on every launch: {e}"),
+    }
 
-    // Fetch initial chart data for default stock (AAA)
-    let chart_data = get_quote(&["AAA"], "ONE_DAY", None, None).await.unwrap();
+    // Load initial chart data for default stock (AAA), fetching only what is not stored yet
+    let chart_data = get_history("AAA", Timeframe::DAILY.base_resolution(), None, None)
+        .await
+        .unwrap();
     let company_info = get_company_info("AAA").await.unwrap();
     let company_info = aim_chart::CompanyInfo {
         roe: company_info.data.company_financial_ratio.ratio[0]
@@ -78,15 +122,15 @@ async fn main() {
     };
 
     // Create a thread-safe chart container with initial chart
-    let chart_metadata = if std::fs::metadata(&cache_file).is_ok() {
-        ChartMetaData::load(&cache_file)
-    } else {
-        ChartMetaData::new(vec![Chart::new_default(
-            "AAA".to_string(),
-            chart_data.0[0].clone(),
-            company_info,
-        )])
-    };
+    let chart_metadata = ChartMetaData::load(&ChartMetaData::cache_file(cache_file))
+        .unwrap_or_else(|| {
+            ChartMetaData::new(vec![Chart::new_default(
+                "AAA".to_string(),
+                OHLCData::from_candlesticks("AAA", &chart_data),
+                company_info,
+                Timeframe::DAILY,
+            )])
+        });
     let chart = Arc::new(Mutex::new(chart_metadata));
 
     let init_data = match get_market_watch(&["AAA"]).await {
@@ -112,9 +156,7 @@ async fn main() {
     log::info!("Page-aware task manager initialized");
 
     // Initialize Monte Carlo module
-    let monte_carlo_state = Arc::new(Mutex::new(
-        tasks::monte_carlo::MonteCarloState::default()
-    ));
+    let monte_carlo_state = Arc::new(Mutex::new(tasks::monte_carlo::MonteCarloState::from_store()));
     tasks::monte_carlo::setup_monte_carlo_callbacks(&ui, monte_carlo_state);
     log::info!("Monte Carlo module initialized");
 
@@ -142,6 +184,23 @@ async fn main() {
 
     ui.set_current_stock(init_data);
 
+    // Price alerts, checked against the market watch feed
+    let alert_book = aim_data::alert::AlertBook::load(&alert_file).unwrap_or_else(|e| {
+        log::error!("Failed to load alerts: {e}");
+        aim_data::alert::AlertBook::default()
+    });
+    ui.set_alert_list(slint::ModelRc::new(VecModel::from(alert_items(
+        &alert_book,
+    ))));
+    let alerts = Arc::new(Mutex::new(alert_book));
+    setup_alert_callbacks(&ui, Arc::clone(&alerts), Arc::clone(&chart));
+
+    // Multi-chart layout restored from the chart cache
+    setup_layout_callbacks(&ui, Arc::clone(&chart)).await;
+
+    // PNG snapshots and chart state files to share with other users
+    setup_share_callbacks(&ui, Arc::clone(&chart));
+
     // Set up callback for adding symbols
     let symbol_list_clone = Arc::clone(&symbol_list);
     let ui_handle: slint::Weak<AppWindow> = ui.as_weak();
@@ -325,11 +384,23 @@ async fn main() {
     let _ui_chart_handle = spawn_ui_chart_task(Arc::clone(&chart), &ui).await;
     // If you only want to read the chart data, you can pass a reference to the Arc<Mutex<ChartMetaData>>
     // Spawn cache storage task with task manager
-    let _cache_handle =
-        spawn_cache_storage_task(Arc::clone(&chart), Arc::clone(&symbol_list)).await;
+    let _cache_handle = spawn_cache_storage_task(
+        Arc::clone(&chart),
+        Arc::clone(&symbol_list),
+        Arc::clone(&alerts),
+    )
+    .await;
     let _stock_update_handles = spawn_stock_update_task(Arc::clone(&chart), &ui).await;
     let _chart_update_handle = spawn_chart_update_task(Arc::clone(&chart)).await;
-    let _data_update_handle = spawn_data_update_task(&ui, Arc::clone(&symbol_list)).await;
+    let _layout_handle = spawn_layout_task(Arc::clone(&chart), &ui).await;
+    let _compare_handle = spawn_compare_task(Arc::clone(&chart), &ui).await;
+    let _data_update_handle = spawn_data_update_task(
+        &ui,
+        Arc::clone(&symbol_list),
+        Arc::clone(&alerts),
+        Arc::clone(&chart),
+    )
+    .await;
     let _balance_sheet_handles = spawn_balance_sheet_task(&ui).await;
     let _company_profile_handles = spawn_company_profile_task(&ui).await;
     let _mini_vnindex_handle = spawn_mini_chart_vnindex_task(&ui).await;
@@ -361,20 +432,3 @@ async fn main() {
     // Run the UI main loop
     ui.run().unwrap();
 }
-
-/// Converts user-friendly interval strings to API interval constants
-///
-/// # Arguments
-/// * `interval` - The interval string (e.g., "1m", "5m", "1H", "1D")
-///
-/// # Returns
-/// The corresponding API interval constant
-fn interval_to_constant(interval: &str) -> &'static str {
-    match interval {
-        "1m" | "5m" | "15m" | "30m" => "ONE_MINUTE",
-        "1H" | "2H" | "4H" => "ONE_HOUR",
-        "1D" | "2D" | "3D" => "ONE_DAY",
-        "1W" | "2W" | "1M" => "ONE_WEEK",
-        _ => "ONE_DAY",
-    }
-}
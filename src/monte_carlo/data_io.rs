@@ -61,10 +61,13 @@ pub fn get_ticker_info(all_data: &[StockRecord], ticker: &str) -> (String, Vec<f
     let count = ticker_data.len();
     let last_price = ticker_data.last().unwrap().close;
 
+    // Back-adjust for dividends and splits so ex-date gaps are not taken
+    // for returns
+    let closes = adjusted_closes(&ticker_data, ticker);
     let mut log_returns = Vec::new();
-    for window in ticker_data.windows(2) {
-        let s1 = window[0].close;
-        let s2 = window[1].close;
+    for window in closes.windows(2) {
+        let s1 = window[0];
+        let s2 = window[1];
         if s1 > 0.0 && s2 > 0.0 {
             log_returns.push((s2 / s1).ln());
         }
@@ -76,4 +79,60 @@ pub fn get_ticker_info(all_data: &[StockRecord], ticker: &str) -> (String, Vec<f
     );
 
     (info, log_returns)
-}
\ No newline at end of file
+}
+
+/// Closes of `records` back-adjusted for the corporate actions stored for
+/// `ticker`, raw closes when none are stored.
+fn adjusted_closes(records: &[&StockRecord], ticker: &str) -> Vec<f64> {
+    let actions = aim_data::corporate_actions::stored_actions(ticker).unwrap_or_else(|e| {
+        log::warn!("Failed to read corporate actions of {ticker}: {e}");
+        Vec::new()
+    });
+    if actions.is_empty() {
+        return records.iter().map(|r| r.close).collect();
+    }
+    let candles: Vec<aim_data::Candlestick> = records
+        .iter()
+        .map(|r| aim_data::Candlestick {
+            timestamp: aim_data::calendar::at_minute(r.date, 0),
+            open: r.open,
+            high: r.high,
+            low: r.low,
+            close: r.close,
+            volume: r.volume,
+        })
+        .collect();
+    aim_data::corporate_actions::adjust(&candles, &actions)
+        .iter()
+        .map(|c| c.close)
+        .collect()
+}
+
+/// Daily records of every ticker in the local candle store, without
+/// network access. Tickers that were never charted have no stored history.
+pub fn load_stored_records() -> Result<(Vec<StockRecord>, Vec<String>)> {
+    let store = aim_data::candle_store()
+        .ok_or_else(|| anyhow::anyhow!("Local candle store is not available"))?;
+    let mut records = Vec::new();
+    let mut ticker_list = Vec::new();
+
+    for ticker in store.symbols()? {
+        let candles = store.range(&ticker, "ONE_DAY", None, None)?;
+        if candles.is_empty() {
+            continue;
+        }
+        records.extend(candles.iter().map(|c| StockRecord {
+            ticker: ticker.clone(),
+            date: aim_data::calendar::to_local(c.timestamp).date_naive(),
+            open: c.open,
+            high: c.high,
+            low: c.low,
+            close: c.close,
+            volume: c.volume,
+        }));
+        ticker_list.push(ticker);
+    }
+
+    records.sort_by_key(|r| r.date);
+    Ok((records, ticker_list))
+}
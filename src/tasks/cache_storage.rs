@@ -1,8 +1,8 @@
-use std::fs::File;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
+use aim_data::alert::{AlertBook, ALERTS_FILE_NAME};
 use dirs_next::cache_dir;
 use tokio::sync::Mutex;
 
@@ -14,6 +14,7 @@ use crate::tasks::ChartMetaData;
 pub async fn spawn_cache_storage_task(
     chart: Arc<Mutex<ChartMetaData>>,
     custom_list: Arc<Mutex<Vec<String>>>,
+    alerts: Arc<Mutex<AlertBook>>,
 ) -> TaskHandle {
     let (tx, mut rx) = tokio::sync::mpsc::channel(10);
     // Register the task with the task manager
@@ -25,13 +26,13 @@ pub async fn spawn_cache_storage_task(
     .await;
 
     tokio::spawn(async move {
-        let mut pre_md5 = "".to_string();
         let mut pre_custom_list = Vec::new();
         let base_cache = cache_dir().expect("Could not find cache directory");
         let app_cache_dir = base_cache.join("Aim");
         std::fs::create_dir_all(&app_cache_dir).unwrap();
-        let cache_file: PathBuf = app_cache_dir.join("cache.bin");
+        let cache_file = ChartMetaData::cache_file(app_cache_dir.join("cache.bin"));
         let user_list: PathBuf = app_cache_dir.join("user_list.json");
+        let alert_file: PathBuf = app_cache_dir.join(ALERTS_FILE_NAME);
         let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
         loop {
             if let Ok(status) = rx.try_recv() {
@@ -45,12 +46,16 @@ pub async fn spawn_cache_storage_task(
                 continue;
             }
 
-            let md5 = chart.lock().await.get_md5();
-            if md5 != pre_md5 {
-                // If the MD5 hash has changed, update the cache
-                pre_md5 = md5;
-                let file = File::create(&cache_file).unwrap();
-                chart.lock().await.save(file);
+            // Save the workspace after the user changed it
+            {
+                let mut charts = chart.lock().await;
+                if charts.take_dirty() {
+                    if let Err(e) = charts.save(&cache_file) {
+                        log::error!("Failed to write the chart cache: {e}");
+                        // Try again rather than lose the edit
+                        charts.mark_dirty();
+                    }
+                }
             }
 
             // check if the user list has changed
@@ -72,6 +77,17 @@ pub async fn spawn_cache_storage_task(
                     }
                 }
             }
+
+            // Save the alerts after they were edited or fired
+            {
+                let mut book = alerts.lock().await;
+                if book.take_dirty() {
+                    if let Err(e) = book.save(&alert_file) {
+                        log::error!("Failed to write {ALERTS_FILE_NAME}: {e}");
+                        book.mark_dirty();
+                    }
+                }
+            }
             tokio::time::sleep(Duration::from_millis(100)).await; // Update every 100 milliseconds
         }
     });
@@ -0,0 +1,364 @@
+use crate::slint_generatedAppWindow::{AlertItem as SlintAlertItem, AppWindow};
+use crate::tasks::{ChartMetaData, DataUpdate};
+use aim_data::alert::{
+    average_volume, post_webhook, Alert, AlertBook, AlertCondition, AlertTarget, Cross, Snapshot,
+    Triggered,
+};
+use aim_data::calendar::to_local;
+use aim_data::explorer::vci::market_watch::VCIMarketWatch;
+use aim_data::{get_history, HttpClient};
+use chrono::{Duration, NaiveDate, Utc};
+use slint::{ComponentHandle, ModelRc, SharedString, VecModel};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Daily bars the volume spike average is taken over
+const AVERAGE_VOLUME_BARS: usize = 20;
+
+/// Average daily volume of each symbol, with the local date it was looked up
+/// on. None while it is fetched, or for the rest of the day when it could
+/// not be.
+pub type AverageVolumes = Arc<Mutex<HashMap<String, (NaiveDate, Option<f64>)>>>;
+
+/// Wire the alert panel callbacks to `alerts`.
+pub fn setup_alert_callbacks(
+    ui: &AppWindow,
+    alerts: Arc<Mutex<AlertBook>>,
+    chart: Arc<Mutex<ChartMetaData>>,
+) {
+    let alerts_clone = Arc::clone(&alerts);
+    let ui_handle = ui.as_weak();
+    ui.on_add_alert(
+        move |kind, value, watchlist, desktop, repeat, webhook, note| {
+            let Some(condition) = parse_condition(&kind, &value) else {
+                log::error!("Invalid {kind} alert: {value}");
+                return;
+            };
+            let Some(ui) = ui_handle.upgrade() else {
+                return;
+            };
+            let target = if watchlist {
+                AlertTarget::Watchlist
+            } else {
+                AlertTarget::Symbol(ui.get_current_stock().symbol.to_string())
+            };
+            let alert = new_alert(target, condition, desktop, repeat, &webhook, &note);
+            add_alert(Arc::clone(&alerts_clone), alert, ui_handle.clone());
+        },
+    );
+
+    // Level and trendline alerts from the selected drawing of the current chart
+    let alerts_clone = Arc::clone(&alerts);
+    let ui_handle = ui.as_weak();
+    ui.on_add_drawing_alert(move |desktop, repeat, webhook, note| {
+        let Some(ui) = ui_handle.upgrade() else {
+            return;
+        };
+        let symbol = ui.get_current_stock().symbol.to_string();
+        let alerts = Arc::clone(&alerts_clone);
+        let chart = Arc::clone(&chart);
+        let ui_handle = ui_handle.clone();
+        tokio::spawn(async move {
+            let condition = chart
+                .lock()
+                .await
+                .data
+                .iter()
+                .find(|chart| chart.stock_name == symbol)
+                .and_then(|chart| chart.selected_alert_condition());
+            let Some(condition) = condition else {
+                log::error!("Select a horizontal line or a trendline on {symbol} first");
+                return;
+            };
+            let alert = new_alert(
+                AlertTarget::Symbol(symbol),
+                condition,
+                desktop,
+                repeat,
+                &webhook,
+                &note,
+            );
+            add_alert(alerts, alert, ui_handle);
+        });
+    });
+
+    let alerts_clone = Arc::clone(&alerts);
+    let ui_handle = ui.as_weak();
+    ui.on_remove_alert(move |id| {
+        update_alerts(Arc::clone(&alerts_clone), ui_handle.clone(), move |book| {
+            book.remove(id as u64);
+        });
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_set_alert_enabled(move |id, enabled| {
+        update_alerts(Arc::clone(&alerts), ui_handle.clone(), move |book| {
+            book.set_enabled(id as u64, enabled);
+        });
+    });
+}
+
+fn new_alert(
+    target: AlertTarget,
+    condition: AlertCondition,
+    desktop: bool,
+    repeat: bool,
+    webhook: &str,
+    note: &str,
+) -> Alert {
+    let mut alert = Alert::new(target, condition);
+    alert.repeat = repeat;
+    alert.note = note.trim().to_string();
+    alert.delivery.desktop = desktop;
+    alert.delivery.webhook = Some(webhook.trim().to_string()).filter(|url| !url.is_empty());
+    alert
+}
+
+fn add_alert(alerts: Arc<Mutex<AlertBook>>, alert: Alert, ui_handle: slint::Weak<AppWindow>) {
+    update_alerts(alerts, ui_handle, move |book| {
+        book.add(alert);
+    });
+}
+
+/// Apply `change` to the alert book and show the updated list.
+fn update_alerts(
+    alerts: Arc<Mutex<AlertBook>>,
+    ui_handle: slint::Weak<AppWindow>,
+    change: impl FnOnce(&mut AlertBook) + Send + 'static,
+) {
+    tokio::spawn(async move {
+        let items = {
+            let mut book = alerts.lock().await;
+            change(&mut book);
+            alert_items(&book)
+        };
+        let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+            ui.set_alert_list(ModelRc::new(VecModel::from(items)));
+        });
+    });
+}
+
+/// Parse the alert form: a price for `Level`, a percentage for `Change`, a
+/// multiple of the average volume for `Volume`, and `EMA 20 / SMA 50` for
+/// `Cross`.
+fn parse_condition(kind: &str, value: &str) -> Option<AlertCondition> {
+    let value = value.trim();
+    match kind {
+        "Level" => Some(AlertCondition::Level {
+            price: value.parse().ok()?,
+            cross: Cross::Either,
+        }),
+        "Change" => Some(AlertCondition::PercentChange {
+            percent: value.trim_end_matches('%').parse().ok()?,
+        }),
+        "Volume" => Some(AlertCondition::VolumeSpike {
+            multiple: value.trim_end_matches(['x', 'X']).parse().ok()?,
+        }),
+        "Cross" => {
+            let (fast, slow) = value.split_once('/')?;
+            let (fast, slow) = (fast.trim(), slow.trim());
+            (!fast.is_empty() && !slow.is_empty()).then(|| AlertCondition::IndicatorCross {
+                fast: fast.to_uppercase(),
+                slow: slow.to_uppercase(),
+                cross: Cross::Either,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Rows of the alert list in the panel.
+pub fn alert_items(book: &AlertBook) -> Vec<SlintAlertItem> {
+    book.alerts()
+        .iter()
+        .map(|alert| {
+            let target = match &alert.target {
+                AlertTarget::Symbol(symbol) => symbol.clone(),
+                AlertTarget::Watchlist => "MY LIST".to_string(),
+            };
+            let mut text = format!("{target} {}", alert.condition.describe());
+            if !alert.note.is_empty() {
+                text = format!("{text}: {}", alert.note);
+            }
+            SlintAlertItem {
+                id: alert.id as i32,
+                text: text.into(),
+                enabled: alert.enabled,
+                repeat: alert.repeat,
+            }
+        })
+        .collect()
+}
+
+/// Evaluate `alerts` on fresh market watch rows.
+///
+/// # Arguments
+/// * `rows` - Latest market watch of every symbol
+/// * `watchlist` - Symbols of the user's list, for watchlist alerts
+/// * `volumes` - Average daily volumes, refreshed once a day as needed in a
+///   task of their own, so a volume spike alert waits for them
+///
+/// # Returns
+/// * `Option<DataUpdate>` - The alerts that fired with the updated list, if any fired
+pub async fn evaluate_alerts(
+    alerts: &Mutex<AlertBook>,
+    chart: &Mutex<ChartMetaData>,
+    rows: &[VCIMarketWatch],
+    watchlist: &[String],
+    volumes: &AverageVolumes,
+) -> Option<DataUpdate> {
+    let now = Utc::now();
+    let today = to_local(now).date_naive();
+    let mut snapshots: Vec<Snapshot> = rows
+        .iter()
+        .map(|row| Snapshot::from_market_watch(row, now.timestamp()))
+        .collect();
+
+    // Collect what the alerts need while the book is locked, fetch it after
+    let needs: Vec<(usize, bool, Vec<String>)> = {
+        let book = alerts.lock().await;
+        if book.alerts().iter().all(|alert| !alert.enabled) {
+            return None;
+        }
+        snapshots
+            .iter()
+            .enumerate()
+            .map(|(i, snapshot)| {
+                (
+                    i,
+                    book.needs_average_volume(&snapshot.symbol, watchlist),
+                    book.indicators_for(&snapshot.symbol, watchlist),
+                )
+            })
+            .filter(|(_, volume, indicators)| *volume || !indicators.is_empty())
+            .collect()
+    };
+    let mut missing = Vec::new();
+    for (i, needs_volume, indicators) in needs {
+        let snapshot = &mut snapshots[i];
+        if needs_volume {
+            let mut volumes = volumes.lock().await;
+            match volumes.get(&snapshot.symbol) {
+                Some((date, average)) if *date == today => snapshot.average_volume = *average,
+                _ => {
+                    volumes.insert(snapshot.symbol.clone(), (today, None));
+                    missing.push(snapshot.symbol.clone());
+                }
+            }
+        }
+        if !indicators.is_empty() {
+            // Indicators come from the symbol's chart, which follows the live bars
+            let mut charts = chart.lock().await;
+            if let Some(chart) = charts
+                .data
+                .iter_mut()
+                .find(|chart| chart.stock_name == snapshot.symbol)
+            {
+                snapshot.indicators = chart.indicator_values();
+            }
+        }
+    }
+
+    if !missing.is_empty() {
+        let volumes = Arc::clone(volumes);
+        tokio::spawn(async move {
+            for symbol in missing {
+                if let Some(average) = daily_average_volume(&symbol, today).await {
+                    volumes.lock().await.insert(symbol, (today, Some(average)));
+                }
+            }
+        });
+    }
+
+    let mut book = alerts.lock().await;
+    let triggered: Vec<Triggered> = snapshots
+        .iter()
+        .flat_map(|snapshot| book.evaluate(snapshot, watchlist))
+        .collect();
+    (!triggered.is_empty()).then(|| DataUpdate::Alerts(triggered, alert_items(&book)))
+}
+
+/// Average volume of the daily bars of `symbol` before `today`.
+async fn daily_average_volume(symbol: &str, today: NaiveDate) -> Option<f64> {
+    let from = Utc::now() - Duration::days(2 * AVERAGE_VOLUME_BARS as i64);
+    let candles = match get_history(symbol, "ONE_DAY", Some(from), None).await {
+        Ok(candles) => candles,
+        Err(e) => {
+            log::error!("Failed to fetch the volume history of {symbol}: {e}");
+            return None;
+        }
+    };
+    // Today's bar is still forming
+    let daily: Vec<f64> = candles
+        .iter()
+        .filter(|candle| to_local(candle.timestamp).date_naive() < today)
+        .map(|candle| candle.volume as f64)
+        .collect();
+    average_volume(&daily, AVERAGE_VOLUME_BARS)
+}
+
+/// Send fired alerts to the desktop and their webhooks.
+pub fn deliver(triggered: &[Triggered]) {
+    for alert in triggered {
+        if alert.delivery.desktop {
+            notify_desktop("AIM alert", &alert.message);
+        }
+        if let Some(url) = alert.delivery.webhook.clone() {
+            let alert = alert.clone();
+            tokio::spawn(async move {
+                if let Err(e) = post_webhook(&HttpClient::shared(), &url, &alert).await {
+                    log::error!("Failed to post alert {} to {url}: {e}", alert.alert_id);
+                }
+            });
+        }
+    }
+}
+
+/// Show a desktop notification with the notifier of the platform.
+fn notify_desktop(title: &str, body: &str) {
+    #[cfg(target_os = "linux")]
+    let result = std::process::Command::new("notify-send")
+        .arg(title)
+        .arg(body)
+        .spawn();
+    #[cfg(target_os = "macos")]
+    let result = std::process::Command::new("osascript")
+        .arg("-e")
+        .arg(format!(
+            "display notification {body:?} with title {title:?}"
+        ))
+        .spawn();
+    #[cfg(target_os = "windows")]
+    let result = {
+        use std::os::windows::process::CommandExt;
+        const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+        let quote = |text: &str| format!("'{}'", text.replace('\'', "''"));
+        let script = format!(
+            "Add-Type -AssemblyName System.Windows.Forms; \
+             $n = New-Object System.Windows.Forms.NotifyIcon; \
+             $n.Icon = [System.Drawing.SystemIcons]::Information; $n.Visible = $true; \
+             $n.ShowBalloonTip(5000, {}, {}, 'Info'); Start-Sleep 6; $n.Dispose()",
+            quote(title),
+            quote(body)
+        );
+        std::process::Command::new("powershell")
+            .args(["-NoProfile", "-Command", &script])
+            .creation_flags(CREATE_NO_WINDOW)
+            .spawn()
+    };
+    #[cfg(not(any(target_os = "linux", target_os = "macos", target_os = "windows")))]
+    let result: std::io::Result<std::process::Child> = Err(std::io::ErrorKind::Unsupported.into());
+
+    if let Err(e) = result {
+        log::error!("Failed to show desktop notification: {e}");
+    }
+}
+
+/// Text of a fired alert in the notification list, with the local time.
+pub fn notice_text(alert: &Triggered) -> SharedString {
+    let time = chrono::DateTime::from_timestamp(alert.time, 0)
+        .map(|time| to_local(time).format("%H:%M:%S").to_string())
+        .unwrap_or_default();
+    format!("{time} {}", alert.message).into()
+}
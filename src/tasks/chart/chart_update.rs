@@ -1,7 +1,10 @@
 use crate::tasks::task_manager::{register_task, TaskHandle};
 use crate::tasks::{chart::is_trading_hours, ChartMetaData};
-use aim_data::get_quote;
+use aim_data::{candle_store, get_quote, market_stream, MarketStream, StreamEvent};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
 use std::{sync::Arc, time::Duration};
+use tokio::sync::broadcast::{self, error::RecvError};
 use tokio::sync::Mutex;
 
 /// Spawns a task to handle real-time stock data updates
@@ -18,6 +21,10 @@ pub async fn spawn_chart_update_task(chart: Arc<Mutex<ChartMetaData>>) -> TaskHa
     tokio::spawn(async move {
         let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
         let mut stock_names: Vec<String> = vec!["AAA".to_string()];
+        let stream = market_stream();
+        let mut events = stream.as_ref().map(|stream| stream.events());
+        let mut subscribed: Vec<String> = Vec::new();
+        let mut refreshed = false;
         loop {
             if let Ok(status) = rx.try_recv() {
                 if task_status != status {
@@ -34,26 +41,87 @@ pub async fn spawn_chart_update_task(chart: Arc<Mutex<ChartMetaData>>) -> TaskHa
                 tokio::time::sleep(Duration::from_millis(100)).await;
                 continue;
             }
-            // Fetch updated chart data for all tracked stocks
-            let stock_name_slices: Vec<&str> = stock_names.iter().map(|s| s.as_str()).collect();
-            if let Ok(chart_data_vec) = get_quote(&stock_name_slices, "ONE_DAY", None, None).await {
-                let mut charts = chart_clone.lock().await;
-                // Update list of tracked stocks
-                let new_stock_names: Vec<String> = charts
-                    .data
-                    .iter()
+            // With a live feed, only refresh once a charted symbol has traded
+            if let (Some(stream), Some(events)) = (&stream, &mut events) {
+                sync_subscriptions(stream, &mut subscribed, &stock_names);
+                if stream.is_connected() && refreshed && !wait_for_tick(events, &stock_names).await
+                {
+                    continue;
+                }
+            }
+            refreshed = true;
+
+            // One request per base resolution, from the oldest last candle in that group
+            let groups: BTreeMap<&'static str, Option<DateTime<Utc>>> = {
+                let charts = chart_clone.lock().await;
+                stock_names = charts
+                    .charts()
                     .map(|chart| chart.stock_name.clone())
                     .collect();
-                stock_names = new_stock_names.clone();
+                // A layout cell may show a stock that has its own chart too
+                stock_names.sort();
+                stock_names.dedup();
+                let mut groups = BTreeMap::new();
+                for chart in charts.charts() {
+                    let from = groups
+                        .entry(chart.time_frame.base_resolution())
+                        .or_insert(None);
+                    if let Some(time) = chart.last_candle_time() {
+                        *from = Some(from.map_or(time, |from: DateTime<Utc>| from.min(time)));
+                    }
+                }
+                groups
+            };
+            for (resolution, from) in groups {
+                let symbols: Vec<String> = {
+                    let charts = chart_clone.lock().await;
+                    // Comparisons follow the feed along with their chart
+                    let mut symbols: Vec<String> = charts
+                        .charts()
+                        .filter(|chart| chart.time_frame.base_resolution() == resolution)
+                        .flat_map(|chart| {
+                            std::iter::once(chart.stock_name.clone())
+                                .chain(chart.loaded_comparisons())
+                        })
+                        .collect();
+                    symbols.sort();
+                    symbols.dedup();
+                    symbols
+                };
+                let stock_name_slices: Vec<&str> = symbols.iter().map(|s| s.as_str()).collect();
+                if let Ok(chart_data_vec) =
+                    get_quote(&stock_name_slices, resolution, from, None).await
+                {
+                    // Keep the local store current so the next launch starts offline
+                    if let Some(store) = candle_store() {
+                        for data in &chart_data_vec.0 {
+                            if let Err(e) =
+                                store.append(&data.symbol, resolution, &data.to_candlesticks())
+                            {
+                                log::warn!("Failed to store {} candles: {e}", data.symbol);
+                            }
+                        }
+                    }
+                    let mut charts = chart_clone.lock().await;
 
-                // Update chart data for each tracked stock
-                for chart in charts.data.iter_mut() {
-                    if let Some(updated_data) = chart_data_vec
-                        .0
-                        .iter()
-                        .find(|data| data.symbol == chart.stock_name)
+                    // Update chart data for each tracked stock
+                    for chart in charts
+                        .charts_mut()
+                        .filter(|chart| chart.time_frame.base_resolution() == resolution)
                     {
-                        chart.update_candle_data(updated_data.clone());
+                        if let Some(updated_data) = chart_data_vec
+                            .0
+                            .iter()
+                            .find(|data| data.symbol == chart.stock_name)
+                        {
+                            let appended = chart.merge_candle_data(updated_data.clone());
+                            if appended > 0 {
+                                log::debug!("Appended {appended} bars to {}", chart.stock_name);
+                            }
+                        }
+                        for data in &chart_data_vec.0 {
+                            chart.merge_comparison(data);
+                        }
                     }
                 }
             }
@@ -63,3 +131,44 @@ pub async fn spawn_chart_update_task(chart: Arc<Mutex<ChartMetaData>>) -> TaskHa
 
     task_handle
 }
+
+/// Subscribe to symbols newly shown on a chart and drop the others.
+fn sync_subscriptions(stream: &MarketStream, subscribed: &mut Vec<String>, stock_names: &[String]) {
+    let added: Vec<&str> = stock_names
+        .iter()
+        .filter(|name| !subscribed.contains(*name))
+        .map(String::as_str)
+        .collect();
+    let removed: Vec<&str> = subscribed
+        .iter()
+        .filter(|name| !stock_names.contains(*name))
+        .map(String::as_str)
+        .collect();
+    if !removed.is_empty() {
+        stream.unsubscribe(&removed);
+    }
+    if !added.is_empty() {
+        // Only the subscription matters, events are read from the shared receiver
+        let _ = stream.subscribe(&added);
+    }
+    if !added.is_empty() || !removed.is_empty() {
+        *subscribed = stock_names.to_vec();
+    }
+}
+
+/// Wait up to a second for a tick of one of `stock_names`.
+async fn wait_for_tick(
+    events: &mut broadcast::Receiver<StreamEvent>,
+    stock_names: &[String],
+) -> bool {
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(1);
+    loop {
+        match tokio::time::timeout_at(deadline, events.recv()).await {
+            Ok(Ok(StreamEvent::Tick(tick))) if stock_names.contains(&tick.symbol) => return true,
+            Ok(Ok(_)) => {}
+            // Missed events may include a tick for a charted symbol
+            Ok(Err(RecvError::Lagged(_))) => return true,
+            Ok(Err(RecvError::Closed)) | Err(_) => return false,
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use super::layout::request_render;
+use crate::slint_generatedAppWindow::AppWindow;
+use crate::tasks::task_manager::{register_task, TaskHandle};
+use crate::tasks::ChartMetaData;
+use aim_chart::parse_comparisons;
+use aim_data::{get_quote, Timeframe};
+use chrono::{DateTime, Utc};
+use slint::ComponentHandle;
+use std::collections::HashSet;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+
+/// How often to look for comparisons left on another time frame.
+const STALE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Spawns a task that applies the compare editor to the current chart and
+/// fetches the bars of new comparisons
+pub async fn spawn_compare_task(chart: Arc<Mutex<ChartMetaData>>, ui: &AppWindow) -> TaskHandle {
+    let ui_handle = ui.as_weak();
+
+    // Edits of the compare editor: stock, comparisons and ratio flag
+    let (compare_tx, mut compare_rx) = tokio::sync::mpsc::unbounded_channel();
+    let callback_handle = ui.as_weak();
+    ui.on_set_compare(move |spec, ratio| {
+        let Some(ui) = callback_handle.upgrade() else {
+            return;
+        };
+        let stock = ui.get_current_stock().symbol.to_uppercase();
+        let _ = compare_tx.send((stock, parse_comparisons(&spec), ratio));
+    });
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+    let task_handle = register_task(
+        "chart.compare".to_string(),
+        tx,
+        "Chart Compare Task".to_string(),
+    )
+    .await;
+
+    tokio::spawn(async move {
+        let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
+        // Comparisons that have no bars to fetch: chart, comparison and time
+        // frame, until the comparisons of that chart are edited
+        let mut failed: HashSet<(String, String, Timeframe)> = HashSet::new();
+        loop {
+            if let Ok(status) = rx.try_recv() {
+                if task_status != status {
+                    log::info!("Chart compare task status changed to: {:?}", status);
+                    task_status = status;
+                }
+            }
+            if task_status != crate::tasks::task_manager::TaskStatus::Running {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+
+            // Fetch the comparisons just added or left on another time frame
+            let stale: Vec<(String, Timeframe, Option<DateTime<Utc>>, Vec<String>)> = {
+                let charts = chart.lock().await;
+                charts
+                    .data
+                    .iter()
+                    .filter_map(|chart| {
+                        let symbols: Vec<String> = chart
+                            .stale_comparisons()
+                            .into_iter()
+                            .filter(|symbol| {
+                                !failed.contains(&(
+                                    chart.stock_name.clone(),
+                                    symbol.clone(),
+                                    chart.time_frame,
+                                ))
+                            })
+                            .collect();
+                        (!symbols.is_empty()).then(|| {
+                            (
+                                chart.stock_name.clone(),
+                                chart.time_frame,
+                                chart.first_candle_time(),
+                                symbols,
+                            )
+                        })
+                    })
+                    .collect()
+            };
+            for (stock, time_frame, from, symbols) in stale {
+                let symbol_slices: Vec<&str> = symbols.iter().map(String::as_str).collect();
+                let resolution = time_frame.base_resolution();
+                // A transient error is retried on the next round
+                let (quotes, retry) = match get_quote(&symbol_slices, resolution, from, None).await
+                {
+                    Ok(quotes) => (quotes.0, false),
+                    Err(e) => {
+                        log::error!("Failed to fetch {symbols:?} to compare with {stock}: {e}");
+                        (Vec::new(), e.is_transient())
+                    }
+                };
+                {
+                    let mut charts = chart.lock().await;
+                    if let Some(chart) = charts
+                        .data
+                        .iter_mut()
+                        .find(|chart| chart.stock_name == stock && chart.time_frame == time_frame)
+                    {
+                        for data in &quotes {
+                            chart.update_comparison(data);
+                        }
+                    }
+                }
+                if !retry {
+                    for symbol in symbols {
+                        if !quotes
+                            .iter()
+                            .any(|data| data.symbol == symbol && !data.c.is_empty())
+                        {
+                            failed.insert((stock.clone(), symbol, time_frame));
+                        }
+                    }
+                }
+                request_render(ui_handle.clone());
+            }
+
+            // Wait for an edit of the comparisons, checking now and then for
+            // charts that moved to another time frame
+            let edit = tokio::select! {
+                edit = compare_rx.recv() => edit,
+                _ = tokio::time::sleep(STALE_CHECK_INTERVAL) => None,
+            };
+            if let Some((stock, symbols, ratio)) = edit {
+                failed.retain(|(chart, _, _)| *chart != stock);
+                if let Some(chart) = chart
+                    .lock()
+                    .await
+                    .data
+                    .iter_mut()
+                    .find(|chart| chart.stock_name == stock)
+                {
+                    chart.set_comparisons(&symbols, ratio);
+                }
+                request_render(ui_handle.clone());
+            }
+        }
+    });
+
+    task_handle
+}
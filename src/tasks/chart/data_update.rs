@@ -1,3 +1,4 @@
+use super::alert::{deliver, evaluate_alerts, notice_text, AverageVolumes};
 use super::convert_to_market_data;
 use super::convert_to_stock_data;
 use crate::slint_generatedAppWindow::{
@@ -10,32 +11,47 @@ use crate::tasks::chart::sort_market_watch;
 use crate::tasks::chart::ALL_STOCK_LIST;
 use crate::tasks::task_manager::TaskStatus;
 use crate::tasks::task_manager::{register_task, TaskHandle};
-use crate::tasks::DataUpdate;
+use crate::tasks::{ChartMetaData, DataUpdate};
+use aim_data::alert::AlertBook;
+use aim_data::explorer::vci::market_watch::VCIMarketWatch;
 use aim_data::explorer::vci::VCIOderBook;
 use aim_data::get_market_watch;
 use aim_data::get_order_list;
+use aim_data::{market_stream, MarketStream, StreamEvent};
 use chrono::Timelike;
 use slint::Weak;
-use slint::{ComponentHandle, ModelRc};
+use slint::{ComponentHandle, Model, ModelRc};
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
 use tokio::sync::mpsc;
 use tokio::sync::Mutex;
 
 use super::VN30_LIST;
 
+/// Delay between REST polls while no market stream is connected
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+/// Longest wait for a stream event, so task status changes are still noticed
+const STREAM_WAIT: Duration = Duration::from_millis(500);
+
 /// Spawns a task to handle market watch data updates
 pub async fn spawn_data_update_task(
     ui: &crate::slint_generatedAppWindow::AppWindow,
     custom_list: Arc<Mutex<Vec<String>>>,
+    alerts: Arc<Mutex<AlertBook>>,
+    chart: Arc<Mutex<ChartMetaData>>,
 ) -> Vec<TaskHandle> {
     let mut handles = Vec::new();
     let (tx_data_update, rx_data_update) = mpsc::channel::<DataUpdate>(10);
     let ui_handle = ui.as_weak();
 
     // Create individual task handles for each sub-task
-    handles.push(spawn_custom_list_polling_task(tx_data_update.clone(), custom_list).await);
-    handles.push(spawn_stock_data_polling_task(tx_data_update.clone()).await);
+    handles.push(
+        spawn_custom_list_polling_task(tx_data_update.clone(), Arc::clone(&custom_list)).await,
+    );
+    handles.push(
+        spawn_stock_data_polling_task(tx_data_update.clone(), custom_list, alerts, chart).await,
+    );
     handles.push(spawn_market_watch_polling_task(tx_data_update.clone()).await);
     handles.push(spawn_order_list_polling_task(tx_data_update.clone(), ui_handle.clone()).await);
     handles.push(spawn_ui_update_task(ui_handle, rx_data_update).await);
@@ -63,7 +79,12 @@ async fn spawn_custom_list_polling_task(
     task_handle
 }
 
-async fn spawn_stock_data_polling_task(tx: mpsc::Sender<DataUpdate>) -> TaskHandle {
+async fn spawn_stock_data_polling_task(
+    tx: mpsc::Sender<DataUpdate>,
+    custom_list: Arc<Mutex<Vec<String>>>,
+    alerts: Arc<Mutex<AlertBook>>,
+    chart: Arc<Mutex<ChartMetaData>>,
+) -> TaskHandle {
     let (tx_task, rx) = tokio::sync::mpsc::channel(10);
     let task_handle = register_task(
         "chart.data_update.stock_data".to_string(),
@@ -73,7 +94,7 @@ async fn spawn_stock_data_polling_task(tx: mpsc::Sender<DataUpdate>) -> TaskHand
     .await;
 
     tokio::spawn(async move {
-        polling_all_stock_data(tx, rx).await;
+        polling_all_stock_data(tx, rx, custom_list, alerts, chart).await;
     });
 
     task_handle
@@ -190,6 +211,9 @@ async fn polling_order_list(
 ) {
     let current_stock = Arc::new(Mutex::new(String::from("AAA")));
     let mut previous_order_list: Option<Vec<VCIOderBook>> = None;
+    let stream = market_stream();
+    let mut events: Option<broadcast::Receiver<StreamEvent>> = None;
+    let mut subscribed_symbol: Option<String> = None;
     let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
     loop {
         if let Ok(status) = rx.try_recv() {
@@ -220,6 +244,30 @@ async fn polling_order_list(
             });
         });
 
+        // Follow the current symbol on the stream; the REST snapshot below seeds the list
+        if let Some(stream) = &stream {
+            if subscribed_symbol.as_deref() != Some(current_stock_str.as_str()) {
+                if let Some(previous) = subscribed_symbol.take() {
+                    stream.unsubscribe(&[previous.as_str()]);
+                }
+                events = Some(stream.subscribe(&[current_stock_str.as_str()]));
+                subscribed_symbol = Some(current_stock_str.clone());
+                previous_order_list = None;
+            }
+            if let (Some(events), Some(order_list)) = (&mut events, &mut previous_order_list) {
+                if stream.is_connected() {
+                    match next_trades(events, &current_stock_str, order_list).await {
+                        Some(true) => {
+                            tx.send(DataUpdate::OrdList(order_list.clone())).await.ok();
+                        }
+                        Some(false) => {}
+                        None => previous_order_list = None,
+                    }
+                    continue;
+                }
+            }
+        }
+
         log::info!("Polling order list for stock: {current_stock_str}");
         let (order_changed, order_list) = match get_order_list(&current_stock_str).await {
             Ok(order_list) => {
@@ -253,7 +301,15 @@ async fn polling_order_list(
     }
 }
 
-async fn polling_all_stock_data(tx: mpsc::Sender<DataUpdate>, mut rx: mpsc::Receiver<TaskStatus>) {
+async fn polling_all_stock_data(
+    tx: mpsc::Sender<DataUpdate>,
+    mut rx: mpsc::Receiver<TaskStatus>,
+    custom_list: Arc<Mutex<Vec<String>>>,
+    alerts: Arc<Mutex<AlertBook>>,
+    chart: Arc<Mutex<ChartMetaData>>,
+) {
+    let mut live = LiveMarketWatch::new(&ALL_STOCK_LIST);
+    let average_volumes = AverageVolumes::default();
     let mut previous_stock_data: Option<Vec<SlintStockData>> = None;
     let mut is_first_update = true; // Track if this is the first update
     let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
@@ -279,19 +335,26 @@ async fn polling_all_stock_data(tx: mpsc::Sender<DataUpdate>, mut rx: mpsc::Rece
             continue;
         }
 
-        // Fetch market data
-        let market_watch_data = match get_market_watch(&ALL_STOCK_LIST).await {
-            Ok(data) => data,
+        // Wait for fresh market data
+        match live.next().await {
+            Ok(true) => {}
+            Ok(false) => continue,
             Err(e) => {
                 log::error!("Failed to fetch market watch data: {e}. try again ...");
                 continue;
             }
         };
-        let mut all_stock_data: Vec<SlintStockData> = market_watch_data
-            .0
-            .iter()
-            .map(convert_to_stock_data)
-            .collect();
+
+        // Check the alerts on every update of the feed
+        let watchlist = custom_list.lock().await.clone();
+        if let Some(update) =
+            evaluate_alerts(&alerts, &chart, &live.rows, &watchlist, &average_volumes).await
+        {
+            tx.send(update).await.ok();
+        }
+
+        let mut all_stock_data: Vec<SlintStockData> =
+            live.rows.iter().map(convert_to_stock_data).collect();
 
         // Check if market watch data has changed
         let market_watch_changed =
@@ -302,11 +365,11 @@ async fn polling_all_stock_data(tx: mpsc::Sender<DataUpdate>, mut rx: mpsc::Rece
             previous_stock_data = Some(all_stock_data.clone());
             tx.send(DataUpdate::StockData(all_stock_data)).await.ok();
         }
-        tokio::time::sleep(Duration::from_millis(100)).await;
     }
 }
 
 async fn polling_market_watch(tx: mpsc::Sender<DataUpdate>, mut rx: mpsc::Receiver<TaskStatus>) {
+    let mut live = LiveMarketWatch::new(&VN30_LIST);
     let mut previous_market_watch_data: Option<Vec<SlintMarketWatchData>> = None;
     let mut is_first_update = true; // Track if this is the first update
     let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
@@ -332,19 +395,17 @@ async fn polling_market_watch(tx: mpsc::Sender<DataUpdate>, mut rx: mpsc::Receiv
             continue;
         }
 
-        // Fetch market data
-        let market_watch_data = match get_market_watch(&VN30_LIST).await {
-            Ok(data) => data,
+        // Wait for fresh market data
+        match live.next().await {
+            Ok(true) => {}
+            Ok(false) => continue,
             Err(e) => {
                 log::error!("Failed to fetch market watch data: {e}. try again ...");
                 continue;
             }
         };
-        let market_watch_stock_data: Vec<SlintMarketWatchData> = market_watch_data
-            .0
-            .iter()
-            .map(convert_to_market_data)
-            .collect();
+        let market_watch_stock_data: Vec<SlintMarketWatchData> =
+            live.rows.iter().map(convert_to_market_data).collect();
 
         // Check if market watch data has changed
         let market_watch_changed =
@@ -357,7 +418,6 @@ async fn polling_market_watch(tx: mpsc::Sender<DataUpdate>, mut rx: mpsc::Receiv
                 .await
                 .ok();
         }
-        tokio::time::sleep(Duration::from_millis(100)).await;
     }
 }
 
@@ -445,6 +505,19 @@ async fn update_ui_with_data(
                         ui.set_order_list(ModelRc::new(slint::VecModel::from(slint_order_list)));
                     });
                 }
+                DataUpdate::Alerts(triggered, items) => {
+                    deliver(&triggered);
+                    let notices: Vec<slint::SharedString> =
+                        triggered.iter().map(notice_text).collect();
+                    let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                        // Newest first, keeping the last hundred
+                        let previous = ui.get_alert_notices();
+                        let mut all: Vec<slint::SharedString> = notices.into_iter().rev().collect();
+                        all.extend(previous.iter().take(100_usize.saturating_sub(all.len())));
+                        ui.set_alert_notices(ModelRc::new(slint::VecModel::from(all)));
+                        ui.set_alert_list(ModelRc::new(slint::VecModel::from(items)));
+                    });
+                }
                 DataUpdate::CustomList(items) => {
                     previous_custom_list = Some(items.clone());
                     let previous_stock_data_clone = previous_stock_data.clone();
@@ -467,6 +540,109 @@ async fn update_ui_with_data(
     }
 }
 
+/// Market watch rows for a fixed symbol list, kept current from the market
+/// stream while it is connected and polled over REST otherwise.
+struct LiveMarketWatch {
+    symbols: &'static [&'static str],
+    rows: Vec<VCIMarketWatch>,
+    stream: Option<(MarketStream, broadcast::Receiver<StreamEvent>)>,
+    polled: bool,
+}
+
+impl LiveMarketWatch {
+    fn new(symbols: &'static [&'static str]) -> Self {
+        let stream = market_stream().map(|stream| {
+            let events = stream.subscribe(symbols);
+            (stream, events)
+        });
+        Self {
+            symbols,
+            rows: Vec::new(),
+            stream,
+            polled: false,
+        }
+    }
+
+    /// Wait for the next update of `rows`.
+    ///
+    /// # Returns
+    /// * `Ok(true)` - `rows` changed
+    /// * `Ok(false)` - nothing changed within [`STREAM_WAIT`]
+    async fn next(&mut self) -> aim_data::Result<bool> {
+        if let Some((stream, events)) = &mut self.stream {
+            if stream.is_connected() && !self.rows.is_empty() {
+                let first = match tokio::time::timeout(STREAM_WAIT, events.recv()).await {
+                    Ok(Ok(event)) => event,
+                    Ok(Err(RecvError::Lagged(skipped))) => {
+                        log::warn!("Market watch missed {skipped} stream events, reloading");
+                        self.rows.clear();
+                        return Ok(false);
+                    }
+                    Ok(Err(RecvError::Closed)) | Err(_) => return Ok(false),
+                };
+                // Fold a burst of events into a single UI update
+                let mut changed = self.rows.iter_mut().any(|row| row.apply(&first));
+                while let Ok(event) = events.try_recv() {
+                    changed |= self.rows.iter_mut().any(|row| row.apply(&event));
+                }
+                return Ok(changed);
+            }
+        }
+
+        if self.polled {
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+        self.polled = true;
+        self.rows = get_market_watch(self.symbols).await?.0;
+        Ok(true)
+    }
+}
+
+impl Drop for LiveMarketWatch {
+    fn drop(&mut self) {
+        if let Some((stream, _)) = &self.stream {
+            stream.unsubscribe(self.symbols);
+        }
+    }
+}
+
+/// Prepend trades for `symbol` arriving on the stream to `order_list`.
+///
+/// # Returns
+/// * `Some(bool)` - Whether any trade was added
+/// * `None` - Events were missed and the list must be reloaded
+async fn next_trades(
+    events: &mut broadcast::Receiver<StreamEvent>,
+    symbol: &str,
+    order_list: &mut Vec<VCIOderBook>,
+) -> Option<bool> {
+    let mut trades = Vec::new();
+    let mut received = match tokio::time::timeout(STREAM_WAIT, events.recv()).await {
+        Ok(Ok(event)) => Some(event),
+        Ok(Err(RecvError::Lagged(_))) => return None,
+        Ok(Err(RecvError::Closed)) | Err(_) => return Some(false),
+    };
+    while let Some(event) = received {
+        if let StreamEvent::Trade(trade) = &event {
+            // The snapshot may already contain trades that were in flight
+            let known = order_list
+                .iter()
+                .take(100)
+                .any(|order| order.id == trade.id);
+            if trade.symbol == symbol && !known {
+                trades.push(VCIOderBook::from(trade));
+            }
+        }
+        received = events.try_recv().ok();
+    }
+
+    // Newest first, like the REST endpoint
+    trades.reverse();
+    let added = !trades.is_empty();
+    order_list.splice(0..0, trades);
+    Some(added)
+}
+
 /// Checks if market watch data has changed
 fn has_market_watch_changed(
     previous: &Option<Vec<SlintMarketWatchData>>,
@@ -0,0 +1,175 @@
+use crate::slint_generatedAppWindow::{AppWindow, ChartCell as SlintChartCell};
+use crate::tasks::task_manager::{register_task, TaskHandle};
+use crate::tasks::ChartMetaData;
+use aim_chart::{Chart, ChartLayout, CompanyInfo, LayoutKind};
+use aim_data::{get_history, OHLCData, Timeframe};
+use chrono::{DateTime, Utc};
+use slint::{ComponentHandle, Model, ModelRc, VecModel, Weak};
+use std::collections::HashMap;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+
+/// Wire the layout picker and the cell editors to the layout in `chart`,
+/// showing the layout restored from the cache.
+pub async fn setup_layout_callbacks(ui: &AppWindow, chart: Arc<Mutex<ChartMetaData>>) {
+    {
+        let charts = chart.lock().await;
+        let layout = charts.layout();
+        ui.set_layout_kind(layout.kind.label().into());
+        ui.set_link_symbol(layout.link_symbol);
+        ui.set_link_crosshair(layout.link_crosshair);
+        ui.set_link_time_frame(layout.link_time_frame);
+    }
+
+    let chart_clone = Arc::clone(&chart);
+    let ui_handle = ui.as_weak();
+    ui.on_set_layout(move |kind, link_symbol, link_crosshair, link_time_frame| {
+        let Some(ui) = ui_handle.upgrade() else {
+            return;
+        };
+        let kind = LayoutKind::parse(&kind).unwrap_or_default();
+        let symbol = ui.get_current_stock().symbol.to_uppercase();
+        let time_frame = Timeframe::parse(&ui.get_ui_data().time_frame).unwrap_or_default();
+        let chart = Arc::clone(&chart_clone);
+        let ui_handle = ui_handle.clone();
+        tokio::spawn(async move {
+            {
+                let mut charts = chart.lock().await;
+                let layout = &mut charts.layout;
+                layout.set_kind(kind, &symbol, time_frame);
+                layout.link_symbol = link_symbol;
+                layout.link_crosshair = link_crosshair;
+                layout.link_time_frame = link_time_frame;
+                layout.follow(&symbol, time_frame);
+                charts.mark_dirty();
+            }
+            request_render(ui_handle);
+        });
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_set_layout_cell(move |index, symbol, time_frame| {
+        let Some(time_frame) = Timeframe::parse(&time_frame) else {
+            log::error!("Invalid time frame {time_frame} for layout cell {index}");
+            return;
+        };
+        let chart = Arc::clone(&chart);
+        let ui_handle = ui_handle.clone();
+        tokio::spawn(async move {
+            {
+                let mut charts = chart.lock().await;
+                charts.layout.set_cell(index as usize, &symbol, time_frame);
+                charts.mark_dirty();
+            }
+            request_render(ui_handle);
+        });
+    });
+}
+
+/// Render the chart again, e.g. after the layout changed.
+pub(super) fn request_render(ui_handle: Weak<AppWindow>) {
+    let _ = ui_handle.upgrade_in_event_loop(|ui| {
+        let mut ui_data = ui.get_ui_data();
+        ui_data.is_in_update = true;
+        ui.set_ui_data(ui_data);
+    });
+}
+
+/// Spawns a task that fetches the bars of layout cells showing a new symbol
+/// or time frame
+pub async fn spawn_layout_task(chart: Arc<Mutex<ChartMetaData>>, ui: &AppWindow) -> TaskHandle {
+    let ui_handle = ui.as_weak();
+    let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+    let task_handle = register_task(
+        "chart.layout".to_string(),
+        tx,
+        "Chart Layout Task".to_string(),
+    )
+    .await;
+
+    tokio::spawn(async move {
+        let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
+        // Last failed fetch of each cell, not retried until the cell changes
+        let mut failed: HashMap<usize, (String, Timeframe)> = HashMap::new();
+        loop {
+            if let Ok(status) = rx.try_recv() {
+                if task_status != status {
+                    log::info!("Chart layout task status changed to: {:?}", status);
+                    task_status = status;
+                }
+            }
+            if task_status != crate::tasks::task_manager::TaskStatus::Running {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+
+            let stale = chart.lock().await.layout.stale_cells();
+            for (index, symbol, time_frame) in stale {
+                if failed.get(&index) == Some(&(symbol.clone(), time_frame)) {
+                    continue;
+                }
+                match get_history(&symbol, time_frame.base_resolution(), None, None).await {
+                    Ok(candles) if !candles.is_empty() => {
+                        let stock_data = OHLCData::from_candlesticks(&symbol, &candles);
+                        let cell_chart = Chart::new_default(
+                            symbol,
+                            stock_data,
+                            CompanyInfo::default(),
+                            time_frame,
+                        );
+                        chart.lock().await.layout.set_chart(index, cell_chart);
+                        failed.remove(&index);
+                        request_render(ui_handle.clone());
+                    }
+                    Ok(_) => {
+                        log::error!("No bars of {symbol} for layout cell {index}");
+                        failed.insert(index, (symbol, time_frame));
+                    }
+                    Err(e) => {
+                        log::error!("Failed to fetch {symbol} for layout cell {index}: {e}");
+                        failed.insert(index, (symbol, time_frame));
+                    }
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    });
+
+    task_handle
+}
+
+/// Render the layout cells and show them, reusing the rows of the model
+/// while the number of cells stays the same and leaving unchanged rows alone.
+///
+/// # Arguments
+/// * `width` / `height` - Size of the chart of the current stock, which every cell shares
+/// * `cursor` - Crosshair time of that chart
+pub fn show_layout_cells(
+    ui: &AppWindow,
+    layout: &mut ChartLayout,
+    width: i32,
+    height: i32,
+    cursor: Option<DateTime<Utc>>,
+) {
+    let images = layout.render_cells(width, height, cursor);
+    let cells: Vec<SlintChartCell> = layout
+        .cells
+        .iter()
+        .zip(images)
+        .map(|(cell, image)| SlintChartCell {
+            symbol: cell.symbol.clone().into(),
+            time_frame: cell.time_frame.to_string().into(),
+            image,
+        })
+        .collect();
+    let model = ui.get_layout_cells();
+    if model.row_count() == cells.len() {
+        for (i, cell) in cells.into_iter().enumerate() {
+            if model.row_data(i).as_ref() != Some(&cell) {
+                model.set_row_data(i, cell);
+            }
+        }
+    } else {
+        ui.set_layout_cells(ModelRc::new(VecModel::from(cells)));
+    }
+}
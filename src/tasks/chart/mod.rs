@@ -4,20 +4,27 @@ use crate::slint_generatedAppWindow::{
     MarketWatchData as SlintMarketWatchData, ShortType, StockData as SlintStockData,
     StockGroup as SlintStockGroup,
 };
-use chrono::{Datelike, Timelike};
 
+mod alert;
 mod chart_update;
+mod compare;
 mod company_profile;
 mod data_update;
 mod finance_sheet;
+mod layout;
+mod share;
 mod stock_update;
 mod ui_chart;
 mod finance_report;
 
+pub use alert::*;
 pub use chart_update::*;
+pub use compare::*;
 pub use company_profile::*;
 pub use data_update::*;
 pub use finance_sheet::*;
+pub use layout::*;
+pub use share::*;
 pub use stock_update::*;
 pub use ui_chart::*;
 pub use finance_report::*;
@@ -343,30 +350,22 @@ pub fn convert_to_stock_data(market_watch: &VCIMarketWatch) -> SlintStockData {
     }
 }
 
+/// Whether a trading day of the calendar of `aim_data` is between the
+/// morning open and the afternoon close now. The lunch break counts, as it
+/// always has for the data updates, so the 11:30 close print still arrives.
 pub fn is_trading_hours() -> bool {
+    use aim_data::calendar::{
+        is_trading_day, minute_of_day, to_local, AFTERNOON_SESSION, MORNING_SESSION,
+    };
     let now = chrono::Utc::now();
-    // Convert to Vietnam time (UTC+7)
-    let vietnam_time = now + chrono::Duration::hours(7);
-    let current_hour = vietnam_time.hour();
-    let current_minute = vietnam_time.minute();
-
-    // Trading hours: 9:00 AM to 3:00 PM (15:00)
-    let start_time = 9 * 60; // 9:00 AM in minutes
-    let end_time = 15 * 60; // 3:00 PM in minutes
-    let current_time_minutes = current_hour * 60 + current_minute;
-
-    // Also check if it's a weekday (Monday = 1, Sunday = 7)
-    let is_weekday = vietnam_time.weekday().num_days_from_monday() < 5;
+    let is_trading = is_trading_day(to_local(now).date_naive())
+        && (MORNING_SESSION.open..AFTERNOON_SESSION.close).contains(&minute_of_day(now));
     log::info!(
-        "Current time: {}:{} - Trading hours: {} to {} - Is weekday: {}",
-        current_hour,
-        current_minute,
-        start_time / 60,
-        end_time / 60,
-        is_weekday
+        "Current time: {} - Trading hours: {}",
+        to_local(now).format("%a %H:%M"),
+        is_trading
     );
-
-    is_weekday && current_time_minutes >= start_time && current_time_minutes < end_time
+    is_trading
 }
 
 /// Create sector-specific stock groups based on watchlist category
@@ -0,0 +1,231 @@
+use crate::slint_generatedAppWindow::AppWindow;
+use crate::tasks::ChartMetaData;
+use aim_chart::{Chart, ChartState, PriceScale, CHART_STATE_VERSION};
+use aim_data::Timeframe;
+use chrono::Local;
+use rfd::AsyncFileDialog;
+use slint::ComponentHandle;
+use std::path::Path;
+use std::{sync::Arc, time::Duration};
+use tokio::sync::Mutex;
+
+/// Size of a PNG export when the chart has not been laid out yet
+const DEFAULT_PNG_SIZE: (u32, u32) = (1200, 800);
+/// How long an imported state waits for the bars of its symbol
+const IMPORT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Wire the PNG export and the chart state export and import of the chart
+/// toolbar to the chart of the current stock.
+pub fn setup_share_callbacks(ui: &AppWindow, chart: Arc<Mutex<ChartMetaData>>) {
+    let chart_clone = Arc::clone(&chart);
+    let ui_handle = ui.as_weak();
+    ui.on_export_png(move |resolution, watermark| {
+        let Some(ui) = ui_handle.upgrade() else {
+            return;
+        };
+        let ui_data = ui.get_ui_data();
+        let size = parse_resolution(&resolution)
+            .or_else(|| {
+                let (width, height) = (ui_data.width as u32, ui_data.height as u32);
+                (width > 0 && height > 0).then_some((width, height))
+            })
+            .unwrap_or(DEFAULT_PNG_SIZE);
+        let symbol = ui.get_current_stock().symbol.to_uppercase();
+        let time_frame = ui_data.time_frame.to_string();
+        let chart = Arc::clone(&chart_clone);
+        tokio::spawn(async move {
+            let Some(file) = AsyncFileDialog::new()
+                .add_filter("PNG", &["png"])
+                .set_file_name(format!("{symbol}_{time_frame}.png"))
+                .save_file()
+                .await
+            else {
+                return;
+            };
+            let Some(snapshot) = find_chart(&chart, &symbol).await else {
+                log::error!("No chart of {symbol} to export");
+                return;
+            };
+            let watermark = watermark.then(|| {
+                format!(
+                    "AIM Trading Pro  {symbol} {time_frame}  {}",
+                    Local::now().format("%d/%m/%Y %H:%M")
+                )
+            });
+            let result = tokio::task::block_in_place(|| {
+                snapshot.export_png(file.path(), size.0, size.1, watermark.as_deref())
+            });
+            match result {
+                Ok(()) => log::info!("Saved the {symbol} chart to {}", file.path().display()),
+                Err(e) => log::error!("Failed to save the {symbol} chart: {e}"),
+            }
+        });
+    });
+
+    let chart_clone = Arc::clone(&chart);
+    let ui_handle = ui.as_weak();
+    ui.on_export_state(move || {
+        let Some(ui) = ui_handle.upgrade() else {
+            return;
+        };
+        let symbol = ui.get_current_stock().symbol.to_uppercase();
+        let chart = Arc::clone(&chart_clone);
+        tokio::spawn(async move {
+            let Some(state) = find_chart(&chart, &symbol)
+                .await
+                .map(|chart| chart.export_state())
+            else {
+                log::error!("No chart of {symbol} to export");
+                return;
+            };
+            let Some(file) = AsyncFileDialog::new()
+                .add_filter("Chart state", &["json"])
+                .set_file_name(format!("{symbol}_{}.json", state.time_frame))
+                .save_file()
+                .await
+            else {
+                return;
+            };
+            match save_state(file.path(), &state) {
+                Ok(()) => log::info!(
+                    "Saved the {symbol} chart state to {}",
+                    file.path().display()
+                ),
+                Err(e) => log::error!("Failed to save the {symbol} chart state: {e}"),
+            }
+        });
+    });
+
+    let ui_handle = ui.as_weak();
+    ui.on_import_state(move || {
+        let chart = Arc::clone(&chart);
+        let ui_handle = ui_handle.clone();
+        tokio::spawn(async move {
+            let Some(file) = AsyncFileDialog::new()
+                .add_filter("Chart state", &["json"])
+                .pick_file()
+                .await
+            else {
+                return;
+            };
+            let state = match load_state(file.path()) {
+                Ok(state) => state,
+                Err(e) => {
+                    log::error!("Failed to read chart state {}: {e}", file.path().display());
+                    return;
+                }
+            };
+            import_state(chart, ui_handle, state).await;
+        });
+    });
+}
+
+/// Parse a resolution such as `1920x1080`; anything else is the size of
+/// the chart on screen.
+fn parse_resolution(text: &str) -> Option<(u32, u32)> {
+    let (width, height) = text.trim().split_once(['x', 'X'])?;
+    let size = (width.trim().parse().ok()?, height.trim().parse().ok()?);
+    (size.0 > 0 && size.1 > 0).then_some(size)
+}
+
+/// A copy of the chart of `symbol`, to render or export without holding the
+/// lock.
+async fn find_chart(chart: &Mutex<ChartMetaData>, symbol: &str) -> Option<Chart> {
+    chart
+        .lock()
+        .await
+        .data
+        .iter()
+        .find(|chart| chart.stock_name == symbol)
+        .cloned()
+}
+
+/// Save chart state to a JSON file
+fn save_state(path: &Path, state: &ChartState) -> anyhow::Result<()> {
+    let json = serde_json::to_string_pretty(state)?;
+    std::fs::write(path, json)?;
+    Ok(())
+}
+
+/// Load chart state from a JSON file
+fn load_state(path: &Path) -> anyhow::Result<ChartState> {
+    let json = std::fs::read_to_string(path)?;
+    let state: ChartState = serde_json::from_str(&json)?;
+    if state.version > CHART_STATE_VERSION {
+        return Err(anyhow::anyhow!(
+            "Chart state version {} is newer than {CHART_STATE_VERSION}",
+            state.version
+        ));
+    }
+    Ok(state)
+}
+
+/// Switch the chart page to the symbol and time frame of `state`, then show
+/// the state once their bars are loaded.
+async fn import_state(
+    chart: Arc<Mutex<ChartMetaData>>,
+    ui_handle: slint::Weak<AppWindow>,
+    state: ChartState,
+) {
+    let Some(time_frame) = Timeframe::parse(&state.time_frame) else {
+        log::error!("Invalid time frame {} in chart state", state.time_frame);
+        return;
+    };
+    let symbol = state.symbol.to_uppercase();
+
+    let (stock, adjusted) = (symbol.clone(), state.adjusted);
+    let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+        let mut current_stock = ui.get_current_stock();
+        current_stock.symbol = stock.into();
+        ui.set_current_stock(current_stock);
+        let mut ui_data = ui.get_ui_data();
+        ui_data.time_frame = time_frame.to_string().into();
+        ui_data.is_adjusted = adjusted;
+        ui_data.is_new_stock = true;
+        ui.set_ui_data(ui_data);
+    });
+
+    let loaded = chart.lock().await.loaded();
+    let deadline = tokio::time::Instant::now() + IMPORT_TIMEOUT;
+    loop {
+        let notified = loaded.notified();
+        tokio::pin!(notified);
+        {
+            let mut charts = chart.lock().await;
+            if let Some(chart) = charts.data.iter_mut().find(|chart| {
+                chart.stock_name == symbol
+                    && chart.time_frame == time_frame
+                    && chart.adjusted == state.adjusted
+                    && !chart.source_data.is_empty()
+            }) {
+                chart.import_state(&state);
+                break;
+            }
+            // Under the lock, so bars loaded from now on wake the wait
+            notified.as_mut().enable();
+        }
+        if tokio::time::timeout_at(deadline, notified).await.is_err() {
+            log::error!("Timed out loading {symbol} to import its chart state");
+            return;
+        }
+    }
+    log::info!("Imported the {symbol} chart state");
+
+    // Show the imported settings in the toolbar and render them
+    let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+        let mut ui_data = ui.get_ui_data();
+        ui_data.indicators = state.indicators.into();
+        ui_data.compare = state.compare.join(", ").into();
+        ui_data.is_compare_ratio = state.compare_ratio;
+        ui_data.chart_type = state.chart_type.into();
+        if !state.box_size.is_empty() {
+            ui_data.box_size = state.box_size.into();
+        }
+        let scale = PriceScale::parse(&state.price_scale).unwrap_or_default();
+        ui_data.price_scale = scale.label().into();
+        ui_data.is_auto_fit = state.auto_fit;
+        ui_data.is_lock_scale = state.lock_scale;
+        ui_data.is_in_update = true;
+        ui.set_ui_data(ui_data);
+    });
+}
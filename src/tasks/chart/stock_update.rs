@@ -1,11 +1,12 @@
-use crate::interval_to_constant;
 use crate::slint_generatedAppWindow::AppWindow;
 use crate::tasks::task_manager::{register_task, TaskHandle};
-use crate::tasks::ChartMetaData;
+use crate::tasks::{report_fetch_error, ChartMetaData};
 use aim_chart::Chart;
 use aim_chart::CompanyInfo;
 use aim_data::get_company_info;
 use aim_data::get_quote;
+use aim_data::Timeframe;
+use aim_data::{get_corporate_actions, get_history, OHLCData};
 use slint::ComponentHandle;
 use std::{sync::Arc, time::Duration};
 use tokio::sync::Mutex;
@@ -38,6 +39,13 @@ pub async fn spawn_new_stock_data_task(
     .await;
 
     tokio::spawn(async move {
+        restore_workspace_charts(&chart_clone, &ui_handle).await;
+        let _ = ui_handle.upgrade_in_event_loop(|ui| {
+            let mut ui_data = ui.get_ui_data();
+            ui_data.is_in_update = true;
+            ui.set_ui_data(ui_data);
+        });
+
         let mut task_status = crate::tasks::task_manager::TaskStatus::Running;
         loop {
             if let Ok(status) = rx.try_recv() {
@@ -60,7 +68,8 @@ pub async fn spawn_new_stock_data_task(
                     ui.set_is_chart_in_update(true);
                     ui_data.is_new_stock = false;
                     ui_data.is_new_time_frame = false;
-                    let time_frame = ui_data.clone().time_frame;
+                    let time_frame = Timeframe::parse(&ui_data.time_frame).unwrap_or_default();
+                    let adjusted = ui_data.is_adjusted;
                     ui.set_ui_data(ui_data);
                     let stock = ui.get_current_stock().symbol;
 
@@ -69,36 +78,74 @@ pub async fn spawn_new_stock_data_task(
                     let ui_handle = ui.as_weak();
                     tokio::spawn(async move {
                         // Check if chart with the same stock name already exists first
-                        let chart_exists = {
+                        let chart_view = {
                             let charts = chart_clone.lock().await;
                             charts
                                 .data
                                 .iter()
-                                .any(|chart| chart.stock_name == stock.to_uppercase())
+                                .find(|chart| chart.stock_name == stock.to_uppercase())
+                                .map(|chart| {
+                                    (
+                                        chart.time_frame,
+                                        chart.adjusted,
+                                        !chart.source_data.is_empty(),
+                                    )
+                                })
                         };
 
-                        // If chart exists, just return without creating a new one
-                        if chart_exists {
+                        // If chart exists with this time frame, prices and its bars, just return without creating a new one
+                        if chart_view == Some((time_frame, adjusted, true)) {
                             let _ = ui_handle.upgrade_in_event_loop(move |ui| {
                                 ui.set_is_chart_in_update(false);
                             });
                             return;
                         }
 
-                        let stock_data = if let Ok(data) =
-                            get_quote(&[&stock], interval_to_constant(&time_frame), None, None)
+                        let symbol = stock.to_uppercase();
+                        let stock_data =
+                            match get_history(&symbol, time_frame.base_resolution(), None, None)
                                 .await
-                        {
-                            data
+                            {
+                                Ok(candles) => OHLCData::from_candlesticks(&symbol, &candles),
+                                Err(e) => {
+                                    report_fetch_error(
+                                        &ui_handle,
+                                        &format!("Failed to fetch stock data for {stock}"),
+                                        &e,
+                                    );
+                                    let _ = ui_handle.upgrade_in_event_loop(move |ui| {
+                                        ui.set_is_chart_in_update(false);
+                                    });
+                                    return;
+                                }
+                            };
+
+                        let actions = if adjusted {
+                            get_corporate_actions(&symbol).await.unwrap_or_else(|e| {
+                                report_fetch_error(
+                                    &ui_handle,
+                                    &format!("Failed to fetch corporate actions for {symbol}"),
+                                    &e,
+                                );
+                                Vec::new()
+                            })
                         } else {
-                            log::error!("Failed to fetch stock data for {stock}");
-                            let _ = ui_handle.upgrade_in_event_loop(move |ui| {
-                                ui.set_is_chart_in_update(false);
-                            });
-                            return;
+                            Vec::new()
                         };
 
-                        if !stock_data.0.is_empty() {
+                        // Existing chart: rebuild its history for the new time frame or prices
+                        if chart_view.is_some() {
+                            let mut charts = chart_clone.lock().await;
+                            if let Some(chart) = charts
+                                .data
+                                .iter_mut()
+                                .find(|chart| chart.stock_name == symbol)
+                            {
+                                chart.set_price_adjustment(adjusted, actions);
+                                chart.update_candle_data(stock_data, time_frame);
+                                charts.notify_loaded();
+                            }
+                        } else if !stock_data.c.is_empty() {
                             let company_info =
                                 if let Ok(stock_info) = get_company_info(&stock).await {
                                     CompanyInfo {
@@ -121,12 +168,20 @@ pub async fn spawn_new_stock_data_task(
                                 } else {
                                     CompanyInfo::default()
                                 };
-                            let mut charts = chart_clone.lock().await;
-                            charts.data.push(Chart::new_default(
-                                stock.to_uppercase(),
-                                stock_data.0[0].clone(),
+                            let mut chart = Chart::new_default(
+                                symbol,
+                                stock_data.clone(),
                                 company_info,
-                            ));
+                                time_frame,
+                            );
+                            if adjusted {
+                                chart.set_price_adjustment(adjusted, actions);
+                                chart.update_candle_data(stock_data, time_frame);
+                            }
+                            let mut charts = chart_clone.lock().await;
+                            charts.data.push(chart);
+                            charts.mark_dirty();
+                            charts.notify_loaded();
                         }
                         let _ = ui_handle.upgrade_in_event_loop(move |ui| {
                             ui.set_is_chart_in_update(false);
@@ -141,6 +196,68 @@ pub async fn spawn_new_stock_data_task(
     task_handle
 }
 
+/// Fetch the bars of the charts restored from the workspace, which the cache
+/// keeps without them, each chart in a task of its own. Most of the history
+/// comes from the candle store.
+async fn restore_workspace_charts(chart: &Arc<Mutex<ChartMetaData>>, ui: &slint::Weak<AppWindow>) {
+    let waiting: Vec<(String, Timeframe, bool)> = chart
+        .lock()
+        .await
+        .data
+        .iter()
+        .filter(|chart| chart.source_data.is_empty())
+        .map(|chart| (chart.stock_name.clone(), chart.time_frame, chart.adjusted))
+        .collect();
+    for (symbol, time_frame, adjusted) in waiting {
+        let chart = Arc::clone(chart);
+        let ui = ui.clone();
+        tokio::spawn(async move {
+            restore_workspace_chart(&chart, &ui, symbol, time_frame, adjusted).await;
+        });
+    }
+}
+
+/// Fetch the bars of the restored chart of `symbol`, unless the new stock
+/// task filled it in the meantime.
+async fn restore_workspace_chart(
+    chart: &Mutex<ChartMetaData>,
+    ui: &slint::Weak<AppWindow>,
+    symbol: String,
+    time_frame: Timeframe,
+    adjusted: bool,
+) {
+    let candles = match get_history(&symbol, time_frame.base_resolution(), None, None).await {
+        Ok(candles) => candles,
+        Err(e) => {
+            report_fetch_error(ui, &format!("Failed to restore the {symbol} chart"), &e);
+            return;
+        }
+    };
+    let actions = if adjusted {
+        get_corporate_actions(&symbol).await.unwrap_or_else(|e| {
+            report_fetch_error(
+                ui,
+                &format!("Failed to fetch corporate actions for {symbol}"),
+                &e,
+            );
+            Vec::new()
+        })
+    } else {
+        Vec::new()
+    };
+    let stock_data = OHLCData::from_candlesticks(&symbol, &candles);
+    let mut charts = chart.lock().await;
+    if let Some(chart) = charts
+        .data
+        .iter_mut()
+        .find(|chart| chart.stock_name == symbol && chart.source_data.is_empty())
+    {
+        chart.set_price_adjustment(adjusted, actions);
+        chart.update_candle_data(stock_data, time_frame);
+        charts.notify_loaded();
+    }
+}
+
 /// Spawns a separate task to handle chart data updates for existing charts
 pub async fn spawn_current_stock_data_task(
     chart: Arc<Mutex<ChartMetaData>>,
@@ -182,18 +299,19 @@ pub async fn spawn_current_stock_data_task(
 
                 tokio::spawn(async move {
                     // Check if chart with the same stock name already exists
-                    let chart_exists = {
+                    let existing_chart = {
                         let charts = chart_clone.lock().await;
                         charts
                             .data
                             .iter()
-                            .any(|chart| chart.stock_name == stock.to_uppercase())
+                            .find(|chart| chart.stock_name == stock.to_uppercase())
+                            .map(|chart| (chart.last_candle_time(), chart.time_frame))
                     };
 
-                    // If chart exists, update it with latest data
-                    if chart_exists {
+                    // If chart exists, update it with the bars since its last candle
+                    if let Some((from, time_frame)) = existing_chart {
                         if let Ok(chart_data_vec) =
-                            get_quote(&[&stock], "ONE_DAY", None, None).await
+                            get_quote(&[&stock], time_frame.base_resolution(), from, None).await
                         {
                             let mut charts = chart_clone.lock().await;
 
@@ -204,7 +322,7 @@ pub async fn spawn_current_stock_data_task(
                                     .iter()
                                     .find(|data| data.symbol == chart.stock_name)
                                 {
-                                    chart.update_candle_data(updated_data.clone());
+                                    chart.merge_candle_data(updated_data.clone());
                                 }
                             }
                         }
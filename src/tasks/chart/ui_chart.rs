@@ -1,7 +1,9 @@
+use super::show_layout_cells;
 use crate::slint_generatedAppWindow::AppWindow;
 use crate::tasks::task_manager::{register_task, TaskHandle};
 use crate::tasks::ChartMetaData;
 use aim_chart::UiData;
+use aim_data::Timeframe;
 use slint::ComponentHandle;
 use std::sync::Arc;
 use tokio::sync::Mutex;
@@ -24,6 +26,21 @@ fn convert_ui_data_to_chart_data(ui_data: crate::slint_generatedAppWindow::UiDat
         crate::slint_generatedAppWindow::MouseType::HorizontalLine => {
             aim_chart::MouseType::HorizontalLine
         }
+        crate::slint_generatedAppWindow::MouseType::FibRetracement => {
+            aim_chart::MouseType::FibRetracement
+        }
+        crate::slint_generatedAppWindow::MouseType::FibExtension => {
+            aim_chart::MouseType::FibExtension
+        }
+        crate::slint_generatedAppWindow::MouseType::Channel => aim_chart::MouseType::Channel,
+        crate::slint_generatedAppWindow::MouseType::Pitchfork => aim_chart::MouseType::Pitchfork,
+        crate::slint_generatedAppWindow::MouseType::GannFan => aim_chart::MouseType::GannFan,
+        crate::slint_generatedAppWindow::MouseType::LongPosition => {
+            aim_chart::MouseType::LongPosition
+        }
+        crate::slint_generatedAppWindow::MouseType::ShortPosition => {
+            aim_chart::MouseType::ShortPosition
+        }
     };
 
     UiData {
@@ -45,8 +62,40 @@ fn convert_ui_data_to_chart_data(ui_data: crate::slint_generatedAppWindow::UiDat
         press_y: ui_data.press_y,
         time_frame: ui_data.time_frame.to_string(),
         is_new_time_frame: ui_data.is_new_time_frame,
+        is_adjusted: ui_data.is_adjusted,
+        indicators: ui_data.indicators.to_string(),
+        is_new_indicators: ui_data.is_new_indicators,
         is_new_stock: ui_data.is_new_stock,
         color: ui_data.color,
+        text: ui_data.text.to_string(),
+        text_size: ui_data.text_size,
+        fib_levels: ui_data.fib_levels.to_string(),
+        line_width: ui_data.line_width,
+        is_dashed: ui_data.is_dashed,
+        fill_opacity: ui_data.fill_opacity,
+        is_locked: ui_data.is_locked,
+        is_hidden: ui_data.is_hidden,
+        is_new_style: ui_data.is_new_style,
+        is_redo: ui_data.is_redo,
+        is_delete: ui_data.is_delete,
+        is_show_hidden: ui_data.is_show_hidden,
+        has_selection: ui_data.has_selection,
+        selected_color: ui_data.selected_color,
+        selected_line_width: ui_data.selected_line_width,
+        selected_dashed: ui_data.selected_dashed,
+        selected_fill_opacity: ui_data.selected_fill_opacity,
+        selected_locked: ui_data.selected_locked,
+        selected_hidden: ui_data.selected_hidden,
+        compare: ui_data.compare.to_string(),
+        is_compare_ratio: ui_data.is_compare_ratio,
+        chart_type: ui_data.chart_type.to_string(),
+        box_size: ui_data.box_size.to_string(),
+        is_new_chart_type: ui_data.is_new_chart_type,
+        price_scale: ui_data.price_scale.to_string(),
+        is_auto_fit: ui_data.is_auto_fit,
+        is_lock_scale: ui_data.is_lock_scale,
+        is_new_scale: ui_data.is_new_scale,
+        hide_cursor: false,
     }
 }
 
@@ -92,14 +141,18 @@ pub async fn spawn_ui_chart_task(chart: Arc<Mutex<ChartMetaData>>, ui: &AppWindo
                 {
                     ui_data.height = height as i32;
                     ui_data.width = width as i32;
+                    // Show the chart's indicator list when another chart comes
+                    // up or after an edit, but not while the user is typing
+                    let is_new_chart = stock_name != ui_data.ticker;
+                    let show_indicators = is_new_chart || ui_data.is_new_indicators;
 
                     // Render the chart plot
-                    let (image, is_in_object) = {
+                    let (image, is_in_object, indicators, compare, chart_type, scale, selected) = {
                         ui_data.is_in_update = false;
                         log::info!("Rendering chart for {stock_name}");
                         tokio::task::block_in_place(|| {
                             let mut charts = chart_clone.blocking_lock();
-                            if let Some(chart) = charts
+                            let (rendered, cursor) = if let Some(chart) = charts
                                 .data
                                 .iter_mut()
                                 .find(|chart| stock_name == chart.stock_name)
@@ -107,11 +160,40 @@ pub async fn spawn_ui_chart_task(chart: Arc<Mutex<ChartMetaData>>, ui: &AppWindo
                                 log::info!("Found existing chart for {stock_name}, rendering...");
                                 ui_data.ticker = stock_name.clone();
                                 let chart_ui_data = convert_ui_data_to_chart_data(ui_data.clone());
-                                chart.render_plot(chart_ui_data)
+                                let (image, is_in_object) = chart.render_plot(chart_ui_data);
+                                let scale = (chart.price_scale, chart.auto_fit, chart.lock_scale);
+                                (
+                                    (
+                                        image,
+                                        is_in_object,
+                                        Some(chart.indicator_spec()),
+                                        Some((chart.compare_spec(), chart.compare_ratio)),
+                                        Some(chart.chart_type),
+                                        Some(scale),
+                                        chart.selected_style(),
+                                    ),
+                                    chart.cursor_time(),
+                                )
                             } else {
                                 log::warn!("No chart found for {stock_name}, using default");
-                                (slint::Image::default(), false)
-                            }
+                                (
+                                    (slint::Image::default(), false, None, None, None, None, None),
+                                    None,
+                                )
+                            };
+
+                            // The other cells of the layout, at the same size
+                            let time_frame =
+                                Timeframe::parse(&ui_data.time_frame).unwrap_or_default();
+                            charts.layout.follow(&stock_name, time_frame);
+                            show_layout_cells(
+                                &ui,
+                                &mut charts.layout,
+                                ui_data.width,
+                                ui_data.height,
+                                cursor,
+                            );
+                            rendered
                         })
                     };
 
@@ -126,6 +208,48 @@ pub async fn spawn_ui_chart_task(chart: Arc<Mutex<ChartMetaData>>, ui: &AppWindo
                     if ui_data.is_undo {
                         ui_data.is_undo = false;
                     }
+                    ui_data.is_redo = false;
+                    ui_data.is_delete = false;
+                    ui_data.is_show_hidden = false;
+                    ui_data.is_new_style = false;
+
+                    // Show the style of the selected drawing in the toolbar,
+                    // keeping the style of new drawings
+                    ui_data.has_selection = selected.is_some();
+                    if let Some((style, color)) = selected {
+                        ui_data.selected_line_width = style.width as i32;
+                        ui_data.selected_dashed = style.dashed;
+                        ui_data.selected_fill_opacity = style.fill_opacity;
+                        ui_data.selected_locked = style.locked;
+                        ui_data.selected_hidden = style.hidden;
+                        ui_data.selected_color =
+                            slint::Color::from_rgb_u8(color.0, color.1, color.2);
+                    }
+                    if let Some(indicators) = indicators.filter(|_| show_indicators) {
+                        ui_data.indicators = indicators.into();
+                    }
+                    ui_data.is_new_indicators = false;
+                    if let Some((compare, ratio)) = compare.filter(|_| is_new_chart) {
+                        ui_data.compare = compare.into();
+                        ui_data.is_compare_ratio = ratio;
+                    }
+                    // The chart type picker follows the chart, and shows the
+                    // type in use after an invalid box size
+                    if let Some(chart_type) =
+                        chart_type.filter(|_| is_new_chart || ui_data.is_new_chart_type)
+                    {
+                        ui_data.chart_type = chart_type.label().into();
+                        if let Some(box_size) = chart_type.box_size() {
+                            ui_data.box_size = box_size.label().into();
+                        }
+                    }
+                    ui_data.is_new_chart_type = false;
+                    if let Some((scale, auto_fit, lock)) = scale.filter(|_| is_new_chart) {
+                        ui_data.price_scale = scale.label().into();
+                        ui_data.is_auto_fit = auto_fit;
+                        ui_data.is_lock_scale = lock;
+                    }
+                    ui_data.is_new_scale = false;
                     if ui_data.is_release {
                         ui_data.move_x = 0;
                         ui_data.move_y = 0;
@@ -135,7 +135,7 @@ pub async fn spawn_heat_map_task(ui: &AppWindow) -> TaskHandle {
                     });
                 }
                 Err(e) => {
-                    log::error!("Failed to fetch stock by gics data: {}", e);
+                    crate::tasks::report_fetch_error(&ui_handle, "Stock by GICS data", &e);
                 }
             }
             tokio::time::sleep(std::time::Duration::from_secs(10)).await;
@@ -63,10 +63,10 @@ macro_rules! create_dual_source_task {
                             });
                         }
                         Err(e) => {
-                            log::error!(
-                                "Failed to fetch {} data (source 1): {}",
-                                $task_description,
-                                e
+                            crate::tasks::report_fetch_error(
+                                &ui_handle,
+                                concat!($task_description, " (source 1)"),
+                                &e,
                             );
                         }
                     }
@@ -84,10 +84,10 @@ macro_rules! create_dual_source_task {
                             });
                         }
                         Err(e) => {
-                            log::error!(
-                                "Failed to fetch {} data (source 2): {}",
-                                $task_description,
-                                e
+                            crate::tasks::report_fetch_error(
+                                &ui_handle,
+                                concat!($task_description, " (source 2)"),
+                                &e,
                             );
                         }
                     }
@@ -1,5 +1,8 @@
 use crate::slint_generatedAppWindow;
-use aim_chart::Chart;
+use aim_chart::{
+    Chart, ChartLayout, CHART_CACHE_LEGACY_VERSIONS, CHART_CACHE_MAGIC, CHART_CACHE_VERSION,
+};
+use aim_data::cache_file::{CacheFile, Frame};
 use aim_data::explorer::vci::OrderList;
 pub use cache_storage::spawn_cache_storage_task;
 pub use chart::*;
@@ -9,7 +12,8 @@ pub use monte_carlo::*;
 use slint_generatedAppWindow::{
     MarketWatchData as SlintMarketWatchData, StockData as SlintStockData,
 };
-use std::{fs::File, io::Write, path::PathBuf};
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use tokio::sync::Notify;
 
 pub mod backend;
 pub mod cache_storage;
@@ -75,7 +79,7 @@ macro_rules! create_simple_task {
                             });
                         }
                         Err(e) => {
-                            log::error!("Failed to fetch {} data: {}", $task_description, e);
+                            $crate::tasks::report_fetch_error(&ui_handle, $task_description, &e);
                         }
                     }
 
@@ -88,22 +92,85 @@ macro_rules! create_simple_task {
     };
 }
 
+/// Log a failed fetch with a short, user-facing reason and show that reason
+/// in the status line of the window.
+///
+/// Network hiccups are expected while polling and are only warned about;
+/// auth and schema problems will not fix themselves and are logged as errors.
+pub fn report_fetch_error(
+    ui: &slint::Weak<crate::AppWindow>,
+    description: &str,
+    e: &aim_data::Error,
+) {
+    let status = if e.is_transient() {
+        log::warn!("{description}: {} ({e}), retrying", e.status_message());
+        format!("{description}: {}, retrying", e.status_message())
+    } else {
+        log::error!("{description}: {} ({e})", e.status_message());
+        format!("{description}: {}", e.status_message())
+    };
+    let status = format!("{} {status}", chrono::Local::now().format("%H:%M:%S"));
+    let _ = ui.upgrade_in_event_loop(move |ui| ui.set_fetch_status(status.into()));
+}
+
+/// Older copies of the chart cache kept next to it
+pub const CHART_CACHE_BACKUPS: usize = 3;
+/// How often the chart cache is backed up while the app runs
+const CHART_CACHE_BACKUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
 #[repr(C)]
 pub struct ChartMetaData {
     data: Vec<Chart>,
+    layout: ChartLayout, // cells shown next to the chart of the current stock
+    dirty: bool,         // the workspace changed outside the charts
+    loaded: Arc<Notify>, // woken whenever a chart of `data` gets its bars
 }
 
 impl ChartMetaData {
     pub fn new(data: Vec<Chart>) -> Self {
-        Self { data }
+        Self {
+            data,
+            layout: ChartLayout::default(),
+            dirty: true, // nothing saved yet
+            loaded: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Woken whenever a chart gets its bars. Wait on it with the lock
+    /// released, after checking the charts and enabling the wait under it.
+    pub fn loaded(&self) -> Arc<Notify> {
+        Arc::clone(&self.loaded)
+    }
+
+    /// Wake the tasks waiting on [`Self::loaded`], after a chart got its bars.
+    pub fn notify_loaded(&self) {
+        self.loaded.notify_waiters();
     }
 
-    pub fn save(&self, mut file: File) {
-        const VERSION: u32 = 1;
+    pub fn layout(&self) -> &ChartLayout {
+        &self.layout
+    }
+
+    /// Every chart that follows the feed: one per stock, then the layout cells.
+    pub fn charts(&self) -> impl Iterator<Item = &Chart> {
+        self.data.iter().chain(self.layout.charts())
+    }
+
+    pub fn charts_mut(&mut self) -> impl Iterator<Item = &mut Chart> {
+        self.data.iter_mut().chain(self.layout.charts_mut())
+    }
 
+    /// The chart cache at `path`, keeping [`CHART_CACHE_BACKUPS`] older copies.
+    pub fn cache_file(path: PathBuf) -> CacheFile {
+        CacheFile::new(path, CHART_CACHE_MAGIC)
+            .with_backups(CHART_CACHE_BACKUPS, CHART_CACHE_BACKUP_INTERVAL)
+            .with_legacy_versions(CHART_CACHE_LEGACY_VERSIONS)
+    }
+
+    /// Write the workspace to `cache`: the charts without their bars, and the
+    /// layout.
+    pub fn save(&self, cache: &CacheFile) -> aim_data::Result<()> {
         let mut bytes = Vec::new();
-        // Write version header
-        bytes.extend_from_slice(&VERSION.to_le_bytes());
         let count = self.data.len() as u32;
         bytes.extend_from_slice(&count.to_le_bytes());
         for (i, chart) in self.data.iter().enumerate() {
@@ -114,88 +181,102 @@ impl ChartMetaData {
                 log::error!("Chart #{} failed to serialize: {}", i, chart.stock_name);
             }
         }
-        if let Err(e) = file.write_all(&bytes) {
-            log::error!("Failed to write chart data to cache file: {e}");
+        self.layout.write_to_bytes(&mut bytes);
+        cache.write(CHART_CACHE_VERSION, &bytes)
+    }
+
+    /// Load the charts from `cache`, or from its newest intact backup. Charts
+    /// saved since version 12 have no bars until the new stock task fetches
+    /// them.
+    ///
+    /// `None` when there is no cache yet or it cannot be read; a cache written
+    /// by a newer version of the app is also copied aside so that saving this
+    /// session does not overwrite it.
+    pub fn load(cache: &CacheFile) -> Option<Self> {
+        match cache.read_with(Self::decode) {
+            Ok(loaded) => loaded.flatten(),
+            Err(e) => {
+                log::error!("Failed to read chart cache: {e}");
+                None
+            }
         }
     }
 
-    // Load charts from a file (manual deserialization, no external crate)
-    pub fn load(path: &PathBuf) -> Self {
+    /// The workspace in `frame`, `None` when a newer version wrote it. A
+    /// chart or layout that does not decode fails the whole frame, so that
+    /// the next backup is tried rather than dropping the charts after it.
+    fn decode(frame: Frame) -> Result<Option<Self>, String> {
+        let (version, bytes, path) = (frame.version, frame.payload, frame.path);
+        if version > CHART_CACHE_VERSION {
+            let mut kept = path.as_os_str().to_owned();
+            kept.push(format!(".v{version}"));
+            log::error!(
+                "Chart cache version {version} in {path:?} is newer than {CHART_CACHE_VERSION}, keeping it as {kept:?}"
+            );
+            if let Err(e) = std::fs::copy(&path, &kept) {
+                log::error!("Failed to keep the newer chart cache: {e}");
+            }
+            return Ok(None);
+        }
+        if version == 0 || bytes.len() < 4 {
+            return Err(format!("unsupported chart cache version {version}"));
+        }
+
         let mut data = Vec::new();
-        match std::fs::read(path) {
-            Ok(bytes) => {
-                let mut pos = 0;
-                if bytes.len() < 8 {
-                    log::error!("File too small to contain version and chart count: {path:?}");
-                    return Self { data };
-                }
-                let version = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
-                pos += 4;
-                if version != 1 {
-                    log::error!("Unsupported chart cache version {version} in {path:?}");
-                    return Self { data };
-                }
-                let count = u32::from_le_bytes([
-                    bytes[pos],
-                    bytes[pos + 1],
-                    bytes[pos + 2],
-                    bytes[pos + 3],
-                ]) as usize;
-                pos += 4;
-                for i in 0..count {
-                    let chart_bytes = &bytes[pos..];
-                    match Chart::read_from_bytes(chart_bytes) {
-                        Some((chart, used)) => {
-                            if used == 0 {
-                                log::error!(
-                                    "Chart #{i} deserialized 0 bytes at pos {pos} in {path:?}"
-                                );
-                                break;
-                            }
-                            data.push(chart);
-                            pos += used;
-                        }
-                        None => {
-                            log::error!(
-                                "Failed to deserialize chart #{i} at pos {pos} in {path:?}"
-                            );
-                            break;
-                        }
-                    }
-                }
-                log::info!("Loaded {} charts from {}", data.len(), path.display());
-                for chart in &data {
-                    log::info!("Chart loaded: {}", chart.stock_name);
+        let mut layout = ChartLayout::default();
+        let mut pos = 0;
+        let count = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+        pos += 4;
+        for i in 0..count {
+            match Chart::read_from_bytes(&bytes[pos..], version) {
+                Some((chart, used)) if used > 0 => {
+                    data.push(chart);
+                    pos += used;
                 }
+                _ => return Err(format!("chart #{i} does not deserialize at pos {pos}")),
             }
-            Err(e) => {
-                log::error!("Failed to read chart file {path:?}: {e}");
+        }
+        // The layout follows the charts since version 8
+        if version >= 8 {
+            match ChartLayout::read_from_bytes(&bytes[pos..], version) {
+                Some((loaded, _)) => layout = loaded,
+                None => return Err("the chart layout does not deserialize".to_string()),
             }
         }
-
-        log::error!(
-            "Chart data loaded from {}: {} charts",
-            path.display(),
-            data.len()
-        );
+        if version < CHART_CACHE_VERSION {
+            log::info!(
+                "Migrated chart cache {path:?} from version {version} to {CHART_CACHE_VERSION}"
+            );
+        }
+        log::info!("Loaded {} charts from {}", data.len(), path.display());
         for chart in &data {
             log::info!("Chart loaded: {}", chart.stock_name);
         }
-        Self { data }
+        let mut loaded = Self {
+            data,
+            layout,
+            dirty: false,
+            loaded: Arc::new(Notify::new()),
+        };
+        // Loading is no edit, but a migrated cache is written again
+        loaded.take_dirty();
+        loaded.dirty = version < CHART_CACHE_VERSION;
+        Ok(Some(loaded))
     }
 
-    // Get a simple hash of the chart data (no external crate)
-    pub fn get_md5(&self) -> String {
-        // Use a simple FNV-1a hash for demonstration
-        let mut hash: u64 = 0xcbf29ce484222325;
-        for chart in &self.data {
-            let bytes = chart.to_bytes();
-            for b in bytes {
-                hash ^= b as u64;
-                hash = hash.wrapping_mul(0x100000001b3);
-            }
+    /// Note a change of the workspace that no chart tracks, such as the
+    /// layout or the list of charts.
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Whether the workspace changed since the last call and should be saved.
+    pub fn take_dirty(&mut self) -> bool {
+        let mut dirty = std::mem::take(&mut self.dirty);
+        for chart in &mut self.data {
+            dirty |= chart.take_dirty();
         }
-        format!("{hash:016x}")
+        dirty
     }
 }
 
@@ -204,4 +285,9 @@ pub enum DataUpdate {
     StockData(Vec<SlintStockData>),
     OrdList(OrderList),
     CustomList(Vec<String>),
+    /// Alerts that fired, and the alert list after they did
+    Alerts(
+        Vec<aim_data::alert::Triggered>,
+        Vec<slint_generatedAppWindow::AlertItem>,
+    ),
 }
@@ -3,7 +3,7 @@ use tokio::sync::Mutex;
 use slint::{ComponentHandle, ModelRc, SharedString, VecModel};
 use crate::monte_carlo::{
     ModelParams, SimStats, estimate_paramaters, run_simulation,
-    load_all_records, get_ticker_info, StockRecord
+    load_all_records, load_stored_records, get_ticker_info, StockRecord
 };
 
 #[derive(Debug, Clone)]
@@ -27,6 +27,24 @@ impl Default for MonteCarloState {
     }
 }
 
+impl MonteCarloState {
+    /// State holding the daily history already in the local candle store,
+    /// so tickers that were charted need neither a CSV nor a fetch.
+    pub fn from_store() -> Self {
+        match load_stored_records() {
+            Ok((all_data, tickers)) => Self {
+                all_data,
+                tickers,
+                ..Self::default()
+            },
+            Err(e) => {
+                log::warn!("No stored history for Monte Carlo: {e}");
+                Self::default()
+            }
+        }
+    }
+}
+
 pub fn setup_monte_carlo_callbacks(
     ui: &crate::slint_generatedAppWindow::AppWindow,
     state: Arc<Mutex<MonteCarloState>>,